@@ -0,0 +1,102 @@
+//! Turns raw account updates of the auction program, as streamed by a Geyser plugin,
+//! into typed notifications for bots and dashboards.
+
+use std::collections::HashMap;
+use std::future::ready;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use auction::{AuctionKind, AuctionStatus, State};
+use futures::stream::{self, Stream, StreamExt};
+
+#[cfg(feature = "yellowstone")]
+pub mod yellowstone;
+
+/// A write to an account owned by the program
+#[derive(Clone, Debug)]
+pub struct AccountUpdate {
+    pub pubkey: Pubkey,
+    pub slot: u64,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuctionEvent {
+    /// First time the auction is seen, not necessarily its creation when the stream starts late
+    Listed { auction: Pubkey, initializer: Pubkey, dutch: bool, end_time: i64, slot: u64 },
+    NewBid { auction: Pubkey, bidder: Pubkey, amount: u64, slot: u64 },
+    Extended { auction: Pubkey, end_time: i64, slot: u64 },
+    Settled { auction: Pubkey, winner: Pubkey, price: u64, slot: u64 },
+}
+
+/// What was last seen of an auction, to tell which fields changed
+#[derive(Clone, Copy)]
+struct Seen {
+    slot: u64,
+    max_bidder: Pubkey,
+    max_price: u64,
+    end_time: i64,
+    status: AuctionStatus,
+}
+
+/// Diffs consecutive states of every auction, ignoring other accounts and updates older than already seen
+#[derive(Default)]
+pub struct Decoder {
+    auctions: HashMap<Pubkey, Seen>,
+}
+
+impl Decoder {
+    pub fn apply(&mut self, update: &AccountUpdate) -> Vec<AuctionEvent> {
+        let state = match State::try_deserialize(&mut &update.data[..]) {
+            Ok(state) => state,
+            Err(_) => return Vec::new(),
+        };
+        let (auction, slot) = (update.pubkey, update.slot);
+        let now = Seen {
+            slot,
+            max_bidder: state.max_bidder,
+            max_price: state.max_price,
+            end_time: state.end_time,
+            status: state.status,
+        };
+
+        let mut events = Vec::new();
+        let before = match self.auctions.get(&auction) {
+            Some(before) if before.slot > slot => return events,
+            Some(before) => *before,
+            None => {
+                events.push(AuctionEvent::Listed {
+                    auction,
+                    initializer: state.initializer,
+                    dutch: state.kind == AuctionKind::Dutch,
+                    end_time: state.end_time,
+                    slot,
+                });
+                Seen { max_bidder: Pubkey::default(), ..now }
+            }
+        };
+
+        if now.max_bidder != Pubkey::default() && (now.max_bidder, now.max_price) != (before.max_bidder, before.max_price) {
+            events.push(AuctionEvent::NewBid { auction, bidder: now.max_bidder, amount: now.max_price, slot });
+        }
+        if now.end_time > before.end_time {
+            events.push(AuctionEvent::Extended { auction, end_time: now.end_time, slot });
+        }
+        if before.status != AuctionStatus::Settled && now.status == AuctionStatus::Settled {
+            events.push(AuctionEvent::Settled { auction, winner: now.max_bidder, price: now.max_price, slot });
+        }
+
+        self.auctions.insert(auction, now);
+        events
+    }
+}
+
+/// Plugs a stream of account updates into a `Decoder`
+pub fn auction_events<S>(updates: S) -> impl Stream<Item = AuctionEvent>
+where
+    S: Stream<Item = AccountUpdate>,
+{
+    updates
+        .scan(Decoder::default(), |decoder, update| ready(Some(stream::iter(decoder.apply(&update)))))
+        .flatten()
+}
@@ -0,0 +1,55 @@
+//! Account updates of the program from a Yellowstone gRPC endpoint
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use futures::stream::{Stream, StreamExt};
+use yellowstone_grpc_client::GeyserGrpcClient;
+use yellowstone_grpc_proto::prelude::{
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts,
+};
+
+use crate::{auction_events, AccountUpdate, AuctionEvent};
+
+/// Subscribes to every account owned by the program, dropping updates that fail to arrive or parse
+pub async fn account_updates(
+    endpoint: String,
+    x_token: Option<String>
+) -> Result<impl Stream<Item = AccountUpdate>, Box<dyn std::error::Error>> {
+    let mut client = GeyserGrpcClient::connect(endpoint, x_token, None)?;
+
+    let mut accounts = HashMap::new();
+    accounts.insert(String::from("auction"), SubscribeRequestFilterAccounts {
+        owner: vec![auction::ID.to_string()],
+        ..Default::default()
+    });
+    let request = SubscribeRequest {
+        accounts,
+        commitment: Some(CommitmentLevel::Confirmed as i32),
+        ..Default::default()
+    };
+
+    let (_sink, updates) = client.subscribe_with_request(Some(request)).await?;
+
+    Ok(updates.filter_map(|update| async move {
+        match update.ok()?.update_oneof? {
+            UpdateOneof::Account(update) => {
+                let account = update.account?;
+                Some(AccountUpdate {
+                    pubkey: Pubkey::new(&account.pubkey),
+                    slot: update.slot,
+                    data: account.data,
+                })
+            }
+            _ => None,
+        }
+    }))
+}
+
+/// Typed notifications straight from a Yellowstone endpoint
+pub async fn subscribe(
+    endpoint: String,
+    x_token: Option<String>
+) -> Result<impl Stream<Item = AuctionEvent>, Box<dyn std::error::Error>> {
+    Ok(auction_events(account_updates(endpoint, x_token).await?))
+}
@@ -0,0 +1,24 @@
+#![no_main]
+
+use auction_harness::{differential, Op};
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+
+/// Longer sequences only slow the validator down without reaching new states
+const MAX_OPS: usize = 64;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    duration: u16,
+    initial_price: u32,
+    ops: Vec<Op>,
+}
+
+fuzz_target!(|input: Input| {
+    let ops = &input.ops[..input.ops.len().min(MAX_OPS)];
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+    if let Err(divergence) = runtime.block_on(differential(input.duration as i64 + 1, input.initial_price as u64, ops)) {
+        panic!("{}", divergence);
+    }
+});
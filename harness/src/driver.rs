@@ -0,0 +1,1107 @@
+//! Runs the same operations as the model against the real program inside `solana-program-test`
+
+use anchor_lang::{AccountDeserialize, AccountSerialize, Discriminator, InstructionData, ToAccountMetas};
+use auction::{
+    AuctionReport, AuctionResult, AuctionStatus, AuditEntry, AuditLog, Counter, FeeVault, GlobalConfig, GlobalStats, Offer, Registry,
+    RegistryPage, State,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::{system_instruction, system_program, sysvar};
+use solana_sdk::transaction::Transaction;
+
+use crate::model::{Model, Op, BIDDERS};
+
+/// Lamports every wallet starts with
+pub const FUNDS: u64 = 1_000_000_000_000;
+
+/// What the program holds after an operation, in the terms of the model
+#[derive(Debug, PartialEq, Eq)]
+pub struct Observed {
+    pub max_price: u64,
+    pub max_bidder: Option<usize>,
+    pub open: bool,
+    pub balances: [i128; BIDDERS],
+    pub seller: i128,
+    pub escrow: u64,
+}
+
+/// How the auction of a driver is created
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Creation {
+    /// Live English auction
+    Live,
+    /// Draft, to be published
+    Draft,
+    /// Reverse auction, the seller of the driver buying with the initial price as the budget
+    Reverse,
+    /// Gradual Dutch Auction starting at the initial price, both factors scaled by `PRECISION`
+    Gda { scale: u64, decay: u64 },
+    /// English auction starting at the EMA of a Pyth `price_feed`, clamped between `min_price` and `max_price`
+    Oracle { price_feed: Pubkey, min_price: u64, max_price: u64 },
+}
+
+pub struct Driver {
+    pub ctx: ProgramTestContext,
+    /// Admin of the program-wide config
+    pub admin: Keypair,
+    pub seller: Keypair,
+    pub bidders: Vec<Keypair>,
+    pub state: Pubkey,
+    pub treasury: Pubkey,
+    /// Receives the entry fees of the auction, the seller unless they go to the protocol
+    pub entry_fee_receiver: Pubkey,
+    /// Audit log of the auction, passed along with every instruction of the program once created
+    pub audit_log: Option<Pubkey>,
+    /// Pyth feed of the USD reserve, passed along with every instruction of the program once set
+    pub price_feed: Option<Pubkey>,
+    /// Creators of the escrowed item, passed along with its metadata to receive the royalties of a purchase
+    pub creators: Vec<Pubkey>,
+    /// Seller's lamports once the auction is created
+    seller_funds: u64,
+    /// Receives a different amount with every transaction so that retried instructions stay unique
+    pub sink: Pubkey,
+    nonce: u64,
+}
+
+impl Driver {
+    /// Starts a validator with funded wallets, returning it with the auction created and its model
+    pub async fn start(duration: i64, initial_price: u64) -> (Self, Model) {
+        Self::start_with(BIDDERS, duration, initial_price).await
+    }
+
+    /// Same as `start` with any number of bidders, the model only following the first `BIDDERS`
+    pub async fn start_with(bidders: usize, duration: i64, initial_price: u64) -> (Self, Model) {
+        Self::create(bidders, duration, initial_price, Creation::Live).await
+    }
+
+    /// Same as `start` with the auction created as a draft, the model applying once published
+    pub async fn start_draft(duration: i64, initial_price: u64) -> (Self, Model) {
+        Self::create(BIDDERS, duration, initial_price, Creation::Draft).await
+    }
+
+    async fn create(bidders: usize, duration: i64, initial_price: u64, creation: Creation) -> (Self, Model) {
+        Self::create_with(ProgramTest::new("auction", auction::ID, processor!(auction::entry)), bidders, duration, initial_price, creation).await
+    }
+
+    /// Same as `start_with` on a preconfigured `ProgramTest`, e.g. with a lower compute budget
+    pub async fn create_with(
+        mut program_test: ProgramTest,
+        bidders: usize,
+        duration: i64,
+        initial_price: u64,
+        creation: Creation
+    ) -> (Self, Model) {
+        let (admin, seller) = (Keypair::new(), Keypair::new());
+        let bidders: Vec<Keypair> = (0..bidders).map(|_| Keypair::new()).collect();
+        let sink = Pubkey::new_unique();
+        for wallet in bidders.iter().map(|bidder| bidder.pubkey()).chain([admin.pubkey(), seller.pubkey(), sink]) {
+            program_test.add_account(wallet, Account::new(FUNDS, 0, &system_program::ID));
+        }
+
+        // The first auction would pay the rent of the global stats, which the model knows nothing about
+        let mut stats = Account::new(rent(GlobalStats::size()), 8 + GlobalStats::size(), &auction::ID);
+        stats.data[..8].copy_from_slice(&GlobalStats::discriminator());
+        program_test.add_account(pda(&[b"stats"]), stats);
+
+        // Every auction is created against the program-wide config, without any limit until a test sets one
+        let (global, bump) = Pubkey::find_program_address(&[b"global"], &auction::ID);
+        let config = GlobalConfig { admin: admin.pubkey(), max_duration: 0, min_increment: 0, fee_bps: 0, bump, paused: false };
+        program_test.add_account(global, program_account(&config, GlobalConfig::size()));
+        let (fees, bump) = Pubkey::find_program_address(&[b"fees"], &auction::ID);
+        program_test.add_account(fees, program_account(&FeeVault { bump }, FeeVault::size()));
+
+        let mut driver = Driver {
+            ctx: program_test.start_with_context().await,
+            admin,
+            entry_fee_receiver: seller.pubkey(),
+            seller,
+            bidders,
+            state: Pubkey::default(),
+            treasury: Pubkey::default(),
+            audit_log: None,
+            price_feed: None,
+            creators: Vec::new(),
+            seller_funds: FUNDS,
+            sink,
+            nonce: 0,
+        };
+
+        let accounts = driver.next_auction(Pubkey::default()).await;
+        driver.state = accounts.state;
+        driver.treasury = accounts.treasury;
+        let ix = match creation {
+            Creation::Live => instruction(accounts, auction::instruction::Initialize { auction_duration: duration, initial_price }),
+            Creation::Draft => instruction(accounts, auction::instruction::CreateDraft { auction_duration: duration, initial_price }),
+            Creation::Reverse => {
+                instruction(accounts, auction::instruction::InitializeReverse { auction_duration: duration, budget: initial_price })
+            }
+            Creation::Gda { scale, decay } => instruction(
+                accounts,
+                auction::instruction::InitializeGda { auction_duration: duration, initial_price, scale, decay }
+            ),
+            Creation::Oracle { price_feed, min_price, max_price } => instruction(
+                auction::accounts::AuctionWithOracle { auction: accounts, price_feed },
+                auction::instruction::InitializeWithOracle { auction_duration: duration, min_price, max_price }
+            ),
+        };
+        let seller = clone(&driver.seller);
+        assert!(driver.send(ix, &[&seller]).await, "auction is created");
+
+        driver.seller_funds = driver.lamports(seller.pubkey()).await;
+        let now = driver.clock().await.unix_timestamp;
+        let model = Model::new(now, duration, initial_price, rent(Offer::size()));
+        (driver, model)
+    }
+
+    /// Accounts creating the next auction of the seller in the `namespace`
+    pub async fn next_auction(&mut self, namespace: Pubkey) -> auction::accounts::Auction {
+        let seller = self.seller.pubkey();
+        let counter = pda(&[b"counter", namespace.as_ref(), seller.as_ref()]);
+        let registry = pda(&[b"registry", namespace.as_ref()]);
+
+        let next_id = self.next_auction_id(namespace).await;
+        let tail = match self.account(registry).await {
+            Some(account) => Registry::try_deserialize(&mut &account.data[..]).expect("registry deserializes").tail,
+            None => 0,
+        };
+        let state = pda(&[b"auction", namespace.as_ref(), seller.as_ref(), &next_id.to_le_bytes()]);
+
+        auction::accounts::Auction {
+            state,
+            treasury: pda(&[b"treasury", state.as_ref()]),
+            namespace,
+            counter,
+            registry,
+            page: pda(&[b"page", namespace.as_ref(), &tail.to_le_bytes()]),
+            stats: pda(&[b"stats"]),
+            global: pda(&[b"global"]),
+            market_config: pda(&[b"config", namespace.as_ref()]),
+            initializer: seller,
+            system_program: system_program::ID,
+        }
+    }
+
+    /// Creates the next English auction of the seller in the `namespace`, which the driver follows from then on
+    pub async fn create_auction(&mut self, namespace: Pubkey, duration: i64, initial_price: u64) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = self.next_auction(namespace).await;
+        let (state, treasury) = (accounts.state, accounts.treasury);
+        let ix = instruction(accounts, auction::instruction::Initialize { auction_duration: duration, initial_price });
+
+        if !self.send(ix, &[&seller]).await {
+            return false;
+        }
+        self.follow(state);
+        self.treasury = treasury;
+        true
+    }
+
+    /// Drives another auction of the seller from now on
+    pub fn follow(&mut self, state: Pubkey) {
+        self.state = state;
+        self.treasury = pda(&[b"treasury", state.as_ref()]);
+        self.audit_log = None;
+    }
+
+    /// Applies the operation, returning whether the program accepted it
+    pub async fn apply(&mut self, op: Op) -> bool {
+        match op {
+            Op::Bid { bidder, raise } => {
+                let price = self.state().await.max_price;
+                self.bid(bidder as usize % BIDDERS, price + raise as u64).await
+            }
+            Op::Warp { seconds } => {
+                self.warp(seconds as i64).await;
+                true
+            }
+            Op::End => self.end().await,
+            Op::Refund { bidder } => self.refund(bidder as usize % BIDDERS).await,
+        }
+    }
+
+    pub async fn bid(&mut self, bidder: usize, amount: u64) -> bool {
+        let buyer = clone(&self.bidders[bidder]);
+        let ix = self.bid_instruction(bidder, amount);
+
+        self.send(ix, &[&buyer]).await
+    }
+
+    /// Raise of the standing bid of the leading `bidder`
+    pub async fn raise(&mut self, bidder: usize, amount: u64) -> bool {
+        let buyer = clone(&self.bidders[bidder]);
+        let mut ix = self.bid_instruction(bidder, amount);
+        ix.data = auction::instruction::RaiseBid { amount }.data();
+
+        self.send(ix, &[&buyer]).await
+    }
+
+    /// Purchase of `count` raffle tickets by the `bidder`
+    pub async fn buy_tickets(&mut self, bidder: usize, count: u64) -> bool {
+        let buyer = clone(&self.bidders[bidder]);
+        let mut ix = self.bid_instruction(bidder, count);
+        ix.data = auction::instruction::BuyTickets { count }.data();
+
+        self.send(ix, &[&buyer]).await
+    }
+
+    /// Proxy bid of the `bidder` escrowing up to `max_amount`
+    pub async fn proxy_bid(&mut self, bidder: usize, max_amount: u64) -> bool {
+        let buyer = clone(&self.bidders[bidder]);
+        let mut ix = self.bid_instruction(bidder, max_amount);
+        ix.data = auction::instruction::ProxyBid { max_amount }.data();
+
+        self.send(ix, &[&buyer]).await
+    }
+
+    /// Offer of the `bidder` to deliver for `amount` in a reverse auction
+    pub async fn reverse_bid(&mut self, bidder: usize, amount: u64) -> bool {
+        let seller = clone(&self.bidders[bidder]);
+        let accounts = auction::accounts::ReverseBid {
+            state: self.state,
+            seller: seller.pubkey(),
+            stats: pda(&[b"stats"]),
+            system_program: system_program::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::ReverseBid { amount }), &[&seller]).await
+    }
+
+    /// Bid of the `bidder`, to be signed by them
+    pub fn bid_instruction(&self, bidder: usize, amount: u64) -> Instruction {
+        let buyer = self.bidders[bidder].pubkey();
+        let accounts = auction::accounts::Bid {
+            offer: pda(&[b"bid", self.state.as_ref(), buyer.as_ref()]),
+            state: self.state,
+            treasury: self.treasury,
+            buyer,
+            entry_fee_receiver: self.entry_fee_receiver,
+            stats: pda(&[b"stats"]),
+            system_program: system_program::ID,
+        };
+
+        instruction(accounts, auction::instruction::Bid { amount })
+    }
+
+    /// Bid of the `bidder` proving their holdings with the `token_account`, for token-gated auctions
+    pub async fn bid_holding(&mut self, bidder: usize, amount: u64, token_account: Pubkey) -> bool {
+        let buyer = clone(&self.bidders[bidder]);
+        let mut ix = self.bid_instruction(bidder, amount);
+        ix.accounts.push(AccountMeta::new_readonly(token_account, false));
+
+        self.send(ix, &[&buyer]).await
+    }
+
+    /// Bid of the `bidder` on an auction which banned somebody, passing the ban list along
+    pub async fn bid_past_bans(&mut self, bidder: usize, amount: u64) -> bool {
+        let buyer = clone(&self.bidders[bidder]);
+        let mut ix = self.bid_instruction(bidder, amount);
+        ix.accounts.push(AccountMeta::new_readonly(pda(&[b"bans", self.state.as_ref()]), false));
+
+        self.send(ix, &[&buyer]).await
+    }
+
+    pub async fn end(&mut self) -> bool {
+        let state = self.state().await;
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Finish {
+            state: self.state,
+            initializer: seller.pubkey(),
+            beneficiary: state.beneficiary,
+            treasury: self.treasury,
+            max_bidder: state.max_bidder,
+            page: pda(&[b"page", state.namespace.as_ref(), &state.registry_page.to_le_bytes()]),
+            report: pda(&[b"report", self.state.as_ref()]),
+            result: pda(&[b"result", self.state.as_ref()]),
+            protocol: protocol(),
+            system_program: system_program::ID,
+        };
+
+        let mut ix = instruction(accounts, auction::instruction::EndAuction {});
+        ix.accounts.extend(self.royalty_accounts(&state.item_mint));
+
+        self.send(ix, &[&seller]).await
+    }
+
+    /// Permissionless settlement cranked by the `bidder`, who pays the rent of the report and the result
+    pub async fn settle(&mut self, bidder: usize) -> bool {
+        let state = self.state().await;
+        let cranker = clone(&self.bidders[bidder]);
+        let accounts = auction::accounts::Settle {
+            state: self.state,
+            beneficiary: state.beneficiary,
+            treasury: self.treasury,
+            max_bidder: state.max_bidder,
+            page: pda(&[b"page", state.namespace.as_ref(), &state.registry_page.to_le_bytes()]),
+            report: pda(&[b"report", self.state.as_ref()]),
+            result: pda(&[b"result", self.state.as_ref()]),
+            cranker: cranker.pubkey(),
+            protocol: protocol(),
+            system_program: system_program::ID,
+        };
+
+        let mut ix = instruction(accounts, auction::instruction::Settle {});
+        ix.accounts.extend(self.royalty_accounts(&state.item_mint));
+
+        self.send(ix, &[&cranker]).await
+    }
+
+    /// Metadata of the escrowed item and its creators, paid the royalties by every sale of the item
+    fn royalty_accounts(&self, item_mint: &Pubkey) -> Vec<AccountMeta> {
+        if *item_mint == Pubkey::default() {
+            return Vec::new();
+        }
+
+        let mut accounts = vec![AccountMeta::new_readonly(metadata(item_mint), false)];
+        accounts.extend(self.creators.iter().map(|&creator| AccountMeta::new(creator, false)));
+        accounts
+    }
+
+    pub async fn refund(&mut self, bidder: usize) -> bool {
+        let buyer = clone(&self.bidders[bidder]);
+        let ix = self.refund_instruction(bidder);
+
+        self.send(ix, &[&buyer]).await
+    }
+
+    /// Takes the escrow of an outbid `bidder` back while the auction runs
+    pub async fn withdraw_bid(&mut self, bidder: usize) -> bool {
+        let buyer = clone(&self.bidders[bidder]);
+        let accounts = auction::accounts::WithdrawBid {
+            state: self.state,
+            treasury: self.treasury,
+            buyer: buyer.pubkey(),
+            offer: pda(&[b"bid", self.state.as_ref(), buyer.pubkey().as_ref()]),
+            system_program: system_program::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::WithdrawBid {}), &[&buyer]).await
+    }
+
+    /// Refunds the `bidders` in one transaction signed by nobody but the context payer
+    pub async fn refund_many(&mut self, bidders: &[usize]) -> bool {
+        let accounts = auction::accounts::RefundMany { state: self.state, treasury: self.treasury };
+        let mut ix = instruction(accounts, auction::instruction::RefundMany {});
+        for bidder in bidders.iter().map(|&bidder| self.bidders[bidder].pubkey()) {
+            ix.accounts.push(AccountMeta::new(pda(&[b"bid", self.state.as_ref(), bidder.as_ref()]), false));
+            ix.accounts.push(AccountMeta::new(bidder, false));
+        }
+
+        self.send(ix, &[]).await
+    }
+
+    /// Lets the `keeper` finish the seller's auction with `crank_settlement`
+    pub async fn schedule_settlement(&mut self, keeper: Pubkey) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::ScheduleSettlement { keeper }), &[&seller]).await
+    }
+
+    /// Settlement cranked by the `keeper` bidder, refunding the `bidders` in the same transaction
+    pub async fn crank_settlement(&mut self, keeper: usize, bidders: &[usize]) -> bool {
+        let state = self.state().await;
+        let keeper = clone(&self.bidders[keeper]);
+        let accounts = auction::accounts::CrankSettlement {
+            state: self.state,
+            beneficiary: state.beneficiary,
+            treasury: self.treasury,
+            max_bidder: state.max_bidder,
+            page: pda(&[b"page", state.namespace.as_ref(), &state.registry_page.to_le_bytes()]),
+            report: pda(&[b"report", self.state.as_ref()]),
+            result: pda(&[b"result", self.state.as_ref()]),
+            keeper: keeper.pubkey(),
+            protocol: protocol(),
+            system_program: system_program::ID,
+        };
+        let mut ix = instruction(accounts, auction::instruction::CrankSettlement {});
+        for bidder in bidders.iter().map(|&bidder| self.bidders[bidder].pubkey()) {
+            ix.accounts.push(AccountMeta::new(pda(&[b"bid", self.state.as_ref(), bidder.as_ref()]), false));
+            ix.accounts.push(AccountMeta::new(bidder, false));
+        }
+
+        self.send(ix, &[&keeper]).await
+    }
+
+    /// Refunds the `bidder` in every one of the seller's `auctions` at once
+    pub async fn refund_all(&mut self, bidder: usize, auctions: &[Pubkey]) -> bool {
+        let buyer = clone(&self.bidders[bidder]);
+        let mut ix = instruction(auction::accounts::RefundAll { buyer: buyer.pubkey() }, auction::instruction::RefundAll {});
+        for state in auctions {
+            ix.accounts.push(AccountMeta::new(*state, false));
+            ix.accounts.push(AccountMeta::new(pda(&[b"bid", state.as_ref(), buyer.pubkey().as_ref()]), false));
+            ix.accounts.push(AccountMeta::new(pda(&[b"treasury", state.as_ref()]), false));
+        }
+
+        self.send(ix, &[&buyer]).await
+    }
+
+    /// Refund of the `bidder`, to be signed by them
+    pub fn refund_instruction(&self, bidder: usize) -> Instruction {
+        let buyer = self.bidders[bidder].pubkey();
+        let accounts = auction::accounts::Refund {
+            state: self.state,
+            treasury: self.treasury,
+            buyer,
+            offer: pda(&[b"bid", self.state.as_ref(), buyer.as_ref()]),
+            system_program: system_program::ID,
+        };
+
+        instruction(accounts, auction::instruction::Refund {})
+    }
+
+    /// The winning `bidder` collects their share of the surplus of a settled auction, closing their offer
+    pub async fn claim_surplus(&mut self, bidder: usize) -> bool {
+        let winner = clone(&self.bidders[bidder]);
+        let accounts = auction::accounts::ClaimSurplus {
+            state: self.state,
+            treasury: self.treasury,
+            max_bidder: winner.pubkey(),
+            offer: pda(&[b"bid", self.state.as_ref(), winner.pubkey().as_ref()]),
+        };
+
+        self.send(instruction(accounts, auction::instruction::ClaimSurplus {}), &[&winner]).await
+    }
+
+    /// Sweeps the unclaimed refund of the `bidder` to the protocol vault, cranked by the `cranker`
+    pub async fn sweep_expired_refund(&mut self, bidder: usize, cranker: usize) -> bool {
+        let namespace = self.state().await.namespace;
+        let cranker = clone(&self.bidders[cranker]);
+        let bidder = self.bidders[bidder].pubkey();
+        let accounts = auction::accounts::SweepExpiredRefund {
+            state: self.state,
+            treasury: self.treasury,
+            bidder,
+            offer: pda(&[b"bid", self.state.as_ref(), bidder.as_ref()]),
+            protocol_vault: pda(&[b"protocol", namespace.as_ref()]),
+            cranker: cranker.pubkey(),
+            system_program: system_program::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::SweepExpiredRefund {}), &[&cranker]).await
+    }
+
+    /// Bans the `bidder` from the seller's auction
+    pub async fn ban_bidder(&mut self, bidder: usize) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::BanBidder {
+            state: self.state,
+            ban_list: pda(&[b"bans", self.state.as_ref()]),
+            authority: seller.pubkey(),
+            system_program: system_program::ID,
+        };
+        let args = auction::instruction::BanBidder { bidder: self.bidders[bidder].pubkey() };
+
+        self.send(instruction(accounts, args), &[&seller]).await
+    }
+
+    /// Makes the seller's auction keep an audit log of up to `capacity` entries, passed along from then on
+    pub async fn create_audit_log(&mut self, capacity: u32) -> bool {
+        let seller = clone(&self.seller);
+        let audit_log = pda(&[b"audit", self.state.as_ref()]);
+        let accounts = auction::accounts::CreateAuditLog {
+            state: self.state,
+            audit_log,
+            initializer: seller.pubkey(),
+            system_program: system_program::ID,
+        };
+
+        if !self.send(instruction(accounts, auction::instruction::CreateAuditLog { capacity }), &[&seller]).await {
+            return false;
+        }
+        self.audit_log = Some(audit_log);
+        true
+    }
+
+    /// Charges the bidders of the seller's auction an `entry_fee` on their first bid, paid to the protocol vault or
+    /// to the seller
+    pub async fn set_entry_fee(&mut self, entry_fee: u64, to_protocol: bool) -> bool {
+        let namespace = self.state().await.namespace;
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        if !self.send(instruction(accounts, auction::instruction::SetEntryFee { entry_fee, to_protocol }), &[&seller]).await {
+            return false;
+        }
+        self.entry_fee_receiver = if to_protocol { pda(&[b"protocol", namespace.as_ref()]) } else { seller.pubkey() };
+        true
+    }
+
+    /// Caps the number of distinct bidders of the seller's auction, zero meaning no limit
+    pub async fn set_max_bidders(&mut self, max_bidders: u32) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::SetMaxBidders { max_bidders }), &[&seller]).await
+    }
+
+    /// Gives the losing bidders of the seller's auction `refund_expiry` seconds to claim their refunds
+    pub async fn set_refund_expiry(&mut self, refund_expiry: i64) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::SetRefundExpiry { refund_expiry }), &[&seller]).await
+    }
+
+    /// Keeps `penalty_bps` / 10 000 of every withdrawn escrow of the seller's auction in its treasury
+    pub async fn set_withdrawal_penalty(&mut self, penalty_bps: u16) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::SetWithdrawalPenalty { penalty_bps }), &[&seller]).await
+    }
+
+    /// Shares the surplus of the treasury of the seller's auction with its bidders
+    pub async fn set_surplus_sharing(&mut self, share_surplus: bool) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::SetSurplusSharing { share_surplus }), &[&seller]).await
+    }
+
+    /// Draws the end of the seller's auction from its last `window` seconds with the `vrf` account
+    pub async fn set_candle(&mut self, window: i64, vrf: Pubkey) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        let mut ix = instruction(accounts, auction::instruction::SetCandle { window, vrf });
+        ix.accounts.push(AccountMeta::new_readonly(vrf, false));
+
+        self.send(ix, &[&seller]).await
+    }
+
+    /// Permissionless draw of the end of the candle auction from its VRF account
+    pub async fn resolve_candle(&mut self) -> bool {
+        let state = self.state().await;
+        let accounts = auction::accounts::ResolveCandle { state: self.state, vrf: state.candle_vrf };
+
+        self.send(instruction(accounts, auction::instruction::ResolveCandle {}), &[]).await
+    }
+
+    /// Sets a reserve of `reserve_cents` US cents on the seller's auction, converted with the Pyth `price_feed`
+    pub async fn set_usd_reserve(&mut self, price_feed: Pubkey, reserve_cents: u64) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        let ix = instruction(accounts, auction::instruction::SetUsdReserve { price_feed, reserve_cents });
+
+        if !self.send(ix, &[&seller]).await {
+            return false;
+        }
+        self.price_feed = Some(price_feed);
+        true
+    }
+
+    /// Lets the `winners` highest bids of the seller's auction all win
+    pub async fn set_winners(&mut self, winners: u8) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::SetWinners { winners }), &[&seller]).await
+    }
+
+    /// Makes every bid of the seller's auction pay, nobody is refunded
+    pub async fn set_all_pay(&mut self, all_pay: bool) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::SetAllPay { all_pay }), &[&seller]).await
+    }
+
+    /// Makes the seller's auction a penny auction
+    pub async fn set_penny(&mut self, bid_fee: u64, step: u64, countdown: i64) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::SetPenny { bid_fee, step, countdown }), &[&seller]).await
+    }
+
+    /// Lets the bidders of the seller's auction bid by proxy
+    pub async fn set_proxy_bidding(&mut self, proxy_bidding: bool) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::SetProxyBidding { proxy_bidding }), &[&seller]).await
+    }
+
+    /// Makes the seller's auction a raffle drawn by the `vrf` account
+    pub async fn set_raffle(&mut self, ticket_price: u64, vrf: Pubkey) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        let mut ix = instruction(accounts, auction::instruction::SetRaffle { ticket_price, vrf });
+        ix.accounts.push(AccountMeta::new_readonly(vrf, false));
+
+        self.send(ix, &[&seller]).await
+    }
+
+    /// Permissionless draw of the raffle from its VRF account, passing the offer of the `holder` of the drawn ticket
+    pub async fn resolve_raffle(&mut self, holder: usize) -> bool {
+        let state = self.state().await;
+        let holder = self.bidders[holder].pubkey();
+        let accounts = auction::accounts::ResolveRaffle {
+            state: self.state,
+            vrf: state.raffle_vrf,
+            holder,
+            offer: pda(&[b"bid", self.state.as_ref(), holder.as_ref()]),
+        };
+
+        self.send(instruction(accounts, auction::instruction::ResolveRaffle {}), &[]).await
+    }
+
+    /// Gates the bids of the seller's auction by holding `amount` tokens of `gate`, or an NFT of the collection
+    pub async fn set_token_gate(&mut self, gate: Pubkey, amount: u64, collection: bool) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::Configure { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::SetTokenGate { gate, amount, collection }), &[&seller]).await
+    }
+
+    /// Escrows `amount` tokens of the `item_mint` from the `seller_tokens` of the seller
+    pub async fn deposit_item(&mut self, item_mint: Pubkey, seller_tokens: Pubkey, amount: u64) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::DepositItem {
+            state: self.state,
+            initializer: seller.pubkey(),
+            item_mint,
+            seller_tokens,
+            escrow: pda(&[b"item", self.state.as_ref()]),
+            item_authority: pda(&[b"authority", self.state.as_ref()]),
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::DepositItem { amount }), &[&seller]).await
+    }
+
+    /// Purchase of the next unit of a GDA by the `bidder` into the `buyer_tokens`, paying `max_price` at most
+    pub async fn purchase(&mut self, bidder: usize, buyer_tokens: Pubkey, max_price: u64) -> bool {
+        let state = self.state().await;
+        let buyer = clone(&self.bidders[bidder]);
+        let accounts = auction::accounts::Purchase {
+            state: self.state,
+            beneficiary: state.beneficiary,
+            buyer: buyer.pubkey(),
+            item_mint: state.item_mint,
+            buyer_tokens,
+            escrow: pda(&[b"item", self.state.as_ref()]),
+            item_authority: pda(&[b"authority", self.state.as_ref()]),
+            token_program: anchor_spl::token::ID,
+            protocol: protocol(),
+            system_program: system_program::ID,
+        };
+
+        let mut ix = instruction(accounts, auction::instruction::Purchase { max_price });
+        ix.accounts.extend(self.royalty_accounts(&state.item_mint));
+
+        self.send(ix, &[&buyer]).await
+    }
+
+    /// Releases the escrowed item to the `winner_tokens` of the `bidder`
+    pub async fn claim_item(&mut self, bidder: usize, winner_tokens: Pubkey) -> bool {
+        let bidder = clone(&self.bidders[bidder]);
+        let accounts = auction::accounts::ClaimItem {
+            state: self.state,
+            max_bidder: bidder.pubkey(),
+            winner_tokens,
+            escrow: pda(&[b"item", self.state.as_ref()]),
+            item_authority: pda(&[b"authority", self.state.as_ref()]),
+            token_program: anchor_spl::token::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::ClaimItem {}), &[&bidder]).await
+    }
+
+    /// Takes the unclaimed item back to the `seller_tokens` of the seller
+    pub async fn recover_item(&mut self, seller_tokens: Pubkey) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::RecoverItem {
+            state: self.state,
+            initializer: seller.pubkey(),
+            seller_tokens,
+            escrow: pda(&[b"item", self.state.as_ref()]),
+            item_authority: pda(&[b"authority", self.state.as_ref()]),
+            token_program: anchor_spl::token::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::RecoverItem {}), &[&seller]).await
+    }
+
+    /// Makes the seller's auction take bids in `payment_mint` tokens
+    pub async fn set_payment_mint(&mut self, payment_mint: Pubkey) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::SetPaymentMint {
+            state: self.state,
+            treasury: self.treasury,
+            initializer: seller.pubkey(),
+            payment_mint,
+            payment_treasury: pda(&[b"payment", self.state.as_ref()]),
+            authority: pda(&[b"authority", self.state.as_ref()]),
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::SetPaymentMint {}), &[&seller]).await
+    }
+
+    /// Creates the next auction of the seller with the parameters of the driver's auction, which the
+    /// driver follows from then on
+    pub async fn clone_auction(&mut self, auction_duration: i64) -> bool {
+        let seller = clone(&self.seller);
+        let namespace = self.state().await.namespace;
+        let auction = self.next_auction(namespace).await;
+        let (state, treasury) = (auction.state, auction.treasury);
+        let accounts = auction::accounts::CloneAuction { source: self.state, auction };
+
+        if !self.send(instruction(accounts, auction::instruction::CloneAuction { auction_duration }), &[&seller]).await {
+            return false;
+        }
+        self.state = state;
+        self.treasury = treasury;
+        true
+    }
+
+    /// Moves the listed `auctions` of the page `index` of the `namespace` to the tail page, cranked by the seller
+    pub async fn compact_page(&mut self, namespace: Pubkey, index: u32, auctions: &[Pubkey]) -> bool {
+        let seller = clone(&self.seller);
+        let registry = self.registry(namespace).await;
+        let accounts = auction::accounts::CompactPage {
+            registry: pda(&[b"registry", namespace.as_ref()]),
+            page: pda(&[b"page", namespace.as_ref(), &index.to_le_bytes()]),
+            tail: pda(&[b"page", namespace.as_ref(), &registry.tail.to_le_bytes()]),
+            cranker: seller.pubkey(),
+            system_program: system_program::ID,
+        };
+        let mut ix = instruction(accounts, auction::instruction::CompactPage {});
+        ix.accounts.extend(auctions.iter().map(|auction| AccountMeta::new(*auction, false)));
+
+        self.send(ix, &[&seller]).await
+    }
+
+    pub async fn publish(&mut self) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::EditDraft { state: self.state, initializer: seller.pubkey() };
+
+        self.send(instruction(accounts, auction::instruction::Publish {}), &[&seller]).await
+    }
+
+    /// Closes the auction of the seller after it ended without bids
+    pub async fn finalize_without_bids(&mut self) -> bool {
+        let state = self.state().await;
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::FinalizeWithoutBids {
+            state: self.state,
+            treasury: self.treasury,
+            page: pda(&[b"page", state.namespace.as_ref(), &state.registry_page.to_le_bytes()]),
+            initializer: seller.pubkey(),
+        };
+
+        self.send(instruction(accounts, auction::instruction::FinalizeWithoutBids {}), &[&seller]).await
+    }
+
+    /// Closes the settled auction of the seller once every bid is refunded
+    pub async fn close_auction(&mut self) -> bool {
+        let seller = clone(&self.seller);
+        let accounts = auction::accounts::CloseAuction {
+            state: self.state,
+            treasury: self.treasury,
+            initializer: seller.pubkey(),
+        };
+
+        self.send(instruction(accounts, auction::instruction::CloseAuction {}), &[&seller]).await
+    }
+
+    /// Copies the auction of an older layout version out to its migration account, paid by the context payer
+    pub async fn migrate_state(&mut self) -> bool {
+        let accounts = auction::accounts::MigrateState {
+            state: self.state,
+            migration: pda(&[b"migration", self.state.as_ref()]),
+            payer: self.ctx.payer.pubkey(),
+            system_program: system_program::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::MigrateState {}), &[]).await
+    }
+
+    /// Recreates the migrated auction with the current layout
+    pub async fn restore_state(&mut self) -> bool {
+        let accounts = auction::accounts::RestoreState {
+            migration: pda(&[b"migration", self.state.as_ref()]),
+            state: self.state,
+            payer: self.ctx.payer.pubkey(),
+            system_program: system_program::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::RestoreState {}), &[]).await
+    }
+
+    /// Recreates the migrated legacy auction, which lives at the address of the `legacy` keypair
+    pub async fn restore_legacy_auction(&mut self, legacy: &Keypair) -> bool {
+        let accounts = auction::accounts::RestoreState {
+            migration: pda(&[b"migration", self.state.as_ref()]),
+            state: self.state,
+            payer: self.ctx.payer.pubkey(),
+            system_program: system_program::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::RestoreState {}), &[legacy]).await
+    }
+
+    /// Copies the offer of the `bidder` of an older layout version out to its migration account
+    pub async fn migrate_offer(&mut self, bidder: usize) -> bool {
+        let bidder = self.bidders[bidder].pubkey();
+        let offer = pda(&[b"bid", self.state.as_ref(), bidder.as_ref()]);
+        let accounts = auction::accounts::MigrateOffer {
+            state: self.state,
+            bidder,
+            offer,
+            migration: pda(&[b"migration", offer.as_ref()]),
+            payer: self.ctx.payer.pubkey(),
+            system_program: system_program::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::MigrateOffer {}), &[]).await
+    }
+
+    /// Recreates the migrated offer of the `bidder` with the current layout
+    pub async fn restore_offer(&mut self, bidder: usize) -> bool {
+        let bidder = self.bidders[bidder].pubkey();
+        let offer = pda(&[b"bid", self.state.as_ref(), bidder.as_ref()]);
+        let accounts = auction::accounts::RestoreOffer {
+            state: self.state,
+            bidder,
+            migration: pda(&[b"migration", offer.as_ref()]),
+            offer,
+            payer: self.ctx.payer.pubkey(),
+            system_program: system_program::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::RestoreOffer {}), &[]).await
+    }
+
+    /// Copies the auction of the legacy layout out to its migration account
+    pub async fn migrate_legacy_auction(&mut self) -> bool {
+        let accounts = auction::accounts::MigrateLegacyAuction {
+            state: self.state,
+            treasury: self.treasury,
+            migration: pda(&[b"migration", self.state.as_ref()]),
+            payer: self.ctx.payer.pubkey(),
+            system_program: system_program::ID,
+        };
+
+        self.send(instruction(accounts, auction::instruction::MigrateLegacyAuction {}), &[]).await
+    }
+
+    pub async fn warp(&mut self, seconds: i64) {
+        let mut clock = self.clock().await;
+        clock.unix_timestamp += seconds;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    pub async fn clock(&mut self) -> Clock {
+        self.ctx.banks_client.get_sysvar::<Clock>().await.expect("clock is readable")
+    }
+
+    pub async fn state(&mut self) -> State {
+        let account = self.account(self.state).await.expect("auction exists");
+        State::try_deserialize(&mut &account.data[..]).expect("auction deserializes")
+    }
+
+    /// Entries of the audit log of the auction, oldest first
+    pub async fn audit_entries(&mut self) -> Vec<AuditEntry> {
+        let account = self.account(pda(&[b"audit", self.state.as_ref()])).await.expect("audit log exists");
+        AuditLog::try_deserialize(&mut &account.data[..]).expect("audit log deserializes").entries
+    }
+
+    /// Registry of the auctions listed in the `namespace`
+    pub async fn registry(&mut self, namespace: Pubkey) -> Registry {
+        let account = self.account(pda(&[b"registry", namespace.as_ref()])).await.expect("registry exists");
+        Registry::try_deserialize(&mut &account.data[..]).expect("registry deserializes")
+    }
+
+    /// Id the next auction of the seller in the `namespace` gets
+    pub async fn next_auction_id(&mut self, namespace: Pubkey) -> u64 {
+        let counter = pda(&[b"counter", namespace.as_ref(), self.seller.pubkey().as_ref()]);
+        match self.account(counter).await {
+            Some(account) => Counter::try_deserialize(&mut &account.data[..]).expect("counter deserializes").next_id,
+            None => 0,
+        }
+    }
+
+    /// Page `index` of the registry of the `namespace`, `None` once it is closed
+    pub async fn page(&mut self, namespace: Pubkey, index: u32) -> Option<RegistryPage> {
+        let account = self.account(pda(&[b"page", namespace.as_ref(), &index.to_le_bytes()])).await?;
+        Some(RegistryPage::try_deserialize(&mut &account.data[..]).expect("page deserializes"))
+    }
+
+    /// Status of the auction as the program sees it now
+    pub async fn status(&mut self) -> AuctionStatus {
+        let now = self.clock().await.unix_timestamp;
+        self.state().await.status(now)
+    }
+
+    /// Lamports of the wallets and of every account the auction created, fees being paid from elsewhere
+    pub async fn total_lamports(&mut self) -> u64 {
+        let state = self.state().await;
+        let seller = self.seller.pubkey();
+        let mut keys = vec![
+            seller,
+            self.state,
+            self.treasury,
+            pda(&[b"counter", state.namespace.as_ref(), seller.as_ref()]),
+            pda(&[b"registry", state.namespace.as_ref()]),
+            pda(&[b"page", state.namespace.as_ref(), &state.registry_page.to_le_bytes()]),
+            pda(&[b"report", self.state.as_ref()]),
+            pda(&[b"result", self.state.as_ref()]),
+            pda(&[b"audit", self.state.as_ref()]),
+            pda(&[b"fees"]),
+        ];
+        for bidder in self.bidders.iter().map(|bidder| bidder.pubkey()) {
+            keys.push(bidder);
+            keys.push(pda(&[b"bid", self.state.as_ref(), bidder.as_ref()]));
+        }
+
+        let mut total = 0;
+        for key in keys {
+            total += self.lamports(key).await;
+        }
+        total
+    }
+
+    pub async fn account(&mut self, key: Pubkey) -> Option<Account> {
+        self.ctx.banks_client.get_account(key).await.expect("account is readable")
+    }
+
+    pub async fn observe(&mut self) -> Observed {
+        let state = self.state().await;
+
+        let mut balances = [0; BIDDERS];
+        for (balance, bidder) in balances.iter_mut().zip(self.bidders.iter().map(|bidder| bidder.pubkey()).collect::<Vec<_>>()) {
+            *balance = self.lamports(bidder).await as i128 - FUNDS as i128;
+        }
+
+        // The seller pays for the report and the result on settlement, which the model leaves out
+        let mut seller = self.lamports(self.seller.pubkey()).await as i128 - self.seller_funds as i128;
+        let open = state.status != AuctionStatus::Settled;
+        if !open {
+            seller += (rent(AuctionReport::size()) + rent(AuctionResult::size())) as i128;
+        }
+
+        Observed {
+            max_price: state.max_price,
+            max_bidder: self.bidders.iter().position(|bidder| bidder.pubkey() == state.max_bidder),
+            open,
+            balances,
+            seller,
+            escrow: self.lamports(self.treasury).await - rent(0),
+        }
+    }
+
+    pub async fn lamports(&mut self, key: Pubkey) -> u64 {
+        self.account(key).await.map(|account| account.lamports).unwrap_or(0)
+    }
+
+    /// Sends the instruction paid by the context payer, so wallets only change by what the program moves
+    pub async fn send(&mut self, ix: Instruction, signers: &[&Keypair]) -> bool {
+        self.send_all(vec![ix], signers).await
+    }
+
+    /// Sends the instructions in a single transaction paid by the context payer
+    pub async fn send_all(&mut self, mut ixs: Vec<Instruction>, signers: &[&Keypair]) -> bool {
+        if let Some(audit_log) = self.audit_log {
+            for ix in ixs.iter_mut().filter(|ix| ix.program_id == auction::ID) {
+                ix.accounts.push(AccountMeta::new(audit_log, false));
+            }
+        }
+        if let Some(price_feed) = self.price_feed {
+            for ix in ixs.iter_mut().filter(|ix| ix.program_id == auction::ID) {
+                ix.accounts.push(AccountMeta::new_readonly(price_feed, false));
+            }
+        }
+
+        self.nonce += 1;
+        let payer = clone(&self.ctx.payer);
+        ixs.push(system_instruction::transfer(&payer.pubkey(), &self.sink, self.nonce));
+
+        let mut all: Vec<&Keypair> = vec![&payer];
+        all.extend_from_slice(signers);
+        let tx = Transaction::new_signed_with_payer(&ixs, Some(&payer.pubkey()), &all, self.ctx.last_blockhash);
+
+        self.ctx.banks_client.process_transaction(tx).await.is_ok()
+    }
+}
+
+/// Runs the operations against both the program and the model, describing the first divergence
+pub async fn differential(duration: i64, initial_price: u64, ops: &[Op]) -> Result<(), String> {
+    let (mut driver, mut model) = Driver::start(duration, initial_price).await;
+
+    for (i, op) in ops.iter().enumerate() {
+        let expected = model.apply(*op);
+        let accepted = driver.apply(*op).await;
+        if accepted != expected.is_ok() {
+            return Err(format!("step {} {:?}: program accepted {}, model returned {:?}", i, op, accepted, expected));
+        }
+
+        let observed = driver.observe().await;
+        let modelled = Observed {
+            max_price: model.max_price,
+            max_bidder: model.max_bidder,
+            open: model.open,
+            balances: model.balances,
+            seller: model.seller,
+            escrow: model.escrow,
+        };
+        if observed != modelled {
+            return Err(format!("step {} {:?}: program {:?}, model {:?}", i, op, observed, modelled));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &auction::ID).0
+}
+
+/// Metaplex metadata address of the `mint`
+pub fn metadata(mint: &Pubkey) -> Pubkey {
+    let program = auction::token_metadata::ID;
+    Pubkey::find_program_address(&[b"metadata", program.as_ref(), mint.as_ref()], &program).0
+}
+
+/// Program-wide config and fee vault every sale pays the protocol fee into
+pub fn protocol() -> auction::accounts::ProtocolFee {
+    auction::accounts::ProtocolFee { config: pda(&[b"global"]), fee_vault: pda(&[b"fees"]) }
+}
+
+/// Rent-exempt account of the program holding the Anchor `account` of `size` bytes
+pub fn program_account(account: &impl AccountSerialize, size: usize) -> Account {
+    let mut data = Vec::with_capacity(8 + size);
+    account.try_serialize(&mut data).expect("account serializes");
+    data.resize(8 + size, 0);
+
+    Account { lamports: rent(size), data, owner: auction::ID, executable: false, rent_epoch: 0 }
+}
+
+pub fn rent(size: usize) -> u64 {
+    Rent::default().minimum_balance(if size == 0 { 0 } else { 8 + size })
+}
+
+pub fn instruction(accounts: impl ToAccountMetas, args: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: auction::ID,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+pub fn clone(keypair: &Keypair) -> Keypair {
+    Keypair::from_bytes(&keypair.to_bytes()).expect("keypair round-trips")
+}
@@ -0,0 +1,9 @@
+//! Checks the auction program against a reference model of its rules
+
+pub mod driver;
+pub mod model;
+pub mod scenario;
+
+pub use driver::{differential, Driver, Observed};
+pub use model::{Model, Op, Rejection, BIDDERS};
+pub use scenario::{Scenario, ScenarioBuilder};
@@ -0,0 +1,138 @@
+//! Reference implementation of the English auction rules, kept deliberately naive so it is
+//! obviously right: plain integers, no accounts, no rent beyond what bidders pay for offers.
+
+use arbitrary::Arbitrary;
+
+/// Wallets bidding in a modelled auction
+pub const BIDDERS: usize = 4;
+
+#[derive(Arbitrary, Clone, Copy, Debug)]
+pub enum Op {
+    /// Bid `raise` lamports above the current price
+    Bid { bidder: u8, raise: u16 },
+    /// Move the clock forward
+    Warp { seconds: u16 },
+    End,
+    Refund { bidder: u8 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    Closed,
+    BidTooLow,
+    AlreadyHighestBidder,
+    Open,
+    AlreadySettled,
+    NoWinner,
+    NoOffer,
+    WinnerRefund,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Model {
+    pub now: i64,
+    pub end_time: i64,
+    pub max_price: u64,
+    pub max_bidder: Option<usize>,
+    pub open: bool,
+    /// Escrowed amount of every bidder, `None` once the offer is closed or before the first bid
+    pub offers: [Option<u64>; BIDDERS],
+    /// Change of every bidder's wallet, including the rent of their offer
+    pub balances: [i128; BIDDERS],
+    /// Change of the seller's wallet, not counting the settlement report
+    pub seller: i128,
+    /// Lamports in the treasury on top of its rent
+    pub escrow: u64,
+    offer_rent: u64,
+}
+
+impl Model {
+    pub fn new(now: i64, duration: i64, initial_price: u64, offer_rent: u64) -> Self {
+        Model {
+            now,
+            end_time: now + duration,
+            max_price: initial_price,
+            max_bidder: None,
+            open: true,
+            offers: [None; BIDDERS],
+            balances: [0; BIDDERS],
+            seller: 0,
+            escrow: 0,
+            offer_rent,
+        }
+    }
+
+    pub fn apply(&mut self, op: Op) -> Result<(), Rejection> {
+        match op {
+            Op::Bid { bidder, raise } => self.bid(bidder as usize % BIDDERS, raise as u64),
+            Op::Warp { seconds } => {
+                self.now += seconds as i64;
+                Ok(())
+            }
+            Op::End => self.end(),
+            Op::Refund { bidder } => self.refund(bidder as usize % BIDDERS),
+        }
+    }
+
+    fn bid(&mut self, bidder: usize, raise: u64) -> Result<(), Rejection> {
+        if self.now >= self.end_time {
+            return Err(Rejection::Closed);
+        }
+        if raise == 0 {
+            return Err(Rejection::BidTooLow);
+        }
+        if self.max_bidder == Some(bidder) {
+            return Err(Rejection::AlreadyHighestBidder);
+        }
+
+        let amount = self.max_price + raise;
+        let previous = match self.offers[bidder] {
+            Some(previous) => previous,
+            None => {
+                self.balances[bidder] -= self.offer_rent as i128;
+                0
+            }
+        };
+
+        // Only the difference to the previous bid is paid
+        self.balances[bidder] -= (amount - previous) as i128;
+        self.escrow += amount - previous;
+        self.offers[bidder] = Some(amount);
+        self.max_price = amount;
+        self.max_bidder = Some(bidder);
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<(), Rejection> {
+        if !self.open {
+            return Err(Rejection::AlreadySettled);
+        }
+        if self.now < self.end_time {
+            return Err(Rejection::Open);
+        }
+        if self.max_bidder.is_none() {
+            return Err(Rejection::NoWinner);
+        }
+
+        self.open = false;
+        self.escrow -= self.max_price;
+        self.seller += self.max_price as i128;
+        Ok(())
+    }
+
+    fn refund(&mut self, bidder: usize) -> Result<(), Rejection> {
+        let amount = self.offers[bidder].ok_or(Rejection::NoOffer)?;
+        if self.open {
+            return Err(Rejection::Open);
+        }
+        if self.max_bidder == Some(bidder) {
+            return Err(Rejection::WinnerRefund);
+        }
+
+        // The bid and the rent of the closed offer go back
+        self.balances[bidder] += (amount + self.offer_rent) as i128;
+        self.escrow -= amount;
+        self.offers[bidder] = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,484 @@
+//! Short scripts of an auction's life on top of the driver, so a new variant is tested by what happens
+//! to it instead of by how its accounts are set up:
+//!
+//! ```ignore
+//! let mut scenario = Scenario::english(60, 100).bidders(2).start().await;
+//! scenario.bid(0, 110).await;
+//! scenario.advance_time(60).await;
+//! scenario.end().await;
+//! scenario.assert_lamports_conserved().await;
+//! ```
+
+use auction::AuctionStatus;
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::driver::{rent, Creation, Driver};
+use crate::model::BIDDERS;
+
+/// Auction a scenario starts with, see `Scenario::english`
+pub struct ScenarioBuilder {
+    program_test: Option<ProgramTest>,
+    bidders: usize,
+    duration: i64,
+    initial_price: u64,
+    creation: Creation,
+}
+
+impl ScenarioBuilder {
+    /// Number of funded bidders, `BIDDERS` by default
+    pub fn bidders(mut self, bidders: usize) -> Self {
+        self.bidders = bidders;
+        self
+    }
+
+    /// Creates the auction as a draft, to be published by the script
+    pub fn draft(mut self) -> Self {
+        self.creation = Creation::Draft;
+        self
+    }
+
+    /// Runs on a preconfigured `ProgramTest`, e.g. the BPF build with a compute budget
+    pub fn program_test(mut self, program_test: ProgramTest) -> Self {
+        self.program_test = Some(program_test);
+        self
+    }
+
+    pub async fn start(self) -> Scenario {
+        let program_test = self.program_test
+            .unwrap_or_else(|| ProgramTest::new("auction", auction::ID, processor!(auction::entry)));
+        let (mut driver, _model) =
+            Driver::create_with(program_test, self.bidders, self.duration, self.initial_price, self.creation).await;
+        let total = driver.total_lamports().await;
+
+        Scenario { driver, total }
+    }
+}
+
+/// A running auction driven step by step. Steps expected to succeed panic otherwise,
+/// the `try_` ones return whether the program accepted them.
+pub struct Scenario {
+    pub driver: Driver,
+    /// Lamports of the wallets and the auction accounts once the auction is created
+    total: u64,
+}
+
+impl Scenario {
+    /// English auction of `duration` seconds starting at `initial_price`
+    pub fn english(duration: i64, initial_price: u64) -> ScenarioBuilder {
+        ScenarioBuilder {
+            program_test: None,
+            bidders: BIDDERS,
+            duration,
+            initial_price,
+            creation: Creation::Live,
+        }
+    }
+
+    /// Reverse auction of `duration` seconds, the seller buying with a `budget` and the bidders offering to deliver
+    pub fn reverse(duration: i64, budget: u64) -> ScenarioBuilder {
+        ScenarioBuilder {
+            program_test: None,
+            bidders: BIDDERS,
+            duration,
+            initial_price: budget,
+            creation: Creation::Reverse,
+        }
+    }
+
+    /// Gradual Dutch Auction of `duration` seconds, its next unit costing `initial_price * scale^sold * decay^elapsed`
+    pub fn gda(duration: i64, initial_price: u64, scale: u64, decay: u64) -> ScenarioBuilder {
+        ScenarioBuilder {
+            program_test: None,
+            bidders: BIDDERS,
+            duration,
+            initial_price,
+            creation: Creation::Gda { scale, decay },
+        }
+    }
+
+    /// English auction of `duration` seconds starting at the EMA of the Pyth `price_feed`, clamped between
+    /// `min_price` and `max_price`. The feed is added to the `program_test` of the builder.
+    pub fn oracle(duration: i64, price_feed: Pubkey, min_price: u64, max_price: u64) -> ScenarioBuilder {
+        ScenarioBuilder {
+            program_test: None,
+            bidders: BIDDERS,
+            duration,
+            initial_price: min_price,
+            creation: Creation::Oracle { price_feed, min_price, max_price },
+        }
+    }
+
+    /// Moves the cluster clock forward, the slot stays
+    pub async fn advance_time(&mut self, seconds: i64) {
+        self.driver.warp(seconds).await;
+    }
+
+    /// Moves the bank `slots` slots forward, e.g. for slot based deadlines
+    pub async fn advance_slots(&mut self, slots: u64) {
+        let slot = self.driver.clock().await.slot;
+        self.driver.ctx.warp_to_slot(slot + slots).expect("slot is in the future");
+    }
+
+    /// Moves the clock to the end of every phase of the auction
+    pub async fn advance_to_end(&mut self) {
+        let now = self.driver.clock().await.unix_timestamp;
+        let closes_at = self.driver.state().await.closes_at();
+        if closes_at > now {
+            self.advance_time(closes_at - now).await;
+        }
+    }
+
+    pub async fn bid(&mut self, bidder: usize, amount: u64) {
+        assert!(self.try_bid(bidder, amount).await, "bid of {} by bidder {} is accepted", amount, bidder);
+    }
+
+    pub async fn try_bid(&mut self, bidder: usize, amount: u64) -> bool {
+        self.driver.bid(bidder, amount).await
+    }
+
+    /// Bid on a token-gated auction, the `bidder` proving their holdings with the `token_account`
+    pub async fn try_bid_holding(&mut self, bidder: usize, amount: u64, token_account: Pubkey) -> bool {
+        self.driver.bid_holding(bidder, amount, token_account).await
+    }
+
+    /// Bid on an auction which banned somebody, checked against its ban list
+    pub async fn try_bid_past_bans(&mut self, bidder: usize, amount: u64) -> bool {
+        self.driver.bid_past_bans(bidder, amount).await
+    }
+
+    /// Raises the bid of the leading `bidder` to `amount`
+    /// Purchase of `count` raffle tickets by the `bidder`
+    pub async fn buy_tickets(&mut self, bidder: usize, count: u64) {
+        assert!(self.try_buy_tickets(bidder, count).await, "{} tickets of bidder {} are sold", count, bidder);
+    }
+
+    pub async fn try_buy_tickets(&mut self, bidder: usize, count: u64) -> bool {
+        self.driver.buy_tickets(bidder, count).await
+    }
+
+    /// Proxy bid of the `bidder` up to `max_amount`
+    pub async fn proxy_bid(&mut self, bidder: usize, max_amount: u64) {
+        assert!(self.try_proxy_bid(bidder, max_amount).await, "proxy bid up to {} by bidder {} is accepted", max_amount, bidder);
+    }
+
+    pub async fn try_proxy_bid(&mut self, bidder: usize, max_amount: u64) -> bool {
+        self.driver.proxy_bid(bidder, max_amount).await
+    }
+
+    /// Offer of the `bidder` to deliver for `amount` in a reverse auction
+    pub async fn reverse_bid(&mut self, bidder: usize, amount: u64) {
+        assert!(self.try_reverse_bid(bidder, amount).await, "offer of {} by bidder {} is accepted", amount, bidder);
+    }
+
+    pub async fn try_reverse_bid(&mut self, bidder: usize, amount: u64) -> bool {
+        self.driver.reverse_bid(bidder, amount).await
+    }
+
+    pub async fn raise(&mut self, bidder: usize, amount: u64) {
+        assert!(self.try_raise(bidder, amount).await, "raise to {} by bidder {} is accepted", amount, bidder);
+    }
+
+    pub async fn try_raise(&mut self, bidder: usize, amount: u64) -> bool {
+        self.driver.raise(bidder, amount).await
+    }
+
+    pub async fn end(&mut self) {
+        assert!(self.try_end().await, "auction is settled");
+    }
+
+    pub async fn try_end(&mut self) -> bool {
+        self.driver.end().await
+    }
+
+    /// Settles without the seller, cranked by the `bidder`
+    pub async fn settle(&mut self, bidder: usize) {
+        assert!(self.try_settle(bidder).await, "auction is settled by bidder {}", bidder);
+    }
+
+    pub async fn try_settle(&mut self, bidder: usize) -> bool {
+        self.driver.settle(bidder).await
+    }
+
+    pub async fn refund(&mut self, bidder: usize) {
+        assert!(self.try_refund(bidder).await, "bidder {} is refunded", bidder);
+    }
+
+    pub async fn try_refund(&mut self, bidder: usize) -> bool {
+        self.driver.refund(bidder).await
+    }
+
+    /// Withdraws the bid of an outbid `bidder` before the end
+    pub async fn withdraw_bid(&mut self, bidder: usize) {
+        assert!(self.try_withdraw_bid(bidder).await, "outbid bidder {} withdraws", bidder);
+    }
+
+    pub async fn try_withdraw_bid(&mut self, bidder: usize) -> bool {
+        self.driver.withdraw_bid(bidder).await
+    }
+
+    /// Refunds the `bidder` in all the `auctions` at once
+    pub async fn refund_all(&mut self, bidder: usize, auctions: &[Pubkey]) {
+        assert!(self.try_refund_all(bidder, auctions).await, "bidder {} is refunded everywhere", bidder);
+    }
+
+    pub async fn try_refund_all(&mut self, bidder: usize, auctions: &[Pubkey]) -> bool {
+        self.driver.refund_all(bidder, auctions).await
+    }
+
+    /// Refunds the `bidders` at once, without their signatures
+    pub async fn refund_many(&mut self, bidders: &[usize]) {
+        assert!(self.try_refund_many(bidders).await, "bidders {:?} are refunded", bidders);
+    }
+
+    pub async fn try_refund_many(&mut self, bidders: &[usize]) -> bool {
+        self.driver.refund_many(bidders).await
+    }
+
+    /// Lets the `keeper` finish the auction after its end
+    pub async fn schedule_settlement(&mut self, keeper: Pubkey) {
+        assert!(self.driver.schedule_settlement(keeper).await, "settlement is scheduled");
+    }
+
+    /// Settles and refunds the `bidders`, cranked by the `keeper` bidder
+    pub async fn crank_settlement(&mut self, keeper: usize, bidders: &[usize]) {
+        assert!(self.try_crank_settlement(keeper, bidders).await, "bidder {} cranks the settlement", keeper);
+    }
+
+    pub async fn try_crank_settlement(&mut self, keeper: usize, bidders: &[usize]) -> bool {
+        self.driver.crank_settlement(keeper, bidders).await
+    }
+
+    /// The winning `bidder` collects their share of the surplus
+    pub async fn claim_surplus(&mut self, bidder: usize) {
+        assert!(self.driver.claim_surplus(bidder).await, "bidder {} claims the surplus", bidder);
+    }
+
+    /// Sweeps the unclaimed refund of the `bidder` to the protocol vault, cranked by another bidder
+    pub async fn sweep(&mut self, bidder: usize, cranker: usize) {
+        assert!(self.try_sweep(bidder, cranker).await, "refund of bidder {} is swept", bidder);
+    }
+
+    pub async fn try_sweep(&mut self, bidder: usize, cranker: usize) -> bool {
+        self.driver.sweep_expired_refund(bidder, cranker).await
+    }
+
+    /// Bans the `bidder` from bidding again, by the seller
+    pub async fn ban_bidder(&mut self, bidder: usize) {
+        assert!(self.driver.ban_bidder(bidder).await, "bidder {} is banned", bidder);
+    }
+
+    /// Escrows `amount` tokens of the `item_mint` from the `seller_tokens`
+    pub async fn deposit_item(&mut self, item_mint: Pubkey, seller_tokens: Pubkey, amount: u64) {
+        assert!(self.driver.deposit_item(item_mint, seller_tokens, amount).await, "item is deposited");
+    }
+
+    /// Creates the next English auction of the seller in the `namespace`, the script going on with it
+    pub async fn create_auction(&mut self, namespace: Pubkey, duration: i64, initial_price: u64) {
+        assert!(self.driver.create_auction(namespace, duration, initial_price).await, "auction is created");
+    }
+
+    /// Moves the listed `auctions` of the page `index` to the tail page
+    pub async fn compact_page(&mut self, namespace: Pubkey, index: u32, auctions: &[Pubkey]) {
+        assert!(self.try_compact_page(namespace, index, auctions).await, "page {} is compacted", index);
+    }
+
+    pub async fn try_compact_page(&mut self, namespace: Pubkey, index: u32, auctions: &[Pubkey]) -> bool {
+        self.driver.compact_page(namespace, index, auctions).await
+    }
+
+    /// Makes the auction take bids in `payment_mint` tokens
+    pub async fn set_payment_mint(&mut self, payment_mint: Pubkey) {
+        assert!(self.driver.set_payment_mint(payment_mint).await, "payment mint is set");
+    }
+
+    /// Creates the next auction of the seller with the parameters of this one, the script going on with the clone
+    pub async fn clone_auction(&mut self, auction_duration: i64) {
+        assert!(self.try_clone_auction(auction_duration).await, "auction is cloned");
+    }
+
+    pub async fn try_clone_auction(&mut self, auction_duration: i64) -> bool {
+        self.driver.clone_auction(auction_duration).await
+    }
+
+    /// The `bidder` buys the next unit of a GDA into the `buyer_tokens` for `max_price` at most
+    pub async fn purchase(&mut self, bidder: usize, buyer_tokens: Pubkey, max_price: u64) {
+        assert!(self.try_purchase(bidder, buyer_tokens, max_price).await, "bidder {} purchases a unit", bidder);
+    }
+
+    pub async fn try_purchase(&mut self, bidder: usize, buyer_tokens: Pubkey, max_price: u64) -> bool {
+        self.driver.purchase(bidder, buyer_tokens, max_price).await
+    }
+
+    /// The winning `bidder` takes the escrowed item into the `winner_tokens`
+    pub async fn claim_item(&mut self, bidder: usize, winner_tokens: Pubkey) {
+        assert!(self.try_claim_item(bidder, winner_tokens).await, "bidder {} claims the item", bidder);
+    }
+
+    pub async fn try_claim_item(&mut self, bidder: usize, winner_tokens: Pubkey) -> bool {
+        self.driver.claim_item(bidder, winner_tokens).await
+    }
+
+    /// The seller takes the unclaimed item back into the `seller_tokens`
+    pub async fn recover_item(&mut self, seller_tokens: Pubkey) {
+        assert!(self.try_recover_item(seller_tokens).await, "seller recovers the item");
+    }
+
+    pub async fn try_recover_item(&mut self, seller_tokens: Pubkey) -> bool {
+        self.driver.recover_item(seller_tokens).await
+    }
+
+    /// Keeps an audit log of up to `capacity` entries, passed along with every later step
+    pub async fn create_audit_log(&mut self, capacity: u32) {
+        assert!(self.driver.create_audit_log(capacity).await, "audit log is created");
+    }
+
+    /// Charges every wallet an `entry_fee` on its first bid, paid to the protocol vault or to the seller
+    pub async fn set_entry_fee(&mut self, entry_fee: u64, to_protocol: bool) {
+        assert!(self.driver.set_entry_fee(entry_fee, to_protocol).await, "entry fee is set");
+    }
+
+    /// Lets only `max_bidders` distinct wallets bid
+    pub async fn set_max_bidders(&mut self, max_bidders: u32) {
+        assert!(self.driver.set_max_bidders(max_bidders).await, "bidder cap is set");
+    }
+
+    /// Gives the losing bidders `refund_expiry` seconds after the end to claim their refunds
+    pub async fn set_refund_expiry(&mut self, refund_expiry: i64) {
+        assert!(self.driver.set_refund_expiry(refund_expiry).await, "refund expiry is set");
+    }
+
+    /// Keeps `penalty_bps` / 10 000 of every withdrawn escrow in the treasury
+    pub async fn set_withdrawal_penalty(&mut self, penalty_bps: u16) {
+        assert!(self.driver.set_withdrawal_penalty(penalty_bps).await, "withdrawal penalty is set");
+    }
+
+    /// Shares the surplus of the treasury with the bidders instead of leaving it to the seller
+    pub async fn set_surplus_sharing(&mut self) {
+        assert!(self.driver.set_surplus_sharing(true).await, "surplus sharing is set");
+    }
+
+    /// Makes it a candle auction, its end drawn from the last `window` seconds by the `vrf` account
+    pub async fn set_candle(&mut self, window: i64, vrf: Pubkey) {
+        assert!(self.driver.set_candle(window, vrf).await, "candle is set");
+    }
+
+    /// Draws the end of the candle auction from its VRF account
+    pub async fn resolve_candle(&mut self) {
+        assert!(self.try_resolve_candle().await, "candle is drawn");
+    }
+
+    pub async fn try_resolve_candle(&mut self) -> bool {
+        self.driver.resolve_candle().await
+    }
+
+    /// Sets a reserve in US cents, converted to lamports with the Pyth `price_feed`
+    pub async fn set_usd_reserve(&mut self, price_feed: Pubkey, reserve_cents: u64) {
+        assert!(self.driver.set_usd_reserve(price_feed, reserve_cents).await, "USD reserve is set");
+    }
+
+    /// Makes it a top-N auction, the `winners` highest bids all win
+    pub async fn set_winners(&mut self, winners: u8) {
+        assert!(self.driver.set_winners(winners).await, "winners are set");
+    }
+
+    /// Makes it an all-pay auction, every bid goes to the beneficiary
+    pub async fn set_all_pay(&mut self) {
+        assert!(self.driver.set_all_pay(true).await, "all-pay is set");
+    }
+
+    /// Makes it a penny auction, every bid raising the price by `step` for a `bid_fee`
+    pub async fn set_penny(&mut self, bid_fee: u64, step: u64, countdown: i64) {
+        assert!(self.driver.set_penny(bid_fee, step, countdown).await, "penny auction is set");
+    }
+
+    /// Lets the bidders escrow a maximum the program bids for them
+    pub async fn set_proxy_bidding(&mut self) {
+        assert!(self.driver.set_proxy_bidding(true).await, "proxy bidding is set");
+    }
+
+    /// Makes it a raffle of tickets at `ticket_price`, its winner drawn by the `vrf` account
+    pub async fn set_raffle(&mut self, ticket_price: u64, vrf: Pubkey) {
+        assert!(self.driver.set_raffle(ticket_price, vrf).await, "raffle is set");
+    }
+
+    /// Draws the raffle from its VRF account, won by the `holder` of the drawn ticket
+    pub async fn resolve_raffle(&mut self, holder: usize) {
+        assert!(self.try_resolve_raffle(holder).await, "raffle is drawn for bidder {}", holder);
+    }
+
+    pub async fn try_resolve_raffle(&mut self, holder: usize) -> bool {
+        self.driver.resolve_raffle(holder).await
+    }
+
+    /// Only lets holders of `amount` tokens of `gate`, or of an NFT of the collection `gate`, bid
+    pub async fn set_token_gate(&mut self, gate: Pubkey, amount: u64, collection: bool) {
+        assert!(self.driver.set_token_gate(gate, amount, collection).await, "token gate is set");
+    }
+
+    pub async fn publish(&mut self) {
+        assert!(self.driver.publish().await, "draft is published");
+    }
+
+    /// Closes an auction which ended without bids, the state and the treasury are gone afterwards
+    pub async fn finalize_without_bids(&mut self) {
+        assert!(self.try_finalize_without_bids().await, "unsold auction is closed");
+    }
+
+    pub async fn try_finalize_without_bids(&mut self) -> bool {
+        self.driver.finalize_without_bids().await
+    }
+
+    /// Closes a settled auction, the state and the treasury are gone afterwards
+    pub async fn close_auction(&mut self) {
+        assert!(self.try_close_auction().await, "settled auction is closed");
+    }
+
+    pub async fn try_close_auction(&mut self) -> bool {
+        self.driver.close_auction().await
+    }
+
+    /// Migrates the auction and the offers of the `bidders` to the current layout versions, copying each one out
+    /// and recreating it in the next transaction
+    pub async fn migrate_state(&mut self, bidders: &[usize]) {
+        assert!(self.driver.migrate_state().await, "auction is copied out");
+        assert!(self.driver.restore_state().await, "auction is recreated with the current layout");
+        for &bidder in bidders {
+            assert!(self.driver.migrate_offer(bidder).await, "offer of bidder {} is copied out", bidder);
+            assert!(self.driver.restore_offer(bidder).await, "offer of bidder {} is recreated", bidder);
+        }
+    }
+
+    /// Migrates the legacy auction living at the address of `legacy`, then the offers of the `bidders`
+    pub async fn migrate_legacy_auction(&mut self, legacy: &Keypair, bidders: &[usize]) {
+        assert!(self.driver.migrate_legacy_auction().await, "legacy auction is copied out");
+        assert!(self.driver.restore_legacy_auction(legacy).await, "legacy auction is recreated with the current layout");
+        for &bidder in bidders {
+            assert!(self.driver.migrate_offer(bidder).await, "offer of bidder {} is copied out", bidder);
+            assert!(self.driver.restore_offer(bidder).await, "offer of bidder {} is recreated", bidder);
+        }
+    }
+
+    /// Lamports only moved between the wallets and the auction accounts, fees being paid from elsewhere
+    pub async fn assert_lamports_conserved(&mut self) {
+        assert_eq!(self.driver.total_lamports().await, self.total, "lamports are conserved");
+    }
+
+    pub async fn assert_status(&mut self, expected: AuctionStatus) {
+        assert_eq!(self.driver.status().await, expected);
+    }
+
+    /// `None` while nobody bid
+    pub async fn assert_leader(&mut self, bidder: Option<usize>, price: u64) {
+        let state = self.driver.state().await;
+        let leader = self.driver.bidders.iter().position(|wallet| wallet.pubkey() == state.max_bidder);
+        assert_eq!((leader, state.max_price), (bidder, price));
+    }
+
+    /// Lamports of the treasury on top of its rent
+    pub async fn assert_escrow(&mut self, expected: u64) {
+        let escrow = self.driver.lamports(self.driver.treasury).await - rent(0);
+        assert_eq!(escrow, expected);
+    }
+}
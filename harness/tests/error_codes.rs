@@ -0,0 +1,72 @@
+//! Clients written against older deployments match on the error codes, so both the number
+//! and the meaning of every code stay fixed. New errors are appended here, never renumbered.
+
+use anchor_lang::error::ERROR_CODE_OFFSET;
+use auction::Errors;
+
+const CODES: [(Errors, u32, &str); 47] = [
+    (Errors::BidTooLow, 6000, "Bid offer too low"),
+    (Errors::AlreadyHighestBidder, 6001, "Already the highest bidder"),
+    (Errors::WrongAccount, 6002, "Wrong account"),
+    (Errors::Open, 6003, "Auction is open"),
+    (Errors::Closed, 6004, "Auction is closed"),
+    (Errors::InvalidOperation, 6005, "Invalid operation"),
+    (Errors::WinnerRefund, 6006, "Winner can not refund"),
+    (Errors::AlreadySettled, 6007, "Auction is already settled"),
+    (Errors::RefundNotExpired, 6008, "Refund has not expired yet"),
+    (Errors::AlreadyBid, 6009, "Auction already has bids"),
+    (Errors::NoItem, 6010, "Auction has no escrowed item"),
+    (Errors::ItemClaimed, 6011, "Item was already claimed"),
+    (Errors::ClaimWindowOpen, 6012, "Winner can still claim the item"),
+    (Errors::NotFinalist, 6013, "Bidder did not qualify for the final round"),
+    (Errors::InvalidReveal, 6014, "Revealed bid does not match the commitment"),
+    (Errors::SoldOut, 6015, "All units are sold"),
+    (Errors::PriceTooHigh, 6016, "Price is higher than the accepted maximum"),
+    (Errors::InvalidOracle, 6017, "Invalid oracle account"),
+    (Errors::StaleOracle, 6018, "Oracle price is too old"),
+    (Errors::Draft, 6019, "Auction is a draft"),
+    (Errors::NotDraft, 6020, "Auction is already published"),
+    (Errors::AuctionFull, 6021, "Auction reached the maximum number of bidders"),
+    (Errors::AuditLogRequired, 6022, "Audit log of the auction is missing"),
+    (Errors::AttestationRequired, 6023, "Attestation of the bidder is missing"),
+    (Errors::InvalidAttestation, 6024, "Attestation does not allow the bidder"),
+    (Errors::UnsupportedCurrency, 6025, "Not supported for the currency of the auction"),
+    (Errors::CreditLimitExceeded, 6026, "Credit limit of the market maker exceeded"),
+    (Errors::CreditDefaulted, 6027, "Credit line of the market maker defaulted"),
+    (Errors::TooManyPendingChanges, 6028, "Too many config changes are already scheduled"),
+    (Errors::OfferExpired, 6029, "Offer has expired"),
+    (Errors::BidExpired, 6030, "Bid landed after its maximum valid time"),
+    (Errors::NotStarted, 6031, "Bidding has not started yet"),
+    (Errors::EarlyAccessRequired, 6032, "Only early access holders may bid before the public window"),
+    (Errors::IdentityRequired, 6033, "Bidder has to conceal their identity first"),
+    (Errors::LinkedSettlement, 6034, "Linked auctions with the same winner are settled together"),
+    (Errors::VolumeCapReached, 6035, "Volume cap of the program reached, wait for the next window"),
+    (Errors::SnapshotRetained, 6036, "Participation snapshot is still retained"),
+    (Errors::OpenBids, 6037, "Auction still holds bids to refund"),
+    (Errors::NotAllowlisted, 6038, "Bidder is not on the allowlist"),
+    (Errors::TokenGateRequired, 6039, "Bidder doesn't hold the tokens of the gate"),
+    (Errors::Banned, 6040, "Bidder is banned from the auction"),
+    (Errors::Paused, 6041, "Program is paused"),
+    (Errors::CandlePending, 6042, "Candle auction waits for its randomness"),
+    (Errors::AllPay, 6043, "Bids of an all-pay auction are not refunded"),
+    (Errors::RafflePending, 6044, "Raffle waits for its randomness"),
+    (Errors::DrawExpired, 6045, "Draw window passed, the auction fails"),
+    (Errors::ReceiverNotRentExempt, 6046, "Entry fee receiver is not rent-exempt"),
+];
+
+#[test]
+fn error_codes_are_stable() {
+    for (error, code, msg) in CODES {
+        assert_eq!(u32::from(error), code, "{:?}", error);
+        assert_eq!(error.to_string(), msg, "{:?}", error);
+    }
+}
+
+#[test]
+fn every_error_is_registered() {
+    assert_eq!(Errors::ALL.len(), CODES.len());
+
+    for (i, error) in Errors::ALL.iter().enumerate() {
+        assert_eq!(u32::from(*error), ERROR_CODE_OFFSET + i as u32, "{:?}", error);
+    }
+}
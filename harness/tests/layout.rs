@@ -0,0 +1,146 @@
+//! Golden values of the account layouts. Live accounts keep their size and discriminator forever,
+//! so any change here has to come with a migration, never by updating the numbers alone.
+
+use anchor_lang::{AccountDeserialize, AccountSerialize, Discriminator};
+use anchor_lang::prelude::Pubkey;
+use auction::{
+    AuctionLink, AuctionReport, AuctionResult, AuctionStatus, AuditEntry, AuditLog, BanList, BuyOffer, Counter, CreditBid, CreditLine,
+    FeeVault, GlobalConfig, GlobalStats, MarketConfig, Notify, Offer, OfferMigration, ParticipationSnapshot, Registry, RegistryPage, Series,
+    Sponsorship, State, StateMigration, UnitBook, Watch, STATUS_OFFSET,
+};
+
+#[test]
+fn account_sizes() {
+    assert_eq!(State::size(), 2184);
+    assert_eq!(Offer::size(), 122);
+    assert_eq!(Counter::size(), 8);
+    assert_eq!(Registry::size(), 40);
+    assert_eq!(RegistryPage::size(), 1032);
+    assert_eq!(AuctionReport::size(), 136);
+    assert_eq!(AuctionResult::size(), 146);
+    assert_eq!(AuditLog::size(0), 76);
+    assert_eq!(AuditEntry::size(), 49);
+    assert_eq!(Sponsorship::size(), 65);
+    assert_eq!(CreditLine::size(), 90);
+    assert_eq!(CreditBid::size(), 9);
+    assert_eq!(MarketConfig::size(), 186);
+    assert_eq!(BuyOffer::size(), 89);
+    assert_eq!(Watch::size(), 73);
+    assert_eq!(UnitBook::size(), 939);
+    assert_eq!(Series::size(), 111);
+    assert_eq!(AuctionLink::size(), 67);
+    assert_eq!(GlobalStats::size(), 33);
+    assert_eq!(ParticipationSnapshot::size(), 81);
+    assert_eq!(Notify::size(), 81);
+    assert_eq!(GlobalConfig::size(), 52);
+    assert_eq!(FeeVault::size(), 1);
+    assert_eq!(BanList::size(), 1061);
+    assert_eq!(StateMigration::size(), 2184);
+    assert_eq!(OfferMigration::size(), 122);
+}
+
+/// Sizes the older layout versions were created with, which the migrations tell them apart by
+#[test]
+fn older_layout_sizes() {
+    let sizes = [1176, 1208, 1249, 1250, 1611, 1651, 1972, 1973, 2005, 2006, 2015, 2064, 2072, 2136, 2152, 2184];
+    for (version, size) in (1..).zip(sizes) {
+        assert_eq!(State::size_at(version), size, "state of version {}", version);
+    }
+    assert_eq!(Offer::size_at(0), 9);
+    assert_eq!(Offer::size_at(1), 106);
+    assert_eq!(Offer::size_at(2), 122);
+}
+
+#[test]
+fn discriminators() {
+    assert_eq!(State::discriminator(), [216, 146, 107, 94, 104, 75, 182, 177]);
+    assert_eq!(Offer::discriminator(), [215, 88, 60, 71, 170, 162, 73, 229]);
+    assert_eq!(Counter::discriminator(), [255, 176, 4, 245, 188, 253, 124, 25]);
+    assert_eq!(Registry::discriminator(), [47, 174, 110, 246, 184, 182, 252, 218]);
+    assert_eq!(RegistryPage::discriminator(), [190, 151, 207, 163, 226, 253, 16, 250]);
+    assert_eq!(AuctionReport::discriminator(), [132, 147, 127, 193, 169, 135, 39, 160]);
+    assert_eq!(AuctionResult::discriminator(), [182, 105, 71, 113, 228, 147, 117, 135]);
+    assert_eq!(AuditLog::discriminator(), [230, 207, 176, 233, 170, 130, 101, 244]);
+    assert_eq!(Sponsorship::discriminator(), [191, 110, 48, 7, 9, 44, 168, 248]);
+    assert_eq!(CreditLine::discriminator(), [220, 226, 205, 24, 220, 151, 129, 104]);
+    assert_eq!(CreditBid::discriminator(), [115, 13, 69, 21, 215, 73, 251, 106]);
+    assert_eq!(BuyOffer::discriminator(), [247, 133, 161, 252, 116, 1, 4, 172]);
+    assert_eq!(Watch::discriminator(), [158, 177, 254, 108, 184, 190, 42, 13]);
+    assert_eq!(MarketConfig::discriminator(), [119, 255, 200, 88, 252, 82, 128, 24]);
+    assert_eq!(UnitBook::discriminator(), [64, 179, 206, 9, 92, 121, 82, 10]);
+    assert_eq!(Series::discriminator(), [240, 97, 8, 183, 139, 77, 250, 162]);
+    assert_eq!(AuctionLink::discriminator(), [195, 132, 139, 25, 230, 233, 165, 120]);
+    assert_eq!(GlobalStats::discriminator(), [119, 53, 78, 3, 254, 129, 78, 28]);
+    assert_eq!(ParticipationSnapshot::discriminator(), [147, 239, 122, 64, 166, 253, 129, 145]);
+    assert_eq!(Notify::discriminator(), [255, 86, 72, 213, 234, 178, 7, 128]);
+    assert_eq!(GlobalConfig::discriminator(), [149, 8, 156, 202, 160, 252, 176, 217]);
+    assert_eq!(FeeVault::discriminator(), [192, 178, 69, 232, 58, 149, 157, 132]);
+    assert_eq!(BanList::discriminator(), [11, 203, 65, 167, 86, 15, 195, 85]);
+    assert_eq!(StateMigration::discriminator(), [203, 60, 96, 95, 6, 33, 160, 221]);
+    assert_eq!(OfferMigration::discriminator(), [166, 107, 2, 85, 221, 146, 3, 216]);
+}
+
+/// Fully populated accounts serialize into exactly the space allocated for them
+#[test]
+fn serialized_sizes_match_allocations() {
+    let mut state: State = blank(State::size());
+    state.payment_mint = Some(Pubkey::new_unique());
+    assert_eq!(serialized(&state), 8 + State::size());
+
+    assert_eq!(serialized(&blank::<Offer>(Offer::size())), 8 + Offer::size());
+    assert_eq!(serialized(&blank::<Counter>(Counter::size())), 8 + Counter::size());
+    assert_eq!(serialized(&blank::<Registry>(Registry::size())), 8 + Registry::size());
+    assert_eq!(serialized(&blank::<RegistryPage>(RegistryPage::size())), 8 + RegistryPage::size());
+    assert_eq!(serialized(&blank::<AuctionReport>(AuctionReport::size())), 8 + AuctionReport::size());
+    assert_eq!(serialized(&blank::<AuctionResult>(AuctionResult::size())), 8 + AuctionResult::size());
+    assert_eq!(serialized(&blank::<Sponsorship>(Sponsorship::size())), 8 + Sponsorship::size());
+    assert_eq!(serialized(&blank::<CreditLine>(CreditLine::size())), 8 + CreditLine::size());
+    assert_eq!(serialized(&blank::<CreditBid>(CreditBid::size())), 8 + CreditBid::size());
+    assert_eq!(serialized(&blank::<MarketConfig>(MarketConfig::size())), 8 + MarketConfig::size());
+    assert_eq!(serialized(&blank::<BuyOffer>(BuyOffer::size())), 8 + BuyOffer::size());
+    assert_eq!(serialized(&blank::<Watch>(Watch::size())), 8 + Watch::size());
+    assert_eq!(serialized(&blank::<UnitBook>(UnitBook::size())), 8 + UnitBook::size());
+    assert_eq!(serialized(&blank::<Series>(Series::size())), 8 + Series::size());
+    assert_eq!(serialized(&blank::<AuctionLink>(AuctionLink::size())), 8 + AuctionLink::size());
+    assert_eq!(serialized(&blank::<GlobalStats>(GlobalStats::size())), 8 + GlobalStats::size());
+    assert_eq!(serialized(&blank::<ParticipationSnapshot>(ParticipationSnapshot::size())), 8 + ParticipationSnapshot::size());
+    assert_eq!(serialized(&blank::<Notify>(Notify::size())), 8 + Notify::size());
+    assert_eq!(serialized(&blank::<GlobalConfig>(GlobalConfig::size())), 8 + GlobalConfig::size());
+    assert_eq!(serialized(&blank::<FeeVault>(FeeVault::size())), 8 + FeeVault::size());
+
+    let mut log: AuditLog = blank(AuditLog::size(0));
+    log.capacity = 3;
+    log.entries = (0..3).map(|_| blank_entry()).collect();
+    assert_eq!(serialized(&log), 8 + AuditLog::size(3));
+}
+
+/// Clients select auctions by lifecycle stage with a `memcmp` filter on a single byte
+#[test]
+fn status_byte() {
+    let mut state: State = blank(State::size());
+    state.status = AuctionStatus::Settled;
+
+    let mut data = Vec::new();
+    state.try_serialize(&mut data).unwrap();
+    assert_eq!(STATUS_OFFSET, 120);
+    assert_eq!(data[STATUS_OFFSET], AuctionStatus::Settled as u8);
+    assert_eq!(AuctionStatus::Cancelled as u8, 7);
+}
+
+fn blank<T: AccountDeserialize + Discriminator>(size: usize) -> T {
+    let mut data = T::discriminator().to_vec();
+    data.resize(8 + size, 0);
+
+    T::try_deserialize(&mut &data[..]).unwrap()
+}
+
+fn blank_entry() -> AuditEntry {
+    let data = vec![0; AuditEntry::size()];
+    anchor_lang::AnchorDeserialize::deserialize(&mut &data[..]).unwrap()
+}
+
+fn serialized<T: AccountSerialize>(account: &T) -> usize {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    data.len()
+}
@@ -0,0 +1,167 @@
+//! Drives random sequences of instructions through a draft auction, checking that the program only
+//! moves along Draft → Open → Ended/Failed → Settled and never creates or destroys lamports.
+//! Closing the auction accounts after settlement is not modelled yet.
+
+use std::cell::RefCell;
+
+use auction::{AuctionStatus, Offer};
+use auction_harness::driver::rent;
+use auction_harness::{Driver, Model, Observed, Op};
+use proptest::prelude::*;
+use proptest_state_machine::{prop_state_machine, ReferenceStateMachine, StateMachineTest};
+use tokio::runtime::Runtime;
+
+const DURATION: i64 = 100;
+const INITIAL_PRICE: u64 = 1_000;
+
+#[derive(Clone, Debug)]
+enum Transition {
+    Publish,
+    Apply(Op),
+}
+
+/// The model of the auction, with the time relative to its creation
+#[derive(Clone, Debug)]
+struct Lifecycle {
+    draft: bool,
+    model: Model,
+    /// Whether the program must accept the last transition
+    accepted: bool,
+}
+
+impl Lifecycle {
+    fn status(&self) -> AuctionStatus {
+        if self.draft {
+            AuctionStatus::Draft
+        } else if !self.model.open {
+            AuctionStatus::Settled
+        } else if self.model.now < self.model.end_time {
+            AuctionStatus::Open
+        } else if self.model.max_bidder.is_none() {
+            AuctionStatus::Failed
+        } else {
+            AuctionStatus::Ended
+        }
+    }
+}
+
+struct LifecycleMachine;
+
+impl ReferenceStateMachine for LifecycleMachine {
+    type State = Lifecycle;
+    type Transition = Transition;
+
+    fn init_state() -> BoxedStrategy<Self::State> {
+        Just(Lifecycle {
+            draft: true,
+            model: Model::new(0, DURATION, INITIAL_PRICE, rent(Offer::size())),
+            accepted: true,
+        }).boxed()
+    }
+
+    fn transitions(_state: &Self::State) -> BoxedStrategy<Self::Transition> {
+        prop_oneof![
+            1 => Just(Transition::Publish),
+            4 => (any::<u8>(), 0..100u16).prop_map(|(bidder, raise)| Transition::Apply(Op::Bid { bidder, raise })),
+            2 => (0..DURATION as u16).prop_map(|seconds| Transition::Apply(Op::Warp { seconds })),
+            1 => Just(Transition::Apply(Op::End)),
+            2 => any::<u8>().prop_map(|bidder| Transition::Apply(Op::Refund { bidder })),
+        ].boxed()
+    }
+
+    fn apply(mut state: Self::State, transition: &Self::Transition) -> Self::State {
+        state.accepted = match transition {
+            // Publishing restarts the auction, so the whole duration is left
+            Transition::Publish if state.draft => {
+                state.draft = false;
+                state.model.end_time = state.model.now + DURATION;
+                true
+            }
+            Transition::Publish => false,
+            Transition::Apply(Op::Warp { seconds }) => {
+                state.model.now += *seconds as i64;
+                true
+            }
+            Transition::Apply(_) if state.draft => false,
+            Transition::Apply(op) => state.model.apply(*op).is_ok(),
+        };
+
+        state
+    }
+}
+
+struct Sut {
+    runtime: Runtime,
+    driver: RefCell<Driver>,
+    status: RefCell<AuctionStatus>,
+    total: u64,
+}
+
+struct LifecycleTest;
+
+impl StateMachineTest for LifecycleTest {
+    type SystemUnderTest = Sut;
+    type Reference = LifecycleMachine;
+
+    fn init_test(_ref_state: &Lifecycle) -> Sut {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let (mut driver, _model) = runtime.block_on(Driver::start_draft(DURATION, INITIAL_PRICE));
+        let total = runtime.block_on(driver.total_lamports());
+
+        Sut { runtime, driver: RefCell::new(driver), status: RefCell::new(AuctionStatus::Draft), total }
+    }
+
+    fn apply(sut: Sut, ref_state: &Lifecycle, transition: Transition) -> Sut {
+        let accepted = sut.runtime.block_on(async {
+            let mut driver = sut.driver.borrow_mut();
+            match transition {
+                Transition::Publish => driver.publish().await,
+                Transition::Apply(op) => driver.apply(op).await,
+            }
+        });
+        assert_eq!(accepted, ref_state.accepted, "{:?} in {:?}", transition, ref_state.status());
+
+        sut
+    }
+
+    fn check_invariants(sut: &Sut, ref_state: &Lifecycle) {
+        let mut driver = sut.driver.borrow_mut();
+        let status = sut.runtime.block_on(driver.status());
+        assert_eq!(status, ref_state.status());
+
+        // Only the legal transitions, a single warp may skip over the end of the open phase
+        let previous = sut.status.replace(status);
+        assert!(
+            previous == status || matches!(
+                (previous, status),
+                (AuctionStatus::Draft, AuctionStatus::Open)
+                    | (AuctionStatus::Open, AuctionStatus::Ended | AuctionStatus::Failed)
+                    | (AuctionStatus::Ended, AuctionStatus::Settled)
+            ),
+            "illegal transition from {:?} to {:?}",
+            previous,
+            status
+        );
+
+        // Lamports only move between the participants and the auction accounts
+        assert_eq!(sut.runtime.block_on(driver.total_lamports()), sut.total);
+
+        let model = &ref_state.model;
+        let observed = sut.runtime.block_on(driver.observe());
+        assert_eq!(observed, Observed {
+            max_price: model.max_price,
+            max_bidder: model.max_bidder,
+            open: model.open,
+            balances: model.balances,
+            seller: model.seller,
+            escrow: model.escrow,
+        });
+    }
+}
+
+prop_state_machine! {
+    #![proptest_config(ProptestConfig { cases: 32, ..ProptestConfig::default() })]
+
+    #[test]
+    fn auction_lifecycle(sequential 1..40 => LifecycleTest);
+}
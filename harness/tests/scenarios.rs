@@ -0,0 +1,1275 @@
+//! Whole auctions written as scenario scripts, see `auction_harness::scenario`
+
+use anchor_lang::{AccountDeserialize, Discriminator};
+use auction::{AuctionStatus, AuditAction, Offer, State, OFFER_VERSION, PAGE_SIZE, STATE_VERSION, STATUS_OFFSET};
+use auction_harness::driver::FUNDS;
+use auction_harness::Scenario;
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use switchboard_v2::VrfAccountData;
+use std::mem::size_of;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DURATION: i64 = 60;
+const INITIAL_PRICE: u64 = 100;
+
+/// Initialized SPL token account of `owner` holding `amount` tokens of `mint`
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> AccountSharedData {
+    let mut data = vec![0; 165];
+    data[..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&amount.to_le_bytes());
+    data[108] = 1;
+
+    AccountSharedData::from(Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap(),
+        executable: false,
+        rent_epoch: 0,
+    })
+}
+
+/// Initialized SPL mint of `supply` tokens with `decimals`, without a mint authority
+fn mint(supply: u64, decimals: u8) -> AccountSharedData {
+    let mut data = vec![0; 82];
+    data[36..44].copy_from_slice(&supply.to_le_bytes());
+    data[44] = decimals;
+    data[45] = 1;
+
+    AccountSharedData::from(Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap(),
+        executable: false,
+        rent_epoch: 0,
+    })
+}
+
+/// Pyth price account trading at `price` with an exponential moving average of `ema`, both with the exponent
+/// `expo` and published at `timestamp`
+fn pyth_price(price: i64, ema: i64, expo: i32, timestamp: i64) -> Account {
+    let mut data = vec![0; 3312];
+    data[..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    data[4..8].copy_from_slice(&2u32.to_le_bytes());
+    data[8..12].copy_from_slice(&3u32.to_le_bytes());
+    data[12..16].copy_from_slice(&3312u32.to_le_bytes());
+    data[16..20].copy_from_slice(&1u32.to_le_bytes());
+    data[20..24].copy_from_slice(&expo.to_le_bytes());
+    data[48..56].copy_from_slice(&ema.to_le_bytes());
+    data[96..104].copy_from_slice(&timestamp.to_le_bytes());
+    data[184..192].copy_from_slice(&price.to_le_bytes());
+    data[200..208].copy_from_slice(&timestamp.to_le_bytes());
+    data[208..216].copy_from_slice(&price.to_le_bytes());
+    data[224..228].copy_from_slice(&1u32.to_le_bytes());
+
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: Pubkey::new_unique(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Switchboard VRF account of the `authority` which served `counter` requests, the last one made at
+/// `requested_at` and answered with `result`
+fn vrf(authority: Pubkey, counter: u128, requested_at: i64, result: [u8; 32]) -> AccountSharedData {
+    let mut data = vec![0; 8 + size_of::<VrfAccountData>()];
+    data[..8].copy_from_slice(&VrfAccountData::discriminator());
+    let account: &mut VrfAccountData = bytemuck::from_bytes_mut(&mut data[8..]);
+    account.counter = counter;
+    account.authority = authority;
+    account.current_round.request_timestamp = requested_at;
+    account.current_round.result = result;
+
+    AccountSharedData::from(Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: auction::switchboard::ID,
+        executable: false,
+        rent_epoch: 0,
+    })
+}
+
+/// Metaplex metadata of the `mint` paying `bps` of every sale to the `creators`, split by their percent shares
+fn metadata(mint: Pubkey, bps: u16, creators: &[(Pubkey, u8)]) -> AccountSharedData {
+    // Version 1 metadata with its update authority, the mint, the name, the symbol and the URI
+    let mut data = vec![4];
+    data.extend_from_slice(Pubkey::default().as_ref());
+    data.extend_from_slice(mint.as_ref());
+    for text in ["Item", "ITEM", ""] {
+        data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        data.extend_from_slice(text.as_bytes());
+    }
+    data.extend_from_slice(&bps.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(&(creators.len() as u32).to_le_bytes());
+    for (address, share) in creators {
+        data.extend_from_slice(address.as_ref());
+        data.extend_from_slice(&[1, *share]);
+    }
+    // Primary sale, mutable, no edition nonce, token standard or collection
+    data.extend_from_slice(&[0, 1, 0, 0, 0]);
+
+    AccountSharedData::from(Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: auction::token_metadata::ID,
+        executable: false,
+        rent_epoch: 0,
+    })
+}
+
+/// VRF result drawing the sample `sample` of a candle window or the ticket `sample` of a raffle
+fn drawn(sample: u64) -> [u8; 32] {
+    let mut result = [0xff; 32];
+    result[..8].copy_from_slice(&sample.to_le_bytes());
+    result
+}
+
+/// Program test with a Pyth feed published now, its EMA `ema` SOL scaled by 10^8 and its price twice that
+fn oracle_test(ema: i64) -> (ProgramTest, Pubkey) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let price_feed = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new("auction", auction::ID, processor!(auction::entry));
+    program_test.add_account(price_feed, pyth_price(2 * ema, ema, -8, now));
+
+    (program_test, price_feed)
+}
+
+/// Tokens held by the SPL token account at `key`
+async fn token_balance(scenario: &mut Scenario, key: Pubkey) -> u64 {
+    let account = scenario.driver.account(key).await.expect("token account exists");
+    u64::from_le_bytes(account.data[64..72].try_into().unwrap())
+}
+
+/// Mint of an escrowed item with a token account of the seller holding it and an empty one of every bidder
+fn item_accounts(scenario: &mut Scenario, supply: u64, decimals: u8) -> (Pubkey, Pubkey, Vec<Pubkey>) {
+    let item_mint = Pubkey::new_unique();
+    scenario.driver.ctx.set_account(&item_mint, &mint(supply, decimals));
+
+    let seller_tokens = Pubkey::new_unique();
+    let seller = scenario.driver.seller.pubkey();
+    scenario.driver.ctx.set_account(&seller_tokens, &token_account(item_mint, seller, supply));
+
+    let bidders: Vec<Pubkey> = scenario.driver.bidders.iter().map(|bidder| bidder.pubkey()).collect();
+    let bidder_tokens = bidders
+        .into_iter()
+        .map(|bidder| {
+            let tokens = Pubkey::new_unique();
+            scenario.driver.ctx.set_account(&tokens, &token_account(item_mint, bidder, 0));
+            tokens
+        })
+        .collect();
+
+    (item_mint, seller_tokens, bidder_tokens)
+}
+
+#[tokio::test]
+async fn english_auction_settles_and_refunds() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    assert!(!scenario.try_bid(0, 115).await, "a lower bid is rejected");
+    scenario.assert_leader(Some(1), 120).await;
+    scenario.assert_escrow(230).await;
+
+    assert!(!scenario.try_end().await, "no settlement before the end");
+    scenario.advance_to_end().await;
+    scenario.assert_status(AuctionStatus::Ended).await;
+
+    scenario.end().await;
+    scenario.refund(0).await;
+    assert!(!scenario.try_refund(1).await, "the winner has nothing to refund");
+
+    scenario.assert_status(AuctionStatus::Settled).await;
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn anybody_settles_for_an_absent_seller() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    assert!(!scenario.try_settle(0).await, "no settlement before the end");
+
+    scenario.advance_to_end().await;
+    scenario.settle(0).await;
+    assert!(!scenario.try_settle(0).await, "the auction is settled once");
+    assert!(!scenario.try_end().await, "the seller can't settle again");
+    scenario.refund(0).await;
+
+    scenario.assert_status(AuctionStatus::Settled).await;
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn losing_bidders_are_refunded_at_once() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(3).start().await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    scenario.bid(2, 130).await;
+    scenario.advance_to_end().await;
+
+    assert!(!scenario.try_refund_many(&[0, 1]).await, "no refunds before the settlement");
+    scenario.end().await;
+    assert!(!scenario.try_refund_many(&[0, 2]).await, "the winner is not refunded");
+    scenario.refund_many(&[0, 1]).await;
+    assert!(!scenario.try_refund(0).await, "a bidder is refunded once");
+
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn keeper_finishes_a_scheduled_auction() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(4).start().await;
+    let keeper = scenario.driver.bidders[3].pubkey();
+    scenario.schedule_settlement(keeper).await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    scenario.bid(2, 130).await;
+    assert!(!scenario.try_crank_settlement(3, &[]).await, "the auction is still open");
+    scenario.advance_to_end().await;
+
+    // The first crank settles, every one refunds
+    assert!(!scenario.try_crank_settlement(0, &[]).await, "only the keeper cranks");
+    scenario.crank_settlement(3, &[0]).await;
+    scenario.assert_status(AuctionStatus::Settled).await;
+    assert!(!scenario.try_crank_settlement(3, &[2]).await, "the winner is not refunded");
+    scenario.crank_settlement(3, &[1]).await;
+    assert!(!scenario.try_refund(1).await, "a bidder is refunded once");
+
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn keeper_leaves_an_escrowed_item_to_the_manual_settlement() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    let keeper = scenario.driver.bidders[1].pubkey();
+    scenario.schedule_settlement(keeper).await;
+    let (item_mint, seller_tokens, _) = item_accounts(&mut scenario, 1, 0);
+    scenario.deposit_item(item_mint, seller_tokens, 1).await;
+
+    scenario.bid(0, 110).await;
+    scenario.advance_to_end().await;
+
+    // The royalties of the item need accounts the crank has no room for
+    assert!(!scenario.try_crank_settlement(1, &[]).await, "the keeper doesn't settle an escrowed item");
+    scenario.settle(1).await;
+    scenario.assert_status(AuctionStatus::Settled).await;
+}
+
+#[tokio::test]
+async fn outbid_bidder_withdraws_before_the_end() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+
+    scenario.bid(0, 110).await;
+    assert!(!scenario.try_withdraw_bid(0).await, "the leader can't withdraw");
+    scenario.bid(1, 120).await;
+    scenario.withdraw_bid(0).await;
+    scenario.assert_escrow(120).await;
+    assert!(!scenario.try_withdraw_bid(0).await, "the escrow is withdrawn once");
+
+    scenario.bid(0, 130).await;
+    scenario.assert_leader(Some(0), 130).await;
+    scenario.advance_to_end().await;
+    assert!(!scenario.try_withdraw_bid(1).await, "ended auctions use the refund");
+
+    scenario.end().await;
+    scenario.refund(1).await;
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn leader_raises_their_own_bid() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+
+    scenario.bid(0, 110).await;
+    assert!(!scenario.try_bid(0, 120).await, "the leader doesn't bid against themselves");
+    assert!(!scenario.try_raise(1, 120).await, "only the leader raises");
+    scenario.raise(0, 150).await;
+    scenario.assert_leader(Some(0), 150).await;
+    scenario.assert_escrow(150).await;
+
+    assert!(!scenario.try_bid(1, 140).await, "the raise is the price to beat");
+    scenario.bid(1, 160).await;
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.refund(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn draft_takes_bids_once_published() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).draft().start().await;
+
+    assert!(!scenario.try_bid(0, 110).await, "a draft takes no bids");
+    scenario.publish().await;
+    scenario.bid(0, 110).await;
+
+    scenario.assert_status(AuctionStatus::Open).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn auction_without_bids_fails() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).start().await;
+
+    scenario.advance_slots(100).await;
+    scenario.advance_time(DURATION).await;
+
+    scenario.assert_leader(None, INITIAL_PRICE).await;
+    scenario.assert_status(AuctionStatus::Failed).await;
+    assert!(!scenario.try_end().await, "there is no winner to settle");
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn unsold_auction_is_closed() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).start().await;
+    assert!(!scenario.try_finalize_without_bids().await, "the auction is still running");
+
+    scenario.advance_time(DURATION).await;
+    let (seller, state, treasury) = (scenario.driver.seller.pubkey(), scenario.driver.state, scenario.driver.treasury);
+    let before = scenario.driver.lamports(seller).await;
+    let rent = scenario.driver.lamports(state).await + scenario.driver.lamports(treasury).await;
+    scenario.finalize_without_bids().await;
+
+    assert!(scenario.driver.account(state).await.is_none(), "the state is closed");
+    assert!(scenario.driver.account(treasury).await.is_none(), "the treasury is closed");
+    assert_eq!(scenario.driver.lamports(seller).await, before + rent, "the seller gets the rent back");
+}
+
+#[tokio::test]
+async fn sold_auction_is_not_finalized_as_unsold() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(1).start().await;
+    scenario.bid(0, 110).await;
+    scenario.advance_to_end().await;
+
+    assert!(!scenario.try_finalize_without_bids().await, "the winner is paid out by the settlement");
+    scenario.end().await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn settled_auction_is_closed_once_refunded() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(3).start().await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    scenario.withdraw_bid(0).await;
+    scenario.bid(2, 130).await;
+    scenario.advance_to_end().await;
+    assert!(!scenario.try_close_auction().await, "the auction is not settled");
+
+    scenario.end().await;
+    assert!(!scenario.try_close_auction().await, "a losing bid is not refunded yet");
+    scenario.refund(1).await;
+
+    let state = scenario.driver.state;
+    scenario.close_auction().await;
+    assert!(scenario.driver.account(state).await.is_none(), "the state is closed");
+    assert!(scenario.driver.account(scenario.driver.treasury).await.is_none(), "the treasury is closed");
+}
+
+#[tokio::test]
+async fn token_gate_admits_holders_only() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    let mint = Pubkey::new_unique();
+    scenario.set_token_gate(mint, 10, false).await;
+
+    let holdings: Vec<Pubkey> = (0..2).map(|_| Pubkey::new_unique()).collect();
+    let (holder, poor) = (scenario.driver.bidders[0].pubkey(), scenario.driver.bidders[1].pubkey());
+    scenario.driver.ctx.set_account(&holdings[0], &token_account(mint, holder, 10));
+    scenario.driver.ctx.set_account(&holdings[1], &token_account(mint, poor, 9));
+
+    assert!(!scenario.try_bid(0, 110).await, "a bid without the token account is rejected");
+    assert!(!scenario.try_bid_holding(1, 110, holdings[1]).await, "too few tokens don't open the gate");
+    assert!(!scenario.try_bid_holding(1, 110, holdings[0]).await, "the tokens of another wallet don't count");
+    assert!(scenario.try_bid_holding(0, 110, holdings[0]).await, "the holder bids");
+    scenario.assert_leader(Some(0), 110).await;
+}
+
+#[tokio::test]
+async fn banned_bidder_bids_no_more() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(3).start().await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    scenario.ban_bidder(0).await;
+
+    assert!(!scenario.try_bid(2, 130).await, "the ban list has to be passed once somebody is banned");
+    assert!(!scenario.try_bid_past_bans(0, 130).await, "the banned bidder is rejected");
+    assert!(scenario.try_bid_past_bans(2, 130).await, "the others keep bidding");
+    scenario.assert_leader(Some(2), 130).await;
+
+    // The bids of the banned bidder stand and are refunded as usual
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.refund(0).await;
+}
+
+#[tokio::test]
+async fn candle_auction_waits_for_its_draw() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    let (seller, candle_vrf) = (scenario.driver.seller.pubkey(), Pubkey::new_unique());
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 0, 0, [0; 32]));
+    scenario.set_candle(DURATION / 2, candle_vrf).await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    assert!(!scenario.try_withdraw_bid(0).await, "every leader of the window may still win");
+
+    scenario.advance_to_end().await;
+    scenario.assert_status(AuctionStatus::Ended).await;
+    assert!(!scenario.try_end().await, "the end is not drawn yet");
+    assert!(!scenario.try_settle(0).await, "the end is not drawn yet");
+}
+
+#[tokio::test]
+async fn candle_is_drawn_by_the_first_round_after_the_end() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    let (seller, candle_vrf) = (scenario.driver.seller.pubkey(), Pubkey::new_unique());
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(Pubkey::new_unique(), 5, 0, [0; 32]));
+    assert!(!scenario.driver.set_candle(DURATION, candle_vrf).await, "the seller controls the VRF account");
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 5, 0, [0; 32]));
+    scenario.set_candle(DURATION, candle_vrf).await;
+
+    // The first bid leads the first half of the window, the second one the rest
+    scenario.bid(0, 110).await;
+    scenario.advance_time(DURATION / 2).await;
+    scenario.bid(1, 120).await;
+    scenario.advance_to_end().await;
+    let end_time = scenario.driver.state().await.end_time;
+
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 6, end_time, [0; 32]));
+    assert!(!scenario.try_resolve_candle().await, "the round is pending");
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 6, end_time - 1, drawn(2)));
+    assert!(!scenario.try_resolve_candle().await, "a round requested before the end doesn't count");
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 7, end_time, drawn(2)));
+    assert!(!scenario.try_resolve_candle().await, "a re-rolled round doesn't count");
+
+    // The sample 2 of 8 falls into the first half of the window, led by the first bid
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 6, end_time, drawn(auction::CANDLE_SAMPLES as u64 + 2)));
+    scenario.resolve_candle().await;
+    scenario.assert_leader(Some(0), 110).await;
+    assert!(!scenario.try_resolve_candle().await, "the end is drawn once");
+
+    scenario.end().await;
+    scenario.refund(1).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn candle_auction_left_undrawn_fails_and_refunds_every_bid() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    let (seller, candle_vrf) = (scenario.driver.seller.pubkey(), Pubkey::new_unique());
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 5, 0, [0; 32]));
+    scenario.set_candle(DURATION, candle_vrf).await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    scenario.advance_to_end().await;
+
+    // Re-rolling the round voids the draw, the auction waits for the draw window to pass
+    let end_time = scenario.driver.state().await.end_time;
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 7, end_time, drawn(2)));
+    assert!(!scenario.try_resolve_candle().await, "a re-rolled round doesn't count");
+    assert!(!scenario.try_settle(0).await, "the draw window is still open");
+
+    scenario.advance_time(auction::DRAW_WINDOW).await;
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 6, end_time, drawn(2)));
+    assert!(!scenario.try_resolve_candle().await, "the draw window passed");
+    scenario.settle(0).await;
+    scenario.assert_status(AuctionStatus::Failed).await;
+
+    // Nobody won, both bids go back in full
+    scenario.refund(0).await;
+    scenario.refund(1).await;
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn usd_reserve_needs_its_price_feed() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(1).start().await;
+    scenario.set_usd_reserve(Pubkey::new_unique(), 10_000).await;
+
+    assert!(!scenario.try_bid(0, 110).await, "the bid can't be checked against the reserve without the feed");
+    scenario.assert_leader(None, INITIAL_PRICE).await;
+}
+
+#[tokio::test]
+async fn auction_missing_its_usd_reserve_at_settlement_fails() {
+    // A cent is 500 000 lamports at 20 USD for a SOL
+    let (program_test, price_feed) = oracle_test(1_000_000_000);
+    let mut scenario = Scenario::english(DURATION, 400_000).bidders(2).program_test(program_test).start().await;
+    scenario.set_usd_reserve(price_feed, 1).await;
+    scenario.bid(0, 500_000).await;
+    scenario.bid(1, 600_000).await;
+
+    // SOL halves by the end, the winning bid no longer meets the reserve
+    scenario.advance_to_end().await;
+    let now = scenario.driver.clock().await.unix_timestamp;
+    let halved = pyth_price(1_000_000_000, 1_000_000_000, -8, now);
+    scenario.driver.ctx.set_account(&price_feed, &AccountSharedData::from(halved));
+    scenario.end().await;
+    scenario.assert_status(AuctionStatus::Failed).await;
+
+    // Nobody won, both bids go back in full
+    scenario.refund(0).await;
+    scenario.refund(1).await;
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn top_bids_all_win() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(4).start().await;
+    scenario.set_winners(3).await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 130).await;
+    scenario.bid(2, 120).await;
+    assert!(!scenario.try_bid(3, 110).await, "the bid has to beat the lowest winning bid");
+    scenario.bid(3, 115).await;
+    scenario.assert_leader(Some(1), 130).await;
+
+    // The winners pay their own bids, the bid falling out of the winners is refunded
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.assert_escrow(110).await;
+    assert!(!scenario.try_refund(3).await, "the winners paid with their bids");
+    scenario.refund(0).await;
+    scenario.assert_escrow(0).await;
+}
+
+#[tokio::test]
+async fn all_pay_auction_keeps_every_bid() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    scenario.set_all_pay().await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    assert!(!scenario.try_withdraw_bid(0).await, "bids of an all-pay auction stay");
+
+    // The beneficiary receives both bids, the losing one included
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.assert_escrow(0).await;
+    assert!(!scenario.try_refund(0).await, "nobody is refunded");
+}
+
+#[tokio::test]
+async fn penny_auction_keeps_the_bid_fees() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    scenario.set_penny(5, 10, DURATION).await;
+
+    assert!(!scenario.try_bid(0, 120).await, "a bid raises the price by the step only");
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    assert!(!scenario.try_raise(1, 130).await, "the leader can't raise");
+    scenario.assert_escrow(240).await;
+
+    // The beneficiary receives the price and both fees, the losing bid is refunded without its fee
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.assert_escrow(110).await;
+    scenario.refund(0).await;
+    scenario.assert_escrow(0).await;
+}
+
+#[tokio::test]
+async fn reverse_auction_pays_the_lowest_offer() {
+    let mut scenario = Scenario::reverse(DURATION, 1_000).bidders(2).start().await;
+    scenario.assert_escrow(1_000).await;
+
+    scenario.reverse_bid(0, 900).await;
+    scenario.reverse_bid(1, 800).await;
+    assert!(!scenario.try_reverse_bid(0, 850).await, "an offer has to undercut the lowest one");
+    assert!(!scenario.try_bid(0, 1_100).await, "nobody bids upwards");
+    scenario.assert_leader(Some(1), 800).await;
+
+    // The winning seller is paid out of the budget, the rest goes back to the buyer
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn proxy_bid_counters_up_to_its_maximum() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    scenario.set_proxy_bidding().await;
+
+    // The proxy only bids what it takes to lead, escrowing the whole maximum
+    scenario.proxy_bid(0, 200).await;
+    scenario.assert_leader(Some(0), 101).await;
+    scenario.assert_escrow(200).await;
+
+    // A bid below the maximum is countered right away, a higher one takes the lead
+    scenario.bid(1, 150).await;
+    scenario.assert_leader(Some(0), 151).await;
+    assert!(!scenario.try_raise(0, 180).await, "the proxy raises its maximum instead");
+    scenario.proxy_bid(1, 300).await;
+    scenario.assert_leader(Some(1), 201).await;
+
+    // The winner gets back the unused rest of their maximum
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.refund(0).await;
+    scenario.refund(1).await;
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn raffle_waits_for_its_draw() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(3).start().await;
+    let (seller, raffle_vrf) = (scenario.driver.seller.pubkey(), Pubkey::new_unique());
+    scenario.driver.ctx.set_account(&raffle_vrf, &vrf(seller, 0, 0, [0; 32]));
+    scenario.set_raffle(10, raffle_vrf).await;
+
+    scenario.buy_tickets(0, 3).await;
+    scenario.buy_tickets(1, 2).await;
+    assert!(!scenario.try_bid(2, 200).await, "a raffle only sells tickets");
+    assert!(!scenario.try_buy_tickets(0, 1).await, "the tickets of a wallet are numbered in a row");
+    scenario.buy_tickets(1, 1).await;
+    scenario.assert_escrow(60).await;
+    assert!(!scenario.try_withdraw_bid(0).await, "every ticket pays into the pot");
+
+    scenario.advance_to_end().await;
+    scenario.assert_status(AuctionStatus::Ended).await;
+    assert!(!scenario.try_end().await, "the winner is not drawn yet");
+}
+
+#[tokio::test]
+async fn raffle_is_won_by_the_holder_of_the_drawn_ticket() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(3).start().await;
+    let (seller, raffle_vrf) = (scenario.driver.seller.pubkey(), Pubkey::new_unique());
+    scenario.driver.ctx.set_account(&raffle_vrf, &vrf(seller, 2, 0, [0; 32]));
+    scenario.set_raffle(10, raffle_vrf).await;
+
+    // The tickets 0 to 2 go to the first bidder, 3 and 4 to the second one and 5 to the third one
+    scenario.buy_tickets(0, 3).await;
+    scenario.buy_tickets(1, 2).await;
+    scenario.buy_tickets(2, 1).await;
+    scenario.advance_to_end().await;
+    let end_time = scenario.driver.state().await.end_time;
+
+    scenario.driver.ctx.set_account(&raffle_vrf, &vrf(seller, 3, end_time - 1, drawn(4)));
+    assert!(!scenario.try_resolve_raffle(1).await, "a round requested before the end doesn't count");
+    scenario.driver.ctx.set_account(&raffle_vrf, &vrf(seller, 4, end_time, drawn(4)));
+    assert!(!scenario.try_resolve_raffle(1).await, "a re-rolled round doesn't count");
+
+    // The draw is taken modulo the 6 tickets sold
+    scenario.driver.ctx.set_account(&raffle_vrf, &vrf(seller, 3, end_time, drawn(6 * 1_000 + 4)));
+    assert!(!scenario.try_resolve_raffle(0).await, "the ticket 4 isn't in the range of the first bidder");
+    assert!(!scenario.try_resolve_raffle(2).await, "the ticket 4 isn't in the range of the third bidder");
+    scenario.resolve_raffle(1).await;
+    assert_eq!(scenario.driver.state().await.max_bidder, scenario.driver.bidders[1].pubkey());
+
+    // The whole pot goes to the seller, nobody is refunded
+    let before = scenario.driver.lamports(seller).await;
+    scenario.settle(2).await;
+    assert_eq!(scenario.driver.lamports(seller).await - before, 60);
+    scenario.assert_escrow(0).await;
+    assert!(!scenario.try_refund(0).await, "every ticket pays into the pot");
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn raffle_left_undrawn_fails_and_refunds_every_ticket() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    let (seller, raffle_vrf) = (scenario.driver.seller.pubkey(), Pubkey::new_unique());
+    scenario.driver.ctx.set_account(&raffle_vrf, &vrf(seller, 2, 0, [0; 32]));
+    scenario.set_raffle(10, raffle_vrf).await;
+
+    scenario.buy_tickets(0, 3).await;
+    scenario.buy_tickets(1, 2).await;
+    scenario.advance_to_end().await;
+    assert!(!scenario.try_settle(0).await, "the draw window is still open");
+
+    scenario.advance_time(auction::DRAW_WINDOW).await;
+    let end_time = scenario.driver.state().await.end_time;
+    scenario.driver.ctx.set_account(&raffle_vrf, &vrf(seller, 3, end_time, drawn(4)));
+    assert!(!scenario.try_resolve_raffle(1).await, "the draw window passed");
+    scenario.settle(0).await;
+    scenario.assert_status(AuctionStatus::Failed).await;
+
+    // Nobody won, every ticket goes back
+    let before = scenario.driver.lamports(seller).await;
+    scenario.refund(0).await;
+    scenario.refund(1).await;
+    assert_eq!(scenario.driver.lamports(seller).await, before);
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn unclaimed_refunds_are_swept_after_the_expiry() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(3).start().await;
+    scenario.set_refund_expiry(auction::MIN_REFUND_EXPIRY).await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.assert_escrow(110).await;
+    assert!(!scenario.try_sweep(0, 2).await, "the loser still has time to claim the refund");
+
+    scenario.advance_time(auction::MIN_REFUND_EXPIRY).await;
+    scenario.sweep(0, 2).await;
+    scenario.assert_escrow(0).await;
+    assert!(!scenario.try_refund(0).await, "a swept refund is gone");
+}
+
+#[tokio::test]
+async fn winner_claims_the_escrowed_item() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    let (item_mint, seller_tokens, bidder_tokens) = item_accounts(&mut scenario, 5, 0);
+    let escrow = auction_harness::driver::pda(&[b"item", scenario.driver.state.as_ref()]);
+
+    scenario.deposit_item(item_mint, seller_tokens, 5).await;
+    assert_eq!(token_balance(&mut scenario, seller_tokens).await, 0);
+    assert_eq!(token_balance(&mut scenario, escrow).await, 5);
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    assert!(!scenario.try_claim_item(1, bidder_tokens[1]).await, "the item waits for the end");
+
+    scenario.advance_to_end().await;
+    assert!(!scenario.try_claim_item(1, bidder_tokens[1]).await, "the item waits for the settlement");
+    scenario.end().await;
+    assert!(!scenario.try_claim_item(0, bidder_tokens[0]).await, "only the winner claims the item");
+    scenario.claim_item(1, bidder_tokens[1]).await;
+    assert_eq!(token_balance(&mut scenario, bidder_tokens[1]).await, 5);
+    assert_eq!(token_balance(&mut scenario, escrow).await, 0);
+
+    assert!(!scenario.try_claim_item(1, bidder_tokens[1]).await, "the item is claimed once");
+    scenario.advance_time(auction::CLAIM_WINDOW).await;
+    assert!(!scenario.try_recover_item(seller_tokens).await, "a claimed item can't be recovered");
+}
+
+#[tokio::test]
+async fn item_of_an_auction_missing_its_usd_reserve_goes_back_to_the_seller() {
+    // A cent is 500 000 lamports at 20 USD for a SOL
+    let (program_test, price_feed) = oracle_test(1_000_000_000);
+    let mut scenario = Scenario::english(DURATION, 400_000).bidders(1).program_test(program_test).start().await;
+    let (item_mint, seller_tokens, bidder_tokens) = item_accounts(&mut scenario, 5, 0);
+    scenario.deposit_item(item_mint, seller_tokens, 5).await;
+    scenario.set_usd_reserve(price_feed, 1).await;
+    scenario.bid(0, 500_000).await;
+
+    // SOL halves by the end, the winning bid no longer meets the reserve
+    scenario.advance_to_end().await;
+    assert!(!scenario.try_claim_item(0, bidder_tokens[0]).await, "the reserve is checked at the settlement");
+    let now = scenario.driver.clock().await.unix_timestamp;
+    let halved = pyth_price(1_000_000_000, 1_000_000_000, -8, now);
+    scenario.driver.ctx.set_account(&price_feed, &AccountSharedData::from(halved));
+    scenario.end().await;
+
+    assert!(!scenario.try_claim_item(0, bidder_tokens[0]).await, "the bid didn't win");
+    scenario.recover_item(seller_tokens).await;
+    assert_eq!(token_balance(&mut scenario, seller_tokens).await, 5);
+    scenario.refund(0).await;
+}
+
+#[tokio::test]
+async fn candle_winner_claims_the_item_once_drawn_and_settled() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    let (item_mint, seller_tokens, bidder_tokens) = item_accounts(&mut scenario, 5, 0);
+    let (seller, candle_vrf) = (scenario.driver.seller.pubkey(), Pubkey::new_unique());
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 5, 0, [0; 32]));
+    scenario.set_candle(DURATION, candle_vrf).await;
+    scenario.deposit_item(item_mint, seller_tokens, 5).await;
+
+    scenario.bid(0, 110).await;
+    scenario.advance_time(DURATION / 2).await;
+    scenario.bid(1, 120).await;
+    scenario.advance_to_end().await;
+    assert!(!scenario.try_claim_item(1, bidder_tokens[1]).await, "the draw may pick an earlier leader");
+
+    // The sample 2 of 8 falls into the first half of the window, led by the first bid
+    let end_time = scenario.driver.state().await.end_time;
+    scenario.driver.ctx.set_account(&candle_vrf, &vrf(seller, 6, end_time, drawn(2)));
+    scenario.resolve_candle().await;
+    assert!(!scenario.try_claim_item(0, bidder_tokens[0]).await, "the item waits for the settlement");
+
+    scenario.end().await;
+    assert!(!scenario.try_claim_item(1, bidder_tokens[1]).await, "the last bid didn't win");
+    scenario.claim_item(0, bidder_tokens[0]).await;
+    assert_eq!(token_balance(&mut scenario, bidder_tokens[0]).await, 5);
+}
+
+#[tokio::test]
+async fn raffle_winner_claims_the_item_once_drawn_and_settled() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    let (item_mint, seller_tokens, bidder_tokens) = item_accounts(&mut scenario, 5, 0);
+    let (seller, raffle_vrf) = (scenario.driver.seller.pubkey(), Pubkey::new_unique());
+    scenario.driver.ctx.set_account(&raffle_vrf, &vrf(seller, 2, 0, [0; 32]));
+    scenario.set_raffle(10, raffle_vrf).await;
+    scenario.deposit_item(item_mint, seller_tokens, 5).await;
+
+    // The tickets 0 and 1 go to the first bidder, 2 to the second one
+    scenario.buy_tickets(0, 2).await;
+    scenario.buy_tickets(1, 1).await;
+    scenario.advance_to_end().await;
+    let end_time = scenario.driver.state().await.end_time;
+    scenario.driver.ctx.set_account(&raffle_vrf, &vrf(seller, 3, end_time, drawn(2)));
+    scenario.resolve_raffle(1).await;
+    assert!(!scenario.try_claim_item(1, bidder_tokens[1]).await, "the item waits for the settlement");
+
+    scenario.settle(0).await;
+    assert!(!scenario.try_claim_item(0, bidder_tokens[0]).await, "only the drawn ticket wins");
+    scenario.claim_item(1, bidder_tokens[1]).await;
+    assert_eq!(token_balance(&mut scenario, bidder_tokens[1]).await, 5);
+}
+
+#[tokio::test]
+async fn seller_recovers_an_unclaimed_item_after_the_claim_window() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    let (item_mint, seller_tokens, bidder_tokens) = item_accounts(&mut scenario, 5, 0);
+
+    scenario.deposit_item(item_mint, seller_tokens, 5).await;
+    scenario.bid(0, 110).await;
+    scenario.advance_to_end().await;
+    assert!(!scenario.try_recover_item(seller_tokens).await, "the winner still has time to claim the item");
+
+    scenario.advance_time(auction::CLAIM_WINDOW).await;
+    scenario.recover_item(seller_tokens).await;
+    assert_eq!(token_balance(&mut scenario, seller_tokens).await, 5);
+    assert!(!scenario.try_claim_item(0, bidder_tokens[0]).await, "a recovered item can't be claimed");
+}
+
+#[tokio::test]
+async fn gda_sells_whole_tokens_at_a_growing_price() {
+    // Every sale doubles the price of the next token, time leaves it be
+    let (scale, decay) = (2 * auction::PRECISION, auction::PRECISION);
+    let mut scenario = Scenario::gda(DURATION, INITIAL_PRICE, scale, decay).bidders(2).start().await;
+    let (item_mint, seller_tokens, bidder_tokens) = item_accounts(&mut scenario, 2_000, 3);
+    scenario.deposit_item(item_mint, seller_tokens, 2_000).await;
+    assert!(!scenario.try_bid(0, 110).await, "a GDA takes no bids");
+
+    let seller = scenario.driver.seller.pubkey();
+    let before = scenario.driver.lamports(seller).await;
+    assert!(!scenario.try_purchase(0, bidder_tokens[0], INITIAL_PRICE - 1).await, "the price is above the maximum");
+    scenario.purchase(0, bidder_tokens[0], INITIAL_PRICE).await;
+    assert_eq!(scenario.driver.lamports(seller).await - before, INITIAL_PRICE);
+    assert_eq!(token_balance(&mut scenario, bidder_tokens[0]).await, 1_000, "a whole token is sold");
+
+    assert!(!scenario.try_purchase(1, bidder_tokens[1], INITIAL_PRICE).await, "the next token costs more");
+    scenario.purchase(1, bidder_tokens[1], 2 * INITIAL_PRICE).await;
+    assert_eq!(scenario.driver.lamports(seller).await - before, 3 * INITIAL_PRICE);
+    assert_eq!(token_balance(&mut scenario, bidder_tokens[1]).await, 1_000);
+
+    assert!(!scenario.try_purchase(0, bidder_tokens[0], 4 * INITIAL_PRICE).await, "every token is sold");
+    scenario.advance_to_end().await;
+    assert!(!scenario.try_claim_item(0, bidder_tokens[0]).await, "nothing is left to claim");
+}
+
+#[tokio::test]
+async fn gda_purchase_pays_the_creator_royalties() {
+    let (scale, decay) = (2 * auction::PRECISION, auction::PRECISION);
+    let mut scenario = Scenario::gda(DURATION, INITIAL_PRICE, scale, decay).bidders(2).start().await;
+    let (item_mint, seller_tokens, bidder_tokens) = item_accounts(&mut scenario, 2_000, 3);
+    scenario.deposit_item(item_mint, seller_tokens, 2_000).await;
+
+    // A tenth of every sale goes to the creators, 60 and 40 percent of it
+    let creators = [scenario.driver.bidders[1].pubkey(), scenario.driver.admin.pubkey()];
+    let royalties = metadata(item_mint, 1_000, &[(creators[0], 60), (creators[1], 40)]);
+    scenario.driver.ctx.set_account(&auction_harness::driver::metadata(&item_mint), &royalties);
+
+    let seller = scenario.driver.seller.pubkey();
+    let before = [
+        scenario.driver.lamports(seller).await,
+        scenario.driver.lamports(creators[0]).await,
+        scenario.driver.lamports(creators[1]).await,
+    ];
+    assert!(!scenario.try_purchase(0, bidder_tokens[0], INITIAL_PRICE).await, "every creator has to be passed");
+
+    scenario.driver.creators = creators.to_vec();
+    scenario.purchase(0, bidder_tokens[0], INITIAL_PRICE).await;
+    assert_eq!(scenario.driver.lamports(seller).await - before[0], INITIAL_PRICE * 9 / 10);
+    assert_eq!(scenario.driver.lamports(creators[0]).await - before[1], INITIAL_PRICE * 6 / 100);
+    assert_eq!(scenario.driver.lamports(creators[1]).await - before[2], INITIAL_PRICE * 4 / 100);
+}
+
+#[tokio::test]
+async fn oracle_auction_starts_at_the_ema() {
+    // 2.5 SOL on average while trading at 5 SOL
+    let (program_test, price_feed) = oracle_test(250_000_000);
+    let mut scenario = Scenario::oracle(DURATION, price_feed, 1_000_000_000, 10_000_000_000)
+        .program_test(program_test)
+        .bidders(1)
+        .start()
+        .await;
+
+    assert_eq!(scenario.driver.state().await.initial_price, 2_500_000_000, "the EMA is the price, not the spot");
+    assert!(!scenario.try_bid(0, 2_000_000_000).await, "a bid has to beat the EMA");
+    scenario.bid(0, 2_600_000_000).await;
+}
+
+#[tokio::test]
+async fn oracle_starting_price_is_clamped() {
+    let (program_test, price_feed) = oracle_test(250_000_000);
+    let mut scenario = Scenario::oracle(DURATION, price_feed, 1_000_000_000, 2_000_000_000)
+        .program_test(program_test)
+        .start()
+        .await;
+    assert_eq!(scenario.driver.state().await.initial_price, 2_000_000_000, "the EMA is capped by the maximum");
+
+    let (program_test, price_feed) = oracle_test(50_000_000);
+    let mut scenario = Scenario::oracle(DURATION, price_feed, 1_000_000_000, 2_000_000_000)
+        .program_test(program_test)
+        .start()
+        .await;
+    assert_eq!(scenario.driver.state().await.initial_price, 1_000_000_000, "the EMA is raised to the minimum");
+}
+
+#[tokio::test]
+async fn clone_copies_the_parameters_with_fresh_deadlines() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    scenario.set_winners(2).await;
+    scenario.set_refund_expiry(auction::MIN_REFUND_EXPIRY).await;
+    scenario.bid(0, 110).await;
+    scenario.advance_time(DURATION / 2).await;
+    let source = scenario.driver.state().await;
+
+    scenario.clone_auction(2 * DURATION).await;
+    let clone = scenario.driver.state().await;
+    assert_eq!(clone.initial_price, INITIAL_PRICE);
+    assert_eq!(clone.winners, 2);
+    assert_eq!(clone.refund_expiry, auction::MIN_REFUND_EXPIRY);
+    assert_eq!(clone.start_time, source.start_time + DURATION / 2, "the clone starts now");
+    assert_eq!(clone.end_time, clone.start_time + 2 * DURATION);
+    assert_eq!((clone.max_bidder, clone.bidders), (Pubkey::default(), 0), "the bids stay with the source");
+
+    scenario.bid(1, 110).await;
+}
+
+#[tokio::test]
+async fn auctions_escrowing_at_creation_are_not_cloned() {
+    let mut scenario = Scenario::reverse(DURATION, INITIAL_PRICE).start().await;
+    assert!(!scenario.try_clone_auction(DURATION).await, "a reverse auction escrows its budget when created");
+
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).start().await;
+    let payment_mint = Pubkey::new_unique();
+    scenario.driver.ctx.set_account(&payment_mint, &mint(1_000, 6));
+    scenario.set_payment_mint(payment_mint).await;
+    assert!(!scenario.try_clone_auction(DURATION).await, "an auction paid in tokens needs its token treasury");
+}
+
+#[tokio::test]
+async fn registry_compacts_sparse_pages_into_the_tail() {
+    let namespace = Pubkey::default();
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).start().await;
+    let mut auctions = vec![scenario.driver.state];
+    for _ in 0..PAGE_SIZE + 1 {
+        scenario.create_auction(namespace, DURATION, INITIAL_PRICE).await;
+        auctions.push(scenario.driver.state);
+    }
+    let registry = scenario.driver.registry(namespace).await;
+    assert_eq!((registry.head, registry.tail), (0, 1), "the first page filled up");
+
+    // Unsold auctions leave holes in the first page, which is not the tail anymore
+    scenario.advance_to_end().await;
+    for &auction in &auctions[2..PAGE_SIZE] {
+        scenario.driver.follow(auction);
+        scenario.finalize_without_bids().await;
+    }
+    let page = scenario.driver.page(namespace, 0).await.expect("the first page is open");
+    assert_eq!((page.len as usize, page.live), (PAGE_SIZE, 2));
+    assert!(!page.auctions.contains(&auctions[2]), "a removed auction leaves a hole");
+
+    assert!(!scenario.try_compact_page(namespace, 1, &[]).await, "the tail page is never compacted");
+    assert!(!scenario.try_compact_page(namespace, 0, &auctions[2..3]).await, "only listed auctions move");
+    scenario.compact_page(namespace, 0, &auctions[..2]).await;
+
+    assert!(scenario.driver.page(namespace, 0).await.is_none(), "the emptied page is closed");
+    let registry = scenario.driver.registry(namespace).await;
+    assert_eq!((registry.head, registry.tail), (1, 1), "the head moves on once its page is empty");
+    let tail = scenario.driver.page(namespace, 1).await.expect("the tail page is open");
+    let listed = [auctions[PAGE_SIZE], auctions[PAGE_SIZE + 1], auctions[0], auctions[1]];
+    assert_eq!((tail.len, tail.live), (4, 4));
+    assert_eq!(tail.auctions[..4], listed);
+    scenario.driver.follow(auctions[0]);
+    assert_eq!(scenario.driver.state().await.registry_page, 1, "a moved auction knows its new page");
+}
+
+#[tokio::test]
+async fn full_auction_keeps_its_bidders_bidding() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(3).start().await;
+    scenario.set_max_bidders(2).await;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    assert!(!scenario.try_bid(2, 130).await, "a third wallet doesn't fit in");
+
+    scenario.bid(0, 130).await;
+    scenario.bid(1, 140).await;
+    scenario.assert_leader(Some(1), 140).await;
+    assert_eq!(scenario.driver.state().await.bidders, 2, "raising bidders are counted once");
+}
+
+#[tokio::test]
+async fn entry_fee_is_paid_exactly_to_a_rent_exempt_receiver() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(3).start().await;
+    scenario.set_entry_fee(1_000, true).await;
+    let vault = scenario.driver.entry_fee_receiver;
+    let exemption = Rent::default().minimum_balance(0);
+    assert_eq!(scenario.driver.lamports(vault).await, 0);
+
+    // An empty vault would stay short of its rent exemption with the fee
+    assert!(!scenario.try_bid(0, 110).await, "the vault isn't rent-exempt");
+    scenario.driver.ctx.set_account(&vault, &AccountSharedData::new(exemption, 0, &solana_sdk::system_program::ID));
+
+    // Every wallet pays exactly the fee, once
+    scenario.bid(0, 110).await;
+    assert_eq!(scenario.driver.lamports(vault).await, exemption + 1_000);
+    scenario.bid(1, 120).await;
+    assert_eq!(scenario.driver.lamports(vault).await, exemption + 2_000);
+    scenario.bid(0, 130).await;
+    assert_eq!(scenario.driver.lamports(vault).await, exemption + 2_000, "the fee is paid once per wallet");
+}
+
+#[tokio::test]
+async fn every_refund_pays_the_bid_back_and_counts_it_out() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(4).start().await;
+    let first = scenario.driver.state;
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    scenario.bid(2, 130).await;
+    scenario.bid(3, 140).await;
+
+    scenario.create_auction(Pubkey::default(), DURATION, INITIAL_PRICE).await;
+    let second = scenario.driver.state;
+    scenario.bid(0, 110).await;
+    scenario.bid(3, 120).await;
+
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.driver.follow(first);
+    scenario.end().await;
+    assert!(!scenario.try_refund_all(3, &[first, second]).await, "the winner has nothing to refund");
+
+    // A bidder of both auctions is refunded in one go, the bids and the rents of the offers come back
+    let offer_rent = auction_harness::driver::rent(Offer::size());
+    let wallets: Vec<Pubkey> = scenario.driver.bidders.iter().map(|bidder| bidder.pubkey()).collect();
+    let before = scenario.driver.lamports(wallets[0]).await;
+    scenario.refund_all(0, &[first, second]).await;
+    assert_eq!(scenario.driver.lamports(wallets[0]).await - before, 2 * (110 + offer_rent));
+
+    let before = scenario.driver.lamports(wallets[1]).await;
+    scenario.refund(1).await;
+    assert_eq!(scenario.driver.lamports(wallets[1]).await - before, 120 + offer_rent);
+
+    let before = scenario.driver.lamports(wallets[2]).await;
+    scenario.refund_many(&[2]).await;
+    assert_eq!(scenario.driver.lamports(wallets[2]).await - before, 130 + offer_rent);
+
+    // Every bid is counted out, so both auctions close
+    scenario.assert_escrow(0).await;
+    assert_eq!(scenario.driver.state().await.open_bids, 0);
+    scenario.close_auction().await;
+    scenario.driver.follow(second);
+    assert_eq!(scenario.driver.state().await.open_bids, 0);
+    scenario.close_auction().await;
+}
+
+#[tokio::test]
+async fn namespaces_keep_their_registries_and_counters_apart() {
+    let (default, market) = (Pubkey::default(), Pubkey::new_unique());
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).start().await;
+    let first = scenario.driver.state;
+    let mut listed = Vec::new();
+    for _ in 0..2 {
+        scenario.create_auction(market, DURATION, INITIAL_PRICE).await;
+        listed.push(scenario.driver.state);
+    }
+
+    // Each namespace numbers the auctions of the seller on its own
+    assert_eq!(scenario.driver.next_auction_id(default).await, 1);
+    assert_eq!(scenario.driver.next_auction_id(market).await, 2);
+    let state = scenario.driver.state().await;
+    assert_eq!((state.namespace, state.auction_id), (market, 1));
+
+    let registry = scenario.driver.registry(market).await;
+    assert_eq!((registry.namespace, registry.head, registry.tail), (market, 0, 0));
+    let page = scenario.driver.page(market, 0).await.expect("the market lists its auctions");
+    assert_eq!((page.len, page.auctions[..2].to_vec()), (2, listed));
+    let page = scenario.driver.page(default, 0).await.expect("the default namespace lists its auction");
+    assert_eq!((page.len, page.auctions[0]), (1, first));
+}
+
+#[tokio::test]
+async fn surplus_is_shared_by_the_time_weighted_escrow() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(3).start().await;
+    scenario.set_withdrawal_penalty(1_000).await;
+    scenario.set_surplus_sharing().await;
+
+    // The withdrawing bidder leaves 15 behind and forfeits their share of it
+    scenario.bid(0, 150).await;
+    scenario.advance_time(10).await;
+    scenario.bid(1, 200).await;
+    scenario.withdraw_bid(0).await;
+    scenario.bid(2, 300).await;
+    scenario.advance_to_end().await;
+    scenario.end().await;
+
+    // Both remaining bids were locked equally long, so the surplus splits 200 : 300
+    scenario.refund(1).await;
+    scenario.claim_surplus(2).await;
+    let wallets: Vec<Pubkey> = scenario.driver.bidders.iter().map(|bidder| bidder.pubkey()).collect();
+    assert_eq!(scenario.driver.lamports(wallets[0]).await, FUNDS - 15);
+    assert_eq!(scenario.driver.lamports(wallets[1]).await, FUNDS + 6);
+    assert_eq!(scenario.driver.lamports(wallets[2]).await, FUNDS - 300 + 9);
+    scenario.assert_escrow(0).await;
+    scenario.assert_lamports_conserved().await;
+}
+
+#[tokio::test]
+async fn audited_auction_rejects_calls_without_its_log() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    scenario.create_audit_log(16).await;
+    scenario.set_max_bidders(2).await;
+
+    // Setters, bans and bids alike have to pass the log along
+    let audit_log = scenario.driver.audit_log.take();
+    assert!(!scenario.driver.set_max_bidders(1).await, "setters need the log");
+    assert!(!scenario.driver.ban_bidder(1).await, "bans need the log");
+    assert!(!scenario.try_bid(0, 110).await, "bids need the log");
+    scenario.driver.audit_log = audit_log;
+
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.refund(0).await;
+    scenario.assert_lamports_conserved().await;
+
+    let entries = scenario.driver.audit_entries().await;
+    let actions: Vec<AuditAction> = entries.iter().map(|entry| entry.action).collect();
+    assert!(actions == [AuditAction::Configure, AuditAction::Bid, AuditAction::Bid, AuditAction::Settle, AuditAction::Refund]);
+    let seller = scenario.driver.seller.pubkey();
+    assert_eq!((entries[0].actor, entries[3].actor, entries[3].amount), (seller, seller, 120));
+}
+
+#[tokio::test]
+async fn audited_draft_is_published_with_its_log() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).draft().start().await;
+    scenario.create_audit_log(4).await;
+
+    let audit_log = scenario.driver.audit_log.take();
+    assert!(!scenario.driver.publish().await, "publishing needs the log");
+    scenario.driver.audit_log = audit_log;
+    scenario.publish().await;
+    scenario.bid(0, 110).await;
+
+    let actions: Vec<AuditAction> = scenario.driver.audit_entries().await.iter().map(|entry| entry.action).collect();
+    assert!(actions == [AuditAction::Publish, AuditAction::Bid]);
+}
+
+#[tokio::test]
+async fn version_2_auction_is_migrated_to_the_current_layout() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+    scenario.bid(0, 110).await;
+    scenario.bid(1, 120).await;
+
+    // Write the auction back in the layout of version 2 and the offer in the one of version 1: the fields added
+    // since are cut off, the version byte ending the written bytes. Without a payment mint the last 32 bytes of
+    // the account were never written.
+    let state = scenario.driver.state;
+    let offer = auction_harness::driver::pda(&[b"bid", state.as_ref(), scenario.driver.bidders[0].pubkey().as_ref()]);
+    let mut account = scenario.driver.account(state).await.expect("auction exists");
+    let mut data = account.data[..8 + State::size_at(2) - 1 - 32].to_vec();
+    data.push(2);
+    data.resize(8 + State::size_at(2), 0);
+    account.data = data;
+    scenario.driver.ctx.set_account(&state, &AccountSharedData::from(account));
+    let mut account = scenario.driver.account(offer).await.expect("offer exists");
+    let mut data = account.data[..8 + Offer::size_at(1) - 1].to_vec();
+    data.push(1);
+    account.data = data;
+    scenario.driver.ctx.set_account(&offer, &AccountSharedData::from(account));
+    assert!(!scenario.try_bid(0, 130).await, "the old layout doesn't load");
+
+    scenario.migrate_state(&[0]).await;
+    let migrated = scenario.driver.state().await;
+    assert_eq!(migrated.version, STATE_VERSION);
+    assert_eq!(migrated.max_bidder, scenario.driver.bidders[1].pubkey());
+    assert_eq!(migrated.max_price, 120);
+    assert_eq!(migrated.refund_expiry, auction::REFUND_EXPIRY);
+    let account = scenario.driver.account(offer).await.expect("offer exists");
+    let migrated = Offer::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!((migrated.version, migrated.amount, migrated.tickets), (OFFER_VERSION, 110, 0));
+
+    // The auction goes on at its address with its offers
+    scenario.bid(0, 130).await;
+    scenario.advance_to_end().await;
+    scenario.end().await;
+    scenario.refund(1).await;
+    scenario.assert_escrow(0).await;
+}
+
+#[tokio::test]
+async fn legacy_auction_is_migrated() {
+    let mut scenario = Scenario::english(DURATION, INITIAL_PRICE).bidders(2).start().await;
+
+    // A closed auction of the legacy layout at the address of a keypair: bidder 1 won at 120 and was paid out,
+    // the 110 bidder 0 bid are still escrowed in a treasury of its own
+    let legacy = Keypair::new();
+    let treasury = Pubkey::new_unique();
+    let seller = scenario.driver.seller.pubkey();
+    let (loser, winner) = (scenario.driver.bidders[0].pubkey(), scenario.driver.bidders[1].pubkey());
+    let mut data = State::discriminator().to_vec();
+    data.extend_from_slice(seller.as_ref());
+    data.extend_from_slice(treasury.as_ref());
+    data.extend_from_slice(winner.as_ref());
+    data.extend_from_slice(&120u64.to_le_bytes());
+    data.extend_from_slice(&0i64.to_le_bytes());
+    data.push(0);
+    assert_eq!(data.len(), STATUS_OFFSET + 1);
+    let rent = Rent::default();
+    let account = |lamports: u64, data: Vec<u8>| {
+        AccountSharedData::from(Account { lamports, data, owner: auction::ID, executable: false, rent_epoch: 0 })
+    };
+    scenario.driver.ctx.set_account(&legacy.pubkey(), &account(rent.minimum_balance(data.len()), data));
+    scenario.driver.ctx.set_account(&treasury, &account(rent.minimum_balance(0) + 110, vec![]));
+    for (bidder, amount) in [(loser, 110u64), (winner, 120)] {
+        let (offer, bump) = Pubkey::find_program_address(&[b"bid", legacy.pubkey().as_ref(), bidder.as_ref()], &auction::ID);
+        let mut data = Offer::discriminator().to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.push(bump);
+        scenario.driver.ctx.set_account(&offer, &account(rent.minimum_balance(data.len()), data));
+    }
+    scenario.driver.state = legacy.pubkey();
+    scenario.driver.treasury = treasury;
+
+    scenario.migrate_legacy_auction(&legacy, &[0, 1]).await;
+    let migrated = scenario.driver.state().await;
+    assert_eq!(migrated.version, STATE_VERSION);
+    assert_eq!((migrated.initializer, migrated.treasury, migrated.max_bidder), (seller, treasury, winner));
+    assert_eq!((migrated.max_price, migrated.escrowed, migrated.open_bids), (120, 110, 1));
+    scenario.assert_status(AuctionStatus::Settled).await;
+
+    // Only the outbid bidder gets their escrow back
+    assert!(!scenario.try_refund(1).await, "the winner was paid out");
+    scenario.refund(0).await;
+    scenario.assert_escrow(0).await;
+    assert_eq!(scenario.driver.state().await.open_bids, 0);
+}
@@ -0,0 +1,74 @@
+//! Runs an auction with 10,000 bidders against the BPF build of the program and reports the cost
+//! of getting there and of refunding everybody. Instructions are held to a compute budget,
+//! so a regression fails the test instead of only showing up in the numbers.
+//!
+//! `anchor build && cargo test -p auction-harness --features stress --release -- --nocapture`
+#![cfg(feature = "stress")]
+
+use std::time::Instant;
+
+use auction::Offer;
+use auction_harness::driver::{clone, rent, Creation};
+use auction_harness::Driver;
+use solana_program_test::ProgramTest;
+use solana_sdk::signature::Signer;
+
+const BIDDERS: usize = 10_000;
+const INITIAL_PRICE: u64 = 1_000;
+const DURATION: i64 = 3600;
+
+/// Bids or refunds of different wallets fitting into one transaction
+const BATCH: usize = 4;
+
+/// Compute units a transaction of `BATCH` instructions may use
+const COMPUTE_BUDGET: u64 = 200_000;
+
+#[tokio::test]
+async fn ten_thousand_bidders() {
+    // No processor, so the BPF build is loaded and metered
+    let mut program_test = ProgramTest::new("auction", auction::ID, None);
+    program_test.set_bpf_compute_max_units(COMPUTE_BUDGET);
+    let (mut driver, _model) = Driver::create_with(program_test, BIDDERS, DURATION, INITIAL_PRICE, Creation::Live).await;
+
+    // Every bidder outbids the previous one, a batch at a time
+    let started = Instant::now();
+    let payer = driver.ctx.payer.pubkey();
+    let (fees_before, sink_before) = (driver.lamports(payer).await, driver.lamports(driver.sink).await);
+    for batch in (0..BIDDERS).collect::<Vec<_>>().chunks(BATCH) {
+        let ixs = batch.iter().map(|bidder| driver.bid_instruction(*bidder, INITIAL_PRICE + *bidder as u64 + 1)).collect();
+        let signers: Vec<_> = batch.iter().map(|bidder| clone(&driver.bidders[*bidder])).collect();
+
+        assert!(driver.send_all(ixs, &signers.iter().collect::<Vec<_>>()).await, "bids {:?} fit the budget", batch);
+    }
+    let bid_fees = fees_before - driver.lamports(payer).await - (driver.lamports(driver.sink).await - sink_before);
+    let bidding = started.elapsed();
+
+    // Account growth: an offer per bidder, nothing else grows with the number of bidders
+    let state = driver.state().await;
+    assert_eq!(state.bidders as usize, BIDDERS);
+    let offer_bytes = BIDDERS * (8 + Offer::size());
+    let offer_rent = BIDDERS as u64 * rent(Offer::size());
+
+    driver.warp(DURATION).await;
+    assert!(driver.end().await, "auction is settled");
+
+    // Full refund processing of every losing bidder
+    let started = Instant::now();
+    let (fees_before, sink_before) = (driver.lamports(payer).await, driver.lamports(driver.sink).await);
+    for batch in (0..BIDDERS - 1).collect::<Vec<_>>().chunks(BATCH) {
+        let ixs = batch.iter().map(|bidder| driver.refund_instruction(*bidder)).collect();
+        let signers: Vec<_> = batch.iter().map(|bidder| clone(&driver.bidders[*bidder])).collect();
+
+        assert!(driver.send_all(ixs, &signers.iter().collect::<Vec<_>>()).await, "refunds {:?} fit the budget", batch);
+    }
+    let refund_fees = fees_before - driver.lamports(payer).await - (driver.lamports(driver.sink).await - sink_before);
+    let refunding = started.elapsed();
+
+    // Only the rent of the treasury and the winner's offer are left behind
+    assert_eq!(driver.lamports(driver.treasury).await, rent(0));
+
+    println!("bidders:        {}", BIDDERS);
+    println!("bidding:        {:?}, {} lamports of fees", bidding, bid_fees);
+    println!("offer accounts: {} bytes, {} lamports of rent", offer_bytes, offer_rent);
+    println!("refunding:      {:?}, {} lamports of fees", refunding, refund_fees);
+}
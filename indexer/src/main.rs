@@ -0,0 +1,174 @@
+//! Reference off-chain pipeline: follows the auction program over websocket and mirrors
+//! its auctions, bids, settlements and refunds into a local SQLite database.
+//!
+//! Usage: `auction-indexer <websocket url> <database path>`
+
+mod store;
+
+use std::sync::mpsc;
+use std::thread;
+
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator};
+use auction::{
+    AuctionCancelled, AuctionCreated, AuctionUnsold, BidPlaced, RefundSponsored, RefundSwept, Refunded, SettlementSummary,
+    State, WinnerRevealed,
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+};
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::store::Store;
+
+/// Prefix of the log lines Anchor writes the serialized events into
+const EVENT_PREFIX: &str = "Program data: ";
+
+enum Update {
+    Logs { logs: Vec<String>, slot: u64 },
+    Account { pubkey: Pubkey, account: Account, slot: u64 },
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let (url, path) = match (args.next(), args.next()) {
+        (Some(url), Some(path)) => (url, path),
+        _ => {
+            eprintln!("usage: auction-indexer <websocket url> <database path>");
+            std::process::exit(2);
+        }
+    };
+
+    let store = Store::open(&path).expect("database could not be opened");
+    let (sender, receiver) = mpsc::channel();
+
+    let logs = {
+        let (url, sender) = (url.clone(), sender.clone());
+        thread::spawn(move || follow_logs(&url, sender))
+    };
+    let accounts = thread::spawn(move || follow_accounts(&url, sender));
+
+    // A single writer keeps the database consistent
+    for update in receiver {
+        let result = match update {
+            Update::Logs { logs, slot } => logs.iter().try_for_each(|log| index_event(&store, log, slot)),
+            Update::Account { pubkey, account, slot } => index_account(&store, &pubkey, &account, slot),
+        };
+        if let Err(err) = result {
+            eprintln!("failed to index an update: {}", err);
+        }
+    }
+
+    // The channel only closes once both subscriptions dropped
+    for subscription in [logs, accounts] {
+        if let Ok(Err(err)) = subscription.join() {
+            eprintln!("subscription failed: {}", err);
+        }
+    }
+}
+
+fn follow_logs(url: &str, sender: mpsc::Sender<Update>) -> Result<(), String> {
+    let (_subscription, receiver) = PubsubClient::logs_subscribe(
+        url,
+        RpcTransactionLogsFilter::Mentions(vec![auction::ID.to_string()]),
+        RpcTransactionLogsConfig { commitment: Some(CommitmentConfig::confirmed()) }
+    ).map_err(|err| err.to_string())?;
+
+    for response in receiver {
+        // Failed transactions emitted events that never happened
+        let update = Update::Logs { logs: response.value.logs, slot: response.context.slot };
+        if response.value.err.is_none() && sender.send(update).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn follow_accounts(url: &str, sender: mpsc::Sender<Update>) -> Result<(), String> {
+    let config = RpcProgramAccountsConfig {
+        filters: None,
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..RpcAccountInfoConfig::default()
+        },
+        with_context: Some(true),
+    };
+    let (_subscription, receiver) = PubsubClient::program_subscribe(url, &auction::ID, Some(config))
+        .map_err(|err| err.to_string())?;
+
+    for response in receiver {
+        let keyed = response.value;
+        let (pubkey, account) = match (keyed.pubkey.parse(), keyed.account.decode::<Account>()) {
+            (Ok(pubkey), Some(account)) => (pubkey, account),
+            _ => continue,
+        };
+
+        if sender.send(Update::Account { pubkey, account, slot: response.context.slot }).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes an Anchor event from a log line and stores it, other lines are skipped
+fn index_event(store: &Store, log: &str, slot: u64) -> rusqlite::Result<()> {
+    let data = match log.strip_prefix(EVENT_PREFIX).and_then(|data| base64::decode(data).ok()) {
+        Some(data) if data.len() >= 8 => data,
+        _ => return Ok(()),
+    };
+    let (discriminator, mut event) = data.split_at(8);
+
+    if discriminator == AuctionCreated::discriminator() {
+        if let Ok(event) = AuctionCreated::deserialize(&mut event) {
+            store.auction_created(&event)?;
+        }
+    } else if discriminator == AuctionCancelled::discriminator() {
+        if let Ok(event) = AuctionCancelled::deserialize(&mut event) {
+            store.auction_cancelled(&event)?;
+        }
+    } else if discriminator == AuctionUnsold::discriminator() {
+        if let Ok(event) = AuctionUnsold::deserialize(&mut event) {
+            store.auction_unsold(&event)?;
+        }
+    } else if discriminator == SettlementSummary::discriminator() {
+        if let Ok(event) = SettlementSummary::deserialize(&mut event) {
+            store.settled(&event)?;
+        }
+    } else if discriminator == WinnerRevealed::discriminator() {
+        if let Ok(event) = WinnerRevealed::deserialize(&mut event) {
+            store.winner_revealed(&event)?;
+        }
+    } else if discriminator == RefundSwept::discriminator() {
+        if let Ok(event) = RefundSwept::deserialize(&mut event) {
+            store.refund_swept(&event)?;
+        }
+    } else if discriminator == RefundSponsored::discriminator() {
+        if let Ok(event) = RefundSponsored::deserialize(&mut event) {
+            store.refund_sponsored(&event)?;
+        }
+    } else if discriminator == BidPlaced::discriminator() {
+        if let Ok(event) = BidPlaced::deserialize(&mut event) {
+            store.bid_placed(&event, slot)?;
+        }
+    } else if discriminator == Refunded::discriminator() {
+        if let Ok(event) = Refunded::deserialize(&mut event) {
+            store.refund(&event)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Mirrors auction accounts, the other accounts of the program are not indexed
+fn index_account(store: &Store, pubkey: &Pubkey, account: &Account, slot: u64) -> rusqlite::Result<()> {
+    match State::try_deserialize(&mut &account.data[..]) {
+        Ok(state) => store.state_updated(pubkey, &state, slot),
+        Err(_) => Ok(()),
+    }
+}
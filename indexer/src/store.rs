@@ -0,0 +1,233 @@
+use auction::{
+    AuctionCancelled, AuctionCreated, AuctionStatus, AuctionUnsold, BidPlaced, RefundSponsored, RefundSwept, Refunded,
+    SettlementSummary, State, WinnerRevealed,
+};
+use rusqlite::{params, Connection};
+use solana_sdk::pubkey::Pubkey;
+
+/// SQLite mirror of the program, every write being an idempotent upsert so replayed updates are harmless
+pub struct Store {
+    db: Connection,
+}
+
+impl Store {
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        let db = Connection::open(path)?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS auctions (
+                auction TEXT PRIMARY KEY,
+                auction_id INTEGER,
+                initializer TEXT NOT NULL,
+                kind INTEGER,
+                category INTEGER,
+                tags INTEGER,
+                initial_price INTEGER,
+                start_time INTEGER,
+                end_time INTEGER,
+                max_bidder TEXT,
+                max_price INTEGER,
+                bidders INTEGER,
+                status INTEGER NOT NULL,
+                slot INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS bids (
+                auction TEXT NOT NULL,
+                bidder TEXT NOT NULL,
+                amount INTEGER NOT NULL,
+                slot INTEGER NOT NULL,
+                PRIMARY KEY (auction, bidder, amount)
+            );
+            CREATE TABLE IF NOT EXISTS settlements (
+                auction TEXT PRIMARY KEY,
+                auction_id INTEGER NOT NULL,
+                lot_id INTEGER NOT NULL,
+                winner TEXT NOT NULL,
+                gross_price INTEGER NOT NULL,
+                protocol_fee INTEGER NOT NULL,
+                royalties INTEGER NOT NULL,
+                referrer_cut INTEGER NOT NULL,
+                net_proceeds INTEGER NOT NULL,
+                bidders INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS refunds (
+                auction TEXT NOT NULL,
+                bidder TEXT NOT NULL,
+                amount INTEGER NOT NULL,
+                sponsor TEXT,
+                swept INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                PRIMARY KEY (auction, bidder)
+            );"
+        )?;
+
+        Ok(Store { db })
+    }
+
+    pub fn auction_created(&self, event: &AuctionCreated) -> rusqlite::Result<()> {
+        self.db.execute(
+            "INSERT INTO auctions (auction, auction_id, initializer, kind, category, tags, initial_price, start_time, end_time, status, slot)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0)
+             ON CONFLICT (auction) DO UPDATE SET
+                auction_id = ?2, kind = ?4, category = ?5, tags = ?6, initial_price = ?7, start_time = ?8, end_time = ?9",
+            params![
+                event.auction.to_string(),
+                event.auction_id as i64,
+                event.initializer.to_string(),
+                event.kind as u8,
+                event.category as u8,
+                event.tags as i64,
+                event.initial_price as i64,
+                event.start_time,
+                event.end_time,
+                AuctionStatus::Open as u8,
+            ]
+        )?;
+
+        Ok(())
+    }
+
+    /// Mirrors the auction account, a new highest bidder being recorded as a bid
+    pub fn state_updated(&self, auction: &Pubkey, state: &State, slot: u64) -> rusqlite::Result<()> {
+        self.db.execute(
+            "INSERT INTO auctions (auction, auction_id, initializer, kind, category, tags, initial_price, start_time, end_time,
+                                   max_bidder, max_price, bidders, status, slot)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+             ON CONFLICT (auction) DO UPDATE SET
+                end_time = ?9, max_bidder = ?10, max_price = ?11, bidders = ?12, status = ?13, slot = ?14
+             WHERE slot <= ?14",
+            params![
+                auction.to_string(),
+                state.auction_id as i64,
+                state.initializer.to_string(),
+                state.kind as u8,
+                state.category as u8,
+                state.tags as i64,
+                state.initial_price as i64,
+                state.start_time,
+                state.end_time,
+                state.max_bidder.to_string(),
+                state.max_price as i64,
+                state.bidders,
+                state.status as u8,
+                slot as i64,
+            ]
+        )?;
+
+        if state.max_bidder != Pubkey::default() {
+            self.db.execute(
+                "INSERT OR IGNORE INTO bids (auction, bidder, amount, slot) VALUES (?1, ?2, ?3, ?4)",
+                params![auction.to_string(), state.max_bidder.to_string(), state.max_price as i64, slot as i64]
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn settled(&self, event: &SettlementSummary) -> rusqlite::Result<()> {
+        self.db.execute(
+            "INSERT OR REPLACE INTO settlements
+             (auction, auction_id, lot_id, winner, gross_price, protocol_fee, royalties, referrer_cut, net_proceeds, bidders, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                event.auction.to_string(),
+                event.auction_id as i64,
+                event.lot_id as i64,
+                event.winner.to_string(),
+                event.gross_price as i64,
+                event.protocol_fee as i64,
+                event.royalties as i64,
+                event.referrer_cut as i64,
+                event.net_proceeds as i64,
+                event.bidders,
+                event.timestamp,
+            ]
+        )?;
+        self.db.execute(
+            "UPDATE auctions SET status = ?2 WHERE auction = ?1",
+            params![event.auction.to_string(), AuctionStatus::Settled as u8]
+        )?;
+
+        Ok(())
+    }
+
+    pub fn auction_cancelled(&self, event: &AuctionCancelled) -> rusqlite::Result<()> {
+        self.db.execute(
+            "UPDATE auctions SET status = ?2 WHERE auction = ?1",
+            params![event.auction.to_string(), AuctionStatus::Cancelled as u8]
+        )?;
+
+        Ok(())
+    }
+
+    pub fn auction_unsold(&self, event: &AuctionUnsold) -> rusqlite::Result<()> {
+        self.db.execute(
+            "UPDATE auctions SET status = ?2 WHERE auction = ?1",
+            params![event.auction.to_string(), AuctionStatus::Failed as u8]
+        )?;
+
+        Ok(())
+    }
+
+    /// Fills in the winner of a settlement published with a private winner
+    pub fn winner_revealed(&self, event: &WinnerRevealed) -> rusqlite::Result<()> {
+        self.db.execute(
+            "UPDATE settlements SET winner = ?2 WHERE auction = ?1",
+            params![event.auction.to_string(), event.winner.to_string()]
+        )?;
+
+        Ok(())
+    }
+
+    /// Records every bid, also the ones outbid within the slot the account updates only show the last of
+    pub fn bid_placed(&self, event: &BidPlaced, slot: u64) -> rusqlite::Result<()> {
+        self.db.execute(
+            "INSERT OR IGNORE INTO bids (auction, bidder, amount, slot) VALUES (?1, ?2, ?3, ?4)",
+            params![event.auction.to_string(), event.bidder.to_string(), event.amount as i64, slot as i64]
+        )?;
+
+        Ok(())
+    }
+
+    /// Keeps the sponsor of a refund, its `RefundSponsored` event being logged before
+    pub fn refund(&self, event: &Refunded) -> rusqlite::Result<()> {
+        self.db.execute(
+            "INSERT OR IGNORE INTO refunds (auction, bidder, amount, sponsor, swept, timestamp) VALUES (?1, ?2, ?3, NULL, 0, ?4)",
+            params![event.auction.to_string(), event.bidder.to_string(), event.amount as i64, event.timestamp]
+        )?;
+
+        Ok(())
+    }
+
+    pub fn refund_swept(&self, event: &RefundSwept) -> rusqlite::Result<()> {
+        self.refunded(&event.auction, &event.bidder, event.amount, None, true, event.timestamp)
+    }
+
+    pub fn refund_sponsored(&self, event: &RefundSponsored) -> rusqlite::Result<()> {
+        self.refunded(&event.auction, &event.bidder, event.amount, Some(&event.sponsor), false, event.timestamp)
+    }
+
+    fn refunded(
+        &self,
+        auction: &Pubkey,
+        bidder: &Pubkey,
+        amount: u64,
+        sponsor: Option<&Pubkey>,
+        swept: bool,
+        timestamp: i64
+    ) -> rusqlite::Result<()> {
+        self.db.execute(
+            "INSERT OR REPLACE INTO refunds (auction, bidder, amount, sponsor, swept, timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                auction.to_string(),
+                bidder.to_string(),
+                amount as i64,
+                sponsor.map(|sponsor| sponsor.to_string()),
+                swept,
+                timestamp,
+            ]
+        )?;
+
+        Ok(())
+    }
+}
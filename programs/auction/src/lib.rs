@@ -4,13 +4,96 @@ use anchor_lang::{
     prelude::*,
     solana_program::{
         clock::UnixTimestamp,
-        program::invoke,
+        keccak,
+        instruction::{AccountMeta, Instruction},
+        program::{invoke, invoke_signed},
         system_instruction
     }
 };
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use pyth_sdk_solana::{load_price_feed_from_account_info, Price};
+use switchboard_v2::VrfAccountData;
 
 declare_id!("BMuqkhWcrVZpP5esxn7EnNfAe3V3CWxHj73KSsUJ53gL");
 
+/// How long after the end of an auction the losing bidders have to claim their refunds by default, one year
+pub const REFUND_EXPIRY: i64 = 365 * 24 * 60 * 60;
+
+/// Shortest refund expiry a seller can set, a week
+pub const MIN_REFUND_EXPIRY: i64 = 7 * 24 * 60 * 60;
+
+/// How long after the end of an auction the winner has to claim the escrowed item, thirty days
+pub const CLAIM_WINDOW: i64 = 30 * 24 * 60 * 60;
+
+/// How long after the end of an auction the participation snapshots are kept, half a year
+pub const SNAPSHOT_RETENTION: i64 = 182 * 24 * 60 * 60;
+
+/// Maximum number of bidders qualifying for the sealed final round of a hybrid auction
+pub const MAX_FINALISTS: usize = 8;
+
+/// Maximum number of steps of a Dutch price schedule
+pub const MAX_PRICE_STEPS: usize = 8;
+
+/// Basis points in a whole
+pub const BPS: u16 = 10_000;
+
+/// Fixed-point scale of the Gradual Dutch Auction factors, `PRECISION` equals 1.0
+pub const PRECISION: u64 = 1_000_000_000;
+
+/// Seed of the PDA signing the self-invocations of `log_event`
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+/// Oldest oracle price in seconds the program accepts
+pub const ORACLE_MAX_AGE: u64 = 60;
+
+/// Number of auctions listed on a single registry page
+pub const PAGE_SIZE: usize = 32;
+
+/// Maximum number of config changes of a marketplace waiting for activation
+pub const MAX_PENDING_CHANGES: usize = 8;
+
+/// Maximum number of bids standing in the book of a multi-unit auction
+pub const MAX_UNIT_BIDS: usize = 16;
+
+/// Number of equally long segments the activity of an auction is reported in
+pub const REPORT_CHECKPOINTS: usize = 8;
+
+/// Maximum number of wallets banned from a single auction
+pub const MAX_BANS: usize = 32;
+
+/// Number of moments of the candle window of a candle auction its end may fall on
+pub const CANDLE_SAMPLES: usize = 8;
+
+/// How long after the end of a candle auction or a raffle its VRF round may be drawn, a day. An auction
+/// still undrawn by then fails at settlement and every bid or ticket is refunded.
+pub const DRAW_WINDOW: i64 = 24 * 60 * 60;
+
+/// Maximum number of winners of a top-N auction
+pub const MAX_WINNERS: usize = 8;
+
+/// Layout version of new `State` accounts, see `migrate_state`
+pub const STATE_VERSION: u8 = 16;
+
+/// Layout version of new `Offer` accounts, see `migrate_state`
+pub const OFFER_VERSION: u8 = 2;
+
+/// Offset of the status byte in the data of a `State` account, for `memcmp` filters
+pub const STATUS_OFFSET: usize = 8 + 32 + 32 + 32 + 8 + 8;
+
+/// The Metaplex token metadata program, owner of the metadata the royalties of an item are read from
+pub mod token_metadata {
+    use super::*;
+
+    declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+}
+
+/// The Switchboard v2 oracle program, owner of the VRF accounts ending candle auctions
+pub mod switchboard {
+    use super::*;
+
+    declare_id!("SW1TCH7qEPTdLsDHRgPuMQjbQxKdH2aBStViMFnt64f");
+}
+
 #[program]
 pub mod auction {
 
@@ -18,277 +101,8766 @@ pub mod auction {
 
     /// Creates and initialize a new state of our program
     pub fn initialize(ctx: Context<Auction>, auction_duration: i64, initial_price: u64) -> Result<()> {
-        let end_time = Clock::get()?.unix_timestamp.checked_add(auction_duration);
-        if end_time == None {
+        ctx.accounts.start(auction_duration, initial_price)?;
+        announce(&ctx.accounts.state);
+
+        Ok(())
+    }
+
+    /// Creates a new auction starting at the exponential moving average of a Pyth price feed
+    /// (e.g. a collection floor in SOL), clamped between `min_price` and `max_price`. Pyth publishes
+    /// the EMA rather than a time-weighted average, so recent prices weigh more than older ones.
+    pub fn initialize_with_oracle(
+        ctx: Context<AuctionWithOracle>,
+        auction_duration: i64,
+        min_price: u64,
+        max_price: u64
+    ) -> Result<()> {
+        if min_price > max_price {
             return Err(error!(Errors::InvalidOperation));
         }
 
-        let x = Clock::get()?.unix_timestamp;
-        msg!("{x}", x = x);
+        let feed = load_price_feed_from_account_info(&ctx.accounts.price_feed)
+            .map_err(|_| error!(Errors::InvalidOracle))?;
+        let ema = feed
+            .get_ema_price_no_older_than(Clock::get()?.unix_timestamp, ORACLE_MAX_AGE)
+            .ok_or(Errors::StaleOracle)?;
+        let initial_price = oracle_lamports(ema)
+            .ok_or(Errors::InvalidOracle)?
+            .clamp(min_price, max_price);
+
+        ctx.accounts.auction.start(auction_duration, initial_price)?;
+        announce(&ctx.accounts.auction.state);
+
+        Ok(())
+    }
+
+    /// Creates a new auction with the same parameters as one of the seller's existing auctions,
+    /// only the deadlines start over from now. Auctions paid in tokens and reverse auctions escrow
+    /// at their creation, so they can't be cloned, and neither can a candle auction or a raffle, whose
+    /// VRF account draws one auction at a time.
+    pub fn clone_auction(ctx: Context<CloneAuction>, auction_duration: i64) -> Result<()> {
+        let source = (*ctx.accounts.source).clone();
+
+        ctx.accounts.auction.start(auction_duration, source.initial_price)?;
+        ctx.accounts.auction.state.copy_parameters(&source)?;
+        announce(&ctx.accounts.auction.state);
+
+        Ok(())
+    }
+
+    /// Turns an auction into the first round of a series relisting the same format, each failed round
+    /// lowering the initial price of the next one by `decay_bps`, never below `floor_price`
+    pub fn create_series(ctx: Context<CreateSeries>, floor_price: u64, decay_bps: u16) -> Result<()> {
+        if decay_bps > BPS {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        let series = &mut ctx.accounts.series;
+        series.first_auction = ctx.accounts.state.key();
+        series.last_auction = ctx.accounts.state.key();
+        series.initializer = ctx.accounts.initializer.key();
+        series.floor_price = floor_price;
+        series.decay_bps = decay_bps;
+        series.rounds = 1;
+        series.bump = *ctx.bumps.get("series").unwrap();
+
+        Ok(())
+    }
+
+    /// Starts the next round of a series once the last one is over, cloning it with the initial price
+    /// lowered after a failed round. A candle auction or a raffle passes its VRF account among the remaining
+    /// accounts, the next round is drawn by its next request.
+    pub fn relist(ctx: Context<Relist>, auction_duration: i64) -> Result<()> {
+        let last = (*ctx.accounts.last_auction).clone();
+
+        let initial_price = match last.status(last.now()?) {
+            AuctionStatus::Failed => ctx.accounts.series.next_price(last.initial_price),
+            AuctionStatus::Settled => last.initial_price,
+            _ => return Err(error!(Errors::Open)),
+        };
+
+        ctx.accounts.auction.start(auction_duration, initial_price)?;
+        ctx.accounts.auction.state.copy_parameters(&last)?;
+        let state = &mut ctx.accounts.auction.state;
+        if state.candle_window > 0 || state.ticket_price > 0 {
+            let vrf = if state.candle_window > 0 { state.candle_vrf } else { state.raffle_vrf };
+            state.vrf_counter = vrf_requests(state, vrf, ctx.remaining_accounts)?;
+        }
+
+        let series = &mut ctx.accounts.series;
+        series.last_auction = ctx.accounts.auction.state.key();
+        series.rounds = series.rounds.checked_add(1).ok_or(Errors::InvalidOperation)?;
+        announce(&ctx.accounts.auction.state);
+
+        Ok(())
+    }
+
+    /// Creates and initializes a new auction from versioned arguments, older versions
+    /// keep working with the defaults of the parameters added after them
+    pub fn create_auction(ctx: Context<Auction>, args: InitializeArgs) -> Result<()> {
+        let args = args.latest();
+        ctx.accounts.start(args.auction_duration, args.initial_price)?;
 
+        // An auction scheduled for later has all its deadlines counted from its start
         let state = &mut ctx.accounts.state;
-        state.initializer = *ctx.accounts.initializer.key;
-        state.treasury = *ctx.accounts.treasury.key;
-        state.max_bidder = Pubkey::default();
+        if let Some(start_time) = args.start_time {
+            if start_time < state.start_time {
+                return Err(error!(Errors::InvalidOperation));
+            }
+            state.restart(start_time)?;
+        }
+
+        state.max_bidders = args.max_bidders;
+        state.entry_fee = args.entry_fee;
+        if let Some(beneficiary) = args.beneficiary {
+            state.beneficiary = beneficiary;
+        }
+        state.lot_id = args.lot_id;
+        state.buy_now_price = args.buy_now_price;
+        state.category = args.category;
+        state.tags = args.tags;
+        announce(state);
+
+        Ok(())
+    }
+
+    /// Creates an auction in the draft status, the seller can still edit it and nobody can bid
+    /// until it is published
+    pub fn create_draft(ctx: Context<Auction>, auction_duration: i64, initial_price: u64) -> Result<()> {
+        ctx.accounts.start(auction_duration, initial_price)?;
+        ctx.accounts.state.draft = true;
+        ctx.accounts.state.status = AuctionStatus::Draft;
+
+        Ok(())
+    }
+
+    /// Changes the parameters of a draft
+    pub fn edit_draft(ctx: Context<EditDraft>, auction_duration: i64, initial_price: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+
+        state.end_time = state.start_time.checked_add(auction_duration).ok_or(Errors::InvalidOperation)?;
         state.max_price = initial_price;
-        state.end_time = end_time.unwrap();
-        state.open = true;
+        state.runner_up_price = initial_price;
+        state.initial_price = initial_price;
+
+        audit(&ctx.accounts.state, ctx.remaining_accounts, AuditAction::Configure, ctx.accounts.initializer.key(), 0)
+    }
+
+    /// Changes the price and the duration of an English auction, counted from its start, and its buy-now
+    /// price, zero for none. Only possible before anybody bids and while the auction is still running.
+    pub fn update_auction(
+        ctx: Context<Configure>,
+        auction_duration: i64,
+        initial_price: u64,
+        buy_now_price: u64
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let now = state.now()?;
+
+        if state.kind != AuctionKind::English {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        if !matches!(state.status(now), AuctionStatus::Draft | AuctionStatus::Open | AuctionStatus::Extended) {
+            return Err(error!(Errors::Closed));
+        }
+
+        // The new end has to be ahead and within the latest end of the soft close
+        let end_time = state.start_time.checked_add(auction_duration).ok_or(Errors::InvalidOperation)?;
+        if auction_duration <= 0 || end_time <= now || (state.latest_end_time != 0 && end_time > state.latest_end_time) {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.end_time = end_time;
+        state.max_price = initial_price;
+        state.runner_up_price = initial_price;
+        state.initial_price = initial_price;
+        state.buy_now_price = buy_now_price;
+
+        configured(&ctx)
+    }
+
+    /// Sets the category and tag bitmap indexers filter auctions by, only possible before anybody bids
+    pub fn set_category(ctx: Context<Configure>, category: Category, tags: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.category = category;
+        state.tags = tags;
+
+        configured(&ctx)
+    }
+
+    /// Limits the number of distinct bidders, zero meaning no limit, only possible before anybody bids
+    pub fn set_max_bidders(ctx: Context<Configure>, max_bidders: u32) -> Result<()> {
+        ctx.accounts.state.max_bidders = max_bidders;
+
+        configured(&ctx)
+    }
+
+    /// Sets how long after the end the losing bidders have to claim their refunds, `REFUND_EXPIRY` by default
+    /// and `MIN_REFUND_EXPIRY` at least. Only possible before anybody bids.
+    pub fn set_refund_expiry(ctx: Context<Configure>, refund_expiry: i64) -> Result<()> {
+        if refund_expiry < MIN_REFUND_EXPIRY {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        ctx.accounts.state.refund_expiry = refund_expiry;
+
+        configured(&ctx)
+    }
+
+    /// Charges every wallet a non-refundable fee on its first bid, paid to the seller
+    /// or to the protocol vault, only possible before anybody bids. The receiver has to stay rent-exempt,
+    /// so a fee below the exemption needs a funded protocol vault.
+    pub fn set_entry_fee(ctx: Context<Configure>, entry_fee: u64, to_protocol: bool) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.entry_fee = entry_fee;
+        state.entry_fee_receiver = if to_protocol {
+            Pubkey::find_program_address(&[b"protocol", state.namespace.as_ref()], ctx.program_id).0
+        } else {
+            state.initializer
+        };
+
+        configured(&ctx)
+    }
+
+    /// Makes the winner pay the second highest bid, or the initial price without another bid,
+    /// only possible before anybody bids
+    pub fn set_second_price(ctx: Context<Configure>, second_price: bool) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if state.kind == AuctionKind::Dutch || state.kind == AuctionKind::Gda || state.kind == AuctionKind::MultiUnit {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        if second_price && (state.candle_window > 0 || state.winners > 0 || state.all_pay || state.penny_step > 0 || state.proxy_bidding) {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        state.second_price = second_price;
+
+        configured(&ctx)
+    }
+
+    /// Keeps the winner out of the settlement records until they reveal themselves, only possible before
+    /// anybody bids. Every bidder has to conceal their identity before bidding. The program still tracks
+    /// the leading bidder in `max_bidder` and bids are signed in the open, so this only protects the
+    /// published outcome, not the bidding itself.
+    pub fn set_private_winner(ctx: Context<Configure>, private_winner: bool) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if state.kind == AuctionKind::Dutch || state.kind == AuctionKind::Gda || state.kind == AuctionKind::MultiUnit {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        if private_winner && (state.candle_window > 0 || state.winners > 0 || state.ticket_price > 0) {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        state.private_winner = private_winner;
+
+        configured(&ctx)
+    }
+
+    /// Opens bidding at `early_access_start` to holders of `mint` only, everybody may bid from `public_start` on.
+    /// Only possible before anybody bids.
+    pub fn set_early_access(ctx: Context<Configure>, mint: Pubkey, early_access_start: i64, public_start: i64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if early_access_start > public_start || public_start > state.end_time {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.early_access_mint = mint;
+        state.early_access_start = early_access_start;
+        state.public_start = public_start;
+
+        configured(&ctx)
+    }
+
+    /// Rejects bids for the first `warmup` seconds after the start, giving indexers, bots and users time to see
+    /// the listing before the race starts. A draft starts once published. Only possible before anybody bids.
+    pub fn set_warmup(ctx: Context<Configure>, warmup: i64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let warmup_until = state.start_time.checked_add(warmup).ok_or(Errors::InvalidOperation)?;
+        if warmup < 0 || warmup_until > state.end_time {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        state.warmup_until = warmup_until;
+
+        configured(&ctx)
+    }
+
+    /// Creates an audit log recording up to `capacity` state-changing calls on the auction,
+    /// only possible before anybody bids. Every later call has to pass the log among its remaining accounts.
+    pub fn create_audit_log(ctx: Context<CreateAuditLog>, capacity: u32) -> Result<()> {
+        let log = &mut ctx.accounts.audit_log;
+        log.auction = ctx.accounts.state.key();
+        log.capacity = capacity;
+
+        ctx.accounts.state.audited = true;
+
+        Ok(())
+    }
+
+    /// Requires every bid to raise the price by at least `min_increment`. During the last `sprint_window`
+    /// seconds the increment is multiplied by `sprint_factor_bps` / 10 000 after every bid.
+    /// Only possible before anybody bids.
+    pub fn set_increments(
+        ctx: Context<Configure>,
+        min_increment: u64,
+        sprint_window: i64,
+        sprint_factor_bps: u16
+    ) -> Result<()> {
+        if sprint_window < 0 || (sprint_window > 0 && sprint_factor_bps < BPS) {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        let state = &mut ctx.accounts.state;
+        state.min_increment = min_increment;
+        state.sprint_window = sprint_window;
+        state.sprint_factor_bps = sprint_factor_bps;
+
+        configured(&ctx)
+    }
+
+    /// Pushes the end of the auction by `extension` seconds for every bid landing in the last `window` seconds,
+    /// so nobody can win by bidding in the final slot. The end moves by at most `max_extension` seconds in total,
+    /// at most `max_extensions` times (zero for no limit) and never past `latest_end_time` (zero for no limit),
+    /// so colluding bidders can't keep the auction alive forever. Only possible before anybody bids.
+    pub fn set_soft_close(
+        ctx: Context<Configure>,
+        window: i64,
+        extension: i64,
+        max_extension: i64,
+        max_extensions: u16,
+        latest_end_time: i64
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if window < 0 || extension < 0 || max_extension < 0 || (latest_end_time != 0 && latest_end_time < state.end_time) {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        if window > 0 && (state.candle_window > 0 || state.penny_step > 0) {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.soft_close_window = window;
+        state.soft_close_extension = extension;
+        state.max_extension = max_extension;
+        state.max_extensions = max_extensions;
+        state.latest_end_time = latest_end_time;
+
+        configured(&ctx)
+    }
+
+    /// Turns an English auction into a candle auction: its real end is drawn afterwards from the last `window`
+    /// seconds by the `vrf` Switchboard account, the leader of that moment winning, so sniping right before
+    /// the known deadline gains nothing. The seller controls the VRF account, passed among the remaining
+    /// accounts: only its next request counts and it has to be made after the end, see `resolve_candle`. A
+    /// candle not drawn within `DRAW_WINDOW` after the end fails the auction. Bids can't be withdrawn since
+    /// every leader may still win. Doesn't go along with a soft close, a second price, a private winner,
+    /// buy-now or credit bids. Only possible before anybody bids.
+    pub fn set_candle(ctx: Context<Configure>, window: i64, vrf: Pubkey) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if state.kind != AuctionKind::English
+            || window <= 0
+            || window > state.end_time.saturating_sub(state.start_time)
+            || state.soft_close_window != 0
+            || state.second_price
+            || state.private_winner
+            || state.buy_now_price != 0
+            || state.linked_auction != Pubkey::default()
+            || state.winners > 0
+            || state.all_pay
+            || state.penny_step > 0
+            || state.proxy_bidding
+        {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.candle_window = window;
+        state.candle_vrf = vrf;
+        state.vrf_counter = vrf_requests(state, vrf, ctx.remaining_accounts)?;
+
+        configured(&ctx)
+    }
+
+    /// Sets a reserve in US cents, converted to lamports at the current price of the Pyth SOL/USD `price_feed`
+    /// whenever it is checked. Every bid has to reach it and the winning bid has to meet it still when the
+    /// auction settles, otherwise the auction fails and every bid is refunded in full. The feed has to be among
+    /// the remaining accounts of `bid` and `end_auction` then. It only goes with auctions paid in lamports
+    /// without linked lots or credit bids. Only possible before anybody bids.
+    pub fn set_usd_reserve(ctx: Context<Configure>, price_feed: Pubkey, reserve_cents: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if state.kind != AuctionKind::English
+            || state.payment_mint.is_some()
+            || state.linked_auction != Pubkey::default()
+            || state.winners > 0
+            || state.all_pay
+            || state.penny_step > 0
+        {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.usd_reserve = reserve_cents;
+        state.usd_price_feed = price_feed;
+
+        configured(&ctx)
+    }
+
+    /// Lets the `winners` highest bids all win, e.g. for selling that many identical items, each winner paying
+    /// their own bid. A bid has to beat the lowest winning bid once all of the winners are taken, the bids below
+    /// it are refunded after settlement. The seller delivers the items, it doesn't go along with an escrowed
+    /// item, a second price, a private winner, a candle, a USD reserve, buy-now, linked lots, surplus sharing,
+    /// credit bids or withdrawn bids. A single winner turns it off. Only possible before anybody bids.
+    pub fn set_winners(ctx: Context<Configure>, winners: u8) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if state.kind != AuctionKind::English
+            || winners as usize > MAX_WINNERS
+            || state.item_mint != Pubkey::default()
+            || state.second_price
+            || state.private_winner
+            || state.candle_window > 0
+            || state.usd_reserve > 0
+            || state.buy_now_price != 0
+            || state.linked_auction != Pubkey::default()
+            || state.share_surplus
+            || state.all_pay
+            || state.penny_step > 0
+            || state.proxy_bidding
+        {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.winners = if winners > 1 { winners } else { 0 };
+
+        configured(&ctx)
+    }
+
+    /// Makes every bid pay, the classic all-pay auction of fundraisers. Nobody is refunded, `end_auction`
+    /// pays the whole escrow of all of the bids to the beneficiary and the highest bidder still wins. Bids
+    /// can't be withdrawn and it doesn't go along with a second price, top-N winners, a candle, a USD reserve,
+    /// buy-now, linked lots, surplus sharing or credit bids. Only possible before anybody bids.
+    pub fn set_all_pay(ctx: Context<Configure>, all_pay: bool) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        // A raffle stays all-pay, `set_raffle` turns both off
+        if !all_pay && state.ticket_price > 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        if all_pay
+            && (state.kind != AuctionKind::English
+                || state.second_price
+                || state.winners > 0
+                || state.candle_window > 0
+                || state.usd_reserve > 0
+                || state.buy_now_price != 0
+                || state.linked_auction != Pubkey::default()
+                || state.share_surplus
+                || state.penny_step > 0
+                || state.proxy_bidding)
+        {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.all_pay = all_pay;
+
+        configured(&ctx)
+    }
+
+    /// Makes it a penny auction: every bid raises the price by exactly `step` and costs a non-refundable
+    /// `bid_fee`, and the auction ends `countdown` seconds after the latest bid at the earliest. The bid fees
+    /// are kept apart from the escrow of the bids and go to the beneficiary with the price, the losing bids
+    /// are refunded as usual. The leader can't raise their bid. Doesn't go along with a soft close, a second
+    /// price, top-N winners, a candle, a USD reserve, all-pay, buy-now, linked lots, surplus sharing or credit
+    /// bids. A zero `step` turns it off. Only possible before anybody bids.
+    pub fn set_penny(ctx: Context<Configure>, bid_fee: u64, step: u64, countdown: i64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if step == 0 {
+            state.penny_fee = 0;
+            state.penny_step = 0;
+            state.penny_countdown = 0;
+            return configured(&ctx);
+        }
+        if state.kind != AuctionKind::English
+            || countdown <= 0
+            || state.soft_close_window != 0
+            || state.second_price
+            || state.winners > 0
+            || state.candle_window > 0
+            || state.usd_reserve > 0
+            || state.all_pay
+            || state.buy_now_price != 0
+            || state.linked_auction != Pubkey::default()
+            || state.share_surplus
+            || state.proxy_bidding
+        {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.penny_fee = bid_fee;
+        state.penny_step = step;
+        state.penny_countdown = countdown;
+
+        configured(&ctx)
+    }
+
+    /// Lets bidders escrow a maximum with `proxy_bid`, the program bidding for them only what it takes to lead
+    /// and countering the later bids up to that maximum. Doesn't go along with a second price, top-N winners,
+    /// a candle, all-pay, a penny auction, buy-now, linked lots or credit bids. Only possible before anybody bids.
+    pub fn set_proxy_bidding(ctx: Context<Configure>, proxy_bidding: bool) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if proxy_bidding
+            && (state.kind != AuctionKind::English
+                || state.second_price
+                || state.winners > 0
+                || state.candle_window > 0
+                || state.all_pay
+                || state.penny_step > 0
+                || state.buy_now_price != 0
+                || state.linked_auction != Pubkey::default())
+        {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.proxy_bidding = proxy_bidding;
+
+        configured(&ctx)
+    }
+
+    /// Makes it a raffle: bidders buy tickets at `ticket_price` with `buy_tickets` and the `vrf` Switchboard
+    /// account draws the winner among the ticket holders after the end. The seller controls the VRF account,
+    /// passed among the remaining accounts, and only its next request counts, see `resolve_raffle`. Like in an all-pay
+    /// auction nobody is refunded and `end_auction` pays the whole pot to the beneficiary, unless the raffle
+    /// isn't drawn within `DRAW_WINDOW` after the end: it fails then and every ticket is refunded. Doesn't go along
+    /// with a second price, a private winner, top-N winners, a candle, a USD reserve, a penny auction, proxy
+    /// bidding, buy-now, linked lots, surplus sharing or credit bids. A zero `ticket_price` turns it off. Only
+    /// possible before anybody bids.
+    pub fn set_raffle(ctx: Context<Configure>, ticket_price: u64, vrf: Pubkey) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if ticket_price == 0 {
+            if state.ticket_price > 0 {
+                state.all_pay = false;
+            }
+            state.ticket_price = 0;
+            state.raffle_vrf = Pubkey::default();
+            return configured(&ctx);
+        }
+        if state.kind != AuctionKind::English
+            || state.second_price
+            || state.private_winner
+            || state.winners > 0
+            || state.candle_window > 0
+            || state.usd_reserve > 0
+            || state.penny_step > 0
+            || state.proxy_bidding
+            || state.buy_now_price != 0
+            || state.linked_auction != Pubkey::default()
+            || state.share_surplus
+        {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.ticket_price = ticket_price;
+        state.raffle_vrf = vrf;
+        state.vrf_counter = vrf_requests(state, vrf, ctx.remaining_accounts)?;
+        state.all_pay = true;
+
+        configured(&ctx)
+    }
+
+    /// Draws the end of a candle auction from the result of its VRF account, the round of the first request
+    /// after `set_candle`, which has to be made after the end so nobody knew it while bidding. A later round,
+    /// e.g. re-rolling an unwelcome result, never counts. The leader of the drawn moment wins at their price
+    /// then, a moment before the first bid falls to the first leader. Anybody can call it within `DRAW_WINDOW`
+    /// after the end, the auction settles as usual after. Left undrawn, it fails at settlement instead.
+    pub fn resolve_candle(ctx: Context<ResolveCandle>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let now = state.now()?;
+        state.require(now, AuctionStatus::Ended)?;
+        if state.draw_expired(now) {
+            return Err(error!(Errors::DrawExpired));
+        }
+
+        let result = vrf_result(state, &ctx.accounts.vrf)?.ok_or(Errors::CandlePending)?;
+
+        let mut drawn = [0; 8];
+        drawn.copy_from_slice(&result[..8]);
+        let sample = (u64::from_le_bytes(drawn) % CANDLE_SAMPLES as u64) as usize;
+        let leader = state.candle_leaders[sample..]
+            .iter()
+            .find(|leader| leader.bidder != Pubkey::default())
+            .copied()
+            .ok_or(Errors::InvalidOperation)?;
+
+        state.max_bidder = leader.bidder;
+        state.max_price = leader.price;
+        state.candle_resolved = true;
+
+        let window_start = state.end_time.saturating_sub(state.candle_window);
+        emit!(CandleResolved {
+            auction: state.key(),
+            lot_id: state.lot_id,
+            end_time: window_start.saturating_add(state.candle_window * (sample as i64 + 1) / CANDLE_SAMPLES as i64),
+            winner: state.public_winner(),
+            price: leader.price,
+        });
+
+        audit(state, ctx.remaining_accounts, AuditAction::Resolve, leader.bidder, leader.price)
+    }
+
+    /// Draws the winner of a raffle from the result of its VRF account, the round of the first request after
+    /// `set_raffle`, which has to be made after the end so nobody knew it while buying tickets. A later round never
+    /// counts. The `offer` of the holder of the drawn ticket has to be passed along, they win the lot. Anybody can
+    /// call it within `DRAW_WINDOW` after the end, the auction settles as usual after. Left undrawn, it fails at
+    /// settlement instead.
+    pub fn resolve_raffle(ctx: Context<ResolveRaffle>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let now = state.now()?;
+        state.require(now, AuctionStatus::Ended)?;
+        if state.draw_expired(now) {
+            return Err(error!(Errors::DrawExpired));
+        }
+
+        let result = vrf_result(state, &ctx.accounts.vrf)?.ok_or(Errors::RafflePending)?;
+
+        let mut drawn = [0; 8];
+        drawn.copy_from_slice(&result[..8]);
+        let ticket = u64::from_le_bytes(drawn).checked_rem(state.tickets).ok_or(Errors::InvalidOperation)?;
+        let offer = &ctx.accounts.offer;
+        if ticket < offer.first_ticket || ticket - offer.first_ticket >= offer.tickets {
+            return Err(error!(Errors::WrongAccount));
+        }
+
+        state.max_bidder = ctx.accounts.holder.key();
+        state.raffle_drawn = true;
+
+        emit!(RaffleDrawn {
+            auction: state.key(),
+            lot_id: state.lot_id,
+            ticket,
+            winner: state.max_bidder,
+            pot: state.price(),
+        });
+
+        audit(state, ctx.remaining_accounts, AuditAction::Resolve, state.max_bidder, state.price())
+    }
+
+    /// Pushes the end of a running English or hybrid auction by `extension` seconds, e.g. for lots drawing
+    /// interest late. The end never moves past the `latest_end_time` of the soft close, so the maximum duration
+    /// has to be set before anybody bids and auctions without one can't be extended. The sealed round of a hybrid
+    /// auction moves along.
+    pub fn extend_auction(ctx: Context<ExtendAuction>, extension: i64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let now = state.now()?;
+
+        if !matches!(state.kind, AuctionKind::English | AuctionKind::Hybrid) {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        state.require(now, AuctionStatus::Open)?;
+
+        let end_time = state.end_time.checked_add(extension).ok_or(Errors::InvalidOperation)?;
+        if extension <= 0 || state.latest_end_time == 0 || end_time > state.latest_end_time {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.extend(extension)?;
+
+        audit(state, ctx.remaining_accounts, AuditAction::Extend, state.initializer, extension as u64)
+    }
+
+    /// Rejects every later bid of the `bidder`, e.g. of an abusive participant of a long-running auction.
+    /// Both the seller and the admin of the program-wide config may ban, the admin passing the config among
+    /// the remaining accounts. Bids already placed stand and are refunded or settled as usual.
+    pub fn ban_bidder(ctx: Context<BanBidder>, bidder: Pubkey) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let authority = ctx.accounts.authority.key();
+
+        if authority != state.initializer {
+            let address = Pubkey::find_program_address(&[b"global"], ctx.program_id).0;
+            let config = ctx.remaining_accounts
+                .iter()
+                .find(|info| *info.key == address)
+                .ok_or(Errors::WrongAccount)?;
+            let config: Account<GlobalConfig> = Account::try_from(config)?;
+            if config.admin != authority {
+                return Err(error!(Errors::WrongAccount));
+            }
+        }
+
+        let ban_list = &mut ctx.accounts.ban_list;
+        ban_list.auction = state.key();
+        ban_list.bump = *ctx.bumps.get("ban_list").unwrap();
+        if !ban_list.banned.contains(&bidder) {
+            if ban_list.banned.len() >= MAX_BANS {
+                return Err(error!(Errors::InvalidOperation));
+            }
+            ban_list.banned.push(bidder);
+        }
+        state.has_bans = true;
+
+        audit(state, ctx.remaining_accounts, AuditAction::Ban, bidder, 0)
+    }
+
+    /// Keeps `penalty_bps` / 10 000 of the escrow of a bidder withdrawing with `withdraw_bid` in the treasury,
+    /// so bids can't be placed and pulled to move the price for free. Only possible before anybody bids.
+    pub fn set_withdrawal_penalty(ctx: Context<Configure>, penalty_bps: u16) -> Result<()> {
+        if penalty_bps > BPS {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        ctx.accounts.state.withdrawal_penalty_bps = penalty_bps;
+
+        configured(&ctx)
+    }
+
+    /// Only lets bidders attested by the `attestor` program to be in one of the `allowed_regions`
+    /// (a bitmap of region codes) bid, `Pubkey::default()` turning the check off. Only possible before anybody bids.
+    pub fn set_compliance(ctx: Context<Configure>, attestor: Pubkey, allowed_regions: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.attestor = attestor;
+        state.allowed_regions = allowed_regions;
+
+        configured(&ctx)
+    }
+
+    /// Only lets the wallets under the merkle `root` bid, a zero root letting everybody bid. Leaves are
+    /// `keccak(bidder)`, every node hashes its two children in ascending order. Bidders prove their wallet with
+    /// `place_bid`, the other ways of bidding only work for a tree of a single wallet. Only possible before
+    /// anybody bids.
+    pub fn set_allowlist(ctx: Context<Configure>, root: [u8; 32]) -> Result<()> {
+        ctx.accounts.state.allowlist_root = root;
+
+        configured(&ctx)
+    }
+
+    /// Only lets wallets holding at least `amount` tokens of `gate` bid, or with `collection` any NFT of the
+    /// verified collection `gate`, `Pubkey::default()` turning the gate off. Bidders pass their token account
+    /// among the remaining accounts, for a collection along with the metadata of its mint. Only possible
+    /// before anybody bids.
+    pub fn set_token_gate(ctx: Context<Configure>, gate: Pubkey, amount: u64, collection: bool) -> Result<()> {
+        if gate != Pubkey::default() && !collection && amount == 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        let state = &mut ctx.accounts.state;
+        state.token_gate = gate;
+        state.token_gate_amount = amount;
+        state.token_gate_collection = collection;
+
+        configured(&ctx)
+    }
+
+    /// Makes the auction take bids in `payment_mint` tokens instead of lamports, replacing the native
+    /// treasury by a token account owned by the program. Token flows need the token program, the escrow
+    /// authority and the token accounts of the paying or receiving parties among the remaining accounts.
+    /// Only possible for English auctions before anybody bids, and such an auction can't be cloned.
+    pub fn set_payment_mint(ctx: Context<SetPaymentMint>) -> Result<()> {
+        // The native treasury is not needed anymore, give its rent back
+        let previous = ctx.accounts.treasury.lamports();
+        move_lamports(
+            &ctx.accounts.treasury,
+            &ctx.accounts.initializer.to_account_info(),
+            previous
+        )?;
+        treasury_delta(&ctx.accounts.state, &ctx.accounts.treasury, previous, TreasuryReason::Close)?;
+
+        let state = &mut ctx.accounts.state;
+        state.payment_mint = Some(ctx.accounts.payment_mint.key());
+        state.treasury = ctx.accounts.payment_treasury.key();
+
+        audit(&ctx.accounts.state, ctx.remaining_accounts, AuditAction::Configure, ctx.accounts.initializer.key(), 0)
+    }
+
+    /// Shares the surplus of the treasury, whatever it holds on top of the escrow and its rent at the settlement,
+    /// with all bidders pro-rata to their bid amount multiplied by the time it was locked, instead of leaving it
+    /// to the seller. The surplus is made of the withdrawal penalties and any lamports sent to the treasury directly,
+    /// the escrow itself earns nothing. Only possible before anybody bids.
+    pub fn set_surplus_sharing(ctx: Context<Configure>, share_surplus: bool) -> Result<()> {
+        let state = &ctx.accounts.state;
+        if share_surplus && (state.winners > 0 || state.all_pay || state.penny_step > 0) {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        ctx.accounts.state.share_surplus = share_surplus;
+
+        configured(&ctx)
+    }
+
+    /// Makes a draft live, its deadlines start over from now and the parameters can't change anymore
+    pub fn publish(ctx: Context<EditDraft>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+
+        state.restart(state.now()?)?;
+        state.draft = false;
+        state.status = AuctionStatus::Open;
+        announce(state);
+
+        audit(state, ctx.remaining_accounts, AuditAction::Publish, state.initializer, 0)
+    }
+
+    /// Withdraws an auction nobody bid on yet, closing the state and the treasury and giving their rent
+    /// back to the seller. Not possible once an item is escrowed, for token auctions and for the linked
+    /// and multi-unit ones, whose other accounts would be left behind.
+    pub fn cancel_auction(ctx: Context<CancelAuction>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        ctx.accounts.page.remove(&state.key());
+        state.status = AuctionStatus::Cancelled;
+
+        let treasury = &ctx.accounts.treasury;
+        let previous = treasury.lamports();
+        move_lamports(treasury, &ctx.accounts.initializer.to_account_info(), previous)?;
+        treasury_delta(state, treasury, previous, TreasuryReason::Close)?;
+
+        emit!(AuctionCancelled {
+            auction: state.key(),
+            auction_id: state.auction_id,
+            lot_id: state.lot_id,
+            initializer: state.initializer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        audit(state, ctx.remaining_accounts, AuditAction::Cancel, state.initializer, 0)
+    }
+
+    /// Closes an auction which ended without a single bid, giving the escrowed item and the rent of the state,
+    /// the treasury and the escrow back to the seller. There is nothing to pay out, so unlike `end_auction`
+    /// no winner is needed. The item accounts are passed among the remaining accounts. Not possible for
+    /// token auctions and for the linked and multi-unit ones, whose other accounts would be left behind.
+    pub fn finalize_without_bids(ctx: Context<FinalizeWithoutBids>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+
+        // Has the auction ended without a winner?
+        state.require(state.now()?, AuctionStatus::Failed)?;
+
+        ctx.accounts.page.remove(&state.key());
+        state.status = AuctionStatus::Failed;
+
+        let initializer = ctx.accounts.initializer.to_account_info();
+        return_item(state, &initializer, ctx.remaining_accounts)?;
+
+        let treasury = &ctx.accounts.treasury;
+        let previous = treasury.lamports();
+        move_lamports(treasury, &initializer, previous)?;
+        treasury_delta(state, treasury, previous, TreasuryReason::Close)?;
+
+        emit!(AuctionUnsold {
+            auction: state.key(),
+            auction_id: state.auction_id,
+            lot_id: state.lot_id,
+            initializer: state.initializer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        audit(state, ctx.remaining_accounts, AuditAction::Finalize, state.initializer, 0)
+    }
+
+    /// Closes a settled auction, or one failed at settlement, once every bid is refunded or claimed, giving the
+    /// rent of the state and the treasury back to the seller together with whatever the bids left in the
+    /// treasury, e.g. withdrawal penalties. An escrowed item has to be claimed first. A winner who still wants
+    /// the rent of their offer back calls `claim_surplus` before.
+    pub fn close_auction(ctx: Context<CloseAuction>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        state.require_closed()?;
+
+        let treasury = &ctx.accounts.treasury;
+        let previous = treasury.lamports();
+        move_lamports(treasury, &ctx.accounts.initializer.to_account_info(), previous)?;
+        treasury_delta(state, treasury, previous, TreasuryReason::Close)?;
+        audit(state, ctx.remaining_accounts, AuditAction::Close, state.initializer, previous)
+    }
+
+    /// Copies an auction of an older layout version, which no longer loads as the current one, out to its
+    /// migration account, decoded by hand and with the fields added since filled with their defaults. The old
+    /// account is emptied and goes away at the end of the transaction, since this runtime can't grow an account
+    /// in place. `restore_state` then recreates it at the same address in a later transaction, so its offers
+    /// and treasury keep pointing at it. Anybody can call it, the payer getting the rent of the old account back.
+    pub fn migrate_state(ctx: Context<MigrateState>) -> Result<()> {
+        let state = State::decode_versioned(&ctx.accounts.state.try_borrow_data()?)?;
+        ctx.accounts.migration.state = state;
+
+        retire(&ctx.accounts.state, &ctx.accounts.payer.to_account_info())
+    }
+
+    /// Recreates an auction copied out by `migrate_state` or `migrate_legacy_auction` at its old address with the
+    /// current size and closes the migration account. The program signs for an address derived from the seller,
+    /// a legacy auction lives at the address of a keypair, which has to sign. Anybody can call it, paying the rent
+    /// of the grown account.
+    pub fn restore_state(ctx: Context<RestoreState>) -> Result<()> {
+        let migrated = &ctx.accounts.migration.state;
+        let state = &ctx.accounts.state;
+
+        let auction_id = migrated.auction_id.to_le_bytes();
+        let seeds = [b"auction".as_ref(), migrated.namespace.as_ref(), migrated.initializer.as_ref(), auction_id.as_ref()];
+        let (address, bump) = Pubkey::find_program_address(&seeds, ctx.program_id);
+        let signer = [seeds[0], seeds[1], seeds[2], seeds[3], &[bump]];
+        let payer = ctx.accounts.payer.to_account_info();
+        if *state.key == address {
+            create_account_at(state, &payer, 8 + State::size(), &[&signer])?;
+        } else if state.is_signer {
+            create_account_at(state, &payer, 8 + State::size(), &[])?;
+        } else {
+            return Err(error!(Errors::WrongAccount));
+        }
+
+        migrated.try_serialize(&mut &mut state.try_borrow_mut_data()?[..])?;
+
+        emit!(AuctionMigrated {
+            auction: state.key(),
+            lot_id: migrated.lot_id,
+            status: migrated.status,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Copies an offer of an older layout version out to its migration account like `migrate_state`, its auction
+    /// being restored already. `restore_offer` recreates it in a later transaction. A legacy offer holding a bid
+    /// counts into the open bids, unless it won an auction which was settled and so paid out already.
+    pub fn migrate_offer(ctx: Context<MigrateOffer>) -> Result<()> {
+        let data = ctx.accounts.offer.try_borrow_data()?;
+        let legacy = data.len() == 8 + Offer::size_at(0);
+        let offer = Offer::decode_versioned(&data)?;
+        drop(data);
+
+        let state = &mut ctx.accounts.state;
+        let paid_out = state.status == AuctionStatus::Settled && state.max_bidder == ctx.accounts.bidder.key();
+        if legacy && offer.amount > 0 && !paid_out {
+            state.open_bids = state.open_bids.checked_add(1).ok_or(Errors::InvalidOperation)?;
+        }
+        ctx.accounts.migration.offer = offer;
+
+        retire(&ctx.accounts.offer, &ctx.accounts.payer.to_account_info())
+    }
+
+    /// Recreates an offer copied out by `migrate_offer` at its old address with the current size and closes the
+    /// migration account
+    pub fn restore_offer(ctx: Context<RestoreOffer>) -> Result<()> {
+        let offer: &mut Offer = &mut ctx.accounts.offer;
+        *offer = ctx.accounts.migration.offer.clone();
+
+        Ok(())
+    }
+
+    /// Adopts an auction created with the legacy layout, before states and treasuries were derived from the
+    /// seller: its 121 bytes are decoded by hand, see `State::decode_legacy`, and copied out to its migration
+    /// account like `migrate_state`. `restore_state` recreates it at its address, signed by its keypair once more.
+    /// Its bids count in as their offers are migrated with `migrate_offer`. Anybody can call it for free, the
+    /// payer getting the rent of the old account back.
+    pub fn migrate_legacy_auction(ctx: Context<MigrateLegacyAuction>) -> Result<()> {
+        // The treasury holds the escrow on top of its rent
+        let treasury = &ctx.accounts.treasury;
+        let escrowed = treasury.lamports().saturating_sub(Rent::get()?.minimum_balance(treasury.data_len()));
+        let state = State::decode_legacy(&ctx.accounts.state.try_borrow_data()?, escrowed)?;
+        if state.treasury != treasury.key() {
+            return Err(error!(Errors::WrongAccount));
+        }
+        ctx.accounts.migration.state = state;
+
+        retire(&ctx.accounts.state, &ctx.accounts.payer.to_account_info())
+    }
+
+    /// Creates a hybrid auction: an open ascending phase qualifies the top `finalists` bidders,
+    /// who then commit and reveal sealed best-and-final bids in two short phases
+    pub fn initialize_hybrid(
+        ctx: Context<Auction>,
+        auction_duration: i64,
+        initial_price: u64,
+        finalists: u8,
+        commit_duration: i64,
+        reveal_duration: i64
+    ) -> Result<()> {
+        if finalists == 0 || finalists as usize > MAX_FINALISTS {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        ctx.accounts.start(auction_duration, initial_price)?;
+
+        let state = &mut ctx.accounts.state;
+        let commit_end = state.end_time.checked_add(commit_duration).ok_or(Errors::InvalidOperation)?;
+        let reveal_end = commit_end.checked_add(reveal_duration).ok_or(Errors::InvalidOperation)?;
+
+        state.kind = AuctionKind::Hybrid;
+        state.finalists_limit = finalists;
+        state.commit_end = commit_end;
+        state.reveal_end = reveal_end;
+        announce(state);
+
+        Ok(())
+    }
+
+    /// Creates a sealed-bid auction. Bidders commit to a hidden bid with a deposit covering it until the end,
+    /// then reveal it within `reveal_duration`, the highest revealed bid at or above `reserve_price` wins.
+    pub fn initialize_sealed(
+        ctx: Context<Auction>,
+        auction_duration: i64,
+        reserve_price: u64,
+        reveal_duration: i64
+    ) -> Result<()> {
+        if reveal_duration <= 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        ctx.accounts.start(auction_duration, reserve_price)?;
+
+        // Commitments are taken in the open phase, so only the reveal phase follows it
+        let state = &mut ctx.accounts.state;
+        state.kind = AuctionKind::Sealed;
+        state.commit_end = state.end_time;
+        state.reveal_end = state.end_time.checked_add(reveal_duration).ok_or(Errors::InvalidOperation)?;
+        announce(state);
+
+        Ok(())
+    }
+
+    /// Creates a Dutch auction whose price follows the given schedule, the first buyer accepting
+    /// the current price wins immediately. Each step sets the price from its `time` on.
+    pub fn initialize_dutch(ctx: Context<Auction>, auction_duration: i64, steps: Vec<PriceStep>) -> Result<()> {
+        if steps.is_empty() || steps.len() > MAX_PRICE_STEPS {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        // Steps have to follow each other in time and the price must never go up
+        for pair in steps.windows(2) {
+            if pair[1].time <= pair[0].time || pair[1].price > pair[0].price {
+                return Err(error!(Errors::InvalidOperation));
+            }
+        }
+
+        ctx.accounts.start(auction_duration, 0)?;
+
+        let state = &mut ctx.accounts.state;
+        state.kind = AuctionKind::Dutch;
+        state.dutch_curve = DutchCurve::Stepped;
+        state.price_steps_count = steps.len() as u8;
+        state.price_steps[..steps.len()].copy_from_slice(&steps);
+        announce(state);
+
+        Ok(())
+    }
+
+    /// Creates a Dutch auction whose price falls linearly from `start_price` now to `floor_price` at the end,
+    /// the first buyer accepting the current price wins immediately
+    pub fn initialize_linear_dutch(
+        ctx: Context<Auction>,
+        auction_duration: i64,
+        start_price: u64,
+        floor_price: u64
+    ) -> Result<()> {
+        if auction_duration <= 0 || floor_price > start_price {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        ctx.accounts.start(auction_duration, 0)?;
+
+        let state = &mut ctx.accounts.state;
+        state.kind = AuctionKind::Dutch;
+        state.dutch_curve = DutchCurve::Linear;
+        state.price_steps_count = 2;
+        state.price_steps[0] = PriceStep { time: state.start_time, price: start_price };
+        state.price_steps[1] = PriceStep { time: state.end_time, price: floor_price };
+        announce(state);
+
+        Ok(())
+    }
+
+    /// Creates a Gradual Dutch Auction selling the escrowed whole tokens one by one. The price of the next unit
+    /// is `initial_price * scale^sold * decay^elapsed_seconds`, both factors scaled by `PRECISION`.
+    pub fn initialize_gda(
+        ctx: Context<Auction>,
+        auction_duration: i64,
+        initial_price: u64,
+        scale: u64,
+        decay: u64
+    ) -> Result<()> {
+        if scale < PRECISION || decay == 0 || decay > PRECISION {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        ctx.accounts.start(auction_duration, 0)?;
+
+        let state = &mut ctx.accounts.state;
+        state.kind = AuctionKind::Gda;
+        state.gda_initial_price = initial_price;
+        state.gda_scale = scale;
+        state.gda_decay = decay;
+        state.gda_sold = 0;
+        announce(state);
+
+        Ok(())
+    }
+
+    /// Creates an auction of the escrowed units, each bidder asking for a quantity at a unit price.
+    /// Bids fill from the highest unit price down at settlement, the last one possibly only partially,
+    /// and the filled units cost what `pricing` says.
+    pub fn initialize_multi_unit(
+        ctx: Context<MultiUnitAuction>,
+        auction_duration: i64,
+        unit_price_reserve: u64,
+        pricing: UnitPricing
+    ) -> Result<()> {
+        ctx.accounts.auction.start(auction_duration, unit_price_reserve)?;
+
+        let state = &mut ctx.accounts.auction.state;
+        state.kind = AuctionKind::MultiUnit;
+
+        let book = &mut ctx.accounts.book;
+        book.auction = state.key();
+        book.pricing = pricing;
+        book.bump = *ctx.bumps.get("book").unwrap();
+        announce(state);
+
+        Ok(())
+    }
+
+    /// Creates a reverse auction for procurement: the initializer buys, escrowing a `budget` of lamports, and
+    /// sellers undercut each other with `reverse_bid`. The lowest offer wins and is paid out of the budget when
+    /// the auction settles, the rest of the budget going back to the beneficiary. Without an offer the whole
+    /// budget goes back with `finalize_without_bids`. Reverse auctions take no further configuration.
+    pub fn initialize_reverse(ctx: Context<Auction>, auction_duration: i64, budget: u64) -> Result<()> {
+        if budget == 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        ctx.accounts.start(auction_duration, budget)?;
+
+        let accounts = &mut ctx.accounts;
+        collect(
+            &accounts.state,
+            &accounts.treasury,
+            &accounts.initializer.to_account_info(),
+            ctx.remaining_accounts,
+            budget,
+            TreasuryReason::Open
+        )?;
+        accounts.state.reverse = true;
+        announce(&accounts.state);
+
+        Ok(())
+    }
+
+    /// Offers to deliver for `amount` in a reverse auction, undercutting the lowest offer so far or the budget.
+    /// Sellers escrow nothing, the budget of the buyer backs the payment.
+    pub fn reverse_bid(ctx: Context<ReverseBid>, amount: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let seller = &ctx.accounts.seller;
+
+        // Is the auction running already and still, past its warm-up?
+        let now = state.now()?;
+        state.require(now, AuctionStatus::Open)?;
+        if now < state.start_time.max(state.warmup_until) {
+            return Err(error!(Errors::NotStarted));
+        }
+        ctx.accounts.stats.require_live()?;
+
+        // The lowest offer keeps its place against an equal or a higher one
+        if amount == 0 || amount >= state.max_price {
+            return Err(error!(Errors::PriceTooHigh));
+        }
+        if *seller.key == state.max_bidder {
+            return Err(error!(Errors::AlreadyHighestBidder));
+        }
+
+        // An offer in the final seconds gives the others time to answer
+        state.soft_close(now)?;
+
+        let previous = state.max_bidder;
+        state.outbid(*seller.key, amount);
+        notify_outbid(previous, state.key(), amount, ctx.remaining_accounts)?;
+        state.record_bid(now, amount)?;
+
+        bid_placed(state, ctx.remaining_accounts, *seller.key, amount)?;
+        audit(state, ctx.remaining_accounts, AuditAction::Bid, *seller.key, amount)
+    }
+
+    /// Bid
+    pub fn bid(ctx: Context<Bid>, amount: u64) -> Result<()> {
+        ctx.accounts.place(ctx.remaining_accounts, amount, &[], *ctx.bumps.get("offer").unwrap())
+    }
+
+    /// Bids the current price of a penny auction plus its step, whatever the price is when the bid lands
+    pub fn penny_bid(ctx: Context<Bid>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        if state.penny_step == 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        let amount = state.max_price.checked_add(state.penny_step).ok_or(Errors::InvalidOperation)?;
+
+        ctx.accounts.place(ctx.remaining_accounts, amount, &[], *ctx.bumps.get("offer").unwrap())
+    }
+
+    /// Buys `count` tickets of a raffle at its ticket price, see `set_raffle`
+    pub fn buy_tickets(ctx: Context<Bid>, count: u64) -> Result<()> {
+        ctx.accounts.buy_tickets(ctx.remaining_accounts, count, *ctx.bumps.get("offer").unwrap())
+    }
+
+    /// Escrows up to `max_amount` in an auction with proxy bidding and bids the current price plus the increment
+    /// it takes to lead. The program counters the later bids for the bidder up to that maximum, the leader
+    /// raises their maximum calling it again. The winner takes the unused rest back with `refund`.
+    pub fn proxy_bid(ctx: Context<Bid>, max_amount: u64) -> Result<()> {
+        ctx.accounts.proxy(ctx.remaining_accounts, max_amount, *ctx.bumps.get("offer").unwrap())
+    }
+
+    /// Lets the highest bidder raise their own bid to `amount`, paying only the difference to their offer
+    pub fn raise_bid(ctx: Context<Bid>, amount: u64) -> Result<()> {
+        ctx.accounts.raise(ctx.remaining_accounts, amount)
+    }
+
+    /// Bid with versioned arguments, older versions keep working with the defaults of the options added after them
+    pub fn place_bid(ctx: Context<Bid>, args: BidArgs) -> Result<()> {
+        let args = args.latest();
+
+        // A transaction landing later than its bidder was willing to commit is rejected
+        if let Some(max_valid_time) = args.max_valid_time {
+            if ctx.accounts.state.now()? > max_valid_time {
+                return Err(error!(Errors::BidExpired));
+            }
+        }
+
+        ctx.accounts.place(ctx.remaining_accounts, args.amount, &args.proof, *ctx.bumps.get("offer").unwrap())
+    }
+
+    /// Links two English auctions of the seller selling paired lots (e.g. an artwork and its frame), so that
+    /// `bid_linked` bids on both at once, `first_share_bps` of every linked bid going to the first one.
+    /// Only possible before anybody bids on either of them.
+    pub fn link_auctions(ctx: Context<LinkAuctions>, first_share_bps: u16) -> Result<()> {
+        if first_share_bps == 0 || first_share_bps >= BPS {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        let link = &mut ctx.accounts.link;
+        link.first = ctx.accounts.first.key();
+        link.second = ctx.accounts.second.key();
+        link.first_share_bps = first_share_bps;
+        link.bump = *ctx.bumps.get("link").unwrap();
+
+        ctx.accounts.first.linked_auction = link.second;
+        ctx.accounts.second.linked_auction = link.first;
+
+        let initializer = ctx.accounts.initializer.key();
+        audit(&ctx.accounts.first, ctx.remaining_accounts, AuditAction::Link, initializer, first_share_bps as u64)?;
+        audit(&ctx.accounts.second, ctx.remaining_accounts, AuditAction::Link, initializer, (BPS - first_share_bps) as u64)
+    }
+
+    /// Bids `amount` on a pair of linked auctions, split between them by the share of the link.
+    /// Each part is a regular bid on its auction and has to beat its current price.
+    pub fn bid_linked(ctx: Context<BidLinked>, amount: u64) -> Result<()> {
+        let first_amount = (amount as u128 * ctx.accounts.link.first_share_bps as u128 / BPS as u128) as u64;
+        let second_amount = amount - first_amount;
+
+        // Both offers are derived with the same seed name, so their bumps can't come from `ctx.bumps`
+        let offer_bump = |state: &Pubkey| {
+            Pubkey::find_program_address(&[b"bid", state.as_ref(), ctx.accounts.first.buyer.key.as_ref()], ctx.program_id).1
+        };
+        let first_bump = offer_bump(&ctx.accounts.first.state.key());
+        let second_bump = offer_bump(&ctx.accounts.second.state.key());
+
+        ctx.accounts.first.place(ctx.remaining_accounts, first_amount, &[], first_bump)?;
+
+        // Both bids share the global stats, the second one has to see the volume the first one recorded
+        ctx.accounts.second.stats.reload()?;
+
+        ctx.accounts.second.place(ctx.remaining_accounts, second_amount, &[], second_bump)
+    }
+
+    /// Buys the item at the buy-now price of the auction, escrowing it like a bid and settling the auction
+    /// right away. Every other offer can be refunded at once. Only possible while the bids stay below the price.
+    pub fn buy_now(ctx: Context<BuyNow>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let buyer = &ctx.accounts.buyer;
+        let price = state.buy_now_price;
+        let now = state.now()?;
+
+        // Is the auction still running and the buy-now price not reached by the bids?
+        state.require(now, AuctionStatus::Open)?;
+        if now < state.start_time.max(state.warmup_until) {
+            return Err(error!(Errors::NotStarted));
+        }
+        ctx.accounts.stats.require_live()?;
+        check_early_access(state, ctx.remaining_accounts, buyer.key, now)?;
+        check_token_gate(state, ctx.remaining_accounts, buyer.key)?;
+        check_banned(state, ctx.remaining_accounts, buyer.key)?;
+        check_allowlist(state, buyer.key, &[])?;
+        if price == 0 || state.max_price >= price {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        if state.attestor != Pubkey::default() {
+            check_attestation(state, ctx.remaining_accounts, buyer.key)?;
+        }
+
+        // Escrow the rest of the price on top of an earlier bid of the buyer
+        let offer = &mut ctx.accounts.offer;
+        let diff = price.checked_sub(offer.amount).ok_or(Errors::InvalidOperation)?;
+        collect(state, &ctx.accounts.treasury, &buyer.to_account_info(), ctx.remaining_accounts, diff, TreasuryReason::BuyNow)?;
+
+        state.accrue_weight(now);
+        offer.accrue_weight(now, state.closes_at());
+        state.escrowed = state.escrowed.checked_add(diff).ok_or(Errors::InvalidOperation)?;
+
+        if offer.amount == 0 {
+            admit_bidder(state, &buyer.to_account_info(), &ctx.accounts.entry_fee_receiver)?;
+        }
+        offer.amount = price;
+        offer.bump = *ctx.bumps.get("offer").unwrap();
+        offer.version = OFFER_VERSION;
+
+        // The auction ends with the purchase, at the buy-now price even in a second-price auction
+        let previous = state.max_bidder;
+        state.outbid(*buyer.key, price);
+        notify_outbid(previous, state.key(), price, ctx.remaining_accounts)?;
+        state.runner_up_price = price;
+        state.conceal_winner(offer)?;
+        state.winner_on_credit = false;
+        state.record_bid(now, price)?;
+        state.end_time = now;
+        state.status = AuctionStatus::Settled;
+        ctx.accounts.page.remove(&state.key());
+
+        // Whatever the treasury holds above the escrow and its rent is the surplus shared by the bidders
+        if state.share_surplus && state.payment_mint.is_none() {
+            state.accrue_weight(now);
+            state.surplus_pool = ctx.accounts.treasury
+                .lamports()
+                .saturating_sub(Rent::get()?.minimum_balance(0))
+                .saturating_sub(state.escrowed);
+        }
+
+        let protocol_fee = charge_protocol_fee(state, &ctx.accounts.treasury, &ctx.accounts.protocol, ctx.remaining_accounts, price)?;
+        let royalties = pay_royalties(state, &ctx.accounts.treasury, ctx.remaining_accounts, price)?;
+        let net_proceeds = price
+            .checked_sub(protocol_fee)
+            .and_then(|price| price.checked_sub(royalties))
+            .ok_or(Errors::InvalidOperation)?;
+        pay_out(state, &ctx.accounts.treasury, &ctx.accounts.beneficiary, ctx.remaining_accounts, net_proceeds, TreasuryReason::Settle)?;
+        state.close_winning_bid();
+
+        emit_event(ctx.remaining_accounts, SettlementSummary {
+            auction: state.key(),
+            auction_id: state.auction_id,
+            lot_id: state.lot_id,
+            winner: state.public_winner(),
+            winner_hash: state.winner_hash,
+            gross_price: price,
+            protocol_fee,
+            royalties,
+            referrer_cut: 0,
+            net_proceeds,
+            bidders: state.bidders,
+            timestamp: now,
+        })?;
+
+        ctx.accounts.result.record(state, *ctx.bumps.get("result").unwrap())?;
+
+        touch_watches(state.key(), ctx.remaining_accounts)?;
+        audit(state, ctx.remaining_accounts, AuditAction::BuyNow, *buyer.key, price)
+    }
+
+    /// Bids for up to `quantity` units of a multi-unit auction at `unit_price` each, escrowing the whole amount.
+    /// A full book drops its lowest bid for a higher one, the dropped bidder is refunded in full after settlement.
+    pub fn bid_units(ctx: Context<BidUnits>, quantity: u64, unit_price: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let buyer = &ctx.accounts.buyer;
+        let now = state.now()?;
+
+        // Is the auction still running?
+        state.require(now, AuctionStatus::Open)?;
+        ctx.accounts.stats.require_live()?;
+        check_early_access(state, ctx.remaining_accounts, buyer.key, now)?;
+        check_token_gate(state, ctx.remaining_accounts, buyer.key)?;
+        check_banned(state, ctx.remaining_accounts, buyer.key)?;
+        check_allowlist(state, buyer.key, &[])?;
+
+        // Are the units escrowed and is there enough of them?
+        if state.item_amount == 0 {
+            return Err(error!(Errors::NoItem));
+        }
+        if quantity == 0 || quantity > state.item_amount {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        // The initial price is the reserve of every unit
+        if unit_price == 0 || unit_price < state.initial_price {
+            return Err(error!(Errors::BidTooLow));
+        }
+
+        // Compliant auctions need an attestation of the bidder's region among the remaining accounts
+        if state.attestor != Pubkey::default() {
+            check_attestation(state, ctx.remaining_accounts, buyer.key)?;
+        }
+
+        let book = &mut ctx.accounts.book;
+        book.insert(UnitBid {
+            bidder: *buyer.key,
+            quantity,
+            unit_price,
+            filled: 0,
+        })?;
+
+        // Escrow every unit asked for, whatever isn't filled is refunded after settlement
+        let amount = quantity.checked_mul(unit_price).ok_or(Errors::InvalidOperation)?;
+        collect(state, &ctx.accounts.treasury, &buyer.to_account_info(), ctx.remaining_accounts, amount, TreasuryReason::Bid)?;
+
+        // The best bid of the book stands for the auction in its status and listings
+        state.max_bidder = book.entries[0].bidder;
+        state.max_price = book.entries[0].unit_price;
+
+        state.record_bid(now, unit_price)?;
+        admit_bidder(state, &buyer.to_account_info(), &ctx.accounts.entry_fee_receiver)?;
+
+        let offer = &mut ctx.accounts.offer;
+        offer.amount = amount;
+        offer.quantity = quantity;
+        offer.bump = *ctx.bumps.get("offer").unwrap();
+        offer.version = OFFER_VERSION;
+
+        bid_placed(state, ctx.remaining_accounts, *buyer.key, unit_price)?;
+        audit(state, ctx.remaining_accounts, AuditAction::Bid, *buyer.key, amount)
+    }
+
+    /// Settles a multi-unit auction, filling the bids of the book from the highest unit price down.
+    /// The seller receives the price of the filled units, unsold units go back to the seller.
+    pub fn settle_units(ctx: Context<SettleUnits>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let now = state.now()?;
+
+        // An auction without any bid is settled too, returning all of the units
+        if state.status(now) != AuctionStatus::Failed {
+            state.require(now, AuctionStatus::Ended)?;
+        }
+
+        state.status = AuctionStatus::Settled;
+        ctx.accounts.page.remove(&state.key());
+
+        let units = state.item_amount;
+        let proceeds = ctx.accounts.book.allocate(units).ok_or(Errors::InvalidOperation)?;
+        let sold = ctx.accounts.book.filled();
+
+        let protocol_fee = charge_protocol_fee(state, &ctx.accounts.treasury, &ctx.accounts.protocol, ctx.remaining_accounts, proceeds)?;
+        let net_proceeds = proceeds - protocol_fee;
+        pay_out(
+            state,
+            &ctx.accounts.treasury,
+            &ctx.accounts.beneficiary,
+            ctx.remaining_accounts,
+            net_proceeds,
+            TreasuryReason::Settle
+        )?;
+
+        // Only the sold units are left in the escrow for the winners to claim
+        state.item_amount = sold;
+        if units > sold {
+            release_item(
+                &ctx.accounts.token_program,
+                &ctx.accounts.escrow,
+                &ctx.accounts.seller_tokens,
+                &ctx.accounts.item_authority,
+                state.key(),
+                *ctx.bumps.get("item_authority").unwrap(),
+                units - sold
+            )?;
+        }
+
+        emit_event(ctx.remaining_accounts, SettlementSummary {
+            auction: state.key(),
+            auction_id: state.auction_id,
+            lot_id: state.lot_id,
+            winner: state.max_bidder,
+            winner_hash: [0; 32],
+            gross_price: proceeds,
+            protocol_fee,
+            royalties: 0,
+            referrer_cut: 0,
+            net_proceeds,
+            bidders: state.bidders,
+            timestamp: now,
+        })?;
+
+        touch_watches(state.key(), ctx.remaining_accounts)?;
+        audit(state, ctx.remaining_accounts, AuditAction::Settle, state.initializer, proceeds)
+    }
+
+    /// After a multi-unit auction is settled, a bidder takes the units filled for them and the escrow
+    /// of the unfilled ones back, closing the offer
+    pub fn claim_units(ctx: Context<ClaimUnits>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let buyer = &ctx.accounts.buyer;
+
+        // Is the auction already settled?
+        state.require(state.now()?, AuctionStatus::Settled)?;
+
+        // Bids dropped from the book got nothing filled, winners of a uniform price get their overpayment back
+        let book = &ctx.accounts.book;
+        let (filled, paid) = book
+            .find(buyer.key)
+            .map(|bid| (bid.filled, bid.filled * book.unit_cost(bid)))
+            .unwrap_or((0, 0));
+
+        let refund = ctx.accounts.offer.amount.checked_sub(paid).ok_or(Errors::InvalidOperation)?;
+        if refund > 0 {
+            pay_out(state, &ctx.accounts.treasury, &buyer.to_account_info(), ctx.remaining_accounts, refund, TreasuryReason::Refund)?;
+            refunded(state, ctx.remaining_accounts, *buyer.key, refund)?;
+            audit(state, ctx.remaining_accounts, AuditAction::Refund, *buyer.key, refund)?;
+        }
+
+        if filled > 0 {
+            audit(state, ctx.remaining_accounts, AuditAction::ClaimItem, *buyer.key, filled)?;
+            release_item(
+                &ctx.accounts.token_program,
+                &ctx.accounts.escrow,
+                &ctx.accounts.buyer_tokens,
+                &ctx.accounts.item_authority,
+                state.key(),
+                *ctx.bumps.get("item_authority").unwrap(),
+                filled
+            )?;
+        }
+
+        ctx.accounts.state.close_bid(ctx.accounts.offer.amount);
+
+        Ok(())
+    }
+
+    /// Lets a marketplace (signing with its namespace key) approve a market maker for bidding on credit
+    /// up to `limit` lamports across all of its auctions
+    pub fn approve_credit(ctx: Context<ApproveCredit>, limit: u64) -> Result<()> {
+        let credit_line = &mut ctx.accounts.credit_line;
+        credit_line.namespace = ctx.accounts.namespace.key();
+        credit_line.maker = ctx.accounts.maker.key();
+        credit_line.limit = limit;
+        credit_line.bump = *ctx.bumps.get("credit_line").unwrap();
+
+        Ok(())
+    }
+
+    /// Changes the credit limit of a market maker, zero stopping new credit bids
+    pub fn set_credit_limit(ctx: Context<SetCreditLimit>, limit: u64) -> Result<()> {
+        ctx.accounts.credit_line.limit = limit;
+
+        Ok(())
+    }
+
+    /// Adds lamports to the collateral of a credit line
+    pub fn deposit_collateral(ctx: Context<Collateral>, amount: u64) -> Result<()> {
+        invoke(
+            &system_instruction::transfer(ctx.accounts.maker.key, &ctx.accounts.credit_line.key(), amount),
+            &[ctx.accounts.maker.to_account_info(), ctx.accounts.credit_line.to_account_info()]
+        )?;
+
+        let credit_line = &mut ctx.accounts.credit_line;
+        credit_line.collateral = credit_line.collateral.checked_add(amount).ok_or(Errors::InvalidOperation)?;
+
+        Ok(())
+    }
+
+    /// Takes collateral back, as long as the rest still covers the outstanding credit bids
+    pub fn withdraw_collateral(ctx: Context<Collateral>, amount: u64) -> Result<()> {
+        let credit_line = &mut ctx.accounts.credit_line;
+
+        let collateral = credit_line.collateral.checked_sub(amount).ok_or(Errors::InvalidOperation)?;
+        if collateral < credit_line.used {
+            return Err(error!(Errors::CreditLimitExceeded));
+        }
+        credit_line.collateral = collateral;
+
+        move_lamports(&credit_line.to_account_info(), &ctx.accounts.maker.to_account_info(), amount)
+    }
+
+    /// Bids without escrowing anything, the bid counts against the credit limit of the market maker.
+    /// Settlement draws the price from the collateral, a losing bid only frees the limit again.
+    pub fn bid_on_credit(ctx: Context<BidOnCredit>, amount: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let maker = &ctx.accounts.maker;
+        let credit_line = &mut ctx.accounts.credit_line;
+        let credit_bid = &mut ctx.accounts.credit_bid;
+
+        let now = state.now()?;
+        ctx.accounts.stats.require_live()?;
+        check_bid(state, ctx.remaining_accounts, maker.key, amount, now)?;
+        check_allowlist(state, maker.key, &[])?;
+        check_banned(state, ctx.remaining_accounts, maker.key)?;
+        if *maker.key == state.max_bidder {
+            return Err(error!(Errors::AlreadyHighestBidder));
+        }
+
+        // Only the difference to the previous credit bid is added to the exposure
+        let diff = amount.checked_sub(credit_bid.amount).ok_or(Errors::InvalidOperation)?;
+        let used = credit_line.used.checked_add(diff).ok_or(Errors::InvalidOperation)?;
+        if used > credit_line.limit {
+            return Err(error!(Errors::CreditLimitExceeded));
+        }
+        credit_line.used = used;
+
+        state.outbid(*maker.key, amount);
+        state.winner_on_credit = true;
+        state.record_bid(now, amount)?;
+
+        if credit_bid.amount == 0 {
+            admit_bidder(state, &maker.to_account_info(), &ctx.accounts.entry_fee_receiver)?;
+        }
+        credit_bid.amount = amount;
+        credit_bid.bump = *ctx.bumps.get("credit_bid").unwrap();
+
+        bid_placed(state, ctx.remaining_accounts, *maker.key, amount)?;
+        audit(state, ctx.remaining_accounts, AuditAction::Bid, *maker.key, amount)
+    }
+
+    /// Frees the credit limit taken by a credit bid which didn't win, anyone can call it once the auction is settled
+    pub fn release_credit(ctx: Context<ReleaseCredit>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        state.require_closed()?;
+
+        let credit_line = &mut ctx.accounts.credit_line;
+        credit_line.used = credit_line.used.saturating_sub(ctx.accounts.credit_bid.amount);
+        ctx.accounts.state.close_bid(ctx.accounts.credit_bid.amount);
+
+        audit(
+            &ctx.accounts.state,
+            ctx.remaining_accounts,
+            AuditAction::Release,
+            ctx.accounts.maker.key(),
+            ctx.accounts.credit_bid.amount
+        )
+    }
+
+    /// After an auction ends (determined by `auction_duration`), a seller can claim the
+    /// heighest bid by calling this instruction
+    ///
+    /// An auction of an item pays the creator royalties of its Metaplex metadata first, the metadata and
+    /// the creators are passed among the remaining accounts. Multi-unit auctions allocate their units
+    /// with `settle_units` instead.
+    pub fn end_auction(ctx: Context<Finish>) -> Result<()> {
+        // Paired lots won by the same wallet are only settled together by `settle_linked`
+        let state = &ctx.accounts.state;
+        if state.linked_auction != Pubkey::default() {
+            check_unpaired(state, ctx.remaining_accounts)?;
+        }
+
+        ctx.accounts.settle(ctx.remaining_accounts, *ctx.bumps.get("result").unwrap())
+    }
+
+    /// Settles an ended auction for an absent seller, anyone can call it once the auction ended with a winner.
+    /// The price goes to the beneficiary like with `end_auction`, the caller pays the rent of the report
+    /// and the result.
+    pub fn settle(ctx: Context<Settle>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        if state.linked_auction != Pubkey::default() {
+            check_unpaired(state, ctx.remaining_accounts)?;
+        }
+
+        let accounts = ctx.accounts;
+        settle_escrow(
+            &mut accounts.state,
+            &mut accounts.page,
+            &accounts.treasury,
+            &accounts.beneficiary,
+            &accounts.max_bidder,
+            &accounts.protocol,
+            ctx.remaining_accounts
+        )?;
+        accounts.report.record(&accounts.state);
+        accounts.result.record(&accounts.state, *ctx.bumps.get("result").unwrap())?;
+
+        let state = &accounts.state;
+        touch_watches(state.key(), ctx.remaining_accounts)?;
+        audit(state, ctx.remaining_accounts, AuditAction::Settle, accounts.cranker.key(), state.price())
+    }
+
+    /// Settles both auctions of a linked pair won by the same wallet, which can't be settled one by one
+    pub fn settle_linked(ctx: Context<SettleLinked>) -> Result<()> {
+        if ctx.accounts.first.state.max_bidder != ctx.accounts.second.state.max_bidder {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        // Both results are derived with the same seed name, so their bumps can't come from `ctx.bumps`
+        let result_bump = |state: &Pubkey| Pubkey::find_program_address(&[b"result", state.as_ref()], ctx.program_id).1;
+        let first_bump = result_bump(&ctx.accounts.first.state.key());
+        let second_bump = result_bump(&ctx.accounts.second.state.key());
+
+        ctx.accounts.first.settle(ctx.remaining_accounts, first_bump)?;
+
+        // Both auctions may be listed on the same page, the second settlement has to see the first one removed
+        ctx.accounts.first.page.exit(ctx.program_id)?;
+        ctx.accounts.second.page.reload()?;
+
+        ctx.accounts.second.settle(ctx.remaining_accounts, second_bump)
+    }
+
+    /// Closes the report of a settled auction, returning its rent to the seller
+    pub fn close_report(_ctx: Context<CloseReport>) -> Result<()> {
+        Ok(())
+    }
+
+    /// After an auction ends (the initializer/seller already received the winning bid), 
+    /// the unsuccessfull bidders can claim their money back by calling this instruction
+    pub fn refund(ctx: Context<Refund>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+
+        // Is the auction already settled, or failed at settlement?
+        state.require_closed()?;
+
+        // Transfer lamports or tokens back to the bidder, together with the share of the surplus
+        let amount = ctx.accounts.offer.amount;
+        refund_one(
+            state,
+            &ctx.accounts.treasury,
+            &mut ctx.accounts.offer,
+            &ctx.accounts.buyer.to_account_info(),
+            ctx.remaining_accounts
+        )?;
+
+        audit(state, ctx.remaining_accounts, AuditAction::Refund, *ctx.accounts.buyer.key, amount)
+    }
+
+    /// Gives an outbid bidder of a running English auction their escrow back right away instead of after the
+    /// end, closing the offer. The withdrawal penalty of the escrow stays in the treasury, shared with the bidders
+    /// if the auction shares its surplus. They forfeit their share of it, bidding again counts as a new bidder.
+    pub fn withdraw_bid(ctx: Context<WithdrawBid>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let now = state.now()?;
+        state.require(now, AuctionStatus::Open)?;
+
+        // The offer leaves the escrow together with the lamport-seconds it accrued
+        let offer = &mut ctx.accounts.offer;
+        state.accrue_weight(now);
+        offer.accrue_weight(now, state.closes_at());
+        state.total_weight = state.total_weight.saturating_sub(offer.weight);
+        state.escrowed = state.escrowed.checked_sub(offer.amount).ok_or(Errors::InvalidOperation)?;
+
+        let penalty = (offer.amount as u128 * state.withdrawal_penalty_bps as u128 / BPS as u128) as u64;
+        pay_out(
+            state,
+            &ctx.accounts.treasury,
+            &ctx.accounts.buyer.to_account_info(),
+            ctx.remaining_accounts,
+            offer.amount - penalty,
+            TreasuryReason::Refund
+        )?;
+
+        let amount = offer.amount;
+        offer.amount = 0;
+        state.close_bid(amount);
+
+        emit!(BidWithdrawn {
+            auction: state.key(),
+            lot_id: state.lot_id,
+            bidder: *ctx.accounts.buyer.key,
+            amount,
+            penalty,
+            timestamp: now,
+        });
+
+        audit(state, ctx.remaining_accounts, AuditAction::Refund, *ctx.accounts.buyer.key, amount)
+    }
+
+    /// Carries an event emitted by the program in its instruction data, see `emit_event`. Only the program
+    /// itself can call it, signing with the event authority.
+    pub fn log_event(_ctx: Context<LogEvent>, _data: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Follows an auction, the watch changes whenever the auction is settled or cranked so bots can notify
+    /// the user by subscribing to the watch alone
+    pub fn create_watch(ctx: Context<CreateWatch>) -> Result<()> {
+        let watch = &mut ctx.accounts.watch;
+        watch.auction = ctx.accounts.state.key();
+        watch.user = ctx.accounts.user.key();
+        watch.bump = *ctx.bumps.get("watch").unwrap();
+
+        Ok(())
+    }
+
+    /// Stops following an auction, returning the rent of the watch
+    pub fn close_watch(_ctx: Context<CloseWatch>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Creates the notify account of a bidder, which changes whenever they are outbid in any auction
+    /// with it among the remaining accounts of the bid, so wallets can subscribe to it alone
+    pub fn create_notify(ctx: Context<CreateNotify>) -> Result<()> {
+        let notify = &mut ctx.accounts.notify;
+        notify.bidder = ctx.accounts.bidder.key();
+        notify.bump = *ctx.bumps.get("notify").unwrap();
+
+        Ok(())
+    }
+
+    /// Stops the outbid notifications, returning the rent of the notify account
+    pub fn close_notify(_ctx: Context<CloseNotify>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Records the highest amount the bidder escrowed in the auction, so that DAOs can weight governance rewards
+    /// by verified participation. Offers only grow until the refund closes them, so the snapshot has to be taken
+    /// before it, taking it again only raises the peak.
+    pub fn snapshot_participation(ctx: Context<SnapshotParticipation>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let snapshot = &mut ctx.accounts.snapshot;
+        snapshot.auction = state.key();
+        snapshot.bidder = ctx.accounts.bidder.key();
+        snapshot.peak_escrow = snapshot.peak_escrow.max(ctx.accounts.offer.amount);
+        snapshot.closes_at = state.closes_at();
+        snapshot.bump = *ctx.bumps.get("snapshot").unwrap();
+
+        Ok(())
+    }
+
+    /// Closes a participation snapshot once `SNAPSHOT_RETENTION` has passed since the end of the auction,
+    /// returning the rent to the bidder
+    pub fn close_snapshot(ctx: Context<CloseSnapshot>) -> Result<()> {
+        let retained_until = ctx.accounts.snapshot.closes_at.saturating_add(SNAPSHOT_RETENTION);
+        if Clock::get()?.unix_timestamp < retained_until {
+            return Err(error!(Errors::SnapshotRetained));
+        }
+
+        Ok(())
+    }
+
+    /// Lets a marketplace (signing with its namespace key) register a sponsor which may execute refunds
+    /// on behalf of bidders, paying the transaction fees
+    pub fn register_sponsor(ctx: Context<RegisterSponsor>) -> Result<()> {
+        let sponsorship = &mut ctx.accounts.sponsorship;
+        sponsorship.namespace = ctx.accounts.namespace.key();
+        sponsorship.sponsor = ctx.accounts.sponsor.key();
+        sponsorship.bump = *ctx.bumps.get("sponsorship").unwrap();
+
+        Ok(())
+    }
+
+    /// Revokes a sponsor, returning the rent to the marketplace
+    pub fn remove_sponsor(_ctx: Context<RemoveSponsor>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Creates the config of a marketplace (signing with its namespace key), holding the defaults of its new auctions.
+    /// Every auction created in the namespace from then on starts with them.
+    pub fn create_config(ctx: Context<CreateConfig>, min_increment: u64, entry_fee: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.namespace = ctx.accounts.namespace.key();
+        config.min_increment = min_increment;
+        config.entry_fee = entry_fee;
+        config.bump = *ctx.bumps.get("config").unwrap();
+
+        Ok(())
+    }
+
+    /// Schedules a change of the config taking effect at `activates_at`, e.g. a fee holiday. Nobody has to
+    /// apply it on time, the first instruction reading the config after that applies it.
+    pub fn schedule_config_change(ctx: Context<UpdateConfig>, change: ConfigChange, activates_at: i64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let now = Clock::get()?.unix_timestamp;
+        config.apply_due(now);
+
+        if activates_at <= now {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        config.schedule(PendingChange { change, activates_at })
+    }
+
+    /// Drops a scheduled change which did not take effect yet, `index` into the pending changes
+    pub fn cancel_config_change(ctx: Context<UpdateConfig>, index: u8) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.apply_due(Clock::get()?.unix_timestamp);
+
+        config.cancel(index as usize)
+    }
+
+    /// Sets the cap on the new escrow all bids of the program may add within `window` seconds, 0 for no cap.
+    /// Only the upgrade authority of the program may change it, a new window starts right away. While a cap
+    /// is set, every bid has to pass the global stats writable and the bids of all auctions land one after
+    /// the other, without a cap the stats are only read.
+    pub fn set_volume_cap(ctx: Context<SetVolumeCap>, volume_cap: u64, window: i64) -> Result<()> {
+        if window < 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        let stats = &mut ctx.accounts.stats;
+        stats.volume_cap = volume_cap;
+        stats.window = window;
+        stats.window_start = Clock::get()?.unix_timestamp;
+        stats.window_volume = 0;
+
+        Ok(())
+    }
+
+    /// Creates the program-wide config with its `admin`, the limits of every new auction and the protocol fee,
+    /// together with the vault collecting the fee. Only the upgrade authority of the program may create them,
+    /// the admin manages them from then on.
+    pub fn initialize_config(
+        ctx: Context<InitializeGlobalConfig>,
+        admin: Pubkey,
+        max_duration: i64,
+        min_increment: u64,
+        fee_bps: u16
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = admin;
+        config.bump = *ctx.bumps.get("config").unwrap();
+        ctx.accounts.fee_vault.bump = *ctx.bumps.get("fee_vault").unwrap();
+
+        config.set(max_duration, min_increment, fee_bps)
+    }
+
+    /// Changes the limits and the fee of the program-wide config, handing it over to `admin` at the same time
+    pub fn update_config(
+        ctx: Context<UpdateGlobalConfig>,
+        admin: Pubkey,
+        max_duration: i64,
+        min_increment: u64,
+        fee_bps: u16
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = admin;
+
+        config.set(max_duration, min_increment, fee_bps)
+    }
+
+    /// Pauses or resumes the program. While paused, no auction is created and no bid is placed, settlement
+    /// and refunds keep working so that no funds get stuck. Only the admin of the config may pause, the flag
+    /// is mirrored into the global stats every bid and every new auction passes anyway.
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        ctx.accounts.config.paused = paused;
+        ctx.accounts.stats.paused = paused;
+
+        Ok(())
+    }
+
+    /// Sends `amount` of the collected protocol fees to `to`, only the admin of the config may withdraw
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        let vault = ctx.accounts.fee_vault.to_account_info();
+        let rent = Rent::get()?.minimum_balance(vault.data_len());
+        if vault.lamports().saturating_sub(rent) < amount {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        move_lamports(&vault, &ctx.accounts.to, amount)
+    }
+
+    /// Sends `amount` of the protocol fees collected in tokens to `to`, only the admin of the config may withdraw
+    pub fn withdraw_fee_tokens(ctx: Context<WithdrawFeeTokens>, amount: u64) -> Result<()> {
+        let bump = ctx.accounts.fee_vault.bump;
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.vault_tokens.to_account_info(),
+                    to: ctx.accounts.to.to_account_info(),
+                    authority: ctx.accounts.fee_vault.to_account_info(),
+                },
+                &[&[b"fees", &[bump]]]
+            ),
+            amount
+        )
+    }
+
+    /// Same as `refund`, but executed by a registered sponsor, so the bidder never has to sign.
+    /// The refund and the rent of the offer still go to the bidder.
+    pub fn refund_for(ctx: Context<RefundFor>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let now = state.now()?;
+
+        // Is the auction already settled, or failed at settlement?
+        state.require_closed()?;
+
+        // Transfer lamports or tokens back to the bidder, together with the share of the surplus
+        let offer = &mut ctx.accounts.offer;
+        let payout = state.refundable(offer, ctx.accounts.bidder.key)
+            .checked_add(state.surplus_share(offer))
+            .ok_or(Errors::InvalidOperation)?;
+        pay_out(state, &ctx.accounts.treasury, &ctx.accounts.bidder, ctx.remaining_accounts, payout, TreasuryReason::Refund)?;
+
+        let amount = offer.amount;
+        offer.amount = 0;
+
+        emit!(RefundSponsored {
+            auction: state.key(),
+            lot_id: state.lot_id,
+            bidder: *ctx.accounts.bidder.key,
+            sponsor: *ctx.accounts.sponsor.key,
+            amount: payout,
+            timestamp: now,
+        });
+
+        refunded(state, ctx.remaining_accounts, *ctx.accounts.bidder.key, payout)?;
+        audit(state, ctx.remaining_accounts, AuditAction::Refund, *ctx.accounts.bidder.key, amount)?;
+        ctx.accounts.state.close_bid(amount);
+
+        Ok(())
+    }
+
+    /// Refunds a bidder in many finished auctions at once, `remaining_accounts` holding
+    /// a (state, offer, treasury) triplet for every auction, optionally followed by the event accounts.
+    /// The states are writable, they count the refunded bid out.
+    pub fn refund_all<'info>(ctx: Context<'_, '_, '_, 'info, RefundAll<'info>>) -> Result<()> {
+        let buyer = ctx.accounts.buyer.to_account_info();
+        let (triplets, event_accounts) = split_event_accounts(ctx.remaining_accounts);
+
+        if triplets.len() % 3 != 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        for accounts in triplets.chunks(3) {
+            let (state_info, offer_info, treasury) = (&accounts[0], &accounts[1], &accounts[2]);
+            let mut state: Account<State> = Account::try_from(state_info)?;
+            let mut offer: Account<Offer> = Account::try_from(offer_info)?;
+
+            // Same checks as the accounts of `refund`
+            if state.treasury != *treasury.key {
+                return Err(error!(Errors::WrongAccount));
+            }
+            let expected = Pubkey::create_program_address(
+                &[b"bid", state.key().as_ref(), buyer.key.as_ref(), &[offer.bump]],
+                ctx.program_id
+            ).map_err(|_| error!(Errors::WrongAccount))?;
+            if expected != offer.key() {
+                return Err(error!(Errors::WrongAccount));
+            }
+            if !state.is_closed() || !state.is_ended(state.now()?) {
+                return Err(error!(Errors::Open));
+            }
+            if state.kind == AuctionKind::MultiUnit {
+                return Err(error!(Errors::InvalidOperation));
+            }
+            if state.all_pay {
+                return Err(error!(Errors::AllPay));
+            }
+            if !state.may_refund(buyer.key) {
+                return Err(error!(Errors::WinnerRefund));
+            }
+
+            // There is no room for the audit log in the triplets, audited auctions use `refund`
+            if state.audited {
+                return Err(error!(Errors::AuditLogRequired));
+            }
+
+            // Neither for token accounts, token auctions use `refund` too
+            if state.payment_mint.is_some() {
+                return Err(error!(Errors::UnsupportedCurrency));
+            }
+
+            // Pay the bid and its surplus share back and close the offer, returning its rent too
+            refund_one(&mut state, treasury, &mut offer, &buyer, event_accounts)?;
+            state.exit(ctx.program_id)?;
+            offer.close(buyer.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Refunds many losing bidders of a finished auction at once, `remaining_accounts` holding an
+    /// (offer, bidder) pair for every bidder, optionally followed by the event accounts. Anyone can call it, the bids and the rent of the offers
+    /// only ever go to their bidders.
+    pub fn refund_many<'info>(ctx: Context<'_, '_, '_, 'info, RefundMany<'info>>) -> Result<()> {
+        let (pairs, event_accounts) = split_event_accounts(ctx.remaining_accounts);
+
+        refund_pairs(&mut ctx.accounts.state, &ctx.accounts.treasury, pairs, event_accounts)
+    }
+
+    /// Lets the `keeper` finish the auction after its end with `crank_settlement`, e.g. a Clockwork thread
+    /// or any bot signing for it, the program doesn't care which. The program doesn't create the thread,
+    /// the seller points it at the address of one created beforehand, e.g. right after
+    /// `initialize`. The `SettlementScheduled` event tells the keeper when. The default pubkey turns it
+    /// off. Only possible before anybody bids.
+    pub fn schedule_settlement(ctx: Context<Configure>, keeper: Pubkey) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        if state.kind == AuctionKind::MultiUnit {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.keeper = keeper;
+        if keeper != Pubkey::default() {
+            emit!(SettlementScheduled {
+                auction: state.key(),
+                lot_id: state.lot_id,
+                keeper,
+                settle_at: state.closes_at(),
+            });
+        }
+
+        configured(&ctx)
+    }
+
+    /// Settles an auction scheduled with `schedule_settlement` and refunds the losing bidders of the
+    /// (offer, bidder) pairs among the remaining accounts, like `settle` and `refund_many` in one go. Only
+    /// the keeper calls it, paying the rent of the report and the result. The first call settles, every
+    /// call refunds, so the keeper runs it until every bidder got their bid back.
+    pub fn crank_settlement<'info>(ctx: Context<'_, '_, '_, 'info, CrankSettlement<'info>>) -> Result<()> {
+        let accounts = ctx.accounts;
+        let (pairs, event_accounts) = split_event_accounts(ctx.remaining_accounts);
+
+        // The fee accounts are fixed ones, the settlement never sees the pairs
+        if !accounts.state.is_closed() {
+            settle_escrow(
+                &mut accounts.state,
+                &mut accounts.page,
+                &accounts.treasury,
+                &accounts.beneficiary,
+                &accounts.max_bidder,
+                &accounts.protocol,
+                event_accounts
+            )?;
+            accounts.report.record(&accounts.state);
+            accounts.result.record(&accounts.state, *ctx.bumps.get("result").unwrap())?;
+        }
+
+        if pairs.is_empty() {
+            return Ok(());
+        }
+
+        refund_pairs(&mut accounts.state, &accounts.treasury, pairs, event_accounts)
+    }
+
+    /// After the refund expiry of the auction passes since its end, anyone can sweep a refund
+    /// that was never claimed to the protocol vault, closing the offer
+    pub fn sweep_expired_refund(ctx: Context<SweepExpiredRefund>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let now = state.now()?;
+
+        // Has the refund already expired?
+        if !state.refund_expired(now)? {
+            return Err(error!(Errors::RefundNotExpired));
+        }
+
+        // Move the forfeited lamports, including the surplus share, to the protocol
+        let offer = &mut ctx.accounts.offer;
+        let amount = state.refundable(offer, ctx.accounts.bidder.key)
+            .checked_add(state.surplus_share(offer))
+            .ok_or(Errors::InvalidOperation)?;
+        let previous = ctx.accounts.treasury.lamports();
+        move_lamports(&ctx.accounts.treasury, &ctx.accounts.protocol_vault, amount)?;
+        treasury_delta(state, &ctx.accounts.treasury, previous, TreasuryReason::Sweep)?;
+        let swept = offer.amount;
+        offer.amount = 0;
+
+        emit!(RefundSwept {
+            auction: state.key(),
+            lot_id: state.lot_id,
+            bidder: *ctx.accounts.bidder.key,
+            amount,
+            timestamp: now,
+        });
+
+        touch_watches(state.key(), ctx.remaining_accounts)?;
+        audit(state, ctx.remaining_accounts, AuditAction::Sweep, *ctx.accounts.bidder.key, amount)?;
+        ctx.accounts.state.close_bid(swept);
+
+        Ok(())
+    }
+
+    /// Buys the item of a Dutch auction at the current price, paying the seller and settling the auction
+    pub fn accept_price(ctx: Context<AcceptPrice>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let buyer = &ctx.accounts.buyer;
+        let now = state.now()?;
+
+        // Is the auction still running?
+        state.require(now, AuctionStatus::Open)?;
+
+        // Pay the protocol fee, the royalties and the beneficiary directly, there are no other bids to refund
+        let price = state.dutch_price(now);
+        let (protocol_fee, royalties) = pay_direct(
+            state,
+            &buyer.to_account_info(),
+            &ctx.accounts.protocol,
+            &ctx.accounts.beneficiary,
+            ctx.remaining_accounts,
+            price
+        )?;
+
+        // The auction ends with the sale
+        state.max_bidder = *buyer.key;
+        state.max_price = price;
+        state.bidders = 1;
+        state.end_time = now;
+        state.status = AuctionStatus::Settled;
+        ctx.accounts.page.remove(&state.key());
+
+        emit_event(ctx.remaining_accounts, SettlementSummary {
+            auction: state.key(),
+            auction_id: state.auction_id,
+            lot_id: state.lot_id,
+            winner: state.max_bidder,
+            winner_hash: [0; 32],
+            gross_price: price,
+            protocol_fee,
+            royalties,
+            referrer_cut: 0,
+            net_proceeds: price - protocol_fee - royalties,
+            bidders: state.bidders,
+            timestamp: now,
+        })?;
+
+        ctx.accounts.result.record(state, *ctx.bumps.get("result").unwrap())?;
+
+        touch_watches(state.key(), ctx.remaining_accounts)?;
+        audit(state, ctx.remaining_accounts, AuditAction::AcceptPrice, *buyer.key, price)
+    }
+
+    /// Buys the next unit of a Gradual Dutch Auction at its current price, paying the seller directly.
+    /// `max_price` protects the buyer from paying more than expected.
+    pub fn purchase(ctx: Context<Purchase>, max_price: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let buyer = &ctx.accounts.buyer;
+        let now = state.now()?;
+
+        // Is the auction still running?
+        state.require(now, AuctionStatus::Open)?;
+
+        // Is a whole token left?
+        let unit = 10u64.checked_pow(ctx.accounts.item_mint.decimals as u32).ok_or(Errors::InvalidOperation)?;
+        if state.item_amount < unit {
+            return Err(error!(Errors::SoldOut));
+        }
+
+        let price = state.gda_price(now).ok_or(Errors::InvalidOperation)?;
+        if price > max_price {
+            return Err(error!(Errors::PriceTooHigh));
+        }
+
+        pay_direct(
+            state,
+            &buyer.to_account_info(),
+            &ctx.accounts.protocol,
+            &ctx.accounts.beneficiary,
+            ctx.remaining_accounts,
+            price
+        )?;
+
+        // Every sale pushes the price of the next unit up
+        state.gda_sold = state.gda_sold.checked_add(1).ok_or(Errors::InvalidOperation)?;
+        state.item_amount -= unit;
+
+        audit(state, ctx.remaining_accounts, AuditAction::Purchase, *buyer.key, price)?;
+        release_item(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.buyer_tokens,
+            &ctx.accounts.item_authority,
+            state.key(),
+            *ctx.bumps.get("item_authority").unwrap(),
+            unit
+        )
+    }
+
+    /// A finalist of a hybrid auction commits to a sealed best-and-final bid,
+    /// `commitment` being `keccak(amount as u64 LE || salt)`
+    pub fn commit_final_bid(ctx: Context<CommitFinalBid>, commitment: [u8; 32]) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let now = state.now()?;
+
+        // Is the sealed round running?
+        state.require_window(now, state.end_time, state.commit_end)?;
+
+        ctx.accounts.offer.commitment = commitment;
+
+        audit(state, ctx.remaining_accounts, AuditAction::Commit, ctx.accounts.buyer.key(), 0)
+    }
+
+    /// A finalist of a hybrid auction reveals the committed bid, paying the difference to the treasury,
+    /// and takes the lead if it beats every bid revealed so far
+    pub fn reveal_final_bid(ctx: Context<RevealFinalBid>, amount: u64, salt: [u8; 32]) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let offer = &mut ctx.accounts.offer;
+        let buyer = &ctx.accounts.buyer;
+        let now = state.now()?;
+
+        // Is the reveal phase running?
+        state.require_window(now, state.commit_end, state.reveal_end)?;
+
+        // Does the bid match the commitment?
+        let hash = keccak::hashv(&[&amount.to_le_bytes(), &salt]);
+        if offer.commitment == [0; 32] || hash.to_bytes() != offer.commitment {
+            return Err(error!(Errors::InvalidReveal));
+        }
+        offer.commitment = [0; 32];
+
+        // Only a bid beating the current leader matters
+        if amount <= state.max_price {
+            return Err(error!(Errors::BidTooLow));
+        }
+
+        // Move the difference to the already escrowed open-phase bid to the treasury
+        let diff = amount.checked_sub(offer.amount).ok_or(Errors::InvalidOperation)?;
+        let previous = ctx.accounts.treasury.lamports();
+        invoke(
+            &system_instruction::transfer(
+                buyer.key,
+                ctx.accounts.treasury.key,
+                diff
+            ),
+            &[
+                buyer.to_account_info().clone(),
+                ctx.accounts.treasury.clone()
+            ]
+        )?;
+        treasury_delta(state, &ctx.accounts.treasury, previous, TreasuryReason::Reveal)?;
+
+        state.accrue_weight(now);
+        offer.accrue_weight(now, state.closes_at());
+        state.escrowed = state.escrowed.checked_add(diff).ok_or(Errors::InvalidOperation)?;
+
+        offer.amount = amount;
+        state.outbid(*buyer.key, amount);
+        state.conceal_winner(offer)?;
+
+        bid_placed(state, ctx.remaining_accounts, *buyer.key, amount)?;
+        audit(state, ctx.remaining_accounts, AuditAction::Reveal, *buyer.key, amount)
+    }
+
+    /// Commits to a sealed bid, `commitment` being `keccak(amount as u64 LE || salt)`, escrowing `deposit`
+    /// on top of the previous deposits. A later commitment replaces the earlier one.
+    pub fn commit_bid(ctx: Context<CommitBid>, commitment: [u8; 32], deposit: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let buyer = &ctx.accounts.buyer;
+        let now = state.now()?;
+
+        // Is the commit phase running?
+        state.require(now, AuctionStatus::Open)?;
+        ctx.accounts.stats.require_live()?;
+        check_early_access(state, ctx.remaining_accounts, buyer.key, now)?;
+        check_token_gate(state, ctx.remaining_accounts, buyer.key)?;
+        check_banned(state, ctx.remaining_accounts, buyer.key)?;
+        check_allowlist(state, buyer.key, &[])?;
+        if state.attestor != Pubkey::default() {
+            check_attestation(state, ctx.remaining_accounts, buyer.key)?;
+        }
+
+        let offer = &mut ctx.accounts.offer;
+        if commitment == [0; 32] || offer.amount.checked_add(deposit).ok_or(Errors::InvalidOperation)? == 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        // Count every wallet only once, on its first commitment
+        if offer.amount == 0 {
+            admit_bidder(state, &buyer.to_account_info(), &ctx.accounts.entry_fee_receiver)?;
+        }
+
+        collect(state, &ctx.accounts.treasury, &buyer.to_account_info(), ctx.remaining_accounts, deposit, TreasuryReason::Bid)?;
+        state.accrue_weight(now);
+        offer.accrue_weight(now, state.closes_at());
+        state.escrowed = state.escrowed.checked_add(deposit).ok_or(Errors::InvalidOperation)?;
+
+        offer.amount += deposit;
+        offer.commitment = commitment;
+        offer.bump = *ctx.bumps.get("offer").unwrap();
+        offer.version = OFFER_VERSION;
+
+        audit(state, ctx.remaining_accounts, AuditAction::Bid, *buyer.key, deposit)
+    }
+
+    /// Reveals a sealed bid, which takes the lead if it beats every bid revealed so far.
+    /// Lower bids are accepted too, they just don't win.
+    pub fn reveal_bid(ctx: Context<RevealBid>, amount: u64, salt: [u8; 32]) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let offer = &mut ctx.accounts.offer;
+        let buyer = &ctx.accounts.buyer;
+        let now = state.now()?;
+
+        // Is the reveal phase running?
+        state.require_window(now, state.end_time, state.reveal_end)?;
+
+        // Does the bid match the commitment and is it covered by the deposit?
+        let hash = keccak::hashv(&[&amount.to_le_bytes(), &salt]);
+        if offer.commitment == [0; 32] || hash.to_bytes() != offer.commitment || amount > offer.amount {
+            return Err(error!(Errors::InvalidReveal));
+        }
+        offer.commitment = [0; 32];
+
+        // Equal bids go to the one revealed first, a lower bid may still set the second price
+        if amount > state.max_price {
+            state.outbid(*buyer.key, amount);
+            state.conceal_winner(offer)?;
+        } else if amount > state.runner_up_price {
+            state.runner_up_price = amount;
+        }
+
+        bid_placed(state, ctx.remaining_accounts, *buyer.key, amount)?;
+        audit(state, ctx.remaining_accounts, AuditAction::Reveal, *buyer.key, amount)
+    }
+
+    /// Commits the bidder to a hidden identity in an auction with a private winner, `identity` being
+    /// `keccak(bidder || salt)`. Has to be done before the first bid, a later call replaces it.
+    pub fn conceal_identity(ctx: Context<ConcealIdentity>, identity: [u8; 32]) -> Result<()> {
+        if identity == [0; 32] {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        let offer = &mut ctx.accounts.offer;
+        offer.identity = identity;
+        offer.bump = *ctx.bumps.get("offer").unwrap();
+        offer.version = OFFER_VERSION;
+
+        audit(&ctx.accounts.state, ctx.remaining_accounts, AuditAction::Conceal, ctx.accounts.buyer.key(), 0)
+    }
+
+    /// The winner of an auction with a private winner discloses themselves with the salt of their identity,
+    /// making the result public
+    pub fn reveal_winner(ctx: Context<RevealWinner>, salt: [u8; 32]) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let winner = &ctx.accounts.max_bidder;
+
+        let hash = keccak::hashv(&[winner.key.as_ref(), &salt]);
+        if hash.to_bytes() != state.winner_hash {
+            return Err(error!(Errors::InvalidReveal));
+        }
+        state.private_winner = false;
+        ctx.accounts.result.winner = *winner.key;
+
+        emit!(WinnerRevealed {
+            auction: state.key(),
+            lot_id: state.lot_id,
+            winner: *winner.key,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        audit(state, ctx.remaining_accounts, AuditAction::RevealWinner, *winner.key, 0)
+    }
+
+    /// Moves the auctions still listed on a sparse page to the tail page and closes the page once
+    /// it is empty, paying its rent to the caller. The states of the moved auctions are passed
+    /// in `remaining_accounts`.
+    pub fn compact_page<'info>(ctx: Context<'_, '_, '_, 'info, CompactPage<'info>>) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        let page = &mut ctx.accounts.page;
+        let tail = &mut ctx.accounts.tail;
+
+        for account in ctx.remaining_accounts {
+            if tail.is_full() {
+                break;
+            }
+
+            let mut state: Account<State> = Account::try_from(account)?;
+            if state.registry_page != page.index || !page.remove(&state.key()) {
+                return Err(error!(Errors::WrongAccount));
+            }
+
+            tail.index = registry.tail;
+            tail.push(state.key())?;
+            state.registry_page = tail.index;
+            state.exit(ctx.program_id)?;
+        }
+
+        if tail.is_full() {
+            registry.tail = registry.tail.checked_add(1).ok_or(Errors::InvalidOperation)?;
+        }
+
+        // Only the pages behind the tail are never written again
+        if page.live == 0 && page.index < registry.tail {
+            if page.index == registry.head {
+                registry.head = registry.head.checked_add(1).ok_or(Errors::InvalidOperation)?;
+            }
+            page.close(ctx.accounts.cranker.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the auctioned tokens from the seller into an escrow held by the program,
+    /// only possible before anybody bids
+    pub fn deposit_item(ctx: Context<DepositItem>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.seller_tokens.to_account_info(),
+                    to: ctx.accounts.escrow.to_account_info(),
+                    authority: ctx.accounts.initializer.to_account_info(),
+                }
+            ),
+            amount
+        )?;
+
+        let state = &mut ctx.accounts.state;
+        state.item_mint = ctx.accounts.item_mint.key();
+        state.item_amount = amount;
+
+        audit(state, ctx.remaining_accounts, AuditAction::DepositItem, state.initializer, amount)
+    }
+
+    /// Makes a standing offer to buy `amount` tokens of `item_mint` for `price` lamports, escrowed in the offer
+    /// until it is accepted, cancelled or expires at `expires_at`
+    pub fn make_buy_offer(ctx: Context<MakeBuyOffer>, amount: u64, price: u64, expires_at: i64) -> Result<()> {
+        if amount == 0 || expires_at <= Clock::get()?.unix_timestamp {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        invoke(
+            &system_instruction::transfer(ctx.accounts.buyer.key, &ctx.accounts.buy_offer.key(), price),
+            &[ctx.accounts.buyer.to_account_info(), ctx.accounts.buy_offer.to_account_info()]
+        )?;
+
+        let buy_offer = &mut ctx.accounts.buy_offer;
+        buy_offer.buyer = ctx.accounts.buyer.key();
+        buy_offer.item_mint = ctx.accounts.item_mint.key();
+        buy_offer.amount = amount;
+        buy_offer.price = price;
+        buy_offer.expires_at = expires_at;
+        buy_offer.bump = *ctx.bumps.get("buy_offer").unwrap();
+
+        Ok(())
+    }
+
+    /// Cancels a buy offer, the escrowed price and the rent go back to the buyer. Also the way out of an expired offer.
+    pub fn cancel_buy_offer(_ctx: Context<CancelBuyOffer>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Any holder of the item accepts a buy offer, selling the tokens for the escrowed price
+    pub fn accept_buy_offer(ctx: Context<AcceptBuyOffer>) -> Result<()> {
+        let buy_offer = &ctx.accounts.buy_offer;
+        let now = Clock::get()?.unix_timestamp;
+
+        if now >= buy_offer.expires_at {
+            return Err(error!(Errors::OfferExpired));
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.seller_tokens.to_account_info(),
+                    to: ctx.accounts.buyer_tokens.to_account_info(),
+                    authority: ctx.accounts.seller.to_account_info(),
+                }
+            ),
+            buy_offer.amount
+        )?;
+
+        // Pay the seller from the escrow, the rent goes back to the buyer when the offer closes
+        move_lamports(&buy_offer.to_account_info(), &ctx.accounts.seller.to_account_info(), buy_offer.price)?;
+
+        emit!(BuyOfferAccepted {
+            buy_offer: buy_offer.key(),
+            item_mint: buy_offer.item_mint,
+            buyer: buy_offer.buyer,
+            seller: ctx.accounts.seller.key(),
+            amount: buy_offer.amount,
+            price: buy_offer.price,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// After an auction is settled, the winner collects the share of the surplus and closes the offer
+    pub fn claim_surplus(ctx: Context<ClaimSurplus>) -> Result<()> {
+        let state = &ctx.accounts.state;
+
+        let previous = ctx.accounts.treasury.lamports();
+        let share = state.surplus_share(&ctx.accounts.offer);
+        move_lamports(&ctx.accounts.treasury, &ctx.accounts.max_bidder.to_account_info(), share)?;
+        treasury_delta(state, &ctx.accounts.treasury, previous, TreasuryReason::ClaimSurplus)?;
+
+        // Without surplus sharing the winning bid was already counted out at the settlement
+        if state.share_surplus {
+            ctx.accounts.state.close_bid(ctx.accounts.offer.amount);
+        }
+
+        audit(&ctx.accounts.state, ctx.remaining_accounts, AuditAction::ClaimSurplus, ctx.accounts.max_bidder.key(), share)
+    }
+
+    /// After an auction is settled, the winner can take the escrowed item. Only the settlement decides who won,
+    /// drawing the end of a candle auction or the ticket of a raffle and checking the USD reserve, a winner who
+    /// doesn't want to wait for the seller settles the auction with `settle`.
+    pub fn claim_item(ctx: Context<ClaimItem>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+
+        state.item_claimed = true;
+        audit(state, ctx.remaining_accounts, AuditAction::ClaimItem, state.max_bidder, state.item_amount)?;
+        release_item(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.winner_tokens,
+            &ctx.accounts.item_authority,
+            state.key(),
+            *ctx.bumps.get("item_authority").unwrap(),
+            state.item_amount
+        )
+    }
+
+    /// When the winner doesn't claim the item within `CLAIM_WINDOW`, the seller can take it back
+    /// while keeping the proceeds. The item of an auction failed at settlement goes back right away.
+    pub fn recover_item(ctx: Context<RecoverItem>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+
+        // Did the winner have enough time to claim the item, if there is any?
+        if state.status != AuctionStatus::Failed && state.in_claim_window(state.now()?)? {
+            return Err(error!(Errors::ClaimWindowOpen));
+        }
+
+        state.item_claimed = true;
+        audit(state, ctx.remaining_accounts, AuditAction::RecoverItem, state.initializer, state.item_amount)?;
+        release_item(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.seller_tokens,
+            &ctx.accounts.item_authority,
+            state.key(),
+            *ctx.bumps.get("item_authority").unwrap(),
+            state.item_amount
+        )
+    }
+}
+
+/// Raises a `PRECISION` scaled number to the given power, returning `None` on overflow
+fn pow_scaled(mut base: u128, mut exponent: u64) -> Option<u128> {
+    let precision = PRECISION as u128;
+    let mut result = precision;
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.checked_mul(base)? / precision;
+        }
+        base = base.checked_mul(base)? / precision;
+        exponent >>= 1;
+    }
+
+    Some(result)
+}
+
+/// Checks shared by every way of bidding in the open phase, counting the bids of the final sprint
+fn check_bid(state: &mut State, remaining_accounts: &[AccountInfo], bidder: &Pubkey, amount: u64, now: i64) -> Result<()> {
+    // Is the auction running already and still, past its warm-up?
+    state.require(now, AuctionStatus::Open)?;
+    if now < state.start_time.max(state.warmup_until) {
+        return Err(error!(Errors::NotStarted));
+    }
+    check_early_access(state, remaining_accounts, bidder, now)?;
+    check_token_gate(state, remaining_accounts, bidder)?;
+
+    // Check if the bid is lower or equal compared to the current highest, or the lowest winning one of a top-N auction
+    let price_to_beat = state.price_to_beat();
+    if amount <= price_to_beat {
+        return Err(error!(Errors::BidTooLow));
+    }
+
+    // Check if the bid raises the price by the required increment, escalating in the final sprint
+    let in_sprint = state.in_closing_window(now);
+    let increment = state.required_increment(in_sprint);
+    if amount < price_to_beat.saturating_add(increment) {
+        return Err(error!(Errors::BidTooLow));
+    }
+    if in_sprint {
+        state.sprint_bids = state.sprint_bids.saturating_add(1);
+    }
+
+    // Compliant auctions need an attestation of the bidder's region among the remaining accounts
+    if state.attestor != Pubkey::default() {
+        check_attestation(state, remaining_accounts, bidder)?;
+    }
+
+    // A reserve in USD is converted at the current SOL price
+    if state.usd_reserve > 0 && amount < usd_reserve_lamports(state, remaining_accounts)? {
+        return Err(error!(Errors::BidTooLow));
+    }
+
+    Ok(())
+}
+
+/// Counts a new wallet in, keeping it out of a full auction and charging the entry fee
+fn admit_bidder<'info>(state: &mut State, bidder: &AccountInfo<'info>, entry_fee_receiver: &AccountInfo<'info>) -> Result<()> {
+    if state.max_bidders != 0 && state.bidders >= state.max_bidders {
+        return Err(error!(Errors::AuctionFull));
+    }
+    state.bidders = state.bidders.checked_add(1).ok_or(Errors::InvalidOperation)?;
+    state.open_bids = state.open_bids.checked_add(1).ok_or(Errors::InvalidOperation)?;
+
+    // Joining the auction costs exactly the non-refundable entry fee. A receiver short of the rent exemption,
+    // like a protocol vault nobody paid into yet, can't take it
+    if state.entry_fee > 0 {
+        invoke(
+            &system_instruction::transfer(bidder.key, entry_fee_receiver.key, state.entry_fee),
+            &[bidder.clone(), entry_fee_receiver.clone()]
+        )?;
+        if !Rent::get()?.is_exempt(entry_fee_receiver.lamports(), entry_fee_receiver.data_len()) {
+            return Err(error!(Errors::ReceiverNotRentExempt));
+        }
+    }
+
+    Ok(())
+}
+
+/// Before the public window only holders of the early access mint may bid, proving it with a token account
+/// among the remaining accounts
+fn check_early_access(state: &State, remaining_accounts: &[AccountInfo], bidder: &Pubkey, now: i64) -> Result<()> {
+    if now >= state.public_start {
+        return Ok(());
+    }
+    if now < state.early_access_start {
+        return Err(error!(Errors::NotStarted));
+    }
+
+    for info in remaining_accounts.iter().filter(|info| *info.owner == token::ID) {
+        if let Ok(account) = Account::<TokenAccount>::try_from(info) {
+            if account.mint == state.early_access_mint && account.owner == *bidder && account.amount > 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    Err(error!(Errors::EarlyAccessRequired))
+}
+
+/// Checks that the bidder holds the tokens of the gate, proving it with a token account among the remaining
+/// accounts, for a gate by collection along with the metadata of the token's mint
+fn check_token_gate(state: &State, remaining_accounts: &[AccountInfo], bidder: &Pubkey) -> Result<()> {
+    if state.token_gate == Pubkey::default() {
+        return Ok(());
+    }
+
+    for info in remaining_accounts.iter().filter(|info| *info.owner == token::ID) {
+        let account = match Account::<TokenAccount>::try_from(info) {
+            Ok(account) if account.owner == *bidder => account,
+            _ => continue,
+        };
+
+        if !state.token_gate_collection {
+            if account.mint == state.token_gate && account.amount >= state.token_gate_amount {
+                return Ok(());
+            }
+            continue;
+        }
+
+        // Any NFT of the verified collection opens the gate
+        if account.amount == 0 {
+            continue;
+        }
+        let (address, _bump) = Pubkey::find_program_address(
+            &[b"metadata", token_metadata::ID.as_ref(), account.mint.as_ref()],
+            &token_metadata::ID
+        );
+        let metadata = match remaining_accounts.iter().find(|info| *info.key == address && *info.owner == token_metadata::ID) {
+            Some(metadata) => metadata,
+            None => continue,
+        };
+        if let Ok(metadata) = Metadata::deserialize(&mut &metadata.try_borrow_data()?[..]) {
+            if matches!(metadata.collection, Some(Collection { verified: true, key }) if key == state.token_gate) {
+                return Ok(());
+            }
+        }
+    }
+
+    Err(error!(Errors::TokenGateRequired))
+}
+
+/// Checks that the bidder is not on the ban list of an auction which banned somebody, the list being
+/// the remaining account owned by the program
+fn check_banned(state: &Account<State>, remaining_accounts: &[AccountInfo], bidder: &Pubkey) -> Result<()> {
+    if !state.has_bans {
+        return Ok(());
+    }
+
+    let address = Pubkey::find_program_address(&[b"bans", state.key().as_ref()], &crate::ID).0;
+    let info = remaining_accounts.iter().find(|info| *info.key == address).ok_or(Errors::WrongAccount)?;
+    let ban_list: Account<BanList> = Account::try_from(info)?;
+    if ban_list.banned.contains(bidder) {
+        return Err(error!(Errors::Banned));
+    }
+
+    Ok(())
+}
+
+/// Checks that the bidder is a leaf of the allowlist, folding the `proof` from `keccak(bidder)` up to the root
+fn check_allowlist(state: &State, bidder: &Pubkey, proof: &[[u8; 32]]) -> Result<()> {
+    if state.allowlist_root == [0; 32] {
+        return Ok(());
+    }
+
+    let root = proof.iter().fold(keccak::hash(bidder.as_ref()).to_bytes(), |node, sibling| {
+        if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+    if root != state.allowlist_root {
+        return Err(error!(Errors::NotAllowlisted));
+    }
+
+    Ok(())
+}
+
+/// Checks that an attestation issued by the auction's attestor is passed in the remaining accounts
+/// and that it allows the bidder in one of the auction's regions
+fn check_attestation(state: &State, remaining_accounts: &[AccountInfo], bidder: &Pubkey) -> Result<()> {
+    let info = remaining_accounts
+        .iter()
+        .find(|info| *info.owner == state.attestor)
+        .ok_or(Errors::AttestationRequired)?;
+    let attestation = Attestation::deserialize(&mut &info.try_borrow_data()?[..])
+        .map_err(|_| error!(Errors::InvalidAttestation))?;
+
+    if attestation.subject != *bidder
+        || attestation.region >= 64
+        || state.allowed_regions & (1 << attestation.region) == 0
+        || attestation.expires_at <= Clock::get()?.unix_timestamp
+    {
+        return Err(error!(Errors::InvalidAttestation));
+    }
+
+    Ok(())
+}
+
+/// Lets a linked auction be settled on its own once the other auction of the pair is over with another winner,
+/// the other auction being passed among the remaining accounts
+fn check_unpaired(state: &State, remaining_accounts: &[AccountInfo]) -> Result<()> {
+    let info = remaining_accounts
+        .iter()
+        .find(|info| *info.key == state.linked_auction)
+        .ok_or(Errors::WrongAccount)?;
+    let other: Account<State> = Account::try_from(info)?;
+
+    match other.status(other.now()?) {
+        AuctionStatus::Open | AuctionStatus::Extended | AuctionStatus::Sealed => Err(error!(Errors::Open)),
+        AuctionStatus::Ended if other.max_bidder == state.max_bidder => Err(error!(Errors::LinkedSettlement)),
+        _ => Ok(()),
+    }
+}
+
+/// Appends an entry to the audit log of an audited auction, the log being the remaining account owned by the program
+fn audit<'info>(
+    state: &Account<'info, State>,
+    remaining_accounts: &[AccountInfo<'info>],
+    action: AuditAction,
+    actor: Pubkey,
+    amount: u64
+) -> Result<()> {
+    if !state.audited {
+        return Ok(());
+    }
+
+    // Other accounts of the program may be passed along, e.g. watches or the log of a linked auction
+    let mut log = remaining_accounts
+        .iter()
+        .filter(|info| *info.owner == crate::ID)
+        .filter_map(|info| Account::<AuditLog>::try_from(info).ok())
+        .find(|log| log.auction == state.key())
+        .ok_or(Errors::AuditLogRequired)?;
+
+    log.append(AuditEntry {
+        action,
+        actor,
+        amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    })?;
+    log.exit(&crate::ID)
+}
+
+/// Audits a change of the parameters of the auction
+fn configured<'info>(ctx: &Context<'_, '_, '_, 'info, Configure<'info>>) -> Result<()> {
+    audit(&ctx.accounts.state, ctx.remaining_accounts, AuditAction::Configure, ctx.accounts.initializer.key(), 0)
+}
+
+/// Requests the Switchboard VRF account `vrf` among the remaining accounts served so far. The seller has
+/// to control it, nobody else may request the round drawing their auction.
+fn vrf_requests(state: &State, vrf: Pubkey, remaining_accounts: &[AccountInfo]) -> Result<u128> {
+    let info = remaining_accounts.iter().find(|info| *info.key == vrf).ok_or(Errors::WrongAccount)?;
+    if *info.owner != switchboard::ID {
+        return Err(error!(Errors::InvalidOracle));
+    }
+    let vrf = VrfAccountData::new(info).map_err(|_| error!(Errors::InvalidOracle))?;
+    if vrf.authority != state.initializer {
+        return Err(error!(Errors::InvalidOracle));
+    }
+
+    Ok(vrf.counter)
+}
+
+/// Result of the round of the VRF account drawing the auction, `None` while it is pending. Only the first
+/// request after the draw was set counts and it has to be made after the end: once the account was requested
+/// before the end or again after, the draw is void.
+fn vrf_result(state: &State, vrf: &AccountInfo) -> Result<Option<[u8; 32]>> {
+    if *vrf.owner != switchboard::ID {
+        return Err(error!(Errors::InvalidOracle));
+    }
+    let vrf = VrfAccountData::new(vrf).map_err(|_| error!(Errors::InvalidOracle))?;
+    let counter = vrf.counter;
+    if counter == state.vrf_counter {
+        return Ok(None);
+    }
+    if counter != state.vrf_counter.saturating_add(1) || vrf.current_round.request_timestamp < state.end_time {
+        return Err(error!(Errors::InvalidOracle));
+    }
+
+    match vrf.get_result() {
+        Ok(result) if result != [0; 32] => Ok(Some(result)),
+        _ => Ok(None),
+    }
+}
+
+/// Counts new escrow against the volume cap of the program. Without a cap the stats stay read only, so bids
+/// of unrelated auctions don't serialize on them; a capped program needs them passed writable, e.g. once more
+/// among the remaining accounts.
+fn record_volume(stats: &mut Account<GlobalStats>, amount: u64, now: i64) -> Result<()> {
+    if stats.volume_cap == 0 {
+        return Ok(());
+    }
+    if !stats.to_account_info().is_writable {
+        return Err(error!(Errors::WrongAccount));
+    }
+
+    stats.record(amount, now)?;
+    stats.exit(&crate::ID)
+}
+
+/// Bumps the counter of every watch of the auction among the remaining accounts, skipping other accounts
+fn touch_watches(auction: Pubkey, remaining_accounts: &[AccountInfo]) -> Result<()> {
+    for info in remaining_accounts.iter().filter(|info| *info.owner == crate::ID && info.is_writable) {
+        if let Ok(mut watch) = Account::<Watch>::try_from(info) {
+            if watch.auction == auction {
+                watch.updates = watch.updates.wrapping_add(1);
+                watch.exit(&crate::ID)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Bumps the notify account of the outbid `bidder` when it is among the remaining accounts
+fn notify_outbid(bidder: Pubkey, auction: Pubkey, price: u64, remaining_accounts: &[AccountInfo]) -> Result<()> {
+    if bidder == Pubkey::default() {
+        return Ok(());
+    }
+
+    for info in remaining_accounts.iter().filter(|info| *info.owner == crate::ID && info.is_writable) {
+        if let Ok(mut notify) = Account::<Notify>::try_from(info) {
+            if notify.bidder == bidder {
+                notify.outbid = notify.outbid.wrapping_add(1);
+                notify.auction = auction;
+                notify.price = price;
+                notify.exit(&crate::ID)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Emits `AuctionCreated` once an auction is fully configured and live
+fn announce(state: &Account<State>) {
+    emit!(AuctionCreated {
+        auction: state.key(),
+        auction_id: state.auction_id,
+        lot_id: state.lot_id,
+        initializer: state.initializer,
+        kind: state.kind,
+        category: state.category,
+        tags: state.tags,
+        initial_price: state.initial_price,
+        start_time: state.start_time,
+        end_time: state.end_time,
+    });
+}
+
+/// Logs the event and, with the event authority and the program among the remaining accounts, also passes
+/// it to a self-invocation of `log_event`. Indexers read it back from the inner instructions of the
+/// transaction, which unlike the logs are never truncated.
+fn emit_event<E: anchor_lang::Event>(remaining_accounts: &[AccountInfo], event: E) -> Result<()> {
+    emit!(event);
+
+    let (authority, bump) = Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &crate::ID);
+    let authority = match remaining_accounts.iter().find(|info| *info.key == authority) {
+        Some(authority) => authority,
+        None => return Ok(()),
+    };
+    let program = remaining_accounts.iter().find(|info| *info.key == crate::ID).ok_or(Errors::WrongAccount)?;
+
+    let ix = Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::new_readonly(*authority.key, true)],
+        data: anchor_lang::InstructionData::data(&crate::instruction::LogEvent { data: event.data() }),
+    };
+    invoke_signed(&ix, &[authority.clone(), program.clone()], &[&[EVENT_AUTHORITY_SEED, &[bump]]])?;
+
+    Ok(())
+}
+
+/// Splits the event authority and the program off the end of the remaining accounts of a batch,
+/// so they aren't taken for a part of it
+fn split_event_accounts<'a, 'info>(remaining_accounts: &'a [AccountInfo<'info>]) -> (&'a [AccountInfo<'info>], &'a [AccountInfo<'info>]) {
+    let authority = Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &crate::ID).0;
+    match remaining_accounts {
+        [batch @ .., first, second] if *first.key == authority && *second.key == crate::ID => {
+            remaining_accounts.split_at(batch.len())
+        }
+        _ => (remaining_accounts, &[]),
+    }
+}
+
+/// Refunds the losing bidders of a finished auction, `pairs` holding an (offer, bidder) pair for every bidder,
+/// and closes their offers
+fn refund_pairs<'info>(
+    state: &mut Account<'info, State>,
+    treasury: &AccountInfo<'info>,
+    pairs: &[AccountInfo<'info>],
+    event_accounts: &[AccountInfo<'info>]
+) -> Result<()> {
+    if !state.is_ended(state.now()?) || pairs.len() % 2 != 0 {
+        return Err(error!(Errors::InvalidOperation));
+    }
+    if state.all_pay {
+        return Err(error!(Errors::AllPay));
+    }
+
+    for accounts in pairs.chunks(2) {
+        let (offer_info, bidder) = (&accounts[0], &accounts[1]);
+        let mut offer: Account<Offer> = Account::try_from(offer_info)?;
+
+        // Same checks as the accounts of `refund`
+        let expected = Pubkey::create_program_address(
+            &[b"bid", state.key().as_ref(), bidder.key.as_ref(), &[offer.bump]],
+            &crate::ID
+        ).map_err(|_| error!(Errors::WrongAccount))?;
+        if expected != offer.key() {
+            return Err(error!(Errors::WrongAccount));
+        }
+        if !state.may_refund(bidder.key) {
+            return Err(error!(Errors::WinnerRefund));
+        }
+
+        // Pay the bid and its surplus share back and close the offer, returning its rent too
+        refund_one(state, treasury, &mut offer, bidder, event_accounts)?;
+        offer.close(bidder.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Pays the refundable part of a bid and its share of the surplus back to the bidder and counts the bid out,
+/// returning the payout. The callers check the accounts their own way and close the offer.
+fn refund_one<'info>(
+    state: &mut Account<'info, State>,
+    treasury: &AccountInfo<'info>,
+    offer: &mut Offer,
+    bidder: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>]
+) -> Result<u64> {
+    let payout = state.refundable(offer, bidder.key)
+        .checked_add(state.surplus_share(offer))
+        .ok_or(Errors::InvalidOperation)?;
+    pay_out(state, treasury, bidder, remaining_accounts, payout, TreasuryReason::Refund)?;
+    refunded(state, remaining_accounts, *bidder.key, payout)?;
+
+    state.close_bid(offer.amount);
+    offer.amount = 0;
+
+    Ok(payout)
+}
+
+/// Emits `BidPlaced` for a bid the auction took
+fn bid_placed(state: &Account<State>, remaining_accounts: &[AccountInfo], bidder: Pubkey, amount: u64) -> Result<()> {
+    emit_event(remaining_accounts, BidPlaced {
+        auction: state.key(),
+        lot_id: state.lot_id,
+        bidder,
+        amount,
+        max_price: state.max_price,
+        timestamp: Clock::get()?.unix_timestamp,
+    })
+}
+
+/// Emits `Refunded` for escrow going back to its bidder
+fn refunded(state: &Account<State>, remaining_accounts: &[AccountInfo], bidder: Pubkey, amount: u64) -> Result<()> {
+    emit_event(remaining_accounts, Refunded {
+        auction: state.key(),
+        lot_id: state.lot_id,
+        bidder,
+        amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    })
+}
+
+/// Converts a price quoted in SOL to lamports, `None` for non-positive or out of range prices
+fn oracle_lamports(price: Price) -> Option<u64> {
+    let value = u128::try_from(price.price).ok().filter(|value| *value > 0)?;
+    let exponent = price.expo.checked_add(9)?;
+
+    let lamports = if exponent >= 0 {
+        value.checked_mul(10u128.checked_pow(exponent as u32)?)?
+    } else {
+        value / 10u128.checked_pow(exponent.unsigned_abs())?
+    };
+
+    u64::try_from(lamports).ok()
+}
+
+/// Converts US cents to lamports at a SOL/USD price, `None` for non-positive or out of range prices
+fn usd_lamports(cents: u64, price: Price) -> Option<u64> {
+    let value = u128::try_from(price.price).ok().filter(|value| *value > 0)?;
+    let exponent = 7i32.checked_sub(price.expo)?;
+
+    let lamports = if exponent >= 0 {
+        u128::from(cents).checked_mul(10u128.checked_pow(exponent as u32)?)? / value
+    } else {
+        u128::from(cents) / 10u128.checked_pow(exponent.unsigned_abs())? / value
+    };
+
+    u64::try_from(lamports).ok()
+}
+
+/// The USD reserve of the auction in lamports, read from its price feed among the remaining accounts
+fn usd_reserve_lamports(state: &State, remaining_accounts: &[AccountInfo]) -> Result<u64> {
+    let info = remaining_accounts
+        .iter()
+        .find(|info| *info.key == state.usd_price_feed)
+        .ok_or(Errors::WrongAccount)?;
+    let feed = load_price_feed_from_account_info(info).map_err(|_| error!(Errors::InvalidOracle))?;
+    let price = feed
+        .get_current_price_no_older_than(Clock::get()?.unix_timestamp, ORACLE_MAX_AGE)
+        .ok_or(Errors::StaleOracle)?;
+
+    usd_lamports(state.usd_reserve, price).ok_or_else(|| error!(Errors::InvalidOracle))
+}
+
+/// Transfers the escrowed item out, signing with the escrow authority of the given auction
+fn release_item<'info>(
+    token_program: &Program<'info, Token>,
+    escrow: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    authority: &AccountInfo<'info>,
+    state: Pubkey,
+    bump: u8,
+    amount: u64
+) -> Result<()> {
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: escrow.to_account_info(),
+                to: to.to_account_info(),
+                authority: authority.clone(),
+            },
+            &[&[b"authority", state.as_ref(), &[bump]]]
+        ),
+        amount
+    )
+}
+
+/// Sends an escrowed item back to the seller and closes its escrow, which together with the escrow authority,
+/// a token account of the seller for the item and the token program is among the remaining accounts
+fn return_item<'info>(
+    state: &Account<'info, State>,
+    initializer: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>]
+) -> Result<()> {
+    if state.item_mint == Pubkey::default() || state.item_claimed {
+        return Ok(());
+    }
+
+    let key = state.key();
+    let find = |address: Pubkey| remaining_accounts.iter().find(|info| *info.key == address).ok_or(Errors::WrongAccount);
+    let escrow = find(Pubkey::find_program_address(&[b"item", key.as_ref()], &crate::ID).0)?;
+    let (authority, bump) = Pubkey::find_program_address(&[b"authority", key.as_ref()], &crate::ID);
+    let authority = find(authority)?;
+    let seller_tokens = remaining_accounts
+        .iter()
+        .find(|info| {
+            Account::<TokenAccount>::try_from(info)
+                .map_or(false, |tokens| tokens.mint == state.item_mint && tokens.owner == state.initializer)
+        })
+        .ok_or(Errors::WrongAccount)?;
+    let token_program = token_program(remaining_accounts)?;
+    let seeds: &[&[u8]] = &[b"authority", key.as_ref(), &[bump]];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            Transfer {
+                from: escrow.clone(),
+                to: seller_tokens.clone(),
+                authority: authority.clone(),
+            },
+            &[seeds]
+        ),
+        state.item_amount
+    )?;
+    token::close_account(CpiContext::new_with_signer(
+        token_program,
+        CloseAccount {
+            account: escrow.clone(),
+            destination: initializer.clone(),
+            authority: authority.clone(),
+        },
+        &[seeds]
+    ))
+}
+
+/// Finds the token account of `owner` in the payment currency of the auction among the remaining accounts
+fn payment_account<'info>(
+    state: &State,
+    remaining_accounts: &[AccountInfo<'info>],
+    owner: &Pubkey
+) -> Result<AccountInfo<'info>> {
+    let mint = state.payment_mint.ok_or(Errors::UnsupportedCurrency)?;
+
+    for info in remaining_accounts.iter().filter(|info| *info.owner == token::ID) {
+        if let Ok(account) = Account::<TokenAccount>::try_from(info) {
+            if account.mint == mint && account.owner == *owner {
+                return Ok(info.clone());
+            }
+        }
+    }
+
+    Err(error!(Errors::WrongAccount))
+}
+
+/// Finds the token program among the remaining accounts
+fn token_program<'info>(remaining_accounts: &[AccountInfo<'info>]) -> Result<AccountInfo<'info>> {
+    remaining_accounts
+        .iter()
+        .find(|info| *info.key == token::ID)
+        .cloned()
+        .ok_or_else(|| error!(Errors::WrongAccount))
+}
+
+/// Balance of the treasury in the auction currency, lamports including its rent or tokens
+fn treasury_balance(state: &State, treasury: &AccountInfo) -> Result<u64> {
+    match state.payment_mint {
+        None => Ok(treasury.lamports()),
+        Some(_) => Ok(Account::<TokenAccount>::try_from(treasury)?.amount),
+    }
+}
+
+/// Emits the movement of the treasury balance from `previous` to the current one
+fn treasury_delta(
+    state: &Account<State>,
+    treasury: &AccountInfo,
+    previous: u64,
+    reason: TreasuryReason
+) -> Result<()> {
+    let balance = treasury_balance(state, treasury)?;
+
+    emit!(TreasuryDelta {
+        auction: state.key(),
+        lot_id: state.lot_id,
+        treasury: *treasury.key,
+        previous_balance: previous,
+        delta: i128::from(balance) - i128::from(previous),
+        new_balance: balance,
+        reason,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Moves `amount` of the auction currency from a signing `payer` to the treasury
+fn collect<'info>(
+    state: &Account<'info, State>,
+    treasury: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    reason: TreasuryReason
+) -> Result<()> {
+    let previous = treasury_balance(state, treasury)?;
+
+    match state.payment_mint {
+        None => invoke(
+            &system_instruction::transfer(payer.key, treasury.key, amount),
+            &[payer.clone(), treasury.clone()]
+        ).map_err(Into::into),
+        Some(_) => token::transfer(
+            CpiContext::new(
+                token_program(remaining_accounts)?,
+                Transfer {
+                    from: payment_account(state, remaining_accounts, payer.key)?,
+                    to: treasury.clone(),
+                    authority: payer.clone(),
+                }
+            ),
+            amount
+        ),
+    }?;
+
+    treasury_delta(state, treasury, previous, reason)
+}
+
+/// Moves `amount` of the auction currency from the treasury to `to`, tokens going to its token account
+fn pay_out<'info>(
+    state: &Account<'info, State>,
+    treasury: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    reason: TreasuryReason
+) -> Result<()> {
+    let previous = treasury_balance(state, treasury)?;
+    if state.payment_mint.is_none() {
+        move_lamports(treasury, to, amount)?;
+        return treasury_delta(state, treasury, previous, reason);
+    }
+
+    let key = state.key();
+    let (authority, bump) = Pubkey::find_program_address(&[b"authority", key.as_ref()], &crate::ID);
+    let authority = remaining_accounts
+        .iter()
+        .find(|info| *info.key == authority)
+        .ok_or(Errors::WrongAccount)?;
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program(remaining_accounts)?,
+            Transfer {
+                from: treasury.clone(),
+                to: payment_account(state, remaining_accounts, to.key)?,
+                authority: authority.clone(),
+            },
+            &[&[b"authority", key.as_ref(), &[bump]]]
+        ),
+        amount
+    )?;
+
+    treasury_delta(state, treasury, previous, reason)
+}
+
+/// Pays the price of an auction won on credit out of the collateral of the winner, passed with the winning
+/// credit bid among the remaining accounts. The protocol fee comes out of the collateral first. A collateral
+/// falling short is taken whole and the credit line defaults, it can't bid again. Returns the fee and what
+/// the seller received.
+fn draw_credit<'info>(
+    state: &Account<'info, State>,
+    maker: &AccountInfo<'info>,
+    seller: &AccountInfo<'info>,
+    protocol: &ProtocolFee<'info>,
+    remaining_accounts: &[AccountInfo<'info>]
+) -> Result<(u64, u64)> {
+    let key = state.key();
+    let find = |seeds: &[&[u8]]| {
+        let address = Pubkey::find_program_address(seeds, &crate::ID).0;
+        remaining_accounts.iter().find(|info| *info.key == address).ok_or(Errors::WrongAccount)
+    };
+    let mut credit_line: Account<CreditLine> = Account::try_from(find(&[b"credit", state.namespace.as_ref(), maker.key.as_ref()])?)?;
+    let credit_bid: Account<CreditBid> = Account::try_from(find(&[b"credit_bid", key.as_ref(), maker.key.as_ref()])?)?;
+
+    let price = state.price().min(credit_line.collateral);
+    let fee = protocol.fee(state.price()).min(price);
+    credit_line.collateral -= price;
+    credit_line.used = credit_line.used.saturating_sub(credit_bid.amount);
+    move_lamports(&credit_line.to_account_info(), &protocol.fee_vault.to_account_info(), fee)?;
+    move_lamports(&credit_line.to_account_info(), seller, price - fee)?;
+
+    if price < state.price() {
+        credit_line.defaulted = true;
+        credit_line.limit = 0;
+
+        emit!(CreditDefaulted {
+            auction: key,
+            lot_id: state.lot_id,
+            maker: *maker.key,
+            credit_line: credit_line.key(),
+            shortfall: state.price() - price,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+    }
+
+    credit_line.exit(&crate::ID)?;
+    credit_bid.close(maker.clone())?;
+
+    Ok((fee, price - fee))
+}
+
+/// Moves lamports between two accounts, failing instead of underflowing when `from` can't cover the amount
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let from_balance = from.lamports().checked_sub(amount).ok_or(Errors::InvalidOperation)?;
+    let to_balance = to.lamports().checked_add(amount).ok_or(Errors::InvalidOperation)?;
+
+    **from.try_borrow_mut_lamports()? = from_balance;
+    **to.try_borrow_mut_lamports()? = to_balance;
+
+    Ok(())
+}
+
+/// Empties an account copied out by a migration, its rent going `to` the payer. The runtime removes it at the end
+/// of the transaction.
+fn retire(info: &AccountInfo, to: &AccountInfo) -> Result<()> {
+    info.try_borrow_mut_data()?.fill(0);
+    move_lamports(info, to, info.lamports())
+}
+
+/// Creates a program account of `space` bytes at the address of `info` the way `init` does, also when somebody
+/// sent lamports to the address in the meantime. A PDA signs with `signer_seeds`.
+fn create_account_at<'info>(
+    info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    space: usize,
+    signer_seeds: &[&[&[u8]]]
+) -> Result<()> {
+    let shortfall = Rent::get()?.minimum_balance(space).saturating_sub(info.lamports());
+    if shortfall > 0 {
+        invoke(&system_instruction::transfer(payer.key, info.key, shortfall), &[payer.clone(), info.clone()])?;
+    }
+    invoke_signed(&system_instruction::allocate(info.key, space as u64), &[info.clone()], signer_seeds)?;
+    invoke_signed(&system_instruction::assign(info.key, &crate::ID), &[info.clone()], signer_seeds)?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Auction<'info> {
+    /// CHECK: Only its key is used, identifying the marketplace the auction belongs to
+    pub namespace: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = initializer,
+        space = 8 + Counter::size(),
+        seeds = [b"counter", namespace.key().as_ref(), initializer.key().as_ref()],
+        bump
+    )]
+    pub counter: Account<'info, Counter>,
+
+    /// Derived from the next lot number of the seller, so clients find every auction of a seller
+    #[account(
+        init,
+        payer = initializer,
+        space = 8 + State::size(),
+        seeds = [
+            b"auction",
+            namespace.key().as_ref(),
+            initializer.key().as_ref(),
+            counter.next_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK: Vault of the escrow owned by the program, which debits it directly
+    #[account(
+        init,
+        payer = initializer,
+        space = 0,
+        seeds = [b"treasury", state.key().as_ref()],
+        bump
+    )]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = initializer,
+        space = 8 + Registry::size(),
+        seeds = [b"registry", namespace.key().as_ref()],
+        bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    #[account(
+        init_if_needed,
+        payer = initializer,
+        space = 8 + RegistryPage::size(),
+        seeds = [b"page", namespace.key().as_ref(), registry.tail.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub page: Account<'info, RegistryPage>,
+
+    #[account(init_if_needed, payer = initializer, space = 8 + GlobalStats::size(), seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+
+    /// Program-wide config, every new auction is held to its limits
+    #[account(seeds = [b"global"], bump = global.bump)]
+    pub global: Account<'info, GlobalConfig>,
+
+    /// CHECK: Marketplace config of the namespace, its rules apply once the marketplace created it
+    #[account(mut, seeds = [b"config", namespace.key().as_ref()], bump)]
+    pub market_config: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Auction<'info> {
+    /// Fills in a fresh auction state
+    fn start(&mut self, auction_duration: i64, initial_price: u64) -> Result<()> {
+        self.stats.require_live()?;
+
+        let end_time = Clock::get()?.unix_timestamp.checked_add(auction_duration);
+        if end_time == None {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        let x = Clock::get()?.unix_timestamp;
+        msg!("{x}", x = x);
+
+        // Every auction of a seller gets the next lot number
+        let auction_id = self.counter.next_id;
+        self.counter.next_id = auction_id.checked_add(1).ok_or(Errors::InvalidOperation)?;
+
+        // List the auction on the tail page, moving the tail on once the page fills up
+        let registry = &mut self.registry;
+        registry.namespace = self.namespace.key();
+        let page = &mut self.page;
+        page.index = registry.tail;
+        page.push(self.state.key())?;
+        if page.is_full() {
+            registry.tail = registry.tail.checked_add(1).ok_or(Errors::InvalidOperation)?;
+        }
+
+        let state = &mut self.state;
+        state.namespace = self.namespace.key();
+        state.registry_page = page.index;
+        state.auction_id = auction_id;
+        state.initializer = *self.initializer.key;
+        state.treasury = *self.treasury.key;
+        state.max_bidder = Pubkey::default();
+        state.max_price = initial_price;
+        state.initial_price = initial_price;
+        state.second_price = false;
+        state.runner_up_price = initial_price;
+        state.private_winner = false;
+        state.winner_hash = [0; 32];
+        state.buy_now_price = 0;
+        state.linked_auction = Pubkey::default();
+        state.soft_close_window = 0;
+        state.soft_close_extension = 0;
+        state.max_extension = 0;
+        state.extended = 0;
+        state.max_extensions = 0;
+        state.extensions = 0;
+        state.latest_end_time = 0;
+        state.warmup_until = 0;
+        state.withdrawal_penalty_bps = 0;
+        state.open_bids = 0;
+        state.allowlist_root = [0; 32];
+        state.token_gate = Pubkey::default();
+        state.token_gate_amount = 0;
+        state.token_gate_collection = false;
+        state.has_bans = false;
+        state.candle_window = 0;
+        state.candle_vrf = Pubkey::default();
+        state.candle_leaders = [CandleLeader::default(); CANDLE_SAMPLES];
+        state.candle_resolved = false;
+        state.usd_reserve = 0;
+        state.usd_price_feed = Pubkey::default();
+        state.winners = 0;
+        state.top_bids = [TopBid::default(); MAX_WINNERS];
+        state.all_pay = false;
+        state.penny_fee = 0;
+        state.penny_step = 0;
+        state.penny_countdown = 0;
+        state.bid_fees = 0;
+        state.reverse = false;
+        state.proxy_bidding = false;
+        state.leader_max = 0;
+        state.ticket_price = 0;
+        state.raffle_vrf = Pubkey::default();
+        state.tickets = 0;
+        state.raffle_drawn = false;
+        state.refund_expiry = REFUND_EXPIRY;
+        state.finalist_bids = [0; MAX_FINALISTS];
+        state.vrf_counter = 0;
+        state.keeper = Pubkey::default();
+        state.version = STATE_VERSION;
+        state.end_time = end_time.unwrap();
+        state.status = AuctionStatus::Open;
+        state.bidders = 0;
+        state.item_mint = Pubkey::default();
+        state.item_amount = 0;
+        state.item_claimed = false;
+        state.kind = AuctionKind::English;
+        state.start_time = Clock::get()?.unix_timestamp;
+        state.draft = false;
+        state.category = Category::Uncategorized;
+        state.tags = 0;
+        state.max_bidders = 0;
+        state.entry_fee = 0;
+        state.entry_fee_receiver = state.initializer;
+        state.beneficiary = state.initializer;
+        state.lot_id = 0;
+        state.dutch_curve = DutchCurve::Stepped;
+        state.early_access_mint = Pubkey::default();
+        state.early_access_start = 0;
+        state.public_start = 0;
+        state.deadline_policy = DeadlinePolicy::UnixTimestamp;
+        state.winner_on_credit = false;
+        state.audited = false;
+        state.min_increment = 0;
+        state.sprint_window = 0;
+        state.sprint_factor_bps = BPS;
+        state.sprint_bids = 0;
+        state.attestor = Pubkey::default();
+        state.allowed_regions = 0;
+        state.share_surplus = false;
+        state.escrowed = 0;
+        state.total_weight = 0;
+        state.weight_updated_at = state.start_time;
+        state.surplus_pool = 0;
+        state.payment_mint = None;
+
+        // The marketplace config of the namespace overrides the defaults, applying due changes first
+        if *self.market_config.owner == crate::ID {
+            let mut config: Account<MarketConfig> = Account::try_from(&self.market_config)?;
+            config.apply_due(state.start_time);
+            config.exit(&crate::ID)?;
+
+            state.min_increment = config.min_increment;
+            if config.entry_fee > 0 {
+                state.entry_fee = config.entry_fee;
+                state.entry_fee_receiver = Pubkey::find_program_address(&[b"protocol", state.namespace.as_ref()], &crate::ID).0;
+            }
+        }
+
+        // The program-wide config caps the duration and raises the increment
+        let global = &self.global;
+        if global.max_duration > 0 && auction_duration > global.max_duration {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        state.min_increment = state.min_increment.max(global.min_increment);
+
+        // The treasury starts out with the rent paid by the initializer
+        treasury_delta(&self.state, &self.treasury, 0, TreasuryReason::Open)
+    }
+}
+
+#[derive(Accounts)]
+pub struct AuctionWithOracle<'info> {
+    pub auction: Auction<'info>,
+
+    /// CHECK: Parsed and validated as a Pyth price feed
+    pub price_feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MultiUnitAuction<'info> {
+    pub auction: Auction<'info>,
+
+    #[account(
+        init,
+        payer = initializer,
+        space = 8 + UnitBook::size(),
+        seeds = [b"book", auction.state.key().as_ref()],
+        bump
+    )]
+    pub book: Account<'info, UnitBook>,
+
+    #[account(mut, address = auction.initializer.key() @ Errors::WrongAccount)]
+    pub initializer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloneAuction<'info> {
+    #[account(
+        constraint = source.initializer == auction.initializer.key() @ Errors::WrongAccount,
+        constraint = source.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = source.payment_mint.is_none() @ Errors::UnsupportedCurrency,
+        constraint = !source.reverse @ Errors::InvalidOperation,
+        constraint = source.candle_window == 0 @ Errors::InvalidOperation,
+        constraint = source.ticket_price == 0 @ Errors::InvalidOperation
+    )]
+    pub source: Account<'info, State>,
+
+    pub auction: Auction<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateSeries<'info> {
+    #[account(
+        init,
+        payer = initializer,
+        space = 8 + Series::size(),
+        seeds = [b"series", state.key().as_ref()],
+        bump
+    )]
+    pub series: Account<'info, Series>,
+
+    #[account(
+        has_one = initializer @ Errors::WrongAccount,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Relist<'info> {
+    #[account(
+        mut,
+        seeds = [b"series", series.first_auction.as_ref()],
+        bump = series.bump,
+        constraint = series.initializer == auction.initializer.key() @ Errors::WrongAccount
+    )]
+    pub series: Account<'info, Series>,
+
+    #[account(address = series.last_auction @ Errors::WrongAccount)]
+    pub last_auction: Account<'info, State>,
+
+    pub auction: Auction<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EditDraft<'info> {
+    #[account(
+        mut,
+        has_one = initializer @ Errors::WrongAccount,
+        constraint = state.draft @ Errors::NotDraft
+    )]
+    pub state: Account<'info, State>,
+
+    pub initializer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAuction<'info> {
+    #[account(
+        mut,
+        close = initializer,
+        has_one = initializer @ Errors::WrongAccount,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.max_bidder == Pubkey::default() && state.bidders == 0 @ Errors::AlreadyBid,
+        constraint = state.item_mint == Pubkey::default() @ Errors::InvalidOperation,
+        constraint = state.payment_mint.is_none() @ Errors::UnsupportedCurrency,
+        constraint = state.linked_auction == Pubkey::default() @ Errors::InvalidOperation,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK: Emptied and so closed, only its rent is left before any bid
+    #[account(mut)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"page", state.namespace.as_ref(), state.registry_page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub page: Account<'info, RegistryPage>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeWithoutBids<'info> {
+    #[account(
+        mut,
+        close = initializer,
+        has_one = initializer @ Errors::WrongAccount,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.bidders == 0 @ Errors::AlreadyBid,
+        constraint = state.payment_mint.is_none() @ Errors::UnsupportedCurrency,
+        constraint = state.linked_auction == Pubkey::default() @ Errors::InvalidOperation,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK: Emptied and so closed, only its rent is left without any bid
+    #[account(mut)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"page", state.namespace.as_ref(), state.registry_page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub page: Account<'info, RegistryPage>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseAuction<'info> {
+    #[account(
+        mut,
+        close = initializer,
+        has_one = initializer @ Errors::WrongAccount,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.open_bids == 0 @ Errors::OpenBids,
+        constraint = state.item_mint == Pubkey::default() || state.item_claimed @ Errors::InvalidOperation,
+        constraint = state.payment_mint.is_none() @ Errors::UnsupportedCurrency,
+        constraint = state.linked_auction == Pubkey::default() @ Errors::InvalidOperation,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK: Emptied and so closed, every bid is paid out
+    #[account(mut)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateState<'info> {
+    /// CHECK: An older layout version, decoded by hand since it doesn't load as the current one
+    #[account(mut, constraint = *state.owner == crate::ID @ Errors::WrongAccount)]
+    pub state: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + StateMigration::size(),
+        seeds = [b"migration", state.key().as_ref()],
+        bump
+    )]
+    pub migration: Account<'info, StateMigration>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RestoreState<'info> {
+    #[account(mut, seeds = [b"migration", state.key().as_ref()], bump, close = payer)]
+    pub migration: Account<'info, StateMigration>,
+
+    /// CHECK: Created by hand at the address of the migrated auction, see `restore_state`
+    #[account(mut)]
+    pub state: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateOffer<'info> {
+    #[account(mut)]
+    pub state: Account<'info, State>,
+
+    /// CHECK: Only its key is used, deriving the offer
+    pub bidder: AccountInfo<'info>,
+
+    /// CHECK: An older layout version, decoded by hand since it doesn't load as the current one
+    #[account(
+        mut,
+        seeds = [b"bid", state.key().as_ref(), bidder.key.as_ref()],
+        bump,
+        constraint = *offer.owner == crate::ID @ Errors::WrongAccount
+    )]
+    pub offer: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + OfferMigration::size(),
+        seeds = [b"migration", offer.key().as_ref()],
+        bump
+    )]
+    pub migration: Account<'info, OfferMigration>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RestoreOffer<'info> {
+    pub state: Account<'info, State>,
+
+    /// CHECK: Only its key is used, deriving the offer
+    pub bidder: AccountInfo<'info>,
+
+    #[account(mut, seeds = [b"migration", offer.key().as_ref()], bump, close = payer)]
+    pub migration: Account<'info, OfferMigration>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Offer::size(),
+        seeds = [b"bid", state.key().as_ref(), bidder.key.as_ref()],
+        bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateLegacyAuction<'info> {
+    /// CHECK: The legacy layout, decoded by hand since it doesn't load as the current one
+    #[account(mut, constraint = *state.owner == crate::ID @ Errors::WrongAccount)]
+    pub state: AccountInfo<'info>,
+
+    /// CHECK: Compared with the treasury the legacy auction holds, only its balance is read
+    #[account(constraint = *treasury.owner == crate::ID @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + StateMigration::size(),
+        seeds = [b"migration", state.key().as_ref()],
+        bump
+    )]
+    pub migration: Account<'info, StateMigration>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendAuction<'info> {
+    #[account(mut, has_one = initializer @ Errors::WrongAccount)]
+    pub state: Account<'info, State>,
+
+    pub initializer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BanBidder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, State>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + BanList::size(),
+        seeds = [b"bans", state.key().as_ref()],
+        bump
+    )]
+    pub ban_list: Account<'info, BanList>,
+
+    /// Seller of the auction or admin of the program-wide config
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveCandle<'info> {
+    #[account(
+        mut,
+        constraint = state.candle_window > 0 @ Errors::InvalidOperation,
+        constraint = !state.candle_resolved @ Errors::InvalidOperation
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK: Parsed as a Switchboard VRF account
+    #[account(address = state.candle_vrf @ Errors::WrongAccount)]
+    pub vrf: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveRaffle<'info> {
+    #[account(
+        mut,
+        constraint = state.ticket_price > 0 @ Errors::InvalidOperation,
+        constraint = !state.raffle_drawn @ Errors::InvalidOperation
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK: Parsed as a Switchboard VRF account
+    #[account(address = state.raffle_vrf @ Errors::WrongAccount)]
+    pub vrf: AccountInfo<'info>,
+
+    /// CHECK: Holder of the drawn ticket, only deriving their offer
+    pub holder: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"bid", state.key().as_ref(), holder.key.as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+}
+
+#[derive(Accounts)]
+pub struct Configure<'info> {
+    #[account(
+        mut,
+        has_one = initializer @ Errors::WrongAccount,
+        constraint = state.max_bidder == Pubkey::default() @ Errors::AlreadyBid,
+        constraint = !state.reverse @ Errors::InvalidOperation
+    )]
+    pub state: Account<'info, State>,
+
+    pub initializer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaymentMint<'info> {
+    #[account(
+        mut,
+        has_one = initializer @ Errors::WrongAccount,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.max_bidder == Pubkey::default() @ Errors::AlreadyBid,
+        constraint = state.kind == AuctionKind::English @ Errors::UnsupportedCurrency,
+        constraint = state.payment_mint.is_none() @ Errors::InvalidOperation,
+        constraint = !state.reverse @ Errors::UnsupportedCurrency
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK: The native treasury, emptied as the token treasury replaces it
+    #[account(mut)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"payment", state.key().as_ref()],
+        bump,
+        token::mint = payment_mint,
+        token::authority = authority
+    )]
+    pub payment_treasury: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA signing for the escrows
+    #[account(seeds = [b"authority", state.key().as_ref()], bump)]
+    pub authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(capacity: u32)]
+pub struct CreateAuditLog<'info> {
+    #[account(
+        mut,
+        has_one = initializer @ Errors::WrongAccount,
+        constraint = state.max_bidder == Pubkey::default() @ Errors::AlreadyBid
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(
+        init,
+        payer = initializer,
+        space = 8 + AuditLog::size(capacity),
+        seeds = [b"audit", state.key().as_ref()],
+        bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Bid<'info> {
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + Offer::size(),
+        seeds = [b"bid", state.key().as_ref(), buyer.key().as_ref()],
+        bump,
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind != AuctionKind::Dutch @ Errors::InvalidOperation,
+        constraint = state.kind != AuctionKind::Gda @ Errors::InvalidOperation,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.kind != AuctionKind::Sealed @ Errors::InvalidOperation,
+        constraint = !state.reverse @ Errors::InvalidOperation,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.entry_fee_receiver @ Errors::WrongAccount)]
+    pub entry_fee_receiver: AccountInfo<'info>,
+
+    /// Read only, a capped program needs it writable among the remaining accounts of the bids
+    #[account(seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReverseBid<'info> {
+    #[account(
+        mut,
+        constraint = state.reverse @ Errors::InvalidOperation,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    /// Read only, only the pause is checked
+    #[account(seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Bid<'info> {
+    /// Raises the offer of the buyer to `amount`, escrowing the difference and taking the lead
+    fn place(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+        amount: u64,
+        proof: &[[u8; 32]],
+        offer_bump: u8
+    ) -> Result<()> {
+        self.place_up_to(remaining_accounts, amount, amount, proof, offer_bump)
+    }
+
+    /// Bids what it takes to lead up to `max_amount`, escrowing all of it, or raises the maximum of the leader's
+    /// proxy
+    fn proxy(&mut self, remaining_accounts: &[AccountInfo<'info>], max_amount: u64, offer_bump: u8) -> Result<()> {
+        let state = &self.state;
+        if !state.proxy_bidding {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        if *self.buyer.key == state.max_bidder {
+            return self.raise_proxy(remaining_accounts, max_amount);
+        }
+
+        // Lead by the increment over all the leader's proxy may bid, the proxy otherwise counters the whole maximum
+        let ceiling = state.max_price.max(state.leader_max);
+        let step = state.required_increment(state.in_closing_window(state.now()?)).max(1);
+        let amount = if max_amount > ceiling {
+            max_amount.min(ceiling.saturating_add(step))
+        } else {
+            max_amount
+        };
+
+        self.place_up_to(remaining_accounts, amount, max_amount, &[], offer_bump)
+    }
+
+    /// Places a bid of `amount` escrowing `max_amount`, the maximum of a proxy bid or the amount itself
+    fn place_up_to(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+        amount: u64,
+        max_amount: u64,
+        proof: &[[u8; 32]],
+        offer_bump: u8
+    ) -> Result<()> {
+        let state = &mut self.state;
+        let buyer = &mut self.buyer;
+
+        let now = state.now()?;
+        self.stats.require_live()?;
+        let step = state.required_increment(state.in_closing_window(now)).max(1);
+        check_bid(state, remaining_accounts, buyer.key, max_amount, now)?;
+        check_allowlist(state, buyer.key, proof)?;
+        check_banned(state, remaining_accounts, buyer.key)?;
+
+        // The highest bidder raises their bid with `raise_bid`
+        if *buyer.key == state.max_bidder {
+            return Err(error!(Errors::AlreadyHighestBidder));
+        }
+
+        // A raffle only sells tickets
+        if state.ticket_price > 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        // A bid in the final seconds gives the others time to answer
+        state.soft_close(now)?;
+
+        // A bid of a penny auction moves the price by its step only, pays the bid fee and starts the countdown over
+        if state.penny_step > 0 {
+            if Some(amount) != state.max_price.checked_add(state.penny_step) {
+                return Err(error!(Errors::InvalidOperation));
+            }
+            collect(state, &self.treasury, &buyer.to_account_info(), remaining_accounts, state.penny_fee, TreasuryReason::BidFee)?;
+            state.bid_fees = state.bid_fees.checked_add(state.penny_fee).ok_or(Errors::InvalidOperation)?;
+
+            let countdown_end = now.checked_add(state.penny_countdown).ok_or(Errors::InvalidOperation)?;
+            if countdown_end > state.end_time {
+                state.extend(countdown_end - state.end_time)?;
+            }
+        }
+
+        // In a case this was not a new bid we have to calculate the difference between an old and a new amount bidded
+        let offer = &mut self.offer;
+        let diff = max_amount.checked_sub(offer.amount);
+        if diff == None {
+            return Err(error!(Errors::InvalidOperation))
+        }
+
+        // Stop taking new escrow once the volume cap of the program is reached in the current window
+        record_volume(&mut self.stats, diff.unwrap(), now)?;
+
+        // Move lamports or tokens to the treasury
+        collect(
+            state,
+            &self.treasury,
+            &buyer.to_account_info(),
+            remaining_accounts,
+            diff.unwrap(),
+            TreasuryReason::Bid
+        )?;
+
+        // Accrue the time-weighted escrow for surplus sharing before the amounts change
+        state.accrue_weight(now);
+        offer.accrue_weight(now, state.closes_at());
+        state.escrowed = state.escrowed.checked_add(diff.unwrap()).ok_or(Errors::InvalidOperation)?;
+
+        // Update state with the new highest bidder and the new highest bid, letting the previous one know.
+        // Only the bid falling out of the winners of a top-N auction is outbid, and a bid covered by the proxy
+        // of the leader is countered right away, staying escrowed for a refund.
+        if state.leader_max >= amount {
+            state.counter(amount, step);
+        } else if state.winners > 0 {
+            let dropped = state.rank(*buyer.key, amount);
+            notify_outbid(dropped, state.key(), amount, remaining_accounts)?;
+        } else {
+            let previous = state.max_bidder;
+            state.outbid(*buyer.key, amount);
+            state.leader_max = if max_amount > amount { max_amount } else { 0 };
+            notify_outbid(previous, state.key(), amount, remaining_accounts)?;
+        }
+        if state.max_bidder == *buyer.key {
+            state.conceal_winner(offer)?;
+            state.winner_on_credit = false;
+        }
+
+        // The highest bidders qualify for the sealed final round of a hybrid auction
+        if state.kind == AuctionKind::Hybrid {
+            state.qualify(*buyer.key, amount);
+        }
+
+        // Keep the statistics for the seller's report
+        state.record_bid(now, amount)?;
+
+        // Count every wallet only once, on its first bid
+        if offer.amount == 0 {
+            admit_bidder(state, &buyer.to_account_info(), &self.entry_fee_receiver)?;
+        }
+
+        // Update the offer for a possible refund, the treasury now holds the whole amount
+        offer.amount = max_amount;
+        offer.bump = offer_bump;
+        offer.version = OFFER_VERSION;
+
+        bid_placed(state, remaining_accounts, *buyer.key, amount)?;
+        audit(state, remaining_accounts, AuditAction::Bid, *buyer.key, amount)
+    }
+
+    /// Raises the standing bid of the highest bidder to `amount`, escrowing only the difference. Nobody was
+    /// outbid, so the soft close doesn't move and the runner-up price stays.
+    fn raise(&mut self, remaining_accounts: &[AccountInfo<'info>], amount: u64) -> Result<()> {
+        let state = &mut self.state;
+        let buyer = &self.buyer;
+
+        // A lead on credit is backed by the credit line, not by an offer to top up, a penny auction
+        // only moves by its step, a proxy raises its maximum with `proxy_bid` and a raffle only sells tickets
+        if *buyer.key != state.max_bidder
+            || state.winner_on_credit
+            || state.penny_step > 0
+            || state.proxy_bidding
+            || state.ticket_price > 0
+        {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        let now = state.now()?;
+        self.stats.require_live()?;
+        check_bid(state, remaining_accounts, buyer.key, amount, now)?;
+        check_banned(state, remaining_accounts, buyer.key)?;
+
+        let offer = &mut self.offer;
+        let diff = amount.checked_sub(offer.amount).ok_or(Errors::InvalidOperation)?;
+        record_volume(&mut self.stats, diff, now)?;
+
+        collect(state, &self.treasury, &buyer.to_account_info(), remaining_accounts, diff, TreasuryReason::Bid)?;
+
+        state.accrue_weight(now);
+        offer.accrue_weight(now, state.closes_at());
+        state.escrowed = state.escrowed.checked_add(diff).ok_or(Errors::InvalidOperation)?;
+
+        if state.winners > 0 {
+            state.rank(*buyer.key, amount);
+        } else {
+            state.max_price = amount;
+        }
+        if state.kind == AuctionKind::Hybrid {
+            state.qualify(*buyer.key, amount);
+        }
+        state.record_bid(now, amount)?;
+
+        offer.amount = amount;
+
+        bid_placed(state, remaining_accounts, *buyer.key, amount)?;
+        audit(state, remaining_accounts, AuditAction::Bid, *buyer.key, amount)
+    }
+
+    /// Buys `count` tickets of a raffle, which are paid to the beneficiary with the others. The tickets of a wallet
+    /// are numbered in a row, so it buys more only while nobody else bought any since.
+    fn buy_tickets(&mut self, remaining_accounts: &[AccountInfo<'info>], count: u64, offer_bump: u8) -> Result<()> {
+        let state = &mut self.state;
+        let buyer = &mut self.buyer;
+
+        let now = state.now()?;
+        self.stats.require_live()?;
+        if state.ticket_price == 0 || count == 0 {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        state.require(now, AuctionStatus::Open)?;
+        if now < state.start_time.max(state.warmup_until) {
+            return Err(error!(Errors::NotStarted));
+        }
+        check_early_access(state, remaining_accounts, buyer.key, now)?;
+        check_token_gate(state, remaining_accounts, buyer.key)?;
+        check_allowlist(state, buyer.key, &[])?;
+        check_banned(state, remaining_accounts, buyer.key)?;
+        if state.attestor != Pubkey::default() {
+            check_attestation(state, remaining_accounts, buyer.key)?;
+        }
+
+        let offer = &mut self.offer;
+        if offer.tickets > 0 && offer.first_ticket.checked_add(offer.tickets) != Some(state.tickets) {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        let cost = count.checked_mul(state.ticket_price).ok_or(Errors::InvalidOperation)?;
+        record_volume(&mut self.stats, cost, now)?;
+
+        collect(state, &self.treasury, &buyer.to_account_info(), remaining_accounts, cost, TreasuryReason::Bid)?;
+        state.escrowed = state.escrowed.checked_add(cost).ok_or(Errors::InvalidOperation)?;
+
+        // The latest buyer stands in for the winner until the draw, the price being the whole pot
+        if offer.tickets == 0 {
+            offer.first_ticket = state.tickets;
+        }
+        offer.tickets = offer.tickets.checked_add(count).ok_or(Errors::InvalidOperation)?;
+        state.tickets = state.tickets.checked_add(count).ok_or(Errors::InvalidOperation)?;
+        state.max_bidder = *buyer.key;
+        state.max_price = state.escrowed;
+        state.record_bid(now, cost)?;
+
+        // Count every wallet only once, on its first tickets
+        if offer.amount == 0 {
+            admit_bidder(state, &buyer.to_account_info(), &self.entry_fee_receiver)?;
+        }
+
+        offer.amount = offer.amount.checked_add(cost).ok_or(Errors::InvalidOperation)?;
+        offer.bump = offer_bump;
+        offer.version = OFFER_VERSION;
+
+        bid_placed(state, remaining_accounts, *buyer.key, cost)?;
+        audit(state, remaining_accounts, AuditAction::Bid, *buyer.key, cost)
+    }
+
+    /// Raises the maximum of the leader's proxy to `max_amount`, escrowing the difference. The price stays
+    /// as nobody was outbid.
+    fn raise_proxy(&mut self, remaining_accounts: &[AccountInfo<'info>], max_amount: u64) -> Result<()> {
+        let state = &mut self.state;
+        let buyer = &self.buyer;
+
+        let now = state.now()?;
+        self.stats.require_live()?;
+        state.require(now, AuctionStatus::Open)?;
+        check_banned(state, remaining_accounts, buyer.key)?;
+        if max_amount <= state.max_price.max(state.leader_max) {
+            return Err(error!(Errors::BidTooLow));
+        }
+
+        let offer = &mut self.offer;
+        let diff = max_amount.checked_sub(offer.amount).ok_or(Errors::InvalidOperation)?;
+        record_volume(&mut self.stats, diff, now)?;
+
+        collect(state, &self.treasury, &buyer.to_account_info(), remaining_accounts, diff, TreasuryReason::Bid)?;
+
+        state.accrue_weight(now);
+        offer.accrue_weight(now, state.closes_at());
+        state.escrowed = state.escrowed.checked_add(diff).ok_or(Errors::InvalidOperation)?;
+
+        state.leader_max = max_amount;
+        offer.amount = max_amount;
+
+        audit(state, remaining_accounts, AuditAction::Bid, *buyer.key, max_amount)
+    }
+}
+
+#[derive(Accounts)]
+pub struct LinkAuctions<'info> {
+    #[account(
+        init,
+        payer = initializer,
+        space = 8 + AuctionLink::size(),
+        seeds = [b"link", first.key().as_ref(), second.key().as_ref()],
+        bump
+    )]
+    pub link: Account<'info, AuctionLink>,
+
+    #[account(
+        mut,
+        has_one = initializer @ Errors::WrongAccount,
+        constraint = first.kind == AuctionKind::English @ Errors::InvalidOperation,
+        constraint = first.max_bidder == Pubkey::default() @ Errors::AlreadyBid,
+        constraint = first.linked_auction == Pubkey::default() @ Errors::InvalidOperation,
+        constraint = first.candle_window == 0 @ Errors::InvalidOperation,
+        constraint = first.usd_reserve == 0 @ Errors::InvalidOperation,
+        constraint = first.winners == 0 @ Errors::InvalidOperation,
+        constraint = !first.all_pay @ Errors::InvalidOperation,
+        constraint = first.penny_step == 0 @ Errors::InvalidOperation,
+        constraint = !first.reverse @ Errors::InvalidOperation,
+        constraint = !first.proxy_bidding @ Errors::InvalidOperation
+    )]
+    pub first: Account<'info, State>,
+
+    #[account(
+        mut,
+        has_one = initializer @ Errors::WrongAccount,
+        constraint = second.key() != first.key() @ Errors::InvalidOperation,
+        constraint = second.kind == AuctionKind::English @ Errors::InvalidOperation,
+        constraint = second.max_bidder == Pubkey::default() @ Errors::AlreadyBid,
+        constraint = second.linked_auction == Pubkey::default() @ Errors::InvalidOperation,
+        constraint = second.candle_window == 0 @ Errors::InvalidOperation,
+        constraint = second.usd_reserve == 0 @ Errors::InvalidOperation,
+        constraint = second.winners == 0 @ Errors::InvalidOperation,
+        constraint = !second.all_pay @ Errors::InvalidOperation,
+        constraint = second.penny_step == 0 @ Errors::InvalidOperation,
+        constraint = !second.reverse @ Errors::InvalidOperation,
+        constraint = !second.proxy_bidding @ Errors::InvalidOperation
+    )]
+    pub second: Account<'info, State>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BidLinked<'info> {
+    #[account(
+        seeds = [b"link", first.state.key().as_ref(), second.state.key().as_ref()],
+        bump = link.bump,
+        constraint = first.buyer.key() == second.buyer.key() @ Errors::WrongAccount
+    )]
+    pub link: Account<'info, AuctionLink>,
+
+    pub first: Bid<'info>,
+
+    pub second: Bid<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BuyNow<'info> {
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + Offer::size(),
+        seeds = [b"bid", state.key().as_ref(), buyer.key().as_ref()],
+        bump,
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        has_one = beneficiary @ Errors::WrongAccount,
+        constraint = state.kind == AuctionKind::English @ Errors::InvalidOperation,
+        constraint = state.candle_window == 0 @ Errors::InvalidOperation,
+        constraint = state.winners == 0 @ Errors::InvalidOperation,
+        constraint = !state.all_pay @ Errors::InvalidOperation,
+        constraint = state.penny_step == 0 @ Errors::InvalidOperation,
+        constraint = !state.reverse @ Errors::InvalidOperation,
+        constraint = !state.proxy_bidding @ Errors::InvalidOperation,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.beneficiary @ Errors::WrongAccount)]
+    pub beneficiary: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.entry_fee_receiver @ Errors::WrongAccount)]
+    pub entry_fee_receiver: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"page", state.namespace.as_ref(), state.registry_page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub page: Account<'info, RegistryPage>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + AuctionResult::size(),
+        seeds = [b"result", state.key().as_ref()],
+        bump
+    )]
+    pub result: Account<'info, AuctionResult>,
+
+    /// Read only, only the pause is checked
+    #[account(seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+
+    pub protocol: ProtocolFee<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BidUnits<'info> {
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + Offer::size(),
+        seeds = [b"bid", state.key().as_ref(), buyer.key().as_ref()],
+        bump,
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind == AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(mut, seeds = [b"book", state.key().as_ref()], bump = book.bump)]
+    pub book: Account<'info, UnitBook>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.entry_fee_receiver @ Errors::WrongAccount)]
+    pub entry_fee_receiver: AccountInfo<'info>,
+
+    /// Read only, only the pause is checked
+    #[account(seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveCredit<'info> {
+    #[account(
+        init,
+        payer = namespace,
+        space = 8 + CreditLine::size(),
+        seeds = [b"credit", namespace.key().as_ref(), maker.key().as_ref()],
+        bump
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+
+    #[account(mut)]
+    pub namespace: Signer<'info>,
+
+    /// CHECK: Only its key is registered
+    pub maker: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCreditLimit<'info> {
+    #[account(
+        mut,
+        has_one = namespace @ Errors::WrongAccount,
+        constraint = !credit_line.defaulted @ Errors::CreditDefaulted
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+
+    pub namespace: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Collateral<'info> {
+    #[account(
+        mut,
+        has_one = maker @ Errors::WrongAccount,
+        constraint = !credit_line.defaulted @ Errors::CreditDefaulted
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BidOnCredit<'info> {
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = 8 + CreditBid::size(),
+        seeds = [b"credit_bid", state.key().as_ref(), maker.key().as_ref()],
+        bump
+    )]
+    pub credit_bid: Account<'info, CreditBid>,
+
+    #[account(
+        mut,
+        constraint = state.kind == AuctionKind::English @ Errors::InvalidOperation,
+        constraint = state.payment_mint.is_none() @ Errors::UnsupportedCurrency,
+        constraint = !state.private_winner @ Errors::InvalidOperation,
+        constraint = state.candle_window == 0 @ Errors::InvalidOperation,
+        constraint = state.usd_reserve == 0 @ Errors::InvalidOperation,
+        constraint = state.winners == 0 @ Errors::InvalidOperation,
+        constraint = !state.all_pay @ Errors::InvalidOperation,
+        constraint = state.penny_step == 0 @ Errors::InvalidOperation,
+        constraint = !state.reverse @ Errors::InvalidOperation,
+        constraint = !state.proxy_bidding @ Errors::InvalidOperation,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(
+        mut,
+        seeds = [b"credit", state.namespace.as_ref(), maker.key().as_ref()],
+        bump = credit_line.bump,
+        constraint = !credit_line.defaulted @ Errors::CreditDefaulted
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.entry_fee_receiver @ Errors::WrongAccount)]
+    pub entry_fee_receiver: AccountInfo<'info>,
+
+    /// Read only, only the pause is checked
+    #[account(seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseCredit<'info> {
+    #[account(
+        mut,
+        constraint = state.is_closed() @ Errors::Open,
+        constraint = !state.winner_on_credit || state.max_bidder != *maker.key @ Errors::WinnerRefund
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(
+        mut,
+        seeds = [b"credit", state.namespace.as_ref(), maker.key.as_ref()],
+        bump = credit_line.bump
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+
+    #[account(
+        mut,
+        seeds = [b"credit_bid", state.key().as_ref(), maker.key.as_ref()],
+        bump = credit_bid.bump,
+        close = maker
+    )]
+    pub credit_bid: Account<'info, CreditBid>,
+
+    /// CHECK: The market maker, receiving the rent of the credit bid
+    #[account(mut)]
+    pub maker: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptPrice<'info> {
+    #[account(
+        mut,
+        has_one = beneficiary @ Errors::WrongAccount,
+        constraint = state.kind == AuctionKind::Dutch @ Errors::InvalidOperation,
+        constraint = state.status != AuctionStatus::Settled @ Errors::Closed,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.beneficiary @ Errors::WrongAccount)]
+    pub beneficiary: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"page", state.namespace.as_ref(), state.registry_page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub page: Account<'info, RegistryPage>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + AuctionResult::size(),
+        seeds = [b"result", state.key().as_ref()],
+        bump
+    )]
+    pub result: Account<'info, AuctionResult>,
+
+    pub protocol: ProtocolFee<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Purchase<'info> {
+    #[account(
+        mut,
+        has_one = beneficiary @ Errors::WrongAccount,
+        constraint = state.kind == AuctionKind::Gda @ Errors::InvalidOperation,
+        constraint = state.status != AuctionStatus::Settled @ Errors::Closed,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.beneficiary @ Errors::WrongAccount)]
+    pub beneficiary: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(address = state.item_mint @ Errors::WrongAccount)]
+    pub item_mint: Account<'info, Mint>,
+
+    #[account(mut, token::mint = state.item_mint, token::authority = buyer)]
+    pub buyer_tokens: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"item", state.key().as_ref()], bump)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA signing for the escrow
+    #[account(seeds = [b"authority", state.key().as_ref()], bump)]
+    pub item_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub protocol: ProtocolFee<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Finish<'info> {
+    #[account(
+        mut,
+        has_one = initializer @ Errors::WrongAccount,
+        has_one = treasury @ Errors::WrongAccount,
+        has_one = max_bidder @ Errors::WrongAccount,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.status != AuctionStatus::Settled @ Errors::AlreadySettled,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(mut, address = state.initializer @ Errors::WrongAccount)]
+    pub initializer: Signer<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.beneficiary @ Errors::WrongAccount)]
+    pub beneficiary: AccountInfo<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.max_bidder @ Errors::WrongAccount)]
+    pub max_bidder: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"page", state.namespace.as_ref(), state.registry_page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub page: Account<'info, RegistryPage>,
+
+    #[account(
+        init_if_needed,
+        payer = initializer,
+        space = 8 + AuctionReport::size(),
+        seeds = [b"report", state.key().as_ref()],
+        bump
+    )]
+    pub report: Account<'info, AuctionReport>,
+
+    #[account(
+        init,
+        payer = initializer,
+        space = 8 + AuctionResult::size(),
+        seeds = [b"result", state.key().as_ref()],
+        bump
+    )]
+    pub result: Account<'info, AuctionResult>,
+
+    pub protocol: ProtocolFee<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Finish<'info> {
+    /// Pays the seller out of the escrow of an ended auction, leaving the report and the result behind
+    fn settle(&mut self, remaining_accounts: &[AccountInfo<'info>], result_bump: u8) -> Result<()> {
+        settle_escrow(
+            &mut self.state,
+            &mut self.page,
+            &self.treasury,
+            &self.beneficiary,
+            &self.max_bidder,
+            &self.protocol,
+            remaining_accounts
+        )?;
+
+        // Leave the seller a report of how the auction went
+        self.report.record(&self.state);
+
+        // Publish the outcome for other programs, outliving the auction accounts
+        self.result.record(&self.state, result_bump)?;
+
+        let state = &self.state;
+        touch_watches(state.key(), remaining_accounts)?;
+        audit(state, remaining_accounts, AuditAction::Settle, state.initializer, state.price())
+    }
+}
+
+/// Closes an ended auction and pays its price to the beneficiary, out of the escrow or the collateral of
+/// a winner on credit
+fn settle_escrow<'info>(
+    state: &mut Account<'info, State>,
+    page: &mut Account<'info, RegistryPage>,
+    treasury: &AccountInfo<'info>,
+    beneficiary: &AccountInfo<'info>,
+    max_bidder: &AccountInfo<'info>,
+    protocol: &ProtocolFee<'info>,
+    remaining_accounts: &[AccountInfo<'info>]
+) -> Result<()> {
+    // Has the auction ended with a winner, drawn already in a candle auction or a raffle? One left undrawn
+    // past the draw window fails
+    let now = state.now()?;
+    state.require(now, AuctionStatus::Ended)?;
+    let candle_undrawn = state.candle_window > 0 && !state.candle_resolved;
+    let raffle_undrawn = state.ticket_price > 0 && !state.raffle_drawn;
+    if candle_undrawn && !state.draw_expired(now) {
+        return Err(error!(Errors::CandlePending));
+    }
+    if raffle_undrawn && !state.draw_expired(now) {
+        return Err(error!(Errors::RafflePending));
+    }
+    let undrawn = candle_undrawn || raffle_undrawn;
+
+    // Close the auction before any lamports move, so a repeated or concurrent call
+    // can never pay out twice
+    state.status = AuctionStatus::Settled;
+    page.remove(&state.key());
+
+    // Neither an undrawn auction nor a winning bid no longer meeting the USD reserve at the current SOL price
+    // has a winner, the auction fails and every bidder gets the whole escrow back, the tickets of a raffle too
+    if undrawn || (state.usd_reserve > 0 && state.price() < usd_reserve_lamports(state, remaining_accounts)?) {
+        state.status = AuctionStatus::Failed;
+        state.max_bidder = Pubkey::default();
+        if raffle_undrawn {
+            state.all_pay = false;
+        }
+        emit_event(remaining_accounts, AuctionUnsold {
+            auction: state.key(),
+            auction_id: state.auction_id,
+            lot_id: state.lot_id,
+            initializer: state.initializer,
+            timestamp: Clock::get()?.unix_timestamp,
+        })?;
+
+        return Ok(());
+    }
+
+    // Whatever the treasury holds above the escrow and its rent is the surplus shared by the bidders
+    if state.share_surplus && state.payment_mint.is_none() {
+        state.accrue_weight(state.closes_at());
+        state.surplus_pool = treasury
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(0))
+            .saturating_sub(state.escrowed);
+    }
+
+    // Transfer lamports or tokens to the beneficiary, a winning credit bid is paid out of the collateral
+    let (protocol_fee, royalties, net_proceeds) = if state.winner_on_credit {
+        // The collateral pays no royalties yet
+        let (protocol_fee, net_proceeds) = draw_credit(state, max_bidder, beneficiary, protocol, remaining_accounts)?;
+        (protocol_fee, 0, net_proceeds)
+    } else {
+        let protocol_fee = charge_protocol_fee(state, treasury, protocol, remaining_accounts, state.price())?;
+        let royalties = pay_royalties(state, treasury, remaining_accounts, state.price())?;
+        let net_proceeds = state.price()
+            .checked_sub(protocol_fee)
+            .and_then(|price| price.checked_sub(royalties))
+            .ok_or(Errors::InvalidOperation)?;
+        if state.winners > 0 {
+            pay_winning_bids(state, treasury, beneficiary, remaining_accounts, net_proceeds)?;
+        } else if state.reverse {
+            // The winning seller is paid out of the budget of the buyer, the rest of it goes back to the buyer
+            pay_out(state, treasury, max_bidder, remaining_accounts, net_proceeds, TreasuryReason::Settle)?;
+            let unspent = state.initial_price.checked_sub(state.price()).ok_or(Errors::InvalidOperation)?;
+            pay_out(state, treasury, beneficiary, remaining_accounts, unspent, TreasuryReason::Refund)?;
+        } else {
+            pay_out(
+                state,
+                treasury,
+                beneficiary,
+                remaining_accounts,
+                net_proceeds,
+                TreasuryReason::Settle
+            )?;
+        }
+
+        (protocol_fee, royalties, net_proceeds)
+    };
+
+    // The bid fees of a penny auction go to the beneficiary on top of the price
+    if state.bid_fees > 0 {
+        pay_out(state, treasury, beneficiary, remaining_accounts, state.bid_fees, TreasuryReason::BidFee)?;
+    }
+
+    state.close_winning_bid();
+
+    // No referrals are taken yet, the seller receives the price less the protocol fee and the royalties
+    emit_event(remaining_accounts, SettlementSummary {
+        auction: state.key(),
+        auction_id: state.auction_id,
+        lot_id: state.lot_id,
+        winner: state.public_winner(),
+        winner_hash: state.winner_hash,
+        gross_price: state.price(),
+        protocol_fee,
+        royalties,
+        referrer_cut: 0,
+        net_proceeds,
+        bidders: state.bidders,
+        timestamp: Clock::get()?.unix_timestamp,
+    })?;
+
+    Ok(())
+}
+
+/// Pays the `net_proceeds` of a top-N auction to the beneficiary one winning bid at a time, each bid in
+/// proportion to its amount and the last one taking the rounding remainder
+fn pay_winning_bids<'info>(
+    state: &Account<'info, State>,
+    treasury: &AccountInfo<'info>,
+    beneficiary: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    net_proceeds: u64
+) -> Result<()> {
+    let gross = state.price();
+    let winning_bids = state.winning_bids();
+    let mut paid = 0u64;
+
+    for (index, bid) in winning_bids.iter().enumerate() {
+        let amount = if index + 1 == winning_bids.len() {
+            net_proceeds.checked_sub(paid).ok_or(Errors::InvalidOperation)?
+        } else {
+            (bid.amount as u128 * net_proceeds as u128 / gross as u128) as u64
+        };
+        pay_out(state, treasury, beneficiary, remaining_accounts, amount, TreasuryReason::Settle)?;
+        paid = paid.checked_add(amount).ok_or(Errors::InvalidOperation)?;
+    }
+
+    Ok(())
+}
+
+/// Pays the creator royalties of a sale of the escrowed item at `price` out of the escrow, see `royalty_shares`
+fn pay_royalties<'info>(
+    state: &Account<'info, State>,
+    treasury: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    price: u64
+) -> Result<u64> {
+    let mut paid = 0u64;
+    for (to, share) in royalty_shares(state, remaining_accounts, price)? {
+        pay_out(state, treasury, &to, remaining_accounts, share, TreasuryReason::Royalty)?;
+        paid = paid.checked_add(share).ok_or(Errors::InvalidOperation)?;
+    }
+
+    Ok(paid)
+}
+
+/// Creator royalties of a sale of the escrowed item at `price`, split by the shares of the Metaplex metadata.
+/// An auction of an item needs the metadata address among the remaining accounts together with every creator,
+/// an item without metadata pays none.
+fn royalty_shares<'info>(
+    state: &State,
+    remaining_accounts: &[AccountInfo<'info>],
+    price: u64
+) -> Result<Vec<(AccountInfo<'info>, u64)>> {
+    if state.item_mint == Pubkey::default() {
+        return Ok(Vec::new());
+    }
+
+    let (address, _bump) = Pubkey::find_program_address(
+        &[b"metadata", token_metadata::ID.as_ref(), state.item_mint.as_ref()],
+        &token_metadata::ID
+    );
+    let info = remaining_accounts.iter().find(|info| *info.key == address).ok_or(Errors::WrongAccount)?;
+    if *info.owner != token_metadata::ID || info.data_is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let metadata = Metadata::deserialize(&mut &info.try_borrow_data()?[..]).map_err(|_| error!(Errors::InvalidOperation))?;
+    let royalties = price as u128 * metadata.seller_fee_basis_points.min(BPS) as u128 / BPS as u128;
+
+    let mut shares = Vec::new();
+    for creator in metadata.creators.unwrap_or_default() {
+        let share = (royalties * creator.share as u128 / 100) as u64;
+        if share == 0 {
+            continue;
+        }
+
+        let to = remaining_accounts.iter().find(|info| *info.key == creator.address).ok_or(Errors::WrongAccount)?;
+        shares.push((to.clone(), share));
+    }
+
+    Ok(shares)
+}
+
+/// Leading fields of a Metaplex metadata account, as far as the royalties and the token gates need them
+#[derive(AnchorDeserialize)]
+struct Metadata {
+    _key: u8,
+    _update_authority: Pubkey,
+    _mint: Pubkey,
+    _name: String,
+    _symbol: String,
+    _uri: String,
+    seller_fee_basis_points: u16,
+    creators: Option<Vec<Creator>>,
+    _primary_sale_happened: bool,
+    _is_mutable: bool,
+    _edition_nonce: Option<u8>,
+    _token_standard: Option<u8>,
+    collection: Option<Collection>,
+}
+
+#[derive(AnchorDeserialize)]
+struct Creator {
+    address: Pubkey,
+    _verified: bool,
+    /// Percent of the royalties
+    share: u8,
+}
+
+#[derive(AnchorDeserialize)]
+struct Collection {
+    verified: bool,
+    key: Pubkey,
+}
+
+/// Moves the protocol fee of a sale at `price` from the treasury to the fee vault, tokens going to the token
+/// account of the vault among the remaining accounts
+fn charge_protocol_fee<'info>(
+    state: &Account<'info, State>,
+    treasury: &AccountInfo<'info>,
+    protocol: &ProtocolFee<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    price: u64
+) -> Result<u64> {
+    let fee = protocol.fee(price);
+    if fee > 0 {
+        let vault = protocol.fee_vault.to_account_info();
+        pay_out(state, treasury, &vault, remaining_accounts, fee, TreasuryReason::ProtocolFee)?;
+    }
+
+    Ok(fee)
+}
+
+/// Pays a sale at `price` in lamports straight from a signing `buyer`: the protocol fee to the fee vault, the
+/// creator royalties of the escrowed item and the rest to the beneficiary. Returns the fee and the royalties.
+fn pay_direct<'info>(
+    state: &State,
+    buyer: &AccountInfo<'info>,
+    protocol: &ProtocolFee<'info>,
+    beneficiary: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    price: u64
+) -> Result<(u64, u64)> {
+    let fee = protocol.fee(price);
+    let mut payments = vec![(protocol.fee_vault.to_account_info(), fee)];
+    payments.extend(royalty_shares(state, remaining_accounts, price)?);
+
+    let royalties = payments[1..].iter().try_fold(0u64, |sum, (_, share)| sum.checked_add(*share)).ok_or(Errors::InvalidOperation)?;
+    let net_proceeds = price
+        .checked_sub(fee)
+        .and_then(|price| price.checked_sub(royalties))
+        .ok_or(Errors::InvalidOperation)?;
+    payments.push((beneficiary.clone(), net_proceeds));
+
+    for (to, amount) in payments {
+        if amount > 0 {
+            invoke(
+                &system_instruction::transfer(buyer.key, to.key, amount),
+                &[buyer.clone(), to]
+            )?;
+        }
+    }
+
+    Ok((fee, royalties))
+}
+
+#[derive(Accounts)]
+pub struct Settle<'info> {
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        has_one = max_bidder @ Errors::WrongAccount,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.status != AuctionStatus::Settled @ Errors::AlreadySettled,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.beneficiary @ Errors::WrongAccount)]
+    pub beneficiary: AccountInfo<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.max_bidder @ Errors::WrongAccount)]
+    pub max_bidder: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"page", state.namespace.as_ref(), state.registry_page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub page: Account<'info, RegistryPage>,
+
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        space = 8 + AuctionReport::size(),
+        seeds = [b"report", state.key().as_ref()],
+        bump
+    )]
+    pub report: Account<'info, AuctionReport>,
+
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + AuctionResult::size(),
+        seeds = [b"result", state.key().as_ref()],
+        bump
+    )]
+    pub result: Account<'info, AuctionResult>,
+
+    /// Anybody, paying the rent of the report and the result
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub protocol: ProtocolFee<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CrankSettlement<'info> {
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        has_one = max_bidder @ Errors::WrongAccount,
+        has_one = keeper @ Errors::WrongAccount,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = !state.draft @ Errors::Draft,
+        // Paired lots settle together and the remaining accounts hold only the pairs and the event accounts,
+        // no audit log, token accounts, royalty accounts of an escrowed item or price feed. Those auctions
+        // are finished by hand
+        constraint = state.linked_auction == Pubkey::default() @ Errors::InvalidOperation,
+        constraint = !state.audited @ Errors::AuditLogRequired,
+        constraint = state.payment_mint.is_none() @ Errors::UnsupportedCurrency,
+        constraint = state.item_mint == Pubkey::default() @ Errors::InvalidOperation,
+        constraint = state.usd_reserve == 0 @ Errors::InvalidOperation
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.beneficiary @ Errors::WrongAccount)]
+    pub beneficiary: AccountInfo<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.max_bidder @ Errors::WrongAccount)]
+    pub max_bidder: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"page", state.namespace.as_ref(), state.registry_page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub page: Account<'info, RegistryPage>,
+
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        space = 8 + AuctionReport::size(),
+        seeds = [b"report", state.key().as_ref()],
+        bump
+    )]
+    pub report: Account<'info, AuctionReport>,
+
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        space = 8 + AuctionResult::size(),
+        seeds = [b"result", state.key().as_ref()],
+        bump
+    )]
+    pub result: Account<'info, AuctionResult>,
+
+    /// Keeper recorded by `schedule_settlement`, paying the rent of the report and the result
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    pub protocol: ProtocolFee<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleLinked<'info> {
+    #[account(
+        seeds = [b"link", first.state.key().as_ref(), second.state.key().as_ref()],
+        bump = link.bump
+    )]
+    pub link: Account<'info, AuctionLink>,
+
+    pub first: Finish<'info>,
+
+    pub second: Finish<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleUnits<'info> {
+    #[account(
+        mut,
+        has_one = initializer @ Errors::WrongAccount,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind == AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.status != AuctionStatus::Settled @ Errors::AlreadySettled,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(mut, seeds = [b"book", state.key().as_ref()], bump = book.bump)]
+    pub book: Account<'info, UnitBook>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.beneficiary @ Errors::WrongAccount)]
+    pub beneficiary: AccountInfo<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"page", state.namespace.as_ref(), state.registry_page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub page: Account<'info, RegistryPage>,
+
+    #[account(mut, token::mint = state.item_mint, token::authority = initializer)]
+    pub seller_tokens: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"item", state.key().as_ref()], bump)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA signing for the escrow
+    #[account(seeds = [b"authority", state.key().as_ref()], bump)]
+    pub item_authority: AccountInfo<'info>,
+
+    pub protocol: ProtocolFee<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimUnits<'info> {
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind == AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.status == AuctionStatus::Settled @ Errors::Open
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(seeds = [b"book", state.key().as_ref()], bump = book.bump)]
+    pub book: Account<'info, UnitBook>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"bid", state.key().as_ref(), buyer.key.as_ref()],
+        bump = offer.bump,
+        close = buyer
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(mut, token::mint = state.item_mint, token::authority = buyer)]
+    pub buyer_tokens: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"item", state.key().as_ref()], bump)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA signing for the escrow
+    #[account(seeds = [b"authority", state.key().as_ref()], bump)]
+    pub item_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseReport<'info> {
+    #[account(has_one = initializer @ Errors::WrongAccount)]
+    pub state: Account<'info, State>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"report", state.key().as_ref()],
+        bump,
+        close = initializer
+    )]
+    pub report: Account<'info, AuctionReport>,
+}
+
+#[derive(Accounts)]
+pub struct Refund<'info> {
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.is_closed() @ Errors::Open,
+        constraint = !state.all_pay @ Errors::AllPay,
+        constraint = state.may_refund(buyer.key) @ Errors::WinnerRefund
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"bid", state.key().as_ref(), buyer.key.as_ref()],
+        bump = offer.bump,
+        close = buyer
+    )]
+    pub offer: Account<'info, Offer>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawBid<'info> {
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind == AuctionKind::English @ Errors::InvalidOperation,
+        constraint = state.candle_window == 0 @ Errors::InvalidOperation,
+        constraint = state.winners == 0 @ Errors::InvalidOperation,
+        constraint = !state.all_pay @ Errors::AllPay,
+        constraint = state.max_bidder != buyer.key() @ Errors::WinnerRefund
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"bid", state.key().as_ref(), buyer.key.as_ref()],
+        bump = offer.bump,
+        close = buyer
+    )]
+    pub offer: Account<'info, Offer>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct LogEvent<'info> {
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateWatch<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Watch::size(),
+        seeds = [b"watch", state.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub watch: Account<'info, Watch>,
+
+    pub state: Account<'info, State>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseWatch<'info> {
+    #[account(mut, has_one = user @ Errors::WrongAccount, close = user)]
+    pub watch: Account<'info, Watch>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateNotify<'info> {
+    #[account(
+        init,
+        payer = bidder,
+        space = 8 + Notify::size(),
+        seeds = [b"notify", bidder.key().as_ref()],
+        bump
+    )]
+    pub notify: Account<'info, Notify>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseNotify<'info> {
+    #[account(mut, has_one = bidder @ Errors::WrongAccount, close = bidder)]
+    pub notify: Account<'info, Notify>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SnapshotParticipation<'info> {
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        space = 8 + ParticipationSnapshot::size(),
+        seeds = [b"snapshot", state.key().as_ref(), bidder.key().as_ref()],
+        bump
+    )]
+    pub snapshot: Account<'info, ParticipationSnapshot>,
+
+    pub state: Account<'info, State>,
+
+    #[account(seeds = [b"bid", state.key().as_ref(), bidder.key().as_ref()], bump = offer.bump)]
+    pub offer: Account<'info, Offer>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSnapshot<'info> {
+    #[account(mut, has_one = bidder @ Errors::WrongAccount, close = bidder)]
+    pub snapshot: Account<'info, ParticipationSnapshot>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterSponsor<'info> {
+    #[account(
+        init,
+        payer = namespace,
+        space = 8 + Sponsorship::size(),
+        seeds = [b"sponsor", namespace.key().as_ref(), sponsor.key().as_ref()],
+        bump
+    )]
+    pub sponsorship: Account<'info, Sponsorship>,
+
+    #[account(mut)]
+    pub namespace: Signer<'info>,
+
+    /// CHECK: Only its key is registered
+    pub sponsor: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateConfig<'info> {
+    #[account(
+        init,
+        payer = namespace,
+        space = 8 + MarketConfig::size(),
+        seeds = [b"config", namespace.key().as_ref()],
+        bump
+    )]
+    pub config: Account<'info, MarketConfig>,
+
+    #[account(mut)]
+    pub namespace: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(mut, has_one = namespace @ Errors::WrongAccount)]
+    pub config: Account<'info, MarketConfig>,
+
+    pub namespace: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVolumeCap<'info> {
+    #[account(init_if_needed, payer = authority, space = 8 + GlobalStats::size(), seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ Errors::WrongAccount)]
+    pub program: Program<'info, crate::program::Auction>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ Errors::WrongAccount)]
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeGlobalConfig<'info> {
+    #[account(init, payer = authority, space = 8 + GlobalConfig::size(), seeds = [b"global"], bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(init, payer = authority, space = 8 + FeeVault::size(), seeds = [b"fees"], bump)]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ Errors::WrongAccount)]
+    pub program: Program<'info, crate::program::Auction>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ Errors::WrongAccount)]
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(mut, seeds = [b"global"], bump = config.bump, has_one = admin @ Errors::WrongAccount)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(init_if_needed, payer = admin, space = 8 + GlobalStats::size(), seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGlobalConfig<'info> {
+    #[account(mut, seeds = [b"global"], bump = config.bump, has_one = admin @ Errors::WrongAccount)]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(seeds = [b"global"], bump = config.bump, has_one = admin @ Errors::WrongAccount)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut, seeds = [b"fees"], bump = fee_vault.bump)]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    pub admin: Signer<'info>,
+
+    /// CHECK: Any account chosen by the admin
+    #[account(mut)]
+    pub to: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFeeTokens<'info> {
+    #[account(seeds = [b"global"], bump = config.bump, has_one = admin @ Errors::WrongAccount)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(seeds = [b"fees"], bump = fee_vault.bump)]
+    pub fee_vault: Account<'info, FeeVault>,
+
+    /// Token account of the fee vault collecting the fees of the auctions paid in its mint
+    #[account(mut, token::authority = fee_vault)]
+    pub vault_tokens: Account<'info, TokenAccount>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut, constraint = to.mint == vault_tokens.mint @ Errors::WrongAccount)]
+    pub to: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Program-wide config and the fee vault, every sale pays the protocol fee of the config into the vault
+#[derive(Accounts)]
+pub struct ProtocolFee<'info> {
+    #[account(seeds = [b"global"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut, seeds = [b"fees"], bump = fee_vault.bump)]
+    pub fee_vault: Account<'info, FeeVault>,
+}
+
+impl<'info> ProtocolFee<'info> {
+    /// Protocol fee of a sale at `price`
+    fn fee(&self, price: u64) -> u64 {
+        (price as u128 * self.config.fee_bps as u128 / BPS as u128) as u64
+    }
+}
+
+#[derive(Accounts)]
+pub struct RemoveSponsor<'info> {
+    #[account(
+        mut,
+        has_one = namespace @ Errors::WrongAccount,
+        close = namespace
+    )]
+    pub sponsorship: Account<'info, Sponsorship>,
+
+    #[account(mut)]
+    pub namespace: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundFor<'info> {
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.is_closed() @ Errors::Open,
+        constraint = !state.all_pay @ Errors::AllPay,
+        constraint = state.may_refund(bidder.key) @ Errors::WinnerRefund
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    /// CHECK: The owner of the offer, receiving the refund
+    #[account(mut)]
+    pub bidder: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"bid", state.key().as_ref(), bidder.key.as_ref()],
+        bump = offer.bump,
+        close = bidder
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        seeds = [b"sponsor", state.namespace.as_ref(), sponsor.key.as_ref()],
+        bump = sponsorship.bump
+    )]
+    pub sponsorship: Account<'info, Sponsorship>,
+
+    pub sponsor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefundAll<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundMany<'info> {
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.is_closed() @ Errors::Open,
+        // No room for the audit log or token accounts in the pairs, those auctions use `refund`
+        constraint = !state.audited @ Errors::AuditLogRequired,
+        constraint = state.payment_mint.is_none() @ Errors::UnsupportedCurrency
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SweepExpiredRefund<'info> {
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.is_closed() @ Errors::Open,
+        constraint = state.payment_mint.is_none() @ Errors::UnsupportedCurrency,
+        constraint = !state.all_pay @ Errors::AllPay,
+        constraint = state.may_refund(bidder.key) @ Errors::WinnerRefund
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    /// CHECK: Only used to derive the offer address
+    pub bidder: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"bid", state.key().as_ref(), bidder.key.as_ref()],
+        bump = offer.bump,
+        close = protocol_vault
+    )]
+    pub offer: Account<'info, Offer>,
+
+    /// CHECK: Lamports-only account owned by the protocol
+    #[account(mut, seeds = [b"protocol", state.namespace.as_ref()], bump)]
+    pub protocol_vault: AccountInfo<'info>,
+
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitFinalBid<'info> {
+    #[account(
+        constraint = state.kind == AuctionKind::Hybrid @ Errors::InvalidOperation,
+        constraint = state.is_finalist(buyer.key) @ Errors::NotFinalist
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(
+        mut,
+        seeds = [b"bid", state.key().as_ref(), buyer.key.as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    pub buyer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealFinalBid<'info> {
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind == AuctionKind::Hybrid @ Errors::InvalidOperation,
+        constraint = state.is_finalist(buyer.key) @ Errors::NotFinalist
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"bid", state.key().as_ref(), buyer.key.as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitBid<'info> {
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + Offer::size(),
+        seeds = [b"bid", state.key().as_ref(), buyer.key().as_ref()],
+        bump,
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        constraint = state.kind == AuctionKind::Sealed @ Errors::InvalidOperation,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK:
+    #[account(mut, address = state.entry_fee_receiver @ Errors::WrongAccount)]
+    pub entry_fee_receiver: AccountInfo<'info>,
+
+    /// Read only, only the pause is checked
+    #[account(seeds = [b"stats"], bump)]
+    pub stats: Account<'info, GlobalStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealBid<'info> {
+    #[account(
+        mut,
+        constraint = state.kind == AuctionKind::Sealed @ Errors::InvalidOperation,
+        constraint = !state.draft @ Errors::Draft
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(
+        mut,
+        seeds = [b"bid", state.key().as_ref(), buyer.key.as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    pub buyer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConcealIdentity<'info> {
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + Offer::size(),
+        seeds = [b"bid", state.key().as_ref(), buyer.key().as_ref()],
+        bump,
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(constraint = state.private_winner @ Errors::InvalidOperation)]
+    pub state: Account<'info, State>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealWinner<'info> {
+    #[account(
+        mut,
+        has_one = max_bidder @ Errors::WrongAccount,
+        constraint = state.private_winner @ Errors::InvalidOperation,
+        constraint = state.status == AuctionStatus::Settled @ Errors::Open
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(mut, seeds = [b"result", state.key().as_ref()], bump = result.bump)]
+    pub result: Account<'info, AuctionResult>,
+
+    pub max_bidder: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CompactPage<'info> {
+    #[account(mut, seeds = [b"registry", registry.namespace.as_ref()], bump)]
+    pub registry: Account<'info, Registry>,
+
+    #[account(
+        mut,
+        seeds = [b"page", registry.namespace.as_ref(), page.index.to_le_bytes().as_ref()],
+        bump,
+        constraint = page.index != registry.tail @ Errors::InvalidOperation
+    )]
+    pub page: Account<'info, RegistryPage>,
+
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        space = 8 + RegistryPage::size(),
+        seeds = [b"page", registry.namespace.as_ref(), registry.tail.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tail: Account<'info, RegistryPage>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimSurplus<'info> {
+    #[account(
+        mut,
+        has_one = treasury @ Errors::WrongAccount,
+        has_one = max_bidder @ Errors::WrongAccount,
+        constraint = !state.may_refund(max_bidder.key) @ Errors::InvalidOperation,
+        constraint = state.is_closed() @ Errors::Open
+    )]
+    pub state: Account<'info, State>,
+
+    /// CHECK:
+    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub max_bidder: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"bid", state.key().as_ref(), max_bidder.key.as_ref()],
+        bump = offer.bump,
+        close = max_bidder
+    )]
+    pub offer: Account<'info, Offer>,
+}
+
+#[derive(Accounts)]
+pub struct DepositItem<'info> {
+    #[account(
+        mut,
+        has_one = initializer @ Errors::WrongAccount,
+        constraint = state.status != AuctionStatus::Settled @ Errors::Closed,
+        constraint = state.max_bidder == Pubkey::default() @ Errors::AlreadyBid,
+        constraint = state.item_mint == Pubkey::default() @ Errors::InvalidOperation,
+        constraint = state.winners == 0 @ Errors::InvalidOperation,
+        constraint = !state.reverse @ Errors::InvalidOperation
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    pub item_mint: Account<'info, Mint>,
+
+    #[account(mut, token::mint = item_mint, token::authority = initializer)]
+    pub seller_tokens: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"item", state.key().as_ref()],
+        bump,
+        token::mint = item_mint,
+        token::authority = item_authority
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA signing for the escrow
+    #[account(seeds = [b"authority", state.key().as_ref()], bump)]
+    pub item_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct MakeBuyOffer<'info> {
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + BuyOffer::size(),
+        seeds = [b"buy_offer", item_mint.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub buy_offer: Account<'info, BuyOffer>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    pub item_mint: Account<'info, Mint>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBuyOffer<'info> {
+    #[account(mut, has_one = buyer @ Errors::WrongAccount, close = buyer)]
+    pub buy_offer: Account<'info, BuyOffer>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptBuyOffer<'info> {
+    #[account(
+        mut,
+        has_one = buyer @ Errors::WrongAccount,
+        seeds = [b"buy_offer", buy_offer.item_mint.as_ref(), buyer.key().as_ref()],
+        bump = buy_offer.bump,
+        close = buyer
+    )]
+    pub buy_offer: Account<'info, BuyOffer>,
+
+    /// CHECK: The maker of the offer, getting the rent back
+    #[account(mut)]
+    pub buyer: AccountInfo<'info>,
+
+    #[account(mut, token::mint = buy_offer.item_mint, constraint = buyer_tokens.owner == buyer.key() @ Errors::WrongAccount)]
+    pub buyer_tokens: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    #[account(mut, token::mint = buy_offer.item_mint, token::authority = seller)]
+    pub seller_tokens: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimItem<'info> {
+    #[account(
+        mut,
+        has_one = max_bidder @ Errors::WrongAccount,
+        constraint = state.status == AuctionStatus::Settled @ Errors::Open,
+        constraint = state.kind != AuctionKind::Gda @ Errors::InvalidOperation,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.item_mint != Pubkey::default() @ Errors::NoItem,
+        constraint = !state.item_claimed @ Errors::ItemClaimed
+    )]
+    pub state: Account<'info, State>,
+
+    pub max_bidder: Signer<'info>,
+
+    #[account(mut, token::mint = state.item_mint, token::authority = max_bidder)]
+    pub winner_tokens: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"item", state.key().as_ref()], bump)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA signing for the escrow
+    #[account(seeds = [b"authority", state.key().as_ref()], bump)]
+    pub item_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RecoverItem<'info> {
+    #[account(
+        mut,
+        has_one = initializer @ Errors::WrongAccount,
+        constraint = state.kind != AuctionKind::MultiUnit @ Errors::InvalidOperation,
+        constraint = state.item_mint != Pubkey::default() @ Errors::NoItem,
+        constraint = !state.item_claimed @ Errors::ItemClaimed
+    )]
+    pub state: Account<'info, State>,
+
+    pub initializer: Signer<'info>,
+
+    #[account(mut, token::mint = state.item_mint, token::authority = initializer)]
+    pub seller_tokens: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"item", state.key().as_ref()], bump)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA signing for the escrow
+    #[account(seeds = [b"authority", state.key().as_ref()], bump)]
+    pub item_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[account]
+pub struct State {
+    pub initializer: Pubkey,
+    pub treasury: Pubkey,
+    pub max_bidder: Pubkey,
+    pub max_price: u64,
+    pub end_time: i64,
+    /// Lifecycle stage as of the last instruction, a single byte to filter auctions by. The clock moves
+    /// an open auction on to ended or failed without an instruction, `status` accounts for that.
+    pub status: AuctionStatus,
+    pub bidders: u32,
+    pub item_mint: Pubkey,
+    pub item_amount: u64,
+    pub item_claimed: bool,
+    pub kind: AuctionKind,
+    pub finalists_limit: u8,
+    pub finalists: [Pubkey; MAX_FINALISTS],
+    pub commit_end: i64,
+    pub reveal_end: i64,
+    pub price_steps_count: u8,
+    pub price_steps: [PriceStep; MAX_PRICE_STEPS],
+    pub start_time: i64,
+    pub gda_initial_price: u64,
+    pub gda_scale: u64,
+    pub gda_decay: u64,
+    pub gda_sold: u64,
+    pub initial_price: u64,
+    pub draft: bool,
+    pub category: Category,
+    pub tags: u64,
+    pub auction_id: u64,
+    pub registry_page: u32,
+    pub max_bidders: u32,
+    pub entry_fee: u64,
+    pub entry_fee_receiver: Pubkey,
+    pub bid_count: u32,
+    pub activity: [Activity; REPORT_CHECKPOINTS],
+    pub namespace: Pubkey,
+    pub audited: bool,
+    pub min_increment: u64,
+    pub sprint_window: i64,
+    pub sprint_factor_bps: u16,
+    pub sprint_bids: u32,
+    pub attestor: Pubkey,
+    pub allowed_regions: u64,
+    pub share_surplus: bool,
+    pub escrowed: u64,
+    pub total_weight: u128,
+    pub weight_updated_at: i64,
+    pub surplus_pool: u64,
+    pub payment_mint: Option<Pubkey>,
+    pub deadline_policy: DeadlinePolicy,
+    pub winner_on_credit: bool,
+    /// Receives the proceeds, the initializer unless set otherwise at creation
+    pub beneficiary: Pubkey,
+    /// Identifier of the sold lot in the seller's books, zero when not given
+    pub lot_id: u64,
+    pub dutch_curve: DutchCurve,
+    /// Holding this mint lets a wallet bid in the early access window
+    pub early_access_mint: Pubkey,
+    pub early_access_start: i64,
+    /// Start of the public window, bids before it need early access
+    pub public_start: i64,
+    /// Whether the winner pays the second highest bid instead of their own
+    pub second_price: bool,
+    /// Highest bid below the winning one, the initial price until there are two bids
+    pub runner_up_price: u64,
+    /// Whether the settlement records show `winner_hash` instead of the winner until they reveal themselves
+    pub private_winner: bool,
+    /// Concealed identity of the leading bidder in an auction with a private winner
+    pub winner_hash: [u8; 32],
+    /// Price settling the auction at once, zero when there is no buy-now
+    pub buy_now_price: u64,
+    /// The other auction of a paired lot, `Pubkey::default()` when the lot is sold alone
+    pub linked_auction: Pubkey,
+    /// Bids in the last `soft_close_window` seconds push the end by `soft_close_extension` seconds
+    pub soft_close_window: i64,
+    pub soft_close_extension: i64,
+    /// Most the soft close may push the end in total
+    pub max_extension: i64,
+    /// How far the soft close has pushed the end so far
+    pub extended: i64,
+    /// Most times the soft close may push the end, zero for no limit
+    pub max_extensions: u16,
+    pub extensions: u16,
+    /// Neither the soft close nor `extend_auction` push the end past this time, zero for no limit
+    pub latest_end_time: i64,
+    /// Bids are rejected until this time, zero without a warm-up
+    pub warmup_until: i64,
+    /// Part of the escrow in basis points `withdraw_bid` keeps in the treasury
+    pub withdrawal_penalty_bps: u16,
+    /// Offers and credit bids holding a bid which are not refunded or claimed yet
+    pub open_bids: u32,
+    /// Merkle root of the wallets allowed to bid, see `set_allowlist`, zero for everybody
+    pub allowlist_root: [u8; 32],
+    /// Mint, or verified collection, of the tokens every bidder has to hold, default for no gate
+    pub token_gate: Pubkey,
+    /// Tokens of the gate mint a bidder has to hold, a gate by collection takes any of its NFTs
+    pub token_gate_amount: u64,
+    pub token_gate_collection: bool,
+    /// Somebody was banned, every bid has to pass the ban list among its remaining accounts
+    pub has_bans: bool,
+    /// Last seconds of a candle auction its real end is drawn from, zero for a fixed end
+    pub candle_window: i64,
+    /// Switchboard VRF account whose first result requested past the end draws the end of a candle auction
+    pub candle_vrf: Pubkey,
+    /// Leader at the end of each of the equally long samples of the candle window
+    pub candle_leaders: [CandleLeader; CANDLE_SAMPLES],
+    /// The end was drawn and the winner is the leader of that moment
+    pub candle_resolved: bool,
+    /// Reserve in US cents converted at the price of `usd_price_feed`, 0 without a USD reserve
+    pub usd_reserve: u64,
+    /// Pyth SOL/USD price account the USD reserve is converted with
+    pub usd_price_feed: Pubkey,
+    /// Number of winners of a top-N auction, 0 with a single winner
+    pub winners: u8,
+    /// Winning bids of a top-N auction from the highest down, the first one being `max_bidder`
+    pub top_bids: [TopBid; MAX_WINNERS],
+    /// Every bid is paid to the beneficiary, nobody is refunded
+    pub all_pay: bool,
+    /// Non-refundable fee of every bid of a penny auction
+    pub penny_fee: u64,
+    /// Exact raise of every bid of a penny auction, 0 outside of penny auctions
+    pub penny_step: u64,
+    /// Seconds a penny auction runs on after every bid at least
+    pub penny_countdown: i64,
+    /// Bid fees collected by a penny auction, held in the treasury apart from the escrow of the bids
+    pub bid_fees: u64,
+    /// The initializer buys with the budget in `initial_price` escrowed and the lowest offer wins
+    pub reverse: bool,
+    /// Bidders may escrow a maximum which the program bids for them as far as needed, see `proxy_bid`
+    pub proxy_bidding: bool,
+    /// Maximum the proxy of the leader counters up to, 0 when the leader bid a plain amount
+    pub leader_max: u64,
+    /// Price of a ticket of a raffle, 0 outside of raffles
+    pub ticket_price: u64,
+    /// Switchboard VRF account drawing the winning ticket of a raffle
+    pub raffle_vrf: Pubkey,
+    /// Tickets sold by a raffle, numbered from 0
+    pub tickets: u64,
+    /// Whether the winning ticket of a raffle was drawn
+    pub raffle_drawn: bool,
+    /// Seconds after the end the losing bidders have to claim their refunds before anyone can sweep them
+    pub refund_expiry: i64,
+    /// Bids the finalists of a hybrid auction qualified with, highest first like the finalists
+    pub finalist_bids: [u64; MAX_FINALISTS],
+    /// Requests the VRF account of a candle auction or a raffle had served when the draw was set, only the next
+    /// one counts
+    pub vrf_counter: u128,
+    /// Keeper finishing the auction with `crank_settlement`, none by default
+    pub keeper: Pubkey,
+    /// Layout version the account was created or last migrated with
+    pub version: u8,
+}
+
+impl State {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<u8>() +
+        size_of::<u32>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<bool>() +
+        size_of::<u8>() +
+        size_of::<u8>() +
+        size_of::<Pubkey>() * MAX_FINALISTS +
+        size_of::<UnixTimestamp>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<u8>() +
+        PriceStep::size() * MAX_PRICE_STEPS +
+        size_of::<UnixTimestamp>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<bool>() +
+        size_of::<u8>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<u32>() +
+        size_of::<u32>() +
+        size_of::<u64>() +
+        size_of::<Pubkey>() +
+        size_of::<u32>() +
+        Activity::size() * REPORT_CHECKPOINTS +
+        size_of::<Pubkey>() +
+        size_of::<bool>() +
+        size_of::<u64>() +
+        size_of::<i64>() +
+        size_of::<u16>() +
+        size_of::<u32>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<bool>() +
+        size_of::<u64>() +
+        size_of::<u128>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<u64>() +
+        size_of::<Option<Pubkey>>() +
+        size_of::<u8>() +
+        size_of::<bool>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<u8>() +
+        size_of::<Pubkey>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<bool>() +
+        size_of::<u64>() +
+        size_of::<bool>() +
+        size_of::<[u8; 32]>() +
+        size_of::<u64>() +
+        size_of::<Pubkey>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<u16>() +
+        size_of::<u16>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<u16>() +
+        size_of::<u32>() +
+        size_of::<[u8; 32]>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<bool>() +
+        size_of::<bool>() +
+        size_of::<i64>() +
+        size_of::<Pubkey>() +
+        CandleLeader::size() * CANDLE_SAMPLES +
+        size_of::<bool>() +
+        size_of::<u64>() +
+        size_of::<Pubkey>() +
+        size_of::<u8>() +
+        TopBid::size() * MAX_WINNERS +
+        size_of::<bool>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<i64>() +
+        size_of::<u64>() +
+        size_of::<bool>() +
+        size_of::<bool>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<bool>() +
+        size_of::<i64>() +
+        size_of::<u64>() * MAX_FINALISTS +
+        size_of::<u128>() +
+        size_of::<Pubkey>() +
+        size_of::<u8>()
+    }
+
+    /// Size of the state of layout `version`, every version having appended its fields right before the version
+    pub fn size_at(version: u8) -> usize {
+        (version + 1..=STATE_VERSION).fold(State::size(), |size, later| size - State::added_in(later))
+    }
+
+    /// Bytes of the fields the layout `version` added, see `upgrade`
+    fn added_in(version: u8) -> usize {
+        match version {
+            2 => size_of::<[u8; 32]>(),
+            3 => size_of::<Pubkey>() + size_of::<u64>() + size_of::<bool>(),
+            4 => size_of::<bool>(),
+            5 => size_of::<i64>() + size_of::<Pubkey>() + CandleLeader::size() * CANDLE_SAMPLES + size_of::<bool>(),
+            6 => size_of::<u64>() + size_of::<Pubkey>(),
+            7 => size_of::<u8>() + TopBid::size() * MAX_WINNERS,
+            8 => size_of::<bool>(),
+            9 => size_of::<u64>() + size_of::<u64>() + size_of::<i64>() + size_of::<u64>(),
+            10 => size_of::<bool>(),
+            11 => size_of::<bool>() + size_of::<u64>(),
+            12 => size_of::<u64>() + size_of::<Pubkey>() + size_of::<u64>() + size_of::<bool>(),
+            13 => size_of::<i64>(),
+            14 => size_of::<u64>() * MAX_FINALISTS,
+            15 => size_of::<u128>(),
+            16 => size_of::<Pubkey>(),
+            _ => 0,
+        }
+    }
+
+    /// Decodes an auction of an older layout version by hand. The size of the account tells the version and the
+    /// version byte ends the written bytes, 32 bytes earlier without a payment mint. The fields added since are
+    /// filled with their defaults, see `upgrade`.
+    pub fn decode_versioned(data: &[u8]) -> Result<State> {
+        let version = (1..STATE_VERSION)
+            .find(|&version| data.len() == 8 + State::size_at(version))
+            .ok_or(Errors::InvalidOperation)?;
+
+        // Every field up to the version byte, the later ones zeroed until `upgrade` sets them
+        let decode = |end: usize| -> Result<State> {
+            let mut buffer = data[..end].to_vec();
+            buffer.resize(8 + State::size(), 0);
+            State::try_deserialize(&mut &buffer[..])
+        };
+        let mut end = data.len() - size_of::<u8>() - size_of::<Pubkey>();
+        let mut state = decode(end)?;
+        if state.payment_mint.is_some() {
+            end = data.len() - size_of::<u8>();
+            state = decode(end)?;
+        }
+        if data[end] != version {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        state.version = version;
+        state.upgrade();
+
+        Ok(state)
+    }
+
+    /// Decodes an auction of the legacy layout by hand, see `LegacyState`. Its treasury holds `escrowed` on top
+    /// of its rent. The fields the legacy layout didn't have take the defaults of `start`, its closed flag
+    /// becomes a settled auction.
+    pub fn decode_legacy(data: &[u8], escrowed: u64) -> Result<State> {
+        if data.len() != 8 + LegacyState::size() {
+            return Err(error!(Errors::InvalidOperation));
+        }
+        let legacy = LegacyState::deserialize(&mut &data[8..])?;
+
+        // The discriminator, every field zeroed until set below or by `upgrade`
+        let mut buffer = data[..8].to_vec();
+        buffer.resize(8 + State::size(), 0);
+        let mut state = State::try_deserialize(&mut &buffer[..])?;
+
+        state.initializer = legacy.initializer;
+        state.treasury = legacy.treasury;
+        state.max_bidder = legacy.max_bidder;
+        state.max_price = legacy.max_price;
+        state.end_time = legacy.end_time;
+        state.status = if legacy.open { AuctionStatus::Open } else { AuctionStatus::Settled };
+        state.initial_price = legacy.max_price;
+        state.runner_up_price = legacy.max_price;
+        state.entry_fee_receiver = legacy.initializer;
+        state.beneficiary = legacy.initializer;
+        state.sprint_factor_bps = BPS;
+        state.escrowed = escrowed;
+
+        state.version = 0;
+        state.upgrade();
+
+        Ok(state)
+    }
+
+    /// Current time on the clock the deadlines of the auction are measured on
+    pub fn now(&self) -> Result<i64> {
+        Ok(self.deadline_policy.now(&Clock::get()?))
+    }
+
+    /// Whether the open phase is taking bids, regardless of the draft and settlement flags
+    pub fn is_open(&self, now: i64) -> bool {
+        now < self.end_time
+    }
+
+    /// Whether every phase is over, including the sealed round of a hybrid auction
+    pub fn is_ended(&self, now: i64) -> bool {
+        now >= self.closes_at()
+    }
+
+    /// Whether the final sprint with escalating increments is running
+    pub fn in_closing_window(&self, now: i64) -> bool {
+        self.sprint_window > 0 && now >= self.end_time.saturating_sub(self.sprint_window)
+    }
+
+    /// Pushes the end by the soft close extension for a bid in the last seconds, as long as the total
+    /// extension, the number of extensions and the end stay within their caps. The sealed round of a hybrid
+    /// auction moves along.
+    fn soft_close(&mut self, now: i64) -> Result<()> {
+        if self.soft_close_window == 0 || now < self.end_time.saturating_sub(self.soft_close_window) {
+            return Ok(());
+        }
+        if self.max_extensions != 0 && self.extensions >= self.max_extensions {
+            return Ok(());
+        }
+
+        let mut extension = self.soft_close_extension.min(self.max_extension.saturating_sub(self.extended));
+        if self.latest_end_time != 0 {
+            extension = extension.min(self.latest_end_time.saturating_sub(self.end_time));
+        }
+        if extension <= 0 {
+            return Ok(());
+        }
+
+        self.extended = self.extended.checked_add(extension).ok_or(Errors::InvalidOperation)?;
+        self.extensions = self.extensions.checked_add(1).ok_or(Errors::InvalidOperation)?;
+        self.extend(extension)
+    }
+
+    /// Moves the end and, for a hybrid auction, its sealed round by `extension` seconds
+    fn extend(&mut self, extension: i64) -> Result<()> {
+        self.end_time = self.end_time.checked_add(extension).ok_or(Errors::InvalidOperation)?;
+        self.status = AuctionStatus::Extended;
+        if self.kind == AuctionKind::Hybrid {
+            self.commit_end = self.commit_end.checked_add(extension).ok_or(Errors::InvalidOperation)?;
+            self.reveal_end = self.reveal_end.checked_add(extension).ok_or(Errors::InvalidOperation)?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the refund expiry has passed since the end, so unclaimed refunds can be swept
+    pub fn refund_expired(&self, now: i64) -> Result<bool> {
+        Ok(now >= self.deadline_policy.after(self.closes_at(), self.refund_expiry)?)
+    }
+
+    /// Whether the VRF round drawing the auction may no longer be drawn, see `DRAW_WINDOW`
+    pub fn draw_expired(&self, now: i64) -> bool {
+        now >= self.end_time.saturating_add(DRAW_WINDOW)
+    }
+
+    /// Whether the winner still has time to claim the escrowed item
+    pub fn in_claim_window(&self, now: i64) -> Result<bool> {
+        Ok(now < self.deadline_policy.after(self.closes_at(), CLAIM_WINDOW)?)
+    }
+
+    /// Fails with `Open` before `from` and with `Closed` from `until` on
+    pub fn require_window(&self, now: i64, from: i64, until: i64) -> Result<()> {
+        if now < from {
+            return Err(error!(Errors::Open));
+        }
+        if now >= until {
+            return Err(error!(Errors::Closed));
+        }
+
+        Ok(())
+    }
+
+    /// Time after which no more bids are accepted in any phase
+    pub fn closes_at(&self) -> i64 {
+        match self.kind {
+            AuctionKind::English | AuctionKind::Dutch | AuctionKind::Gda | AuctionKind::MultiUnit => self.end_time,
+            AuctionKind::Hybrid | AuctionKind::Sealed => self.reveal_end,
+        }
+    }
+
+    /// The single place the lifecycle of an auction is derived from its stored status and the clock
+    pub fn status(&self, now: i64) -> AuctionStatus {
+        if matches!(self.status, AuctionStatus::Draft | AuctionStatus::Settled | AuctionStatus::Failed | AuctionStatus::Cancelled) {
+            self.status
+        } else if self.is_open(now) {
+            self.status
+        } else if !self.is_ended(now) {
+            AuctionStatus::Sealed
+        } else if self.max_bidder == Pubkey::default() {
+            AuctionStatus::Failed
+        } else {
+            AuctionStatus::Ended
+        }
+    }
+
+    /// Fails with the error explaining why the auction is not in the `expected` status, an extended
+    /// auction being open too
+    pub fn require(&self, now: i64, expected: AuctionStatus) -> Result<()> {
+        let status = self.status(now);
+        if status == expected || (expected, status) == (AuctionStatus::Open, AuctionStatus::Extended) {
+            return Ok(());
+        }
+
+        Err(error!(match (expected, status) {
+            (_, AuctionStatus::Draft) => Errors::Draft,
+            (AuctionStatus::Draft, _) => Errors::NotDraft,
+            (AuctionStatus::Open, _) => Errors::Closed,
+            (AuctionStatus::Settled, _) => Errors::Open,
+            (_, AuctionStatus::Settled) => Errors::AlreadySettled,
+            (_, AuctionStatus::Open | AuctionStatus::Extended | AuctionStatus::Sealed) => Errors::Open,
+            _ => Errors::InvalidOperation,
+        }))
+    }
+
+    /// Whether the auction is over for good, settled or failed at settlement, so its bids can go back
+    pub fn is_closed(&self) -> bool {
+        matches!(self.status, AuctionStatus::Settled | AuctionStatus::Failed)
+    }
+
+    /// Fails with the error explaining why the auction is not over for good yet, see `is_closed`
+    pub fn require_closed(&self) -> Result<()> {
+        if self.is_closed() {
+            return Ok(());
+        }
+
+        self.require(self.now()?, AuctionStatus::Settled)
+    }
+
+    /// Price of a Dutch auction at the given time. On a stepped curve the last step that already started
+    /// applies, on a linear one the price moves evenly between the steps around `now`.
+    pub fn dutch_price(&self, now: i64) -> u64 {
+        let steps = &self.price_steps[..self.price_steps_count as usize];
+        let started = steps.iter().take_while(|step| step.time <= now).count();
+        let current = steps[started.saturating_sub(1)];
+
+        match (self.dutch_curve, steps.get(started)) {
+            (DutchCurve::Linear, Some(next)) if started > 0 => {
+                let drop = (current.price - next.price) as u128 * (now - current.time) as u128
+                    / (next.time - current.time) as u128;
+                current.price - drop as u64
+            }
+            _ => current.price,
+        }
+    }
+
+    /// Price of the next unit of a Gradual Dutch Auction at the given time
+    pub fn gda_price(&self, now: i64) -> Option<u64> {
+        let elapsed = now.checked_sub(self.start_time)?.max(0) as u64;
+        let precision = PRECISION as u128;
+
+        let price = (self.gda_initial_price as u128)
+            .checked_mul(pow_scaled(self.gda_scale as u128, self.gda_sold)?)?
+            .checked_div(precision)?
+            .checked_mul(pow_scaled(self.gda_decay as u128, elapsed)?)?
+            / precision;
+
+        u64::try_from(price).ok()
+    }
+
+    /// Copies the format and parameters of another auction, moving its deadlines relative to this
+    /// auction's start. Every new auction parameter has to be copied here too.
+    fn copy_parameters(&mut self, source: &State) -> Result<()> {
+        let shift = self.start_time.checked_sub(source.start_time).ok_or(Errors::InvalidOperation)?;
+        let commit_duration = source.commit_end.checked_sub(source.end_time).ok_or(Errors::InvalidOperation)?;
+        let reveal_duration = source.reveal_end.checked_sub(source.commit_end).ok_or(Errors::InvalidOperation)?;
+
+        self.kind = source.kind;
+        self.deadline_policy = source.deadline_policy;
+
+        self.finalists_limit = source.finalists_limit;
+        self.commit_end = self.end_time.checked_add(commit_duration).ok_or(Errors::InvalidOperation)?;
+        self.reveal_end = self.commit_end.checked_add(reveal_duration).ok_or(Errors::InvalidOperation)?;
+
+        self.early_access_mint = source.early_access_mint;
+        if source.public_start != 0 {
+            self.early_access_start = source.early_access_start.checked_add(shift).ok_or(Errors::InvalidOperation)?;
+            self.public_start = source.public_start.checked_add(shift).ok_or(Errors::InvalidOperation)?;
+        }
+        if source.warmup_until != 0 {
+            self.warmup_until = source.warmup_until.checked_add(shift).ok_or(Errors::InvalidOperation)?;
+        }
+        if source.latest_end_time != 0 {
+            self.latest_end_time = source.latest_end_time.checked_add(shift).ok_or(Errors::InvalidOperation)?;
+        }
+
+        self.dutch_curve = source.dutch_curve;
+        self.price_steps_count = source.price_steps_count;
+        for (step, source_step) in self.price_steps.iter_mut().zip(source.price_steps.iter()) {
+            step.time = source_step.time.checked_add(shift).ok_or(Errors::InvalidOperation)?;
+            step.price = source_step.price;
+        }
+
+        self.gda_initial_price = source.gda_initial_price;
+        self.gda_scale = source.gda_scale;
+        self.gda_decay = source.gda_decay;
+
+        self.category = source.category;
+        self.tags = source.tags;
+        self.max_bidders = source.max_bidders;
+        self.entry_fee = source.entry_fee;
+        self.entry_fee_receiver = source.entry_fee_receiver;
+        self.min_increment = source.min_increment;
+        self.sprint_window = source.sprint_window;
+        self.sprint_factor_bps = source.sprint_factor_bps;
+        self.soft_close_window = source.soft_close_window;
+        self.soft_close_extension = source.soft_close_extension;
+        self.max_extension = source.max_extension;
+        self.max_extensions = source.max_extensions;
+        self.attestor = source.attestor;
+        self.allowed_regions = source.allowed_regions;
+        self.allowlist_root = source.allowlist_root;
+        self.token_gate = source.token_gate;
+        self.token_gate_amount = source.token_gate_amount;
+        self.token_gate_collection = source.token_gate_collection;
+        self.candle_window = source.candle_window;
+        self.candle_vrf = source.candle_vrf;
+        self.usd_reserve = source.usd_reserve;
+        self.usd_price_feed = source.usd_price_feed;
+        self.winners = source.winners;
+        self.all_pay = source.all_pay;
+        self.penny_fee = source.penny_fee;
+        self.penny_step = source.penny_step;
+        self.penny_countdown = source.penny_countdown;
+        self.proxy_bidding = source.proxy_bidding;
+        self.ticket_price = source.ticket_price;
+        self.raffle_vrf = source.raffle_vrf;
+        self.refund_expiry = source.refund_expiry;
+        self.share_surplus = source.share_surplus;
+        self.withdrawal_penalty_bps = source.withdrawal_penalty_bps;
+        self.second_price = source.second_price;
+        self.private_winner = source.private_winner;
+        self.buy_now_price = source.buy_now_price;
+        self.beneficiary = source.beneficiary;
+        self.keeper = source.keeper;
+        // The lot id and the link to a paired lot stay behind, a clone sells another lot
+
+        Ok(())
+    }
+
+    /// Moves the start and every deadline of the auction so that it starts at `now`
+    fn restart(&mut self, now: i64) -> Result<()> {
+        let shift = now.checked_sub(self.start_time).ok_or(Errors::InvalidOperation)?;
+        let moved = |time: i64| time.checked_add(shift).ok_or(Errors::InvalidOperation);
+
+        self.start_time = now;
+        self.weight_updated_at = now;
+        self.end_time = moved(self.end_time)?;
+        self.commit_end = moved(self.commit_end)?;
+        self.reveal_end = moved(self.reveal_end)?;
+        if self.public_start != 0 {
+            self.early_access_start = moved(self.early_access_start)?;
+            self.public_start = moved(self.public_start)?;
+        }
+        if self.warmup_until != 0 {
+            self.warmup_until = moved(self.warmup_until)?;
+        }
+        if self.latest_end_time != 0 {
+            self.latest_end_time = moved(self.latest_end_time)?;
+        }
+        for step in self.price_steps[..self.price_steps_count as usize].iter_mut() {
+            step.time = moved(step.time)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the lamport-seconds of the whole escrow until `now`, capped at the close of the auction
+    fn accrue_weight(&mut self, now: i64) {
+        let until = now.min(self.closes_at());
+        if until > self.weight_updated_at {
+            let elapsed = (until - self.weight_updated_at) as u128;
+            self.total_weight = self.total_weight.saturating_add(self.escrowed as u128 * elapsed);
+            self.weight_updated_at = until;
+        }
+    }
+
+    /// Part of the surplus pool belonging to the offer
+    pub fn surplus_share(&self, offer: &Offer) -> u64 {
+        if !self.share_surplus || self.total_weight == 0 {
+            return 0;
+        }
+
+        let weight = offer.weight_until(self.closes_at());
+        (self.surplus_pool as u128 * weight / self.total_weight) as u64
+    }
+
+    /// Increment the next bid has to add to the current price
+    pub fn required_increment(&self, in_sprint: bool) -> u64 {
+        if !in_sprint || self.min_increment == 0 {
+            return self.min_increment;
+        }
+
+        // An increment too large to compute can't be bid anyway
+        let factor = self.sprint_factor_bps as u128 * PRECISION as u128 / BPS as u128;
+        pow_scaled(factor, self.sprint_bids as u64)
+            .and_then(|scale| (self.min_increment as u128).checked_mul(scale))
+            .and_then(|increment| u64::try_from(increment / PRECISION as u128).ok())
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Counts the bid in the equally long segment of the auction it was placed in
+    fn record_bid(&mut self, now: i64, amount: u64) -> Result<()> {
+        self.bid_count = self.bid_count.checked_add(1).ok_or(Errors::InvalidOperation)?;
+
+        let elapsed = now.saturating_sub(self.start_time).max(0) as u128;
+        let duration = self.end_time.saturating_sub(self.start_time).max(1) as u128;
+        let segment = (elapsed * REPORT_CHECKPOINTS as u128 / duration).min(REPORT_CHECKPOINTS as u128 - 1) as usize;
+
+        let activity = &mut self.activity[segment];
+        activity.bids = activity.bids.saturating_add(1);
+        activity.price = amount;
+
+        self.record_candle(now);
+
+        Ok(())
+    }
+
+    /// Makes the current leader the leader of every sample of the candle window from the one of `now` on.
+    /// A bid before the window leads all of them until outbid.
+    fn record_candle(&mut self, now: i64) {
+        if self.candle_window == 0 {
+            return;
+        }
+
+        let window_start = self.end_time.saturating_sub(self.candle_window);
+        let elapsed = now.saturating_sub(window_start).max(0) as u128;
+        let sample = (elapsed * CANDLE_SAMPLES as u128 / self.candle_window as u128).min(CANDLE_SAMPLES as u128 - 1) as usize;
+
+        let leader = CandleLeader { bidder: self.max_bidder, price: self.max_price };
+        for candle_leader in self.candle_leaders[sample..].iter_mut() {
+            *candle_leader = leader;
+        }
+    }
+
+    /// Start of the segment of the auction with the most bids
+    pub fn peak_activity_time(&self) -> i64 {
+        let segment = self.activity
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, activity)| activity.bids)
+            .map_or(0, |(segment, _)| segment) as i64;
+        let duration = self.end_time.saturating_sub(self.start_time);
+
+        self.start_time.saturating_add(duration.saturating_mul(segment) / REPORT_CHECKPOINTS as i64)
+    }
+
+    /// Ranks the bidder among the finalists by the `amount` of their bid, dropping the lowest bid when
+    /// the list is full. Equal bids keep the earlier bidder ahead.
+    fn qualify(&mut self, bidder: Pubkey, amount: u64) {
+        let limit = self.finalists_limit as usize;
+
+        // A bidder raising their bid is ranked again
+        if let Some(position) = self.finalists[..limit].iter().position(|finalist| *finalist == bidder) {
+            self.finalists.copy_within(position + 1..limit, position);
+            self.finalist_bids.copy_within(position + 1..limit, position);
+            self.finalists[limit - 1] = Pubkey::default();
+            self.finalist_bids[limit - 1] = 0;
+        }
+
+        let position = match self.finalist_bids[..limit].iter().position(|bid| *bid < amount) {
+            Some(position) => position,
+            None => return,
+        };
+        self.finalists.copy_within(position..limit - 1, position + 1);
+        self.finalist_bids.copy_within(position..limit - 1, position + 1);
+        self.finalists[position] = bidder;
+        self.finalist_bids[position] = amount;
+    }
+
+    pub fn is_finalist(&self, bidder: &Pubkey) -> bool {
+        self.finalists[..self.finalists_limit as usize].contains(bidder)
+    }
+
+    /// Fills the fields added after the version of the account with their defaults. Version 1 only introduced
+    /// the version itself, every later version adds its defaults here.
+    pub fn upgrade(&mut self) {
+        // Version 2 added the allowlist, nobody is left out by default
+        if self.version < 2 {
+            self.allowlist_root = [0; 32];
+        }
+        // Version 3 added the token gate, off by default
+        if self.version < 3 {
+            self.token_gate = Pubkey::default();
+            self.token_gate_amount = 0;
+            self.token_gate_collection = false;
+        }
+        // Version 4 added the ban list, nobody is banned by default
+        if self.version < 4 {
+            self.has_bans = false;
+        }
+        // Version 5 added candle auctions, the end stays fixed by default
+        if self.version < 5 {
+            self.candle_window = 0;
+            self.candle_vrf = Pubkey::default();
+            self.candle_leaders = [CandleLeader::default(); CANDLE_SAMPLES];
+            self.candle_resolved = false;
+        }
+        // Version 6 added the USD reserve, off by default
+        if self.version < 6 {
+            self.usd_reserve = 0;
+            self.usd_price_feed = Pubkey::default();
+        }
+        // Version 7 added top-N auctions, a single winner by default
+        if self.version < 7 {
+            self.winners = 0;
+            self.top_bids = [TopBid::default(); MAX_WINNERS];
+        }
+        // Version 8 added all-pay auctions, off by default
+        if self.version < 8 {
+            self.all_pay = false;
+        }
+        // Version 9 added penny auctions, off by default
+        if self.version < 9 {
+            self.penny_fee = 0;
+            self.penny_step = 0;
+            self.penny_countdown = 0;
+            self.bid_fees = 0;
+        }
+        // Version 10 added reverse auctions, the initializer sells by default
+        if self.version < 10 {
+            self.reverse = false;
+        }
+        // Version 11 added proxy bidding, off by default
+        if self.version < 11 {
+            self.proxy_bidding = false;
+            self.leader_max = 0;
+        }
+        // Version 12 added raffles, off by default
+        if self.version < 12 {
+            self.ticket_price = 0;
+            self.raffle_vrf = Pubkey::default();
+            self.tickets = 0;
+            self.raffle_drawn = false;
+        }
+        // Version 13 made the refund expiry configurable, keeping the year it was before
+        if self.version < 13 {
+            self.refund_expiry = REFUND_EXPIRY;
+        }
+        // Version 14 ranked the finalists by their bids, the ones qualified before rank below any new bid
+        if self.version < 14 {
+            self.finalist_bids = [0; MAX_FINALISTS];
+        }
+        // Version 15 pinned the VRF round drawing a candle auction or a raffle, a draw set before takes the first
+        // request its account ever served
+        if self.version < 15 {
+            self.vrf_counter = 0;
+        }
+        // Version 16 added the keeper of a scheduled settlement
+        if self.version < 16 {
+            self.keeper = Pubkey::default();
+        }
+        self.version = self.version.max(STATE_VERSION);
+    }
+
+    /// Counts a closed offer or credit bid out of the open bids, one which never held a bid was never counted
+    pub fn close_bid(&mut self, amount: u64) {
+        if amount > 0 {
+            self.open_bids = self.open_bids.saturating_sub(1);
+        }
+    }
+
+    /// Counts the winning bid out at the settlement, every one of a top-N auction, when the winner has no refund
+    /// or surplus share left to claim, its offer then only closes with `claim_surplus`
+    pub fn close_winning_bid(&mut self) {
+        // Every bid of an all-pay auction was paid out
+        if self.all_pay {
+            self.open_bids = 0;
+            return;
+        }
+        if self.winners > 0 {
+            for bid in self.winning_bids().to_vec() {
+                self.close_bid(bid.amount);
+            }
+            return;
+        }
+        if self.winner_on_credit || (!self.may_refund(&self.max_bidder) && !self.share_surplus) {
+            let price = self.price();
+            self.close_bid(price);
+        }
+    }
+
+    /// Whether the offer of the bidder can be refunded, the winner paying out of the escrow only
+    /// takes back what is left above the price, e.g. a raise placed after the drawn end of a candle auction
+    /// or the unused rest of a proxy
+    pub fn may_refund(&self, bidder: &Pubkey) -> bool {
+        (self.max_bidder != *bidder && self.winning_bid(bidder).is_none())
+            || self.winner_on_credit
+            || self.kind == AuctionKind::Sealed
+            || self.second_price
+            || self.candle_window > 0
+            || self.leader_max > 0
+    }
+
+    /// Part of the escrow of the offer going back to the bidder, without the surplus share
+    pub fn refundable(&self, offer: &Offer, bidder: &Pubkey) -> u64 {
+        if let Some(amount) = self.winning_bid(bidder) {
+            offer.amount.saturating_sub(amount)
+        } else if self.max_bidder == *bidder && !self.winner_on_credit {
+            offer.amount.saturating_sub(self.price())
+        } else {
+            offer.amount
+        }
+    }
+
+    /// Price the winner pays, the runner-up's bid in a second-price auction, the winning bids
+    /// together in a top-N auction and every bid together in an all-pay auction
+    pub fn price(&self) -> u64 {
+        if self.all_pay {
+            self.escrowed
+        } else if self.winners > 0 {
+            self.winning_bids().iter().fold(0, |price, bid| price.saturating_add(bid.amount))
+        } else if self.second_price {
+            self.runner_up_price
+        } else {
+            self.max_price
+        }
+    }
+
+    /// Bid a new bid has to beat, the lowest winning bid once all of the winners of a top-N auction are taken
+    pub fn price_to_beat(&self) -> u64 {
+        if self.winners == 0 {
+            return self.max_price;
+        }
+
+        let last = self.top_bids[self.winners as usize - 1];
+        if last.bidder == Pubkey::default() {
+            self.initial_price
+        } else {
+            last.amount
+        }
+    }
+
+    /// Winning bids of a top-N auction placed so far, from the highest down
+    pub fn winning_bids(&self) -> &[TopBid] {
+        let count = self.top_bids[..self.winners as usize]
+            .iter()
+            .take_while(|bid| bid.bidder != Pubkey::default())
+            .count();
+
+        &self.top_bids[..count]
+    }
+
+    /// Winning bid of the bidder in a top-N auction
+    pub fn winning_bid(&self, bidder: &Pubkey) -> Option<u64> {
+        self.winning_bids().iter().find(|bid| bid.bidder == *bidder).map(|bid| bid.amount)
+    }
+
+    /// Puts the bid among the winning bids of a top-N auction, replacing the previous bid of the same bidder,
+    /// and returns the bidder falling out of the winners
+    fn rank(&mut self, bidder: Pubkey, amount: u64) -> Pubkey {
+        let winners = self.winners as usize;
+        let last = self.top_bids[..winners]
+            .iter()
+            .position(|bid| bid.bidder == bidder)
+            .unwrap_or(winners - 1);
+        let dropped = if self.top_bids[last].bidder == bidder {
+            Pubkey::default()
+        } else {
+            self.top_bids[last].bidder
+        };
+
+        let index = self.top_bids[..last].iter().take_while(|bid| bid.amount >= amount).count();
+        self.top_bids.copy_within(index..last, index + 1);
+        self.top_bids[index] = TopBid { bidder, amount };
+
+        self.max_bidder = self.top_bids[0].bidder;
+        self.max_price = self.top_bids[0].amount;
+
+        dropped
+    }
+
+    /// Winner as published in the settlement records, nobody while the winner is private
+    pub fn public_winner(&self) -> Pubkey {
+        if self.private_winner {
+            Pubkey::default()
+        } else {
+            self.max_bidder
+        }
+    }
+
+    /// Takes over the concealed identity of a new leading bid in an auction with a private winner
+    fn conceal_winner(&mut self, offer: &Offer) -> Result<()> {
+        if self.private_winner {
+            if offer.identity == [0; 32] {
+                return Err(error!(Errors::IdentityRequired));
+            }
+            self.winner_hash = offer.identity;
+        }
+
+        Ok(())
+    }
+
+    /// Lets the proxy of the leader counter a bid of `amount`, the price rising by `step` above it as far as
+    /// the maximum of the proxy goes
+    fn counter(&mut self, amount: u64, step: u64) {
+        self.runner_up_price = amount;
+        self.max_price = amount.saturating_add(step).min(self.leader_max);
+    }
+
+    /// Puts a new bid in the lead, the previous one becoming the runner-up
+    fn outbid(&mut self, bidder: Pubkey, amount: u64) {
+        self.runner_up_price = self.max_price;
+        self.max_price = amount;
+        self.max_bidder = bidder;
+    }
+}
+
+/// Lifecycle of an auction, see `State::status`. Stored as a single byte in the order of the variants.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AuctionStatus {
+    /// Being prepared by the seller, invisible to bidders
+    Draft,
+    /// Taking bids
+    Open,
+    /// Taking bids past the original end, moved by a soft close
+    Extended,
+    /// Past the open phase, finalists of a hybrid auction are sealing their bids
+    Sealed,
+    /// Finished with a winner, waiting for the settlement
+    Ended,
+    /// The winner paid the seller, losing bidders can take their bids back
+    Settled,
+    /// Finished without any bid, there is nothing to settle
+    Failed,
+    /// Withdrawn by the seller before anybody bid
+    Cancelled,
+}
+
+/// Clock the deadlines of an auction are measured on. Every deadline check goes through the methods
+/// of `State`, so a new clock (e.g. slots or epochs) only has to be added here.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeadlinePolicy {
+    /// Unix timestamps of the cluster clock
+    UnixTimestamp,
+}
+
+impl DeadlinePolicy {
+    /// Current time on this clock
+    pub fn now(&self, clock: &Clock) -> i64 {
+        match self {
+            DeadlinePolicy::UnixTimestamp => clock.unix_timestamp,
+        }
+    }
+
+    /// Time `seconds` after `time` on this clock
+    pub fn after(&self, time: i64, seconds: i64) -> Result<i64> {
+        match self {
+            DeadlinePolicy::UnixTimestamp => time.checked_add(seconds).ok_or_else(|| error!(Errors::InvalidOperation)),
+        }
+    }
+}
+
+/// How the price of a Dutch auction moves between the steps of its schedule
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DutchCurve {
+    /// Each step holds its price until the next one
+    Stepped,
+    /// The price falls evenly from one step to the next
+    Linear,
+}
+
+/// Format of an auction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AuctionKind {
+    /// Open ascending auction
+    English,
+    /// Open ascending phase followed by a sealed final round of the top bidders
+    Hybrid,
+    /// Descending price, the first buyer wins
+    Dutch,
+    /// Stream of identical units, exponentially decaying price jumping up with every sale
+    Gda,
+    /// Identical units sold at once to the highest bids, each asking for a quantity
+    MultiUnit,
+    /// Hidden bids committed in the open phase and revealed after it
+    Sealed,
+}
+
+/// What is being sold, for filtering auctions on-chain
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Uncategorized,
+    Art,
+    Collectibles,
+    Gaming,
+    Music,
+    Tickets,
+    RealWorldAsset,
+    Other,
+}
+
+/// Layout of the accounts an attestor program issues, attesting the region of a wallet
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct Attestation {
+    pub subject: Pubkey,
+    pub region: u16,
+    pub expires_at: i64,
+}
+
+/// Winning bid of a top-N auction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct TopBid {
+    pub bidder: Pubkey,
+    pub amount: u64,
+}
+
+impl TopBid {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<u64>()
+    }
+}
+
+/// Leading bid of a candle auction at the end of a sample of its candle window
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct CandleLeader {
+    pub bidder: Pubkey,
+    pub price: u64,
+}
+
+impl CandleLeader {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<u64>()
+    }
+}
+
+/// Bids placed in a segment of an auction and the highest price reached in it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct Activity {
+    pub bids: u32,
+    pub price: u64,
+}
+
+impl Activity {
+    pub fn size() -> usize {
+        size_of::<u32>() +
+        size_of::<u64>()
+    }
+}
+
+/// A step of a Dutch price schedule, `price` applies from `time` until the next step
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PriceStep {
+    pub time: i64,
+    pub price: u64,
+}
+
+impl PriceStep {
+    pub fn size() -> usize {
+        size_of::<UnixTimestamp>() +
+        size_of::<u64>()
+    }
+}
+
+/// Arguments of `create_auction`. Parameters are only ever added in a new version, so transactions built
+/// against an older IDL keep decoding.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum InitializeArgs {
+    V1 {
+        auction_duration: i64,
+        initial_price: u64,
+    },
+    V2 {
+        auction_duration: i64,
+        initial_price: u64,
+        /// Maximum number of distinct bidders, zero meaning no limit
+        max_bidders: u32,
+        /// Non-refundable fee of the first bid of every wallet, paid to the seller
+        entry_fee: u64,
+    },
+    V3 {
+        auction_duration: i64,
+        initial_price: u64,
+        max_bidders: u32,
+        entry_fee: u64,
+        /// Receives the proceeds instead of the initializer, who keeps only operating the auction
+        beneficiary: Pubkey,
+    },
+    V4 {
+        auction_duration: i64,
+        initial_price: u64,
+        max_bidders: u32,
+        entry_fee: u64,
+        beneficiary: Pubkey,
+        /// Identifier of the lot in the seller's books, carried in every event of the auction
+        lot_id: u64,
+    },
+    V5 {
+        auction_duration: i64,
+        initial_price: u64,
+        max_bidders: u32,
+        entry_fee: u64,
+        beneficiary: Pubkey,
+        lot_id: u64,
+        /// Price settling the auction at once when a bidder pays it, zero meaning no buy-now
+        buy_now_price: u64,
+    },
+    V6 {
+        auction_duration: i64,
+        initial_price: u64,
+        max_bidders: u32,
+        entry_fee: u64,
+        beneficiary: Pubkey,
+        lot_id: u64,
+        buy_now_price: u64,
+        /// Bids are only accepted from this time on, the auction starts right away when not given
+        start_time: Option<i64>,
+    },
+    V7 {
+        auction_duration: i64,
+        initial_price: u64,
+        max_bidders: u32,
+        entry_fee: u64,
+        beneficiary: Pubkey,
+        lot_id: u64,
+        buy_now_price: u64,
+        start_time: Option<i64>,
+        /// What is sold and the tag bitmap indexers filter by, already in the `AuctionCreated` event
+        category: Category,
+        tags: u64,
+    },
+}
+
+/// Parameters of the latest version of `InitializeArgs`
+pub struct InitializeParams {
+    pub auction_duration: i64,
+    pub initial_price: u64,
+    pub max_bidders: u32,
+    pub entry_fee: u64,
+    pub beneficiary: Option<Pubkey>,
+    pub lot_id: u64,
+    pub buy_now_price: u64,
+    pub start_time: Option<i64>,
+    pub category: Category,
+    pub tags: u64,
+}
+
+impl InitializeArgs {
+    /// Upgrades the arguments to the latest version, filling in the defaults of the missing parameters
+    pub fn latest(self) -> InitializeParams {
+        match self {
+            InitializeArgs::V1 { auction_duration, initial_price } => InitializeParams {
+                auction_duration,
+                initial_price,
+                max_bidders: 0,
+                entry_fee: 0,
+                beneficiary: None,
+                lot_id: 0,
+                buy_now_price: 0,
+                start_time: None,
+                category: Category::Uncategorized,
+                tags: 0,
+            },
+            InitializeArgs::V2 { auction_duration, initial_price, max_bidders, entry_fee } => InitializeParams {
+                auction_duration,
+                initial_price,
+                max_bidders,
+                entry_fee,
+                beneficiary: None,
+                lot_id: 0,
+                buy_now_price: 0,
+                start_time: None,
+                category: Category::Uncategorized,
+                tags: 0,
+            },
+            InitializeArgs::V3 { auction_duration, initial_price, max_bidders, entry_fee, beneficiary } => InitializeParams {
+                auction_duration,
+                initial_price,
+                max_bidders,
+                entry_fee,
+                beneficiary: Some(beneficiary),
+                lot_id: 0,
+                buy_now_price: 0,
+                start_time: None,
+                category: Category::Uncategorized,
+                tags: 0,
+            },
+            InitializeArgs::V4 { auction_duration, initial_price, max_bidders, entry_fee, beneficiary, lot_id } => InitializeParams {
+                auction_duration,
+                initial_price,
+                max_bidders,
+                entry_fee,
+                beneficiary: Some(beneficiary),
+                lot_id,
+                buy_now_price: 0,
+                start_time: None,
+                category: Category::Uncategorized,
+                tags: 0,
+            },
+            InitializeArgs::V5 { auction_duration, initial_price, max_bidders, entry_fee, beneficiary, lot_id, buy_now_price } => InitializeParams {
+                auction_duration,
+                initial_price,
+                max_bidders,
+                entry_fee,
+                beneficiary: Some(beneficiary),
+                lot_id,
+                buy_now_price,
+                start_time: None,
+                category: Category::Uncategorized,
+                tags: 0,
+            },
+            InitializeArgs::V6 {
+                auction_duration,
+                initial_price,
+                max_bidders,
+                entry_fee,
+                beneficiary,
+                lot_id,
+                buy_now_price,
+                start_time,
+            } => InitializeParams {
+                auction_duration,
+                initial_price,
+                max_bidders,
+                entry_fee,
+                beneficiary: Some(beneficiary),
+                lot_id,
+                buy_now_price,
+                start_time,
+                category: Category::Uncategorized,
+                tags: 0,
+            },
+            InitializeArgs::V7 {
+                auction_duration,
+                initial_price,
+                max_bidders,
+                entry_fee,
+                beneficiary,
+                lot_id,
+                buy_now_price,
+                start_time,
+                category,
+                tags,
+            } => InitializeParams {
+                auction_duration,
+                initial_price,
+                max_bidders,
+                entry_fee,
+                beneficiary: Some(beneficiary),
+                lot_id,
+                buy_now_price,
+                start_time,
+                category,
+                tags,
+            },
+        }
+    }
+}
+
+/// Arguments of `place_bid`, versioned like `InitializeArgs`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum BidArgs {
+    V1 {
+        amount: u64,
+    },
+    V2 {
+        amount: u64,
+        /// Latest time on the clock of the auction the bid may land at
+        max_valid_time: Option<i64>,
+    },
+    V3 {
+        amount: u64,
+        max_valid_time: Option<i64>,
+        /// Siblings from the leaf of the bidder up to the allowlist root, see `set_allowlist`
+        proof: Vec<[u8; 32]>,
+    },
+}
+
+/// Options of the latest version of `BidArgs`
+pub struct BidParams {
+    pub amount: u64,
+    pub max_valid_time: Option<i64>,
+    pub proof: Vec<[u8; 32]>,
+}
+
+impl BidArgs {
+    /// Upgrades the arguments to the latest version, filling in the defaults of the missing options
+    pub fn latest(self) -> BidParams {
+        match self {
+            BidArgs::V1 { amount } => BidParams { amount, max_valid_time: None, proof: Vec::new() },
+            BidArgs::V2 { amount, max_valid_time } => BidParams { amount, max_valid_time, proof: Vec::new() },
+            BidArgs::V3 { amount, max_valid_time, proof } => BidParams { amount, max_valid_time, proof },
+        }
+    }
+}
+
+/// A user following an auction, `updates` counts the settlements and cranks touching it
+#[account]
+pub struct Watch {
+    pub auction: Pubkey,
+    pub user: Pubkey,
+    pub updates: u64,
+    pub bump: u8,
+}
+
+impl Watch {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<u8>()
+    }
+}
+
+/// A bidder to be told when outbid, `outbid` counts the times and the rest describes the latest one
+#[account]
+pub struct Notify {
+    pub bidder: Pubkey,
+    pub outbid: u64,
+    pub auction: Pubkey,
+    /// The bid that took the lead
+    pub price: u64,
+    pub bump: u8,
+}
+
+impl Notify {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<u8>()
+    }
+}
+
+/// Highest amount a bidder ever escrowed in an auction, kept for `SNAPSHOT_RETENTION` after its end
+#[account]
+pub struct ParticipationSnapshot {
+    pub auction: Pubkey,
+    pub bidder: Pubkey,
+    pub peak_escrow: u64,
+    /// End of the auction when the snapshot was last taken, the retention counts from it
+    pub closes_at: i64,
+    pub bump: u8,
+}
+
+impl ParticipationSnapshot {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<u8>()
+    }
+}
+
+/// Recurring relists of an auction, the starting price converging down to the market after failed rounds
+#[account]
+pub struct Series {
+    pub first_auction: Pubkey,
+    pub last_auction: Pubkey,
+    pub initializer: Pubkey,
+    pub floor_price: u64,
+    /// Cut of the initial price after every failed round, in basis points
+    pub decay_bps: u16,
+    pub rounds: u32,
+    pub bump: u8,
+}
+
+impl Series {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<u16>() +
+        size_of::<u32>() +
+        size_of::<u8>()
+    }
+
+    /// Initial price of the round following a failed round starting at `price`
+    pub fn next_price(&self, price: u64) -> u64 {
+        let lowered = price as u128 * (BPS - self.decay_bps) as u128 / BPS as u128;
+        (lowered as u64).max(self.floor_price)
+    }
+}
+
+/// A service allowed to execute refunds for bidders of a marketplace
+#[account]
+pub struct Sponsorship {
+    pub namespace: Pubkey,
+    pub sponsor: Pubkey,
+    pub bump: u8,
+}
+
+impl Sponsorship {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<u8>()
+    }
+}
+
+/// Defaults of the new auctions of a marketplace, with changes scheduled for later kept sorted by activation
+#[account]
+pub struct MarketConfig {
+    pub namespace: Pubkey,
+    /// Increment every bid has to add to the price
+    pub min_increment: u64,
+    /// Fee of the first bid of every wallet, paid to the protocol vault of the marketplace
+    pub entry_fee: u64,
+    pub pending_count: u8,
+    pub pending: [PendingChange; MAX_PENDING_CHANGES],
+    pub bump: u8,
+}
+
+impl MarketConfig {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<u8>() +
+        PendingChange::size() * MAX_PENDING_CHANGES +
+        size_of::<u8>()
+    }
+
+    /// Applies every pending change activated by `now`, in the order of activation
+    pub fn apply_due(&mut self, now: i64) {
+        let count = self.pending_count as usize;
+        let due = self.pending[..count].iter().take_while(|pending| pending.activates_at <= now).count();
+
+        for pending in self.pending[..due].to_vec() {
+            match pending.change {
+                ConfigChange::MinIncrement { value } => self.min_increment = value,
+                ConfigChange::EntryFee { value } => self.entry_fee = value,
+            }
+        }
+
+        self.pending.copy_within(due..count, 0);
+        for pending in self.pending[count - due..count].iter_mut() {
+            *pending = PendingChange::default();
+        }
+        self.pending_count = (count - due) as u8;
+    }
+
+    fn schedule(&mut self, change: PendingChange) -> Result<()> {
+        let count = self.pending_count as usize;
+        if count == MAX_PENDING_CHANGES {
+            return Err(error!(Errors::TooManyPendingChanges));
+        }
+
+        // Changes activating at the same time apply in the order they were scheduled
+        let index = self.pending[..count].iter().take_while(|pending| pending.activates_at <= change.activates_at).count();
+        self.pending.copy_within(index..count, index + 1);
+        self.pending[index] = change;
+        self.pending_count += 1;
+
+        Ok(())
+    }
+
+    fn cancel(&mut self, index: usize) -> Result<()> {
+        let count = self.pending_count as usize;
+        if index >= count {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        self.pending.copy_within(index + 1..count, index);
+        self.pending[count - 1] = PendingChange::default();
+        self.pending_count -= 1;
+
+        Ok(())
+    }
+}
+
+/// A config change waiting for its activation time
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PendingChange {
+    pub change: ConfigChange,
+    pub activates_at: i64,
+}
+
+impl PendingChange {
+    pub fn size() -> usize {
+        size_of::<u8>() +
+        size_of::<u64>() +
+        size_of::<UnixTimestamp>()
+    }
+}
+
+/// A parameter of the marketplace config with its new value
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum ConfigChange {
+    MinIncrement { value: u64 },
+    EntryFee { value: u64 },
+}
+
+impl Default for ConfigChange {
+    fn default() -> Self {
+        ConfigChange::MinIncrement { value: 0 }
+    }
+}
+
+/// Standing offer to buy `amount` tokens of a mint, the escrowed `price` is held in this account
+#[account]
+pub struct BuyOffer {
+    pub buyer: Pubkey,
+    pub item_mint: Pubkey,
+    pub amount: u64,
+    pub price: u64,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl BuyOffer {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<u8>()
+    }
+}
+
+/// Collateral of a market maker approved by a marketplace for bidding on credit, held in this account.
+/// `used` is the sum of the credit bids not released yet and never exceeds `limit`.
+#[account]
+pub struct CreditLine {
+    pub namespace: Pubkey,
+    pub maker: Pubkey,
+    pub limit: u64,
+    pub collateral: u64,
+    pub used: u64,
+    pub defaulted: bool,
+    pub bump: u8,
+}
+
+impl CreditLine {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<bool>() +
+        size_of::<u8>()
+    }
+}
+
+/// The credit bid of a market maker in an auction
+#[account]
+pub struct CreditBid {
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl CreditBid {
+    pub fn size() -> usize {
+        size_of::<u64>() +
+        size_of::<u8>()
+    }
+}
+
+/// Two auctions of paired lots bid on together. The same winner of both settles them together by `settle_linked`,
+/// otherwise each of them is settled on its own.
+#[account]
+pub struct AuctionLink {
+    pub first: Pubkey,
+    pub second: Pubkey,
+    /// Part of every linked bid going to the first auction, in basis points
+    pub first_share_bps: u16,
+    pub bump: u8,
+}
+
+impl AuctionLink {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<u16>() +
+        size_of::<u8>()
+    }
+}
+
+/// Wallets banned from bidding on an auction by `ban_bidder`
+#[account]
+pub struct BanList {
+    pub auction: Pubkey,
+    pub banned: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl BanList {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<u32>() + size_of::<Pubkey>() * MAX_BANS +
+        size_of::<u8>()
+    }
+}
+
+/// Program-wide settings, the single account at the `global` seed. It has to exist before the first
+/// auction, every auction is created against it and held to its limits.
+#[account]
+pub struct GlobalConfig {
+    /// Signer of every later change of the config
+    pub admin: Pubkey,
+    /// Longest duration of a new auction in seconds, 0 for no limit
+    pub max_duration: i64,
+    /// Lowest increment every bid of a new auction has to add to the price
+    pub min_increment: u64,
+    /// Protocol fee taken from the price of every sale, paid into the fee vault
+    pub fee_bps: u16,
+    pub bump: u8,
+    /// No new auctions and no bids, see `set_paused`
+    pub paused: bool,
+}
+
+impl GlobalConfig {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<i64>() +
+        size_of::<u64>() +
+        size_of::<u16>() +
+        size_of::<u8>() +
+        size_of::<bool>()
+    }
+
+    fn set(&mut self, max_duration: i64, min_increment: u64, fee_bps: u16) -> Result<()> {
+        if max_duration < 0 || fee_bps > BPS {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        self.max_duration = max_duration;
+        self.min_increment = min_increment;
+        self.fee_bps = fee_bps;
+
+        Ok(())
+    }
+}
+
+/// Protocol fees taken by every sale, held as the lamports of the single account at the `fees` seed, or in its
+/// token accounts for auctions paid in tokens
+#[account]
+pub struct FeeVault {
+    pub bump: u8,
+}
+
+impl FeeVault {
+    pub fn size() -> usize {
+        size_of::<u8>()
+    }
+}
+
+/// Program-wide statistics shared by every auction, the single account at the `stats` seed
+#[account]
+pub struct GlobalStats {
+    /// Most new escrow the bids may add within one window, 0 for no cap
+    pub volume_cap: u64,
+    /// Length of the window in seconds, 0 for a window which never rolls
+    pub window: i64,
+    pub window_start: i64,
+    /// New escrow added by the bids since the start of the window
+    pub window_volume: u64,
+    /// Copy of the pause of the global config
+    pub paused: bool,
+}
+
+impl GlobalStats {
+    pub fn size() -> usize {
+        size_of::<u64>() +
+        size_of::<i64>() +
+        size_of::<i64>() +
+        size_of::<u64>() +
+        size_of::<bool>()
+    }
+
+    /// Fails while the program is paused
+    pub fn require_live(&self) -> Result<()> {
+        if self.paused {
+            return Err(error!(Errors::Paused));
+        }
+
+        Ok(())
+    }
+
+    /// Counts `amount` of new escrow into the current window, starting a new window once the old one is over
+    pub fn record(&mut self, amount: u64, now: i64) -> Result<()> {
+        if self.window > 0 && now >= self.window_start.saturating_add(self.window) {
+            self.window_start = now;
+            self.window_volume = 0;
+        }
+
+        let volume = self.window_volume.checked_add(amount).ok_or(Errors::InvalidOperation)?;
+        if self.volume_cap != 0 && volume > self.volume_cap {
+            return Err(error!(Errors::VolumeCapReached));
+        }
+
+        self.window_volume = volume;
+        Ok(())
+    }
+}
+
+/// Directory of live auctions of a marketplace split into pages, `head` is the oldest page which may still list
+/// an auction and `tail` the page new auctions are added to. `head` only moves on when the head page itself is
+/// compacted empty, so pages after it may already be closed and readers skip the missing ones.
+///
+/// Every new auction writes the registry and the tail page, so the creations within a namespace land one after
+/// the other, while a settlement only writes the page of its own auction. Marketplaces which list a lot at once
+/// spread over namespaces, which share none of these accounts.
+#[account]
+pub struct Registry {
+    pub namespace: Pubkey,
+    pub head: u32,
+    pub tail: u32,
+}
+
+impl Registry {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<u32>() +
+        size_of::<u32>()
+    }
+}
+
+/// A page of the registry, removed auctions leave `Pubkey::default()` holes behind
+#[account]
+pub struct RegistryPage {
+    pub index: u32,
+    pub len: u16,
+    pub live: u16,
+    pub auctions: [Pubkey; PAGE_SIZE],
+}
+
+impl RegistryPage {
+    pub fn size() -> usize {
+        size_of::<u32>() +
+        size_of::<u16>() +
+        size_of::<u16>() +
+        size_of::<Pubkey>() * PAGE_SIZE
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len as usize == PAGE_SIZE
+    }
+
+    fn push(&mut self, auction: Pubkey) -> Result<()> {
+        if self.is_full() {
+            return Err(error!(Errors::InvalidOperation));
+        }
+
+        self.auctions[self.len as usize] = auction;
+        self.len += 1;
+        self.live += 1;
+
+        Ok(())
+    }
+
+    /// Returns whether the auction was listed on the page
+    fn remove(&mut self, auction: &Pubkey) -> bool {
+        match self.auctions[..self.len as usize].iter().position(|listed| listed == auction) {
+            Some(position) => {
+                self.auctions[position] = Pubkey::default();
+                self.live -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Post-auction statistics for the seller, `price_checkpoints` holding the highest bid
+/// of each of the equally long segments of the auction
+#[account]
+pub struct AuctionReport {
+    pub auction: Pubkey,
+    pub bidders: u32,
+    pub bids: u32,
+    pub final_price: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub peak_activity_time: i64,
+    pub price_checkpoints: [u64; REPORT_CHECKPOINTS],
+}
+
+impl AuctionReport {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<u32>() +
+        size_of::<u32>() +
+        size_of::<u64>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<u64>() * REPORT_CHECKPOINTS
+    }
+
+    fn record(&mut self, state: &Account<State>) {
+        self.auction = state.key();
+        self.bidders = state.bidders;
+        self.bids = state.bid_count;
+        self.final_price = state.price();
+        self.start_time = state.start_time;
+        self.end_time = state.end_time;
+        self.peak_activity_time = state.peak_activity_time();
+        for (checkpoint, activity) in self.price_checkpoints.iter_mut().zip(state.activity.iter()) {
+            *checkpoint = activity.price;
+        }
+    }
+}
+
+/// Permanent outcome of a settled auction, other programs can read it as a price oracle of the item
+/// once the auction accounts are closed. Only the program writes it, at the address derived from the auction.
+#[account]
+pub struct AuctionResult {
+    pub auction: Pubkey,
+    /// Mint of the escrowed item, `Pubkey::default()` when nothing was escrowed
+    pub item_mint: Pubkey,
+    pub winner: Pubkey,
+    /// Clearing price in the payment currency, lamports unless `payment_mint` is set
+    pub price: u64,
+    pub payment_mint: Option<Pubkey>,
+    pub settled_at: i64,
+    pub bump: u8,
+}
+
+impl AuctionResult {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<Option<Pubkey>>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<u8>()
+    }
+
+    fn record(&mut self, state: &Account<State>, bump: u8) -> Result<()> {
+        self.auction = state.key();
+        self.item_mint = state.item_mint;
+        self.winner = state.public_winner();
+        self.price = state.price();
+        self.payment_mint = state.payment_mint;
+        self.settled_at = Clock::get()?.unix_timestamp;
+        self.bump = bump;
+
+        Ok(())
+    }
+}
+
+/// Append-only trail of the calls on an auction. `hash` chains every entry ever appended,
+/// entries beyond `capacity` are only counted in `dropped` and the hash.
+#[account]
+pub struct AuditLog {
+    pub auction: Pubkey,
+    pub capacity: u32,
+    pub dropped: u32,
+    pub hash: [u8; 32],
+    pub entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    pub fn size(capacity: u32) -> usize {
+        size_of::<Pubkey>() +
+        size_of::<u32>() +
+        size_of::<u32>() +
+        size_of::<[u8; 32]>() +
+        size_of::<u32>() + AuditEntry::size() * capacity as usize
+    }
+
+    fn append(&mut self, entry: AuditEntry) -> Result<()> {
+        self.hash = keccak::hashv(&[&self.hash, &entry.try_to_vec()?]).to_bytes();
+
+        if self.entries.len() < self.capacity as usize {
+            self.entries.push(entry);
+        } else {
+            self.dropped = self.dropped.saturating_add(1);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct AuditEntry {
+    pub action: AuditAction,
+    pub actor: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+impl AuditEntry {
+    pub fn size() -> usize {
+        size_of::<u8>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<UnixTimestamp>()
+    }
+}
+
+/// Instruction an audit log entry was written by
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Bid,
+    Reveal,
+    AcceptPrice,
+    Purchase,
+    Settle,
+    Refund,
+    Sweep,
+    ClaimItem,
+    RecoverItem,
+    BuyNow,
+    Configure,
+    Publish,
+    Cancel,
+    Finalize,
+    Close,
+    Resolve,
+    Extend,
+    /// The actor is the banned bidder
+    Ban,
+    Link,
+    Release,
+    Commit,
+    Conceal,
+    RevealWinner,
+    DepositItem,
+    ClaimSurplus,
+}
+
+/// Why the balance of a treasury moved
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TreasuryReason {
+    Open,
+    Bid,
+    Reveal,
+    BuyNow,
+    Settle,
+    Refund,
+    Sweep,
+    ClaimSurplus,
+    Close,
+    ProtocolFee,
+    Royalty,
+    BidFee,
+}
+
+/// Standing bids of a multi-unit auction, highest unit price first and earlier bids first among equal prices
+#[account]
+pub struct UnitBook {
+    pub auction: Pubkey,
+    pub pricing: UnitPricing,
+    /// Lowest filled unit price, set at settlement
+    pub clearing_price: u64,
+    pub count: u8,
+    pub entries: [UnitBid; MAX_UNIT_BIDS],
+    pub bump: u8,
+}
+
+impl UnitBook {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<u8>() +
+        size_of::<u64>() +
+        size_of::<u8>() +
+        UnitBid::size() * MAX_UNIT_BIDS +
+        size_of::<u8>()
+    }
+
+    /// Places a bid by its unit price, a full book drops its lowest bid unless the new one is not higher
+    fn insert(&mut self, bid: UnitBid) -> Result<()> {
+        let count = self.count as usize;
+        let index = self.entries[..count].iter().take_while(|entry| entry.unit_price >= bid.unit_price).count();
+        if index == MAX_UNIT_BIDS {
+            return Err(error!(Errors::BidTooLow));
+        }
+
+        let last = count.min(MAX_UNIT_BIDS - 1);
+        self.entries.copy_within(index..last, index + 1);
+        self.entries[index] = bid;
+        self.count = (last + 1) as u8;
 
         Ok(())
     }
 
-    /// Bid
-    pub fn bid(ctx: Context<Bid>, amount: u64) -> Result<()> {
-        let state = &mut ctx.accounts.state;
-        let buyer = &mut ctx.accounts.buyer;
-
-        // Is the auction still running?
-        if Clock::get()?.unix_timestamp >= state.end_time {
-            return Err(error!(Errors::Closed));
-        }
+    /// Fills the bids in the order of the book until the units run out, returning what the filled units cost
+    fn allocate(&mut self, units: u64) -> Option<u64> {
+        let mut remaining = units;
 
-        // Check if the bid is lower or equal compared to the current highest
-        if amount <= state.max_price {
-            return Err(error!(Errors::BidTooLow));
+        for entry in self.entries[..self.count as usize].iter_mut() {
+            entry.filled = entry.quantity.min(remaining);
+            remaining -= entry.filled;
+            if entry.filled > 0 {
+                self.clearing_price = entry.unit_price;
+            }
         }
 
-        // Don't allow increasing the bid for the highest bidder
-        if *buyer.key == state.max_bidder {
-            return Err(error!(Errors::AlreadyHighestBidder));
-        }
+        let book = &*self;
+        book.entries[..book.count as usize]
+            .iter()
+            .try_fold(0u64, |proceeds, entry| proceeds.checked_add(entry.filled.checked_mul(book.unit_cost(entry))?))
+    }
 
-        // In a case this was not a new bid we have to calculate the difference between an old and a new amount bidded
-        let offer = &mut ctx.accounts.offer;
-        let diff = amount.checked_sub(offer.amount);
-        if diff == None {
-            return Err(error!(Errors::InvalidOperation))
+    /// Price a winning bid pays for each of its filled units
+    pub fn unit_cost(&self, bid: &UnitBid) -> u64 {
+        match self.pricing {
+            UnitPricing::PayAsBid => bid.unit_price,
+            UnitPricing::Uniform => self.clearing_price,
         }
+    }
 
-        // Move lamports to the treasury
-        let treasury = &mut ctx.accounts.treasury;
-        invoke(
-            &system_instruction::transfer(
-                buyer.key,
-                treasury.key,
-                diff.unwrap()
-            ),
-            &[
-                buyer.to_account_info().clone(),
-                treasury.clone()
-            ]
-        )?;
+    /// Units filled across the whole book
+    pub fn filled(&self) -> u64 {
+        self.entries[..self.count as usize].iter().map(|entry| entry.filled).sum()
+    }
 
-        // Update state with the new highest bidder and the new highest bid
-        state.max_price = amount;
-        state.max_bidder = *buyer.key;
+    /// Standing bid of the given bidder
+    pub fn find(&self, bidder: &Pubkey) -> Option<&UnitBid> {
+        self.entries[..self.count as usize].iter().find(|entry| entry.bidder == *bidder)
+    }
+}
 
-        // Update the offer price
-        let new_offer_price = offer.amount.checked_add(amount);
-        if new_offer_price == None {
-            return Err(error!(Errors::InvalidOperation))
-        }
+/// What the winners of a multi-unit auction pay for their units
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum UnitPricing {
+    /// Every winner pays their own unit price
+    PayAsBid,
+    /// Every winner pays the clearing price, the lowest filled unit price
+    Uniform,
+}
 
-        // Update the offer for a possible refund
-        offer.amount = new_offer_price.unwrap();
-        offer.bump = *ctx.bumps.get("offer").unwrap();
+/// A bid for units, `filled` being set at settlement
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct UnitBid {
+    pub bidder: Pubkey,
+    pub quantity: u64,
+    pub unit_price: u64,
+    pub filled: u64,
+}
 
-        Ok(())
+impl UnitBid {
+    pub fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<u64>()
     }
+}
 
-    /// After an auction ends (determined by `auction_duration`), a seller can claim the
-    /// heighest bid by calling this instruction
-    pub fn end_auction(ctx: Context<Finish>) -> Result<()> {
-        let state = &mut ctx.accounts.state;
+/// Sequence of auction ids of a single seller
+#[account]
+pub struct Counter {
+    pub next_id: u64,
+}
 
-        // Is the auction already closed?
-        if Clock::get()?.unix_timestamp < state.end_time {
-            return Err(error!(Errors::Open));
-        }
+impl Counter {
+    pub fn size() -> usize {
+        size_of::<u64>()
+    }
+}
 
-        // Transfer lamports to the seller
-        **ctx.accounts.treasury.try_borrow_mut_lamports()? -= state.max_price;
-        **ctx.accounts.initializer.try_borrow_mut_lamports()? += state.max_price;
+#[account]
+pub struct Offer {
+    pub amount: u64,
+    pub bump: u8,
+    pub commitment: [u8; 32],
+    pub weight: u128,
+    pub weight_updated_at: i64,
+    /// Units asked for in a multi-unit auction, `amount` escrowing all of them
+    pub quantity: u64,
+    /// Concealed identity of the bidder in an auction with a private winner
+    pub identity: [u8; 32],
+    /// Number of the first ticket of the bidder in a raffle
+    pub first_ticket: u64,
+    /// Tickets of the bidder in a raffle, numbered in a row from `first_ticket`
+    pub tickets: u64,
+    /// Layout version the account was created or last migrated with
+    pub version: u8,
+}
 
-        // Close the auction
-        state.open = false;
+impl Offer {
+    pub fn size() -> usize {
+        size_of::<u64>() +
+        size_of::<u8>() +
+        size_of::<[u8; 32]>() +
+        size_of::<u128>() +
+        size_of::<UnixTimestamp>() +
+        size_of::<u64>() +
+        size_of::<[u8; 32]>() +
+        size_of::<u64>() +
+        size_of::<u64>() +
+        size_of::<u8>()
+    }
 
-        Ok(())
+    /// Size of an offer of layout `version`, like `State::size_at`
+    pub fn size_at(version: u8) -> usize {
+        match version {
+            // The legacy offer, only the escrowed amount and the bump
+            0 => size_of::<u64>() + size_of::<u8>(),
+            1 => Offer::size() - size_of::<u64>() - size_of::<u64>(),
+            _ => Offer::size(),
+        }
     }
 
-    /// After an auction ends (the initializer/seller already received the winning bid), 
-    /// the unsuccessfull bidders can claim their money back by calling this instruction
-    pub fn refund(ctx: Context<Refund>) -> Result<()> {
-        let state = &ctx.accounts.state;
+    /// Decodes an offer of an older layout version by hand like `State::decode_versioned`. The legacy offer
+    /// predates the version byte.
+    pub fn decode_versioned(data: &[u8]) -> Result<Offer> {
+        let version = (0..OFFER_VERSION)
+            .find(|&version| data.len() == 8 + Offer::size_at(version))
+            .ok_or(Errors::InvalidOperation)?;
 
-        // Is the auction already closed?
-        if Clock::get()?.unix_timestamp < state.end_time {
-            return Err(error!(Errors::Open));
+        let mut end = data.len();
+        if version > 0 {
+            end -= size_of::<u8>();
+            if data[end] != version {
+                return Err(error!(Errors::InvalidOperation));
+            }
         }
+        let mut buffer = data[..end].to_vec();
+        buffer.resize(8 + Offer::size(), 0);
+        let mut offer = Offer::try_deserialize(&mut &buffer[..])?;
 
-        // Transfer lamports back to the bidder
-        let offer = &mut ctx.accounts.offer;
-        **ctx.accounts.treasury.try_borrow_mut_lamports()? -= offer.amount;
-        **ctx.accounts.buyer.try_borrow_mut_lamports()? += offer.amount;
+        offer.version = version;
+        offer.upgrade();
 
-        // Set the remaining amount of lamports to pay out to zero
-        offer.amount = 0;
+        Ok(offer)
+    }
 
-        Ok(())
+    /// Fills the fields added after the version of the account with their defaults, like `State::upgrade`
+    pub fn upgrade(&mut self) {
+        // Version 2 added the tickets of raffles, none by default
+        if self.version < 2 {
+            self.first_ticket = 0;
+            self.tickets = 0;
+        }
+        self.version = self.version.max(OFFER_VERSION);
+    }
+
+    /// Adds the lamport-seconds of the offer until `now`, capped at `until`
+    fn accrue_weight(&mut self, now: i64, until: i64) {
+        self.weight = self.weight_until(now.min(until));
+        self.weight_updated_at = self.weight_updated_at.max(now.min(until));
+    }
+
+    /// Lamport-seconds the offer will have accrued at `until`
+    pub fn weight_until(&self, until: i64) -> u128 {
+        let elapsed = until.saturating_sub(self.weight_updated_at).max(0) as u128;
+        self.weight.saturating_add(self.amount as u128 * elapsed)
     }
 }
 
-#[derive(Accounts)]
-pub struct Auction<'info> {
-    #[account(
-        init,
-        payer = initializer,
-        space = 8 + State::size()
-    )]
-    pub state: Account<'info, State>,
+/// Fields of an auction of the legacy layout, before states and treasuries were derived from the seller and
+/// the status replaced the `open` flag
+#[derive(AnchorDeserialize)]
+struct LegacyState {
+    initializer: Pubkey,
+    treasury: Pubkey,
+    max_bidder: Pubkey,
+    max_price: u64,
+    end_time: i64,
+    open: bool,
+}
 
-    /// CHECK:
-    #[account(
-        init,
-        payer = initializer,
-        space = 0
-    )]
-    pub treasury: AccountInfo<'info>,
+impl LegacyState {
+    fn size() -> usize {
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<Pubkey>() +
+        size_of::<u64>() +
+        size_of::<i64>() +
+        size_of::<bool>()
+    }
+}
 
-    #[account(mut)]
-    pub initializer: Signer<'info>,
+/// Auction copied out by `migrate_state` or `migrate_legacy_auction` until `restore_state` recreates it with the
+/// current size, an account type of its own so that no instruction takes it for the auction
+#[account]
+pub struct StateMigration {
+    pub state: State,
+}
 
-    pub system_program: Program<'info, System>,
+impl StateMigration {
+    pub fn size() -> usize {
+        State::size()
+    }
 }
 
-#[derive(Accounts)]
-pub struct Bid<'info> {
-    #[account(
-        init_if_needed,
-        payer = buyer,
-        space = 8 + Offer::size(),
-        seeds = [b"bid", state.key().as_ref(), buyer.key().as_ref()],
-        bump,
-    )]
-    pub offer: Account<'info, Offer>,
+/// Offer copied out by `migrate_offer` until `restore_offer` recreates it, like `StateMigration`
+#[account]
+pub struct OfferMigration {
+    pub offer: Offer,
+}
 
-    #[account(mut, has_one = treasury @ Errors::WrongAccount)]
-    pub state: Account<'info, State>,
+impl OfferMigration {
+    pub fn size() -> usize {
+        Offer::size()
+    }
+}
 
-    /// CHECK:
-    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
-    pub treasury: AccountInfo<'info>,
+/// A new auction is live
+#[event]
+pub struct AuctionCreated {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub lot_id: u64,
+    pub initializer: Pubkey,
+    pub kind: AuctionKind,
+    pub category: Category,
+    pub tags: u64,
+    pub initial_price: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
 
-    #[account(mut)]
-    pub buyer: Signer<'info>,
+/// An auction was withdrawn by the seller before any bid
+#[event]
+pub struct AuctionCancelled {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub lot_id: u64,
+    pub initializer: Pubkey,
+    pub timestamp: i64,
+}
 
-    pub system_program: Program<'info, System>,
+/// The end of a candle auction was drawn, `end_time` being the end of the drawn sample of the candle window
+#[event]
+pub struct CandleResolved {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub end_time: i64,
+    pub winner: Pubkey,
+    pub price: u64,
 }
 
-#[derive(Accounts)]
-pub struct Finish<'info> {
-    #[account(
-        mut,
-        has_one = initializer @ Errors::WrongAccount,
-        has_one = treasury @ Errors::WrongAccount,
-        has_one = max_bidder @ Errors::WrongAccount,
-        constraint = state.open @ Errors::Open
-    )]
-    pub state: Account<'info, State>,
+/// The seller scheduled the settlement of an auction with a `keeper`, due at `settle_at` unless the end moves
+#[event]
+pub struct SettlementScheduled {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub keeper: Pubkey,
+    pub settle_at: i64,
+}
 
-    #[account(mut, address = state.initializer @ Errors::WrongAccount)]
-    pub initializer: Signer<'info>,
+/// The winning ticket of a raffle was drawn, the `pot` of all of the tickets going to the beneficiary
+#[event]
+pub struct RaffleDrawn {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub ticket: u64,
+    pub winner: Pubkey,
+    pub pot: u64,
+}
 
-    /// CHECK:
-    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
-    pub treasury: AccountInfo<'info>,
+/// An auction ended without any bid and was closed by the seller, or its winning bid fell below the USD reserve
+#[event]
+pub struct AuctionUnsold {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub lot_id: u64,
+    pub initializer: Pubkey,
+    pub timestamp: i64,
+}
 
-    /// CHECK:
-    #[account(mut, address = state.max_bidder @ Errors::WrongAccount)]
-    pub max_bidder: AccountInfo<'info>,
+/// An auction of an older layout, or of the legacy one, was recreated with the current one by `restore_state`
+#[event]
+pub struct AuctionMigrated {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub status: AuctionStatus,
+    pub timestamp: i64,
+}
 
-    pub system_program: Program<'info, System>,
+/// Complete accounting of a finished sale, emitted once by `end_auction`
+#[event]
+pub struct SettlementSummary {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub lot_id: u64,
+    /// `Pubkey::default()` when the winner is private
+    pub winner: Pubkey,
+    /// Concealed identity of a private winner, zeroes otherwise
+    pub winner_hash: [u8; 32],
+    pub gross_price: u64,
+    pub protocol_fee: u64,
+    pub royalties: u64,
+    pub referrer_cut: u64,
+    pub net_proceeds: u64,
+    pub bidders: u32,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct Refund<'info> {
-    #[account(
-        has_one = treasury @ Errors::WrongAccount,
-        constraint = !state.open @ Errors::Open,
-        constraint = state.max_bidder != *buyer.key @ Errors::WinnerRefund
-    )]
-    pub state: Account<'info, State>,
+/// The private winner of an auction disclosed themselves
+#[event]
+pub struct WinnerRevealed {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub winner: Pubkey,
+    pub timestamp: i64,
+}
 
-    /// CHECK:
-    #[account(mut, address = state.treasury @ Errors::WrongAccount)]
-    pub treasury: AccountInfo<'info>,
+/// A bid was taken, `amount` being the unit price in a multi-unit auction and `max_price` the price to beat after it
+#[event]
+pub struct BidPlaced {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub max_price: u64,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub buyer: Signer<'info>,
+/// Escrow went back to a bidder after the settlement, together with their share of the surplus
+#[event]
+pub struct Refunded {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"bid", state.key().as_ref(), buyer.key.as_ref()],
-        bump = offer.bump,
-        close = buyer
-    )]
-    pub offer: Account<'info, Offer>,
+/// An outbid bidder took their escrow back before the end, leaving `penalty` of it in the treasury
+#[event]
+pub struct BidWithdrawn {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub penalty: u64,
+    pub timestamp: i64,
+}
 
-    pub system_program: Program<'info, System>,
+/// An unclaimed refund was forfeited to the protocol
+#[event]
+pub struct RefundSwept {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct State {
-    pub initializer: Pubkey,
+/// The balance of a treasury moved, `delta` being negative for payouts. Balances are in lamports including
+/// the rent of the treasury, or in tokens of the payment mint.
+#[event]
+pub struct TreasuryDelta {
+    pub auction: Pubkey,
+    pub lot_id: u64,
     pub treasury: Pubkey,
-    pub max_bidder: Pubkey,
-    pub max_price: u64,
-    pub end_time: i64,
-    pub open: bool
+    pub previous_balance: u64,
+    pub delta: i128,
+    pub new_balance: u64,
+    pub reason: TreasuryReason,
+    pub timestamp: i64,
 }
 
-impl State {
-    pub fn size() -> usize {
-        size_of::<Pubkey>() +
-        size_of::<Pubkey>() +
-        size_of::<Pubkey>() +
-        size_of::<u64>() +
-        size_of::<UnixTimestamp>() +
-        size_of::<bool>()
-    }
+/// A sponsor refunded a bidder, `amount` includes the surplus share but not the offer rent
+#[event]
+pub struct RefundSponsored {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub bidder: Pubkey,
+    pub sponsor: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct Offer {
+/// A holder sold the item to a standing buy offer
+#[event]
+pub struct BuyOfferAccepted {
+    pub buy_offer: Pubkey,
+    pub item_mint: Pubkey,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
     pub amount: u64,
-    pub bump: u8,
+    pub price: u64,
+    pub timestamp: i64,
 }
 
-impl Offer {
-    pub fn size() -> usize {
-        size_of::<u64>() +
-        size_of::<u8>() }
+/// The collateral of a market maker didn't cover an auction won on credit, the seller got all of it
+#[event]
+pub struct CreditDefaulted {
+    pub auction: Pubkey,
+    pub lot_id: u64,
+    pub maker: Pubkey,
+    pub credit_line: Pubkey,
+    pub shortfall: u64,
+    pub timestamp: i64,
 }
 
+/// Codes are `ERROR_CODE_OFFSET` plus the explicit number of the variant. Clients match on them,
+/// so a number is never reused or changed, new variants take the next free one.
 #[error_code]
 pub enum Errors {
     #[msg("Bid offer too low")]
-    BidTooLow,
+    BidTooLow = 0,
 
     #[msg("Already the highest bidder")]
-    AlreadyHighestBidder,
+    AlreadyHighestBidder = 1,
 
     #[msg("Wrong account")]
-    WrongAccount,
+    WrongAccount = 2,
 
     #[msg("Auction is open")]
-    Open,
+    Open = 3,
 
     #[msg("Auction is closed")]
-    Closed,
+    Closed = 4,
 
     #[msg("Invalid operation")]
-    InvalidOperation,
+    InvalidOperation = 5,
 
     #[msg("Winner can not refund")]
-    WinnerRefund,
+    WinnerRefund = 6,
+
+    #[msg("Auction is already settled")]
+    AlreadySettled = 7,
+
+    #[msg("Refund has not expired yet")]
+    RefundNotExpired = 8,
+
+    #[msg("Auction already has bids")]
+    AlreadyBid = 9,
+
+    #[msg("Auction has no escrowed item")]
+    NoItem = 10,
+
+    #[msg("Item was already claimed")]
+    ItemClaimed = 11,
+
+    #[msg("Winner can still claim the item")]
+    ClaimWindowOpen = 12,
+
+    #[msg("Bidder did not qualify for the final round")]
+    NotFinalist = 13,
+
+    #[msg("Revealed bid does not match the commitment")]
+    InvalidReveal = 14,
+
+    #[msg("All units are sold")]
+    SoldOut = 15,
+
+    #[msg("Price is higher than the accepted maximum")]
+    PriceTooHigh = 16,
+
+    #[msg("Invalid oracle account")]
+    InvalidOracle = 17,
+
+    #[msg("Oracle price is too old")]
+    StaleOracle = 18,
+
+    #[msg("Auction is a draft")]
+    Draft = 19,
+
+    #[msg("Auction is already published")]
+    NotDraft = 20,
+
+    #[msg("Auction reached the maximum number of bidders")]
+    AuctionFull = 21,
+
+    #[msg("Audit log of the auction is missing")]
+    AuditLogRequired = 22,
+
+    #[msg("Attestation of the bidder is missing")]
+    AttestationRequired = 23,
+
+    #[msg("Attestation does not allow the bidder")]
+    InvalidAttestation = 24,
+
+    #[msg("Not supported for the currency of the auction")]
+    UnsupportedCurrency = 25,
+
+    #[msg("Credit limit of the market maker exceeded")]
+    CreditLimitExceeded = 26,
+
+    #[msg("Credit line of the market maker defaulted")]
+    CreditDefaulted = 27,
+
+    #[msg("Too many config changes are already scheduled")]
+    TooManyPendingChanges = 28,
+
+    #[msg("Offer has expired")]
+    OfferExpired = 29,
+
+    #[msg("Bid landed after its maximum valid time")]
+    BidExpired = 30,
+
+    #[msg("Bidding has not started yet")]
+    NotStarted = 31,
+
+    #[msg("Only early access holders may bid before the public window")]
+    EarlyAccessRequired = 32,
+
+    #[msg("Bidder has to conceal their identity first")]
+    IdentityRequired = 33,
+
+    #[msg("Linked auctions with the same winner are settled together")]
+    LinkedSettlement = 34,
+
+    #[msg("Volume cap of the program reached, wait for the next window")]
+    VolumeCapReached = 35,
+
+    #[msg("Participation snapshot is still retained")]
+    SnapshotRetained = 36,
+
+    #[msg("Auction still holds bids to refund")]
+    OpenBids = 37,
+
+    #[msg("Bidder is not on the allowlist")]
+    NotAllowlisted = 38,
+
+    #[msg("Bidder doesn't hold the tokens of the gate")]
+    TokenGateRequired = 39,
+
+    #[msg("Bidder is banned from the auction")]
+    Banned = 40,
+
+    #[msg("Program is paused")]
+    Paused = 41,
+
+    #[msg("Candle auction waits for its randomness")]
+    CandlePending = 42,
+
+    #[msg("Bids of an all-pay auction are not refunded")]
+    AllPay = 43,
+
+    #[msg("Raffle waits for its randomness")]
+    RafflePending = 44,
+
+    #[msg("Draw window passed, the auction fails")]
+    DrawExpired = 45,
+
+    #[msg("Entry fee receiver is not rent-exempt")]
+    ReceiverNotRentExempt = 46,
+}
+
+impl Errors {
+    /// Every error of the program, in the order of their codes
+    pub const ALL: [Errors; 47] = [
+        Errors::BidTooLow,
+        Errors::AlreadyHighestBidder,
+        Errors::WrongAccount,
+        Errors::Open,
+        Errors::Closed,
+        Errors::InvalidOperation,
+        Errors::WinnerRefund,
+        Errors::AlreadySettled,
+        Errors::RefundNotExpired,
+        Errors::AlreadyBid,
+        Errors::NoItem,
+        Errors::ItemClaimed,
+        Errors::ClaimWindowOpen,
+        Errors::NotFinalist,
+        Errors::InvalidReveal,
+        Errors::SoldOut,
+        Errors::PriceTooHigh,
+        Errors::InvalidOracle,
+        Errors::StaleOracle,
+        Errors::Draft,
+        Errors::NotDraft,
+        Errors::AuctionFull,
+        Errors::AuditLogRequired,
+        Errors::AttestationRequired,
+        Errors::InvalidAttestation,
+        Errors::UnsupportedCurrency,
+        Errors::CreditLimitExceeded,
+        Errors::CreditDefaulted,
+        Errors::TooManyPendingChanges,
+        Errors::OfferExpired,
+        Errors::BidExpired,
+        Errors::NotStarted,
+        Errors::EarlyAccessRequired,
+        Errors::IdentityRequired,
+        Errors::LinkedSettlement,
+        Errors::VolumeCapReached,
+        Errors::SnapshotRetained,
+        Errors::OpenBids,
+        Errors::NotAllowlisted,
+        Errors::TokenGateRequired,
+        Errors::Banned,
+        Errors::Paused,
+        Errors::CandlePending,
+        Errors::AllPay,
+        Errors::RafflePending,
+        Errors::DrawExpired,
+        Errors::ReceiverNotRentExempt,
+    ];
 }
@@ -0,0 +1,287 @@
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anchor_client::solana_sdk::account::from_account;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::instruction::{AccountMeta, Instruction};
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{Keypair, Signature, Signer};
+use anchor_client::solana_sdk::system_program;
+use anchor_client::solana_sdk::sysvar::clock::{self, Clock};
+use anchor_client::{Client, Cluster, Program};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use auction::{AuctionKind, AuctionStatus, State};
+
+use crate::error::ClientError;
+
+/// How often and how patiently a request is resent when it fails for a transient reason
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+/// Async API over the auction program, checking the auction before sending anything
+/// and decoding program failures into `ClientError::Program`
+pub struct AuctionClient {
+    cluster: Cluster,
+    payer: Arc<Keypair>,
+    commitment: CommitmentConfig,
+    retry: RetryPolicy,
+}
+
+impl AuctionClient {
+    pub fn new(cluster: Cluster, payer: Keypair) -> Self {
+        AuctionClient {
+            cluster,
+            payer: Arc::new(payer),
+            commitment: CommitmentConfig::confirmed(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    /// Fetches the state of an auction
+    pub async fn state(&self, auction: Pubkey) -> Result<State, ClientError> {
+        self.with_retries(move |program| {
+            program.account::<State>(auction).map_err(|err| match err {
+                anchor_client::ClientError::AccountNotFound => ClientError::AccountNotFound(auction.to_string()),
+                err => err.into(),
+            })
+        }).await
+    }
+
+    /// Bids `amount` lamports, failing early when the auction is closed or the bid too low.
+    /// Compliant auctions need the attestation of the payer passed in `extra_accounts`.
+    pub async fn bid(&self, auction: Pubkey, amount: u64, extra_accounts: Vec<AccountMeta>) -> Result<Signature, ClientError> {
+        let state = self.state(auction).await?;
+        let now = self.now(&state).await?;
+
+        if !matches!(state.status(now), AuctionStatus::Open | AuctionStatus::Extended) {
+            return Err(ClientError::Closed);
+        }
+        if state.kind == AuctionKind::Dutch {
+            return Err(ClientError::Unsupported("Dutch auctions are bought with `accept_price`"));
+        }
+        if state.payment_mint.is_some() {
+            return Err(ClientError::Unsupported("token payments"));
+        }
+
+        let minimum = state.max_price
+            .saturating_add(state.required_increment(state.in_closing_window(now)).max(1));
+        if amount < minimum {
+            return Err(ClientError::BidTooLow { minimum });
+        }
+
+        let buyer = self.payer();
+        let accounts = auction::accounts::Bid {
+            offer: pda(&[b"bid", auction.as_ref(), buyer.as_ref()]),
+            state: auction,
+            treasury: state.treasury,
+            buyer,
+            entry_fee_receiver: state.entry_fee_receiver,
+            stats: pda(&[b"stats"]),
+            system_program: system_program::ID,
+        };
+        let ix = instruction(accounts, auction::instruction::Bid { amount }, audit_accounts(&auction, &state, extra_accounts));
+
+        self.send(ix).await
+    }
+
+    /// Settles an auction whose seller is the payer
+    pub async fn end_auction(&self, auction: Pubkey) -> Result<Signature, ClientError> {
+        let state = self.state(auction).await?;
+
+        if state.status == AuctionStatus::Settled {
+            return Err(ClientError::Program(auction::Errors::AlreadySettled));
+        }
+        if !state.is_ended(self.now(&state).await?) {
+            return Err(ClientError::Open);
+        }
+
+        let accounts = auction::accounts::Finish {
+            state: auction,
+            initializer: self.payer(),
+            beneficiary: state.beneficiary,
+            treasury: state.treasury,
+            max_bidder: state.max_bidder,
+            page: pda(&[b"page", state.namespace.as_ref(), &state.registry_page.to_le_bytes()]),
+            report: pda(&[b"report", auction.as_ref()]),
+            result: pda(&[b"result", auction.as_ref()]),
+            system_program: system_program::ID,
+        };
+        let ix = instruction(accounts, auction::instruction::EndAuction {}, audit_accounts(&auction, &state, Vec::new()));
+
+        self.send(ix).await
+    }
+
+    /// Settles an ended auction of any seller, the payer covering the rent of the report and the result
+    pub async fn settle(&self, auction: Pubkey) -> Result<Signature, ClientError> {
+        let state = self.state(auction).await?;
+
+        if state.status == AuctionStatus::Settled {
+            return Err(ClientError::Program(auction::Errors::AlreadySettled));
+        }
+        if !state.is_ended(self.now(&state).await?) {
+            return Err(ClientError::Open);
+        }
+
+        let accounts = auction::accounts::Settle {
+            state: auction,
+            beneficiary: state.beneficiary,
+            treasury: state.treasury,
+            max_bidder: state.max_bidder,
+            page: pda(&[b"page", state.namespace.as_ref(), &state.registry_page.to_le_bytes()]),
+            report: pda(&[b"report", auction.as_ref()]),
+            result: pda(&[b"result", auction.as_ref()]),
+            cranker: self.payer(),
+            system_program: system_program::ID,
+        };
+        let ix = instruction(accounts, auction::instruction::Settle {}, audit_accounts(&auction, &state, Vec::new()));
+
+        self.send(ix).await
+    }
+
+    /// Takes the bid of the payer back from a settled auction
+    pub async fn refund(&self, auction: Pubkey) -> Result<Signature, ClientError> {
+        let state = self.state(auction).await?;
+
+        if state.status != AuctionStatus::Settled || !state.is_ended(self.now(&state).await?) {
+            return Err(ClientError::Open);
+        }
+
+        let buyer = self.payer();
+        let accounts = auction::accounts::Refund {
+            state: auction,
+            treasury: state.treasury,
+            buyer,
+            offer: pda(&[b"bid", auction.as_ref(), buyer.as_ref()]),
+            system_program: system_program::ID,
+        };
+        let ix = instruction(accounts, auction::instruction::Refund {}, audit_accounts(&auction, &state, Vec::new()));
+
+        self.send(ix).await
+    }
+
+    /// Time on the clock of the deadline policy of the auction, the same the program compares its deadlines with
+    async fn now(&self, state: &State) -> Result<i64, ClientError> {
+        let policy = state.deadline_policy;
+
+        self.with_retries(|program| {
+            let account = program.rpc()
+                .get_account(&clock::ID)
+                .map_err(|err| ClientError::Rpc(err.to_string()))?;
+            from_account::<Clock, _>(&account)
+                .map(|clock| policy.now(&clock))
+                .ok_or_else(|| ClientError::Rpc(String::from("clock sysvar could not be decoded")))
+        }).await
+    }
+
+    /// Signs and sends a single instruction paid by the payer
+    async fn send(&self, ix: Instruction) -> Result<Signature, ClientError> {
+        self.with_retries(move |program| {
+            program.request()
+                .instruction(ix.clone())
+                .send()
+                .map_err(Into::into)
+        }).await
+    }
+
+    /// Runs the blocking `request` on its own thread, backing off and retrying transient failures
+    pub(crate) async fn with_retries<T, F>(&self, request: F) -> Result<T, ClientError>
+    where
+        T: Send + 'static,
+        F: Fn(&Program) -> Result<T, ClientError> + Clone + Send + 'static,
+    {
+        let mut backoff = self.retry.initial_backoff;
+        let mut attempt = 1;
+
+        loop {
+            let cluster = self.cluster.clone();
+            let payer = self.payer.clone();
+            let commitment = self.commitment;
+            let request = request.clone();
+
+            // `anchor-client` is blocking and holds its signer in an `Rc`, so it is built on the worker thread
+            let result = tokio::task::spawn_blocking(move || {
+                let payer = Keypair::from_bytes(&payer.to_bytes())
+                    .map_err(|err| ClientError::Rpc(err.to_string()))?;
+                let client = Client::new_with_options(cluster, Rc::new(payer), commitment);
+                request(&client.program(auction::ID))
+            })
+            .await
+            .map_err(|err| ClientError::Rpc(err.to_string()))?;
+
+            match result {
+                Err(err) if err.is_retryable() && attempt < self.retry.max_attempts => {
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.retry.max_backoff);
+                    attempt += 1;
+                }
+                Err(err) if err.is_retryable() => {
+                    return Err(ClientError::RetriesExhausted { attempts: attempt, last: Box::new(err) });
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &auction::ID).0
+}
+
+/// Adds the audit log of an audited auction and the event accounts to the remaining accounts
+fn audit_accounts(auction: &Pubkey, state: &State, mut extra_accounts: Vec<AccountMeta>) -> Vec<AccountMeta> {
+    if state.audited {
+        extra_accounts.push(AccountMeta::new(pda(&[b"audit", auction.as_ref()]), false));
+    }
+    extra_accounts.extend(event_accounts());
+
+    extra_accounts
+}
+
+/// Remaining accounts making the program also emit its events through `log_event`, whose inner
+/// instructions indexers can read when the logs are truncated
+pub fn event_accounts() -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(pda(&[auction::EVENT_AUTHORITY_SEED]), false),
+        AccountMeta::new_readonly(auction::ID, false),
+    ]
+}
+
+fn instruction(accounts: impl ToAccountMetas, args: impl InstructionData, remaining_accounts: Vec<AccountMeta>) -> Instruction {
+    let mut metas = accounts.to_account_metas(None);
+    metas.extend(remaining_accounts);
+
+    Instruction {
+        program_id: auction::ID,
+        accounts: metas,
+        data: args.data(),
+    }
+}
@@ -0,0 +1,77 @@
+use anchor_client::solana_client::client_error::ClientError as RpcError;
+use anchor_client::solana_sdk::instruction::InstructionError;
+use anchor_client::solana_sdk::transaction::TransactionError;
+use auction::Errors;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("account {0} does not exist")]
+    AccountNotFound(String),
+
+    #[error("auction is not accepting bids")]
+    Closed,
+
+    #[error("auction is still running")]
+    Open,
+
+    #[error("bid must be at least {minimum} lamports")]
+    BidTooLow { minimum: u64 },
+
+    #[error("not supported by the client: {0}")]
+    Unsupported(&'static str),
+
+    #[error("program failed with {0:?}")]
+    Program(Errors),
+
+    #[error("program failed with an unknown code {0}")]
+    UnknownProgramError(u32),
+
+    #[error("transaction failed: {0}")]
+    Transaction(TransactionError),
+
+    #[error("rpc request failed: {0}")]
+    Rpc(String),
+
+    #[error("gave up after {attempts} attempts: {last}")]
+    RetriesExhausted { attempts: u32, last: Box<ClientError> },
+}
+
+impl ClientError {
+    /// Whether sending the same request again may succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Rpc(_) => true,
+            ClientError::Transaction(err) => matches!(
+                err,
+                TransactionError::BlockhashNotFound | TransactionError::AccountInUse | TransactionError::WouldExceedMaxBlockCostLimit
+            ),
+            _ => false,
+        }
+    }
+
+    fn from_rpc(err: RpcError) -> Self {
+        match err.get_transaction_error() {
+            Some(TransactionError::InstructionError(_, InstructionError::Custom(code))) => Self::from_code(code),
+            Some(err) => ClientError::Transaction(err),
+            None => ClientError::Rpc(err.to_string()),
+        }
+    }
+
+    fn from_code(code: u32) -> Self {
+        Errors::ALL
+            .iter()
+            .find(|err| u32::from(**err) == code)
+            .map(|err| ClientError::Program(*err))
+            .unwrap_or(ClientError::UnknownProgramError(code))
+    }
+}
+
+impl From<anchor_client::ClientError> for ClientError {
+    fn from(err: anchor_client::ClientError) -> Self {
+        match err {
+            anchor_client::ClientError::SolanaClientError(err) => Self::from_rpc(err),
+            anchor_client::ClientError::AccountNotFound => ClientError::AccountNotFound(String::from("requested")),
+            err => ClientError::Rpc(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Ready-made account states for local development: funded bidders, running and expired auctions.
+//! The accounts are written straight into `solana-test-validator` or `solana-program-test`
+//! instead of being scripted with dozens of transactions.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anchor_client::solana_sdk::account::Account;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::rent::Rent;
+use anchor_client::solana_sdk::signature::{Keypair, Signer};
+use anchor_client::solana_sdk::system_program;
+use anchor_lang::{AccountDeserialize, AccountSerialize, Discriminator};
+use auction::{AuctionStatus, Offer, Registry, RegistryPage, State, BPS, OFFER_VERSION, PAGE_SIZE, STATE_VERSION};
+use serde_json::json;
+
+/// Lamports of every funded wallet
+pub const FUNDED_LAMPORTS: u64 = 100_000_000_000;
+
+/// Accounts to preload, with the keypairs of the wallets they belong to
+pub struct Fixtures {
+    pub now: i64,
+    pub accounts: Vec<(Pubkey, Account)>,
+    pub wallets: Vec<Keypair>,
+    auctions: Vec<Pubkey>,
+}
+
+/// Keys of a generated auction, its wallets being in `Fixtures::wallets`
+pub struct AuctionFixture {
+    pub state: Pubkey,
+    pub treasury: Pubkey,
+    pub seller: Pubkey,
+    pub bidders: Vec<Pubkey>,
+}
+
+impl Fixtures {
+    /// `now` should be close to the clock of the validator the fixtures are loaded into
+    pub fn new(now: i64) -> Self {
+        Fixtures { now, accounts: Vec::new(), wallets: Vec::new(), auctions: Vec::new() }
+    }
+
+    /// A system account holding `FUNDED_LAMPORTS`
+    pub fn funded_wallet(&mut self) -> Pubkey {
+        let wallet = Keypair::new();
+        let key = wallet.pubkey();
+
+        self.accounts.push((key, Account::new(FUNDED_LAMPORTS, 0, &system_program::ID)));
+        self.wallets.push(wallet);
+        key
+    }
+
+    /// An English auction ending in `remaining` seconds with `offers` bidders, each outbidding the previous one
+    pub fn running_auction(&mut self, offers: usize, remaining: i64) -> AuctionFixture {
+        self.auction(offers, self.now - 60, self.now + remaining)
+    }
+
+    /// An auction whose end passed `ago` seconds before `now` but which was never settled
+    pub fn expired_auction(&mut self, offers: usize, ago: i64) -> AuctionFixture {
+        self.auction(offers, self.now - ago - 3600, self.now - ago)
+    }
+
+    /// Lists the generated auctions in the registry of the default namespace, needed to settle them.
+    /// Call once after generating all auctions.
+    pub fn finish(mut self) -> Self {
+        assert!(self.auctions.len() <= PAGE_SIZE, "fixtures list at most one registry page of auctions");
+
+        let namespace = Pubkey::default();
+        let mut registry: Registry = blank(Registry::size());
+        registry.namespace = namespace;
+
+        let mut page: RegistryPage = blank(RegistryPage::size());
+        page.len = self.auctions.len() as u16;
+        page.live = self.auctions.len() as u16;
+        page.auctions[..self.auctions.len()].copy_from_slice(&self.auctions);
+
+        self.accounts.push((pda(&[b"registry", namespace.as_ref()]), program_account(&registry, Registry::size())));
+        self.accounts.push((pda(&[b"page", namespace.as_ref(), &0u32.to_le_bytes()]), program_account(&page, RegistryPage::size())));
+        self
+    }
+
+    /// Writes the accounts in the format of `solana-test-validator --account <pubkey> <file>`,
+    /// returning the arguments to pass
+    pub fn write_validator_accounts(&self, dir: &Path) -> io::Result<Vec<String>> {
+        fs::create_dir_all(dir)?;
+
+        let mut args = Vec::new();
+        for (pubkey, account) in &self.accounts {
+            let path = dir.join(format!("{}.json", pubkey));
+            let file = json!({
+                "pubkey": pubkey.to_string(),
+                "account": {
+                    "lamports": account.lamports,
+                    "data": [base64::encode(&account.data), "base64"],
+                    "owner": account.owner.to_string(),
+                    "executable": account.executable,
+                    "rentEpoch": account.rent_epoch,
+                },
+            });
+            fs::write(&path, file.to_string())?;
+
+            args.push(String::from("--account"));
+            args.push(pubkey.to_string());
+            args.push(path.display().to_string());
+        }
+
+        Ok(args)
+    }
+
+    /// Adds the accounts to a `solana-program-test` bank
+    #[cfg(feature = "program-test")]
+    pub fn load(&self, program_test: &mut solana_program_test::ProgramTest) {
+        for (pubkey, account) in &self.accounts {
+            program_test.add_account(*pubkey, account.clone());
+        }
+    }
+
+    fn auction(&mut self, offers: usize, start_time: i64, end_time: i64) -> AuctionFixture {
+        let seller = self.funded_wallet();
+        let auction_id = self.auctions.len() as u64;
+        let state_key = pda(&[b"auction", Pubkey::default().as_ref(), seller.as_ref(), &auction_id.to_le_bytes()]);
+        let treasury = pda(&[b"treasury", state_key.as_ref()]);
+        let initial_price = 1_000_000;
+
+        let mut state: State = blank(State::size());
+        state.initializer = seller;
+        state.treasury = treasury;
+        state.max_price = initial_price;
+        state.initial_price = initial_price;
+        state.start_time = start_time;
+        state.end_time = end_time;
+        state.status = AuctionStatus::Open;
+        state.entry_fee_receiver = seller;
+        state.sprint_factor_bps = BPS;
+        state.weight_updated_at = start_time;
+        state.auction_id = auction_id;
+        state.version = STATE_VERSION;
+
+        let mut bidders = Vec::new();
+        for i in 0..offers {
+            let bidder = self.funded_wallet();
+            let amount = initial_price + (i as u64 + 1) * 100_000;
+            let (offer_key, bump) = Pubkey::find_program_address(&[b"bid", state_key.as_ref(), bidder.as_ref()], &auction::ID);
+
+            let mut offer: Offer = blank(Offer::size());
+            offer.amount = amount;
+            offer.bump = bump;
+            offer.weight_updated_at = start_time;
+            offer.version = OFFER_VERSION;
+            self.accounts.push((offer_key, program_account(&offer, Offer::size())));
+
+            state.max_bidder = bidder;
+            state.max_price = amount;
+            state.escrowed += amount;
+            state.bidders += 1;
+            state.open_bids += 1;
+            state.bid_count += 1;
+            bidders.push(bidder);
+        }
+
+        // The treasury holds the escrow of every offer on top of its rent
+        let treasury_account = Account::new(
+            Rent::default().minimum_balance(0) + state.escrowed,
+            0,
+            &auction::ID
+        );
+        self.accounts.push((treasury, treasury_account));
+        self.accounts.push((state_key, program_account(&state, State::size())));
+        self.auctions.push(state_key);
+
+        AuctionFixture { state: state_key, treasury, seller, bidders }
+    }
+}
+
+fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &auction::ID).0
+}
+
+/// An account of the given size with every field zeroed, to be filled in by the fixture
+fn blank<T: AccountDeserialize + Discriminator>(size: usize) -> T {
+    let mut data = T::discriminator().to_vec();
+    data.resize(8 + size, 0);
+
+    T::try_deserialize(&mut &data[..]).expect("zeroed account deserializes")
+}
+
+/// A rent-exempt account owned by the program
+fn program_account<T: AccountSerialize>(value: &T, size: usize) -> Account {
+    let mut data = Vec::with_capacity(8 + size);
+    value.try_serialize(&mut data).expect("fixture serializes");
+    data.resize(8 + size, 0);
+
+    let mut account = Account::new(Rent::default().minimum_balance(data.len()), 0, &auction::ID);
+    account.data = data;
+    account
+}
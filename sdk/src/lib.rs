@@ -0,0 +1,11 @@
+//! Off-chain client for the auction program
+
+pub mod client;
+pub mod error;
+#[cfg(feature = "test-support")]
+pub mod fixtures;
+pub mod snapshot;
+
+pub use client::{AuctionClient, RetryPolicy};
+pub use error::ClientError;
+pub use snapshot::Snapshot;
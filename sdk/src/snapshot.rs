@@ -0,0 +1,168 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::Program;
+use auction::{AuctionReport, AuditLog, Counter, Offer, Registry, RegistryPage, Sponsorship, State};
+use serde_json::{json, Value};
+
+use crate::client::AuctionClient;
+use crate::error::ClientError;
+
+/// Every account of the program at one point in time, for audits, migrations and recovery drills
+pub struct Snapshot {
+    pub slot: u64,
+    pub auctions: Vec<(Pubkey, State)>,
+    pub offers: Vec<(Pubkey, Offer)>,
+    pub counters: Vec<(Pubkey, Counter)>,
+    pub registries: Vec<(Pubkey, Registry)>,
+    pub pages: Vec<(Pubkey, RegistryPage)>,
+    pub sponsorships: Vec<(Pubkey, Sponsorship)>,
+    pub reports: Vec<(Pubkey, AuctionReport)>,
+    pub audit_logs: Vec<(Pubkey, AuditLog)>,
+}
+
+impl Snapshot {
+    /// Scans the program accounts with `getProgramAccounts`, one request per account type
+    pub fn scan(program: &Program) -> Result<Self, ClientError> {
+        let slot = program.rpc().get_slot().map_err(|err| ClientError::Rpc(err.to_string()))?;
+
+        Ok(Snapshot {
+            slot,
+            auctions: program.accounts(Vec::new())?,
+            offers: program.accounts(Vec::new())?,
+            counters: program.accounts(Vec::new())?,
+            registries: program.accounts(Vec::new())?,
+            pages: program.accounts(Vec::new())?,
+            sponsorships: program.accounts(Vec::new())?,
+            reports: program.accounts(Vec::new())?,
+            audit_logs: program.accounts(Vec::new())?,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "slot": self.slot,
+            "auctions": self.auctions.iter().map(|(key, state)| auction_json(key, state)).collect::<Vec<_>>(),
+            "offers": self.offers.iter().map(|(key, offer)| json!({
+                "offer": key.to_string(),
+                "amount": offer.amount,
+                "weight": offer.weight.to_string(),
+            })).collect::<Vec<_>>(),
+            "counters": self.counters.iter().map(|(key, counter)| json!({
+                "counter": key.to_string(),
+                "next_id": counter.next_id,
+            })).collect::<Vec<_>>(),
+            "registries": self.registries.iter().map(|(key, registry)| json!({
+                "registry": key.to_string(),
+                "namespace": registry.namespace.to_string(),
+                "head": registry.head,
+                "tail": registry.tail,
+            })).collect::<Vec<_>>(),
+            "pages": self.pages.iter().map(|(key, page)| json!({
+                "page": key.to_string(),
+                "index": page.index,
+                "auctions": page.auctions[..page.len as usize]
+                    .iter()
+                    .filter(|auction| **auction != Pubkey::default())
+                    .map(|auction| auction.to_string())
+                    .collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+            "sponsorships": self.sponsorships.iter().map(|(key, sponsorship)| json!({
+                "sponsorship": key.to_string(),
+                "namespace": sponsorship.namespace.to_string(),
+                "sponsor": sponsorship.sponsor.to_string(),
+            })).collect::<Vec<_>>(),
+            "reports": self.reports.iter().map(|(key, report)| json!({
+                "report": key.to_string(),
+                "auction": report.auction.to_string(),
+                "bidders": report.bidders,
+                "bids": report.bids,
+                "final_price": report.final_price,
+            })).collect::<Vec<_>>(),
+            "audit_logs": self.audit_logs.iter().map(|(key, log)| json!({
+                "audit_log": key.to_string(),
+                "auction": log.auction.to_string(),
+                "entries": log.entries.len(),
+                "dropped": log.dropped,
+                "hash": log.hash.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Writes one CSV file per account type into `dir`, the columns being those of `to_json`
+    pub fn write_csv(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        if let Value::Object(tables) = self.to_json() {
+            for (name, rows) in tables {
+                if let Value::Array(rows) = rows {
+                    fs::write(dir.join(format!("{}.csv", name)), csv(&rows))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl AuctionClient {
+    pub async fn snapshot(&self) -> Result<Snapshot, ClientError> {
+        self.with_retries(Snapshot::scan).await
+    }
+}
+
+fn auction_json(key: &Pubkey, state: &State) -> Value {
+    json!({
+        "auction": key.to_string(),
+        "auction_id": state.auction_id,
+        "namespace": state.namespace.to_string(),
+        "initializer": state.initializer.to_string(),
+        "treasury": state.treasury.to_string(),
+        "payment_mint": state.payment_mint.map(|mint| mint.to_string()),
+        "kind": state.kind as u8,
+        "category": state.category as u8,
+        "draft": state.draft,
+        "status": state.status as u8,
+        "start_time": state.start_time,
+        "end_time": state.end_time,
+        "closes_at": state.closes_at(),
+        "initial_price": state.initial_price,
+        "max_price": state.max_price,
+        "max_bidder": state.max_bidder.to_string(),
+        "bidders": state.bidders,
+        "bid_count": state.bid_count,
+        "escrowed": state.escrowed,
+        "item_mint": state.item_mint.to_string(),
+        "item_amount": state.item_amount,
+        "item_claimed": state.item_claimed,
+        "audited": state.audited,
+    })
+}
+
+/// Flat CSV of JSON rows, nested values being written as JSON
+fn csv(rows: &[Value]) -> String {
+    let columns: Vec<String> = match rows.first() {
+        Some(Value::Object(row)) => row.keys().cloned().collect(),
+        _ => return String::new(),
+    };
+
+    let mut out = columns.join(",");
+    out.push('\n');
+    for row in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| match &row[column] {
+                Value::Null => String::new(),
+                Value::String(text) => text.clone(),
+                value @ (Value::Array(_) | Value::Object(_)) => format!("\"{}\"", value.to_string().replace('"', "\"\"")),
+                value => value.to_string(),
+            })
+            .collect();
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+
+    out
+}
@@ -0,0 +1,184 @@
+import { setTimeout as sleep } from 'timers/promises'
+import chai, { assert, expect } from 'chai'
+import chaiAsPromised from 'chai-as-promised';
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, reportSeed, resultSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+chai.use(chaiAsPromised)
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 8
+
+describe('adversarial', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const seller = anchor.web3.Keypair.generate()
+  const honest = anchor.web3.Keypair.generate()
+  const attacker = anchor.web3.Keypair.generate()
+
+  // The victim auction and an auction the attacker controls, whose accounts get substituted
+  let state: anchor.web3.PublicKey, fakeState: anchor.web3.PublicKey
+  let treasury: anchor.web3.PublicKey, fakeTreasury: anchor.web3.PublicKey
+
+  let page: anchor.web3.PublicKey, report: anchor.web3.PublicKey, result: anchor.web3.PublicKey
+  let timeStart: Date
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  const offerOf = async (auction: anchor.web3.PublicKey, bidder: anchor.web3.PublicKey) =>
+    (await pda(bidSeed(auction, bidder)))[0]
+
+  const bid = (bidder: anchor.web3.Keypair, amount: number, accounts: any = {}) => offerOf(state, bidder.publicKey)
+    .then(async offer => program.methods
+      .bid(new anchor.BN(amount))
+      .accounts({ state, treasury, buyer: bidder.publicKey, offer, entryFeeReceiver: seller.publicKey, stats: (await pda(statsSeed()))[0], ...accounts })
+      .signers([bidder])
+      .rpc())
+
+  const refund = (bidder: anchor.web3.Keypair, accounts: any = {}) => offerOf(state, bidder.publicKey)
+    .then(offer => program.methods
+      .refund()
+      .accounts({ state, treasury, buyer: bidder.publicKey, offer, ...accounts })
+      .signers([bidder])
+      .rpc())
+
+  const endAuction = (signer: anchor.web3.Keypair, accounts: any = {}) => program.methods
+    .endAuction()
+    .accounts({ state, treasury, initializer: signer.publicKey, beneficiary: seller.publicKey, maxBidder: honest.publicKey, page, report, result, ...accounts, protocol: await protocol() })
+    .signers([signer])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(seller.publicKey)
+    await airdrop(honest.publicKey)
+    await airdrop(attacker.publicKey)
+  })
+
+  it('Auctions are initialized', async () => {
+    state = await nextAuction(seller.publicKey)
+    fakeState = await nextAuction(attacker.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    fakeTreasury = (await pda(treasurySeed(fakeState)))[0]
+
+    for (const [initializer, auction, vault] of [[seller, state, treasury], [attacker, fakeState, fakeTreasury]] as const) {
+      const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+      const accounts = await listing()
+      if (auction === state) {
+        page = accounts.page
+        report = (await pda(reportSeed(state)))[0]
+        result = (await pda(resultSeed(state)))[0]
+        timeStart = new Date()
+      }
+
+      await program.methods
+        .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+        .accounts({ initializer: initializer.publicKey, state: auction, treasury: vault, counter, ...accounts })
+        .signers([initializer])
+        .rpc()
+    }
+  })
+
+  it('Dont allow paying a bid into a substituted treasury', async () => {
+    await expectError(bid(attacker, INITIAL_PRICE + 10, { treasury: fakeTreasury }), 'WrongAccount')
+  })
+
+  it('Dont allow bidding through the offer of another bidder', async () => {
+    await bid(attacker, INITIAL_PRICE + 5)
+    await bid(honest, INITIAL_PRICE + 10)
+
+    // Re-initializing or topping up somebody else's offer would let the attacker bid with their escrow
+    const offer = await offerOf(state, honest.publicKey)
+    await expectError(bid(attacker, INITIAL_PRICE + 20, { offer }), 'ConstraintSeeds')
+  })
+
+  it('Dont allow bidding through an offer of another auction', async () => {
+    const offer = await offerOf(fakeState, attacker.publicKey)
+    await expectError(bid(attacker, INITIAL_PRICE + 20, { offer }), 'ConstraintSeeds')
+  })
+
+  it('Dont allow settling before the end', async () => {
+    await expectError(endAuction(seller), 'Open')
+  })
+
+  it('Dont allow settling by anybody but the seller', async () => {
+    await expectError(endAuction(attacker), 'WrongAccount')
+  })
+
+  it('Waiting for auction end', async () => {
+    const endTime = +timeStart + (AUCTION_LENGTH * 1000)
+    const now = +new Date()
+
+    if (endTime >= now)
+      await sleep((endTime - now) + 1000)
+  })
+
+  // Deadlines are checked against the cluster clock only, nothing a client sends can move them
+  it('Dont allow bidding after the end by the cluster clock', async () => {
+    await expectError(bid(attacker, INITIAL_PRICE + 30), 'Closed')
+  })
+
+  it('Dont allow settling with a substituted winner', async () => {
+    await expectError(endAuction(seller, { maxBidder: attacker.publicKey }), 'WrongAccount')
+  })
+
+  it('Dont allow paying a substituted beneficiary', async () => {
+    await expectError(endAuction(seller, { beneficiary: attacker.publicKey }), 'WrongAccount')
+  })
+
+  it('Dont allow settling out of a substituted treasury', async () => {
+    await expectError(endAuction(seller, { treasury: fakeTreasury }), 'WrongAccount')
+  })
+
+  it('Auction is settled', async () => {
+    await endAuction(seller)
+  })
+
+  it('Dont allow settling twice', async () => {
+    await expectError(endAuction(seller), 'AlreadySettled')
+  })
+
+  it('Dont allow the winner to take the bid back', async () => {
+    await expectError(refund(honest), 'WinnerRefund')
+  })
+
+  it('Dont allow draining the treasury through the offer of another bidder', async () => {
+    const offer = await offerOf(state, honest.publicKey)
+    await expectError(refund(attacker, { offer }), 'ConstraintSeeds')
+  })
+
+  it('Dont allow draining a treasury of another auction', async () => {
+    await expectError(refund(attacker, { treasury: fakeTreasury }), 'WrongAccount')
+  })
+
+  it('Dont allow refunding twice', async () => {
+    await refund(attacker)
+    await expectError(refund(attacker), 'AccountNotInitialized')
+  })
+
+  it('Treasury keeps only its rent after settlement', async () => {
+    const balance = await provider.connection.getBalance(treasury)
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(0)
+
+    expect(balance).to.equal(rent)
+  })
+})
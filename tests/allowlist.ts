@@ -0,0 +1,109 @@
+import { assert, expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { keccak_256 } from 'js-sha3'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, bidSeed, counterSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+
+const keccak = (...parts: Buffer[]) => Buffer.from(keccak_256.arrayBuffer(Buffer.concat(parts)))
+
+// Nodes hash their children in ascending order, like the program folds the proofs
+const node = (a: Buffer, b: Buffer) => Buffer.compare(a, b) <= 0 ? keccak(a, b) : keccak(b, a)
+
+describe('allowlist', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const allowed1 = anchor.web3.Keypair.generate()
+  const allowed2 = anchor.web3.Keypair.generate()
+  const stranger = anchor.web3.Keypair.generate()
+
+  const leaf1 = keccak(allowed1.publicKey.toBuffer())
+  const leaf2 = keccak(allowed2.publicKey.toBuffer())
+  const root = node(leaf1, leaf2)
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  const bidAccounts = async (bidder: anchor.web3.Keypair) => ({
+    state,
+    treasury,
+    buyer: bidder.publicKey,
+    offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+    entryFeeReceiver: initializer.publicKey,
+    stats: (await pda(statsSeed()))[0],
+  })
+
+  const placeBid = async (bidder: anchor.web3.Keypair, amount: number, proof: Buffer[]) => program.methods
+    .placeBid({ v3: { amount: new anchor.BN(amount), maxValidTime: null, proof: proof.map(sibling => Array.from(sibling)) } })
+    .accounts(await bidAccounts(bidder))
+    .signers([bidder])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(allowed1.publicKey)
+    await airdrop(allowed2.publicKey)
+    await airdrop(stranger.publicKey)
+  })
+
+  it('Auction is initialized with an allowlist', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...await listing() })
+      .signers([initializer])
+      .rpc()
+
+    await program.methods
+      .setAllowlist(Array.from(root))
+      .accounts({ state, initializer: initializer.publicKey })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Dont allow wallets outside of the allowlist', async () => {
+    await expectError(placeBid(stranger, INITIAL_PRICE + 10, [leaf1]), 'NotAllowlisted')
+  })
+
+  it('Dont allow a bid without the proof', async () => {
+    await expectError(program.methods
+      .bid(new anchor.BN(INITIAL_PRICE + 10))
+      .accounts(await bidAccounts(allowed1))
+      .signers([allowed1])
+      .rpc(), 'NotAllowlisted')
+  })
+
+  it('Allowlisted wallets bid with their proof', async () => {
+    await placeBid(allowed1, INITIAL_PRICE + 10, [leaf2])
+    await placeBid(allowed2, INITIAL_PRICE + 20, [leaf1])
+
+    const account = await program.account.state.fetch(state)
+    expect(account.maxBidder.toBase58()).to.equal(allowed2.publicKey.toBase58())
+    expect(account.maxPrice.toNumber()).to.equal(INITIAL_PRICE + 20)
+  })
+})
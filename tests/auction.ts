@@ -5,14 +5,16 @@ import chaiAsPromised from 'chai-as-promised';
 import * as anchor from '@project-serum/anchor'
 import { AnchorError, Program } from '@project-serum/anchor'
 import { Auction } from '../target/types/auction'
-import { airdropFn, pdaFn, bidSeed, speedCheck } from './utils'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, reportSeed, resultSeed, protocolSeed, speedCheck, statsSeed, nextAuctionFn, treasurySeed } from './utils'
 
 chai.use(chaiAsPromised)
 
 const INITIAL_PRICE = 100
 const AUCTION_LENGTH = 60
 
-const STRUCT_SIZE_OFFER = 17
+const STRUCT_SIZE_OFFER = 113
+const STRUCT_SIZE_REPORT = 144
+const STRUCT_SIZE_RESULT = 154
 
 let _price = INITIAL_PRICE
 const getPrice = (jump: number = 0) => _price += jump
@@ -28,6 +30,9 @@ describe('auction', () => {
   // Init util functions
   const airdrop = airdropFn(provider)
   const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
 
   // Initialize humans
   const initializer = anchor.web3.Keypair.generate()
@@ -41,21 +46,33 @@ describe('auction', () => {
   })
 
   // Init accounts for the initialize function
-  const state = anchor.web3.Keypair.generate()
-  const treasury = anchor.web3.Keypair.generate()
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
 
   // This will be the starting time of the auction
   let timeStart: Date;
 
+  // Registry page listing the auction, the seller's report and the published result
+  let page: anchor.web3.PublicKey, report: anchor.web3.PublicKey, result: anchor.web3.PublicKey;
+
   it('Program is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing()
+    page = accounts.page
+    report = (await pda(reportSeed(state)))[0]
+    result = (await pda(resultSeed(state)))[0]
+
     const tx = await program.methods
       .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
       .accounts({
         initializer: initializer.publicKey,
-        state: state.publicKey,
-        treasury: treasury.publicKey,
+        state,
+        treasury,
+        counter,
+        ...accounts,
       })
-      .signers([initializer, state, treasury])
+      .signers([initializer])
       .rpc()
 
     timeStart = new Date()
@@ -67,16 +84,18 @@ describe('auction', () => {
   it('Cant place bid lower than the inital price', async () => {
     speedCheck(timeStart, AUCTION_LENGTH)
 
-    const [_pda, _bump] = await pda(bidSeed(state.publicKey, bidder1.publicKey))
+    const [_pda, _bump] = await pda(bidSeed(state, bidder1.publicKey))
 
     try {
       await program.methods
         .bid(new anchor.BN(INITIAL_PRICE - 1))
         .accounts({
-          state: state.publicKey,
-          treasury: treasury.publicKey,
+          state,
+          treasury,
           buyer: bidder1.publicKey,
-          offer: _pda
+          offer: _pda,
+          entryFeeReceiver: initializer.publicKey,
+          stats: (await pda(statsSeed()))[0]
         })
         .signers([bidder1])
         .rpc()
@@ -92,16 +111,18 @@ describe('auction', () => {
     speedCheck(timeStart, AUCTION_LENGTH)
 
     const currentBidder = bidder1;
-    const [_pda, _bump] = await pda(bidSeed(state.publicKey, currentBidder.publicKey))
+    const [_pda, _bump] = await pda(bidSeed(state, currentBidder.publicKey))
 
     try {
       await program.methods
         .bid(new anchor.BN(INITIAL_PRICE))
         .accounts({
-          state: state.publicKey,
-          treasury: treasury.publicKey,
+          state,
+          treasury,
           buyer: currentBidder.publicKey,
-          offer: _pda
+          offer: _pda,
+          entryFeeReceiver: initializer.publicKey,
+          stats: (await pda(statsSeed()))[0]
         })
         .signers([currentBidder])
         .rpc()
@@ -118,7 +139,7 @@ describe('auction', () => {
     speedCheck(timeStart, AUCTION_LENGTH)
 
     const currentBidder = bidder1;
-    const [_pda, _bump] = await pda(bidSeed(state.publicKey, currentBidder.publicKey))
+    const [_pda, _bump] = await pda(bidSeed(state, currentBidder.publicKey))
     bidFirstAmount = getPrice(10);
 
     const balanceBefore = await provider.connection.getBalance(currentBidder.publicKey)
@@ -127,10 +148,12 @@ describe('auction', () => {
     const tx = await program.methods
       .bid(new anchor.BN(bidFirstAmount))
       .accounts({
-        state: state.publicKey,
-        treasury: treasury.publicKey,
+        state,
+        treasury,
         buyer: currentBidder.publicKey,
-        offer: _pda
+        offer: _pda,
+        entryFeeReceiver: initializer.publicKey,
+        stats: (await pda(statsSeed()))[0]
       })
       .signers([currentBidder])
       .rpc()
@@ -145,16 +168,18 @@ describe('auction', () => {
     speedCheck(timeStart, AUCTION_LENGTH)
 
     const currentBidder = bidder1;
-    const [_pda, _bump] = await pda(bidSeed(state.publicKey, currentBidder.publicKey))
+    const [_pda, _bump] = await pda(bidSeed(state, currentBidder.publicKey))
 
     try {
       await program.methods
         .bid(new anchor.BN(getPrice() + 10))
         .accounts({
-          state: state.publicKey,
-          treasury: treasury.publicKey,
+          state,
+          treasury,
           buyer: currentBidder.publicKey,
-          offer: _pda
+          offer: _pda,
+          entryFeeReceiver: initializer.publicKey,
+          stats: (await pda(statsSeed()))[0]
         })
         .signers([currentBidder])
         .rpc()
@@ -170,16 +195,18 @@ describe('auction', () => {
     speedCheck(timeStart, AUCTION_LENGTH)
 
     const currentBidder = bidder2;
-    const [_pda, _bump] = await pda(bidSeed(state.publicKey, currentBidder.publicKey))
+    const [_pda, _bump] = await pda(bidSeed(state, currentBidder.publicKey))
 
     try {
       await program.methods
         .bid(new anchor.BN(getPrice() - 1))
         .accounts({
-          state: state.publicKey,
-          treasury: treasury.publicKey,
+          state,
+          treasury,
           buyer: currentBidder.publicKey,
-          offer: _pda
+          offer: _pda,
+          entryFeeReceiver: initializer.publicKey,
+          stats: (await pda(statsSeed()))[0]
         })
         .signers([currentBidder])
         .rpc()
@@ -196,7 +223,7 @@ describe('auction', () => {
     speedCheck(timeStart, AUCTION_LENGTH)
 
     const currentBidder = bidder2;
-    const [_pda, _bump] = await pda(bidSeed(state.publicKey, currentBidder.publicKey))
+    const [_pda, _bump] = await pda(bidSeed(state, currentBidder.publicKey))
     bidPenultimate = _pda, bidderPenultimate = currentBidder, bidPenultimateAmount = getPrice(10);
 
     const balanceBefore = await provider.connection.getBalance(currentBidder.publicKey)
@@ -205,10 +232,12 @@ describe('auction', () => {
     const tx = await program.methods
       .bid(new anchor.BN(bidPenultimateAmount))
       .accounts({
-        state: state.publicKey,
-        treasury: treasury.publicKey,
+        state,
+        treasury,
         buyer: currentBidder.publicKey,
-        offer: _pda
+        offer: _pda,
+        entryFeeReceiver: initializer.publicKey,
+        stats: (await pda(statsSeed()))[0]
       })
       .signers([currentBidder])
       .rpc()
@@ -224,7 +253,7 @@ describe('auction', () => {
     speedCheck(timeStart, AUCTION_LENGTH)
 
     const currentBidder = bidder1;
-    const [_pda, _bump] = await pda(bidSeed(state.publicKey, currentBidder.publicKey))
+    const [_pda, _bump] = await pda(bidSeed(state, currentBidder.publicKey))
     bidHighest = _pda, bidderHighest = currentBidder, bidHighestAmount = getPrice(10);
 
     const balanceBefore = await provider.connection.getBalance(currentBidder.publicKey)
@@ -232,10 +261,12 @@ describe('auction', () => {
     const tx = await program.methods
       .bid(new anchor.BN(bidHighestAmount))
       .accounts({
-        state: state.publicKey,
-        treasury: treasury.publicKey,
+        state,
+        treasury,
         buyer: currentBidder.publicKey,
-        offer: _pda
+        offer: _pda,
+        entryFeeReceiver: initializer.publicKey,
+        stats: (await pda(statsSeed()))[0]
       })
       .signers([currentBidder])
       .rpc()
@@ -244,6 +275,10 @@ describe('auction', () => {
 
     const balanceAfter = await provider.connection.getBalance(currentBidder.publicKey)
     expect(balanceBefore - balanceAfter).to.be.equal(bidHighestAmount - bidFirstAmount)
+
+    // The offer holds the whole bid, not the sum of every bid the bidder placed
+    const offer = await program.account.offer.fetch(_pda)
+    expect(offer.amount.toNumber()).to.be.equal(bidHighestAmount)
   })
 
   it('Dont allow closing the auction before the end', async () => {
@@ -253,10 +288,15 @@ describe('auction', () => {
       await program.methods
         .endAuction()
         .accounts({
-          state: state.publicKey,
-          treasury: treasury.publicKey,
+          state,
+          treasury,
           initializer: initializer.publicKey,
-          maxBidder: bidderHighest.publicKey
+          beneficiary: initializer.publicKey,
+          maxBidder: bidderHighest.publicKey,
+          page,
+          report,
+          result
+          protocol: await protocol(),
         })
         .signers([initializer])
         .rpc()
@@ -275,8 +315,8 @@ describe('auction', () => {
       await program.methods
         .refund()
         .accounts({
-          state: state.publicKey,
-          treasury: treasury.publicKey,
+          state,
+          treasury,
           buyer: bidderPenultimate.publicKey,
           offer: bidPenultimate
         })
@@ -303,8 +343,8 @@ describe('auction', () => {
       await program.methods
         .refund()
         .accounts({
-          state: state.publicKey,
-          treasury: treasury.publicKey,
+          state,
+          treasury,
           buyer: bidderPenultimate.publicKey,
           offer: bidPenultimate
         })
@@ -323,10 +363,15 @@ describe('auction', () => {
       await program.methods
         .endAuction()
         .accounts({
-          state: state.publicKey,
-          treasury: treasury.publicKey,
+          state,
+          treasury,
           initializer: bidder1.publicKey,
-          maxBidder: bidderHighest.publicKey
+          beneficiary: initializer.publicKey,
+          maxBidder: bidderHighest.publicKey,
+          page,
+          report,
+          result
+          protocol: await protocol(),
         })
         .signers([bidder1])
         .rpc()
@@ -341,13 +386,21 @@ describe('auction', () => {
   it('Close the auction', async () => {
     const balanceBefore = await provider.connection.getBalance(initializer.publicKey)
 
+    let summary: any
+    const listener = program.addEventListener('SettlementSummary', (event) => summary = event)
+
     const tx = await program.methods
       .endAuction()
       .accounts({
-        state: state.publicKey,
-        treasury: treasury.publicKey,
+        state,
+        treasury,
         initializer: initializer.publicKey,
-        maxBidder: bidderHighest.publicKey
+        beneficiary: initializer.publicKey,
+        maxBidder: bidderHighest.publicKey,
+        page,
+        report,
+        result
+        protocol: await protocol(),
       })
       .signers([initializer])
       .rpc()
@@ -356,7 +409,27 @@ describe('auction', () => {
 
     const balanceAfter = await provider.connection.getBalance(initializer.publicKey)
 
-    expect(balanceAfter - balanceBefore).to.be.equal(getPrice())
+    // The seller pays for the report and the result out of the proceeds
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(STRUCT_SIZE_REPORT)
+      + await provider.connection.getMinimumBalanceForRentExemption(STRUCT_SIZE_RESULT)
+    expect(balanceAfter - balanceBefore).to.be.equal(getPrice() - rent)
+
+    await sleep(1000)
+    await program.removeEventListener(listener)
+
+    expect(summary.winner.toBase58()).to.be.equal(bidderHighest.publicKey.toBase58())
+    expect(summary.grossPrice.toNumber()).to.be.equal(getPrice())
+    expect(summary.netProceeds.toNumber()).to.be.equal(getPrice())
+    expect(summary.bidders).to.be.equal(2)
+  })
+
+  it('Result of the auction is published', async () => {
+    const account = await program.account.auctionResult.fetch(result)
+
+    expect(account.auction.toBase58()).to.be.equal(state.toBase58())
+    expect(account.winner.toBase58()).to.be.equal(bidderHighest.publicKey.toBase58())
+    expect(account.price.toNumber()).to.be.equal(getPrice())
+    expect(account.paymentMint).to.be.null
   })
 
   it('Dont allow double close', async () => {
@@ -364,10 +437,15 @@ describe('auction', () => {
       await program.methods
         .endAuction()
         .accounts({
-          state: state.publicKey,
-          treasury: treasury.publicKey,
+          state,
+          treasury,
           initializer: initializer.publicKey,
-          maxBidder: bidderHighest.publicKey
+          beneficiary: initializer.publicKey,
+          maxBidder: bidderHighest.publicKey,
+          page,
+          report,
+          result
+          protocol: await protocol(),
         })
         .signers([initializer])
         .rpc()
@@ -375,7 +453,90 @@ describe('auction', () => {
       assert(false)
     } catch (e) {
       const err = e as AnchorError
-      expect(err.error.errorCode.code).to.equal('Open')
+      expect(err.error.errorCode.code).to.equal('AlreadySettled')
+    }
+  })
+
+  it('Dont allow parallel closes of a settled auction', async () => {
+    const balanceBefore = await provider.connection.getBalance(treasury)
+
+    // Two distinct transactions sent together after the settlement
+    const close = (lamports: number) => program.methods
+      .endAuction()
+      .accounts({
+        state,
+        treasury,
+        initializer: initializer.publicKey,
+        beneficiary: initializer.publicKey,
+        maxBidder: bidderHighest.publicKey,
+        page,
+        report,
+        result
+        protocol: await protocol(),
+      })
+      .preInstructions([anchor.web3.SystemProgram.transfer({
+        fromPubkey: initializer.publicKey,
+        toPubkey: initializer.publicKey,
+        lamports
+      })])
+      .signers([initializer])
+      .rpc()
+
+    const results = await Promise.all([close(0).catch(e => e), close(1).catch(e => e)])
+    for (const result of results) {
+      const err = result as AnchorError
+      expect(err.error.errorCode.code).to.equal('AlreadySettled')
+    }
+
+    const balanceAfter = await provider.connection.getBalance(treasury)
+    expect(balanceAfter).to.be.equal(balanceBefore)
+  })
+
+  it('Dont allow sweeping the winning offer', async () => {
+    const [protocolVault, _bump] = await pda(protocolSeed())
+
+    try {
+      await program.methods
+        .sweepExpiredRefund()
+        .accounts({
+          state,
+          treasury,
+          bidder: bidderHighest.publicKey,
+          offer: bidHighest,
+          protocolVault,
+          cranker: bidder2.publicKey
+        })
+        .signers([bidder2])
+        .rpc()
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('WinnerRefund')
+    }
+  })
+
+  it('Dont allow sweeping a refund before it expires', async () => {
+    const [protocolVault, _bump] = await pda(protocolSeed())
+
+    try {
+      await program.methods
+        .sweepExpiredRefund()
+        .accounts({
+          state,
+          treasury,
+          bidder: bidderPenultimate.publicKey,
+          offer: bidPenultimate,
+          protocolVault,
+          cranker: bidder1.publicKey
+        })
+        .signers([bidder1])
+        .rpc()
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('RefundNotExpired')
     }
   })
 
@@ -384,11 +545,17 @@ describe('auction', () => {
     const balanceBefore = await provider.connection.getBalance(currentBidder.publicKey)
 
     const rent = await provider.connection.getMinimumBalanceForRentExemption(STRUCT_SIZE_OFFER)
+    const treasuryBefore = await provider.connection.getBalance(treasury)
+
+    let delta: any, refunded: any
+    const listener = program.addEventListener('TreasuryDelta', (event) => delta = event)
+    const refundListener = program.addEventListener('Refunded', (event) => refunded = event)
+
     const tx = await program.methods
       .refund()
       .accounts({
-        state: state.publicKey,
-        treasury: treasury.publicKey,
+        state,
+        treasury,
         buyer: currentBidder.publicKey,
         offer: bidPenultimate
       })
@@ -401,6 +568,19 @@ describe('auction', () => {
 
     // The rent will be refunded too
     expect(balanceAfter - balanceBefore).to.be.equal(bidPenultimateAmount + rent)
+
+    await sleep(1000)
+    await program.removeEventListener(listener)
+    await program.removeEventListener(refundListener)
+
+    expect(refunded.bidder.toBase58()).to.be.equal(currentBidder.publicKey.toBase58())
+    expect(refunded.amount.toNumber()).to.be.equal(bidPenultimateAmount)
+
+    // The refund leaves a trail of the treasury balance
+    expect(delta.reason).to.have.property('refund')
+    expect(delta.previousBalance.toNumber()).to.be.equal(treasuryBefore)
+    expect(delta.delta.toNumber()).to.be.equal(-bidPenultimateAmount)
+    expect(delta.newBalance.toNumber()).to.be.equal(await provider.connection.getBalance(treasury))
   })
 
   it('Dont allow double refund', async () => {
@@ -408,8 +588,8 @@ describe('auction', () => {
       await program.methods
         .refund()
         .accounts({
-          state: state.publicKey,
-          treasury: treasury.publicKey,
+          state,
+          treasury,
           buyer: bidderPenultimate.publicKey,
           offer: bidPenultimate
         })
@@ -0,0 +1,126 @@
+import { expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, resultSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const BUY_NOW_PRICE = 1_000
+const AUCTION_LENGTH = 60
+
+describe('buy-now', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder1 = anchor.web3.Keypair.generate()
+  const bidder2 = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  let page: anchor.web3.PublicKey
+
+  const bid = async (bidder: anchor.web3.Keypair, amount: number) => program.methods
+    .bid(new anchor.BN(amount))
+    .accounts({
+      state,
+      treasury,
+      buyer: bidder.publicKey,
+      offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+      entryFeeReceiver: initializer.publicKey,
+      stats: (await pda(statsSeed()))[0],
+    })
+    .signers([bidder])
+    .rpc()
+
+  const buyNow = async (bidder: anchor.web3.Keypair) => program.methods
+    .buyNow()
+    .accounts({
+      state,
+      treasury,
+      beneficiary: initializer.publicKey,
+      buyer: bidder.publicKey,
+      offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+      entryFeeReceiver: initializer.publicKey,
+      page,
+      result: (await pda(resultSeed(state)))[0],
+      stats: (await pda(statsSeed()))[0],
+      protocol: await protocol(),
+    })
+    .signers([bidder])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder1.publicKey)
+    await airdrop(bidder2.publicKey)
+  })
+
+  it('Auction with a buy-now price is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing()
+    page = accounts.page
+
+    await program.methods
+      .createAuction({
+        v5: {
+          auctionDuration: new anchor.BN(AUCTION_LENGTH),
+          initialPrice: new anchor.BN(INITIAL_PRICE),
+          maxBidders: 0,
+          entryFee: new anchor.BN(0),
+          beneficiary: initializer.publicKey,
+          lotId: new anchor.BN(0),
+          buyNowPrice: new anchor.BN(BUY_NOW_PRICE),
+        },
+      })
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    const account = await program.account.state.fetch(state)
+    expect(account.buyNowPrice.toNumber()).to.equal(BUY_NOW_PRICE)
+  })
+
+  it('Buying now settles the auction and pays the seller', async () => {
+    await bid(bidder1, 200)
+    await bid(bidder2, 300)
+
+    const before = await provider.connection.getBalance(initializer.publicKey)
+    await buyNow(bidder2)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.status).to.have.property('settled')
+    expect(account.maxBidder.toBase58()).to.equal(bidder2.publicKey.toBase58())
+    expect(account.maxPrice.toNumber()).to.equal(BUY_NOW_PRICE)
+    expect(await provider.connection.getBalance(initializer.publicKey)).to.equal(before + BUY_NOW_PRICE)
+
+    const result = await program.account.auctionResult.fetch((await pda(resultSeed(state)))[0])
+    expect(result.price.toNumber()).to.equal(BUY_NOW_PRICE)
+  })
+
+  it('Other bidders are refunded right away', async () => {
+    await program.methods
+      .refund()
+      .accounts({
+        state,
+        treasury,
+        buyer: bidder1.publicKey,
+        offer: (await pda(bidSeed(state, bidder1.publicKey)))[0],
+      })
+      .signers([bidder1])
+      .rpc()
+
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(0)
+    expect(await provider.connection.getBalance(treasury)).to.equal(rent)
+  })
+})
@@ -0,0 +1,92 @@
+import { assert, expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, bidSeed, counterSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+
+describe('cancel', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder = anchor.web3.Keypair.generate()
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  const create = async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    const state = await nextAuction(initializer.publicKey)
+    const [treasury, _treasuryBump] = await pda(treasurySeed(state))
+    const accounts = await listing()
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    return { state, treasury, page: accounts.page }
+  }
+
+  const cancel = (auction: { state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey, page: anchor.web3.PublicKey }) => program.methods
+    .cancelAuction()
+    .accounts({ ...auction, initializer: initializer.publicKey })
+    .signers([initializer])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder.publicKey)
+  })
+
+  it('Auction without bids is cancelled and its rent reclaimed', async () => {
+    const auction = await create()
+    const balanceBefore = await provider.connection.getBalance(initializer.publicKey)
+    const rent = await provider.connection.getBalance(auction.state)
+      + await provider.connection.getBalance(auction.treasury)
+
+    await cancel(auction)
+
+    expect(await provider.connection.getAccountInfo(auction.state)).to.equal(null)
+    expect(await provider.connection.getAccountInfo(auction.treasury)).to.equal(null)
+    expect(await provider.connection.getBalance(initializer.publicKey)).to.equal(balanceBefore + rent)
+  })
+
+  it('Dont allow cancelling once anybody bid', async () => {
+    const auction = await create()
+
+    await program.methods
+      .bid(new anchor.BN(INITIAL_PRICE + 10))
+      .accounts({
+        state: auction.state,
+        treasury: auction.treasury,
+        buyer: bidder.publicKey,
+        offer: (await pda(bidSeed(auction.state, bidder.publicKey)))[0],
+        entryFeeReceiver: initializer.publicKey,
+        stats: (await pda(statsSeed()))[0],
+      })
+      .signers([bidder])
+      .rpc()
+
+    await expectError(cancel(auction), 'AlreadyBid')
+  })
+})
@@ -0,0 +1,128 @@
+import { setTimeout as sleep } from 'timers/promises'
+import chai, { assert, expect } from 'chai'
+import chaiAsPromised from 'chai-as-promised';
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, counterSeed, configSeed, protocolSeed, nextAuctionFn, treasurySeed } from './utils'
+
+chai.use(chaiAsPromised)
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+const ENTRY_FEE = 1_000
+const MIN_INCREMENT = 10
+const HOLIDAY_DELAY = 4
+
+describe('marketplace config', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const marketplace = anchor.web3.Keypair.generate()
+  const initializer = anchor.web3.Keypair.generate()
+
+  let config: anchor.web3.PublicKey
+
+  const create = async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey, marketplace.publicKey))
+    const state = await nextAuction(initializer.publicKey, marketplace.publicKey)
+    const [treasury, _treasuryBump] = await pda(treasurySeed(state))
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...await listing(marketplace.publicKey) })
+      .signers([initializer])
+      .rpc()
+
+    return await program.account.state.fetch(state)
+  }
+
+  const schedule = (change: any, activatesAt: number) => program.methods
+    .scheduleConfigChange(change, new anchor.BN(activatesAt))
+    .accounts({ config, namespace: marketplace.publicKey })
+    .signers([marketplace])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(marketplace.publicKey)
+    await airdrop(initializer.publicKey)
+  })
+
+  it('Config is created', async () => {
+    config = (await pda(configSeed(marketplace.publicKey)))[0]
+
+    await program.methods
+      .createConfig(new anchor.BN(MIN_INCREMENT), new anchor.BN(ENTRY_FEE))
+      .accounts({ config, namespace: marketplace.publicKey })
+      .signers([marketplace])
+      .rpc()
+  })
+
+  it('New auctions start with the defaults of the config', async () => {
+    const state = await create()
+    const [vault, _bump] = await pda(protocolSeed(marketplace.publicKey))
+
+    expect(state.minIncrement.toNumber()).to.equal(MIN_INCREMENT)
+    expect(state.entryFee.toNumber()).to.equal(ENTRY_FEE)
+    expect(state.entryFeeReceiver.toBase58()).to.equal(vault.toBase58())
+  })
+
+  it('Dont allow scheduling a change in the past', async () => {
+    try {
+      await schedule({ entryFee: { value: new anchor.BN(0) } }, Math.floor(Date.now() / 1000) - 10)
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('InvalidOperation')
+    }
+  })
+
+  it('Dont allow another account in place of the config of the namespace', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey, marketplace.publicKey))
+    const state = await nextAuction(initializer.publicKey, marketplace.publicKey)
+    const [treasury, _treasuryBump] = await pda(treasurySeed(state))
+    const accounts = await listing(marketplace.publicKey)
+
+    try {
+      await program.methods
+        .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+        .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts, marketConfig: initializer.publicKey })
+        .signers([initializer])
+        .rpc()
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('ConstraintSeeds')
+    }
+  })
+
+  it('Fee holiday is scheduled', async () => {
+    await schedule({ entryFee: { value: new anchor.BN(0) } }, Math.floor(Date.now() / 1000) + HOLIDAY_DELAY)
+
+    // Nothing changes before the activation
+    expect((await create()).entryFee.toNumber()).to.equal(ENTRY_FEE)
+
+    const account = await program.account.marketConfig.fetch(config)
+    expect(account.pendingCount).to.equal(1)
+  })
+
+  it('Fee holiday applies to the first auction after its activation', async () => {
+    await sleep((HOLIDAY_DELAY + 2) * 1000)
+
+    expect((await create()).entryFee.toNumber()).to.equal(0)
+
+    const account = await program.account.marketConfig.fetch(config)
+    expect(account.pendingCount).to.equal(0)
+    expect(account.entryFee.toNumber()).to.equal(0)
+    expect(account.minIncrement.toNumber()).to.equal(MIN_INCREMENT)
+  })
+})
@@ -0,0 +1,183 @@
+import chai, { assert, expect } from 'chai'
+import chaiAsPromised from 'chai-as-promised';
+import { setTimeout as sleep } from 'timers/promises'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, reportSeed, resultSeed, creditSeed, creditBidSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+chai.use(chaiAsPromised)
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 5
+const CREDIT_LIMIT = 10_000
+const COLLATERAL = 5_000
+
+describe('credit lines', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const marketplace = anchor.web3.Keypair.generate()
+  const maker = anchor.web3.Keypair.generate()
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  let creditLine: anchor.web3.PublicKey, creditBid: anchor.web3.PublicKey, page: anchor.web3.PublicKey
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  const bidOnCredit = async (amount: number) => program.methods
+    .bidOnCredit(new anchor.BN(amount))
+    .accounts({
+      creditBid,
+      state,
+      creditLine,
+      maker: maker.publicKey,
+      entryFeeReceiver: initializer.publicKey,
+      stats: (await pda(statsSeed()))[0],
+    })
+    .signers([maker])
+    .rpc()
+
+  const withdraw = (amount: number) => program.methods
+    .withdrawCollateral(new anchor.BN(amount))
+    .accounts({ creditLine, maker: maker.publicKey })
+    .signers([maker])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(marketplace.publicKey)
+    await airdrop(maker.publicKey)
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder.publicKey)
+  })
+
+  it('Market maker is approved and deposits collateral', async () => {
+    creditLine = (await pda(creditSeed(maker.publicKey, marketplace.publicKey)))[0]
+    creditBid = (await pda(creditBidSeed(state, maker.publicKey)))[0]
+
+    await program.methods
+      .approveCredit(new anchor.BN(CREDIT_LIMIT))
+      .accounts({ creditLine, namespace: marketplace.publicKey, maker: maker.publicKey })
+      .signers([marketplace])
+      .rpc()
+
+    await program.methods
+      .depositCollateral(new anchor.BN(COLLATERAL))
+      .accounts({ creditLine, maker: maker.publicKey })
+      .signers([maker])
+      .rpc()
+
+    const account = await program.account.creditLine.fetch(creditLine)
+    expect(account.limit.toNumber()).to.equal(CREDIT_LIMIT)
+    expect(account.collateral.toNumber()).to.equal(COLLATERAL)
+  })
+
+  it('Market maker bids on credit', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey, marketplace.publicKey))
+    state = await nextAuction(initializer.publicKey, marketplace.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing(marketplace.publicKey)
+    page = accounts.page
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    const [offer, _offerBump] = await pda(bidSeed(state, bidder.publicKey))
+    await program.methods
+      .bid(new anchor.BN(INITIAL_PRICE + 10))
+      .accounts({ state, treasury, buyer: bidder.publicKey, offer, entryFeeReceiver: initializer.publicKey, stats: (await pda(statsSeed()))[0] })
+      .signers([bidder])
+      .rpc()
+
+    await bidOnCredit(INITIAL_PRICE + 100)
+
+    const account = await program.account.creditLine.fetch(creditLine)
+    expect(account.used.toNumber()).to.equal(INITIAL_PRICE + 100)
+    expect(await provider.connection.getBalance(treasury)).to.equal(
+      await provider.connection.getMinimumBalanceForRentExemption(0) + INITIAL_PRICE + 10
+    )
+  })
+
+  it('Dont allow bidding over the credit limit', async () => {
+    await program.methods
+      .setCreditLimit(new anchor.BN(INITIAL_PRICE + 150))
+      .accounts({ creditLine, namespace: marketplace.publicKey })
+      .signers([marketplace])
+      .rpc()
+
+    const [offer, _bump] = await pda(bidSeed(state, bidder.publicKey))
+    await program.methods
+      .bid(new anchor.BN(INITIAL_PRICE + 120))
+      .accounts({ state, treasury, buyer: bidder.publicKey, offer, entryFeeReceiver: initializer.publicKey, stats: (await pda(statsSeed()))[0] })
+      .signers([bidder])
+      .rpc()
+
+    await expectError(bidOnCredit(INITIAL_PRICE + 200), 'CreditLimitExceeded')
+    await bidOnCredit(INITIAL_PRICE + 140)
+  })
+
+  it('Dont allow withdrawing collateral backing outstanding bids', async () => {
+    await expectError(withdraw(COLLATERAL - INITIAL_PRICE), 'CreditLimitExceeded')
+    await withdraw(COLLATERAL - INITIAL_PRICE - 140)
+  })
+
+  it('Settlement draws the price from the collateral', async () => {
+    await sleep((AUCTION_LENGTH + 1) * 1000)
+
+    const [report, _reportBump] = await pda(reportSeed(state))
+    const [result, _resultBump] = await pda(resultSeed(state))
+    await program.methods
+      .endAuction()
+      .accounts({ state, treasury, initializer: initializer.publicKey, beneficiary: initializer.publicKey, maxBidder: maker.publicKey, page, report, result, protocol: await protocol() })
+      .remainingAccounts([
+        { pubkey: creditLine, isSigner: false, isWritable: true },
+        { pubkey: creditBid, isSigner: false, isWritable: true },
+      ])
+      .signers([initializer])
+      .rpc()
+
+    const account = await program.account.creditLine.fetch(creditLine)
+    expect(account.collateral.toNumber()).to.equal(0)
+    expect(account.used.toNumber()).to.equal(0)
+    expect(account.defaulted).to.equal(false)
+    expect(await program.account.creditBid.fetchNullable(creditBid)).to.equal(null)
+
+    // Only the rent is left in the credit line, the whole collateral went to the seller
+    expect(await provider.connection.getBalance(creditLine)).to.equal(
+      await provider.connection.getMinimumBalanceForRentExemption(8 + 90)
+    )
+  })
+
+  it('Losing escrowed bid is refunded as usual', async () => {
+    const [offer, _bump] = await pda(bidSeed(state, bidder.publicKey))
+
+    await program.methods
+      .refund()
+      .accounts({ state, treasury, buyer: bidder.publicKey, offer })
+      .signers([bidder])
+      .rpc()
+  })
+})
@@ -0,0 +1,106 @@
+import chai, { assert, expect } from 'chai'
+import chaiAsPromised from 'chai-as-promised';
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, bidSeed, counterSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+chai.use(chaiAsPromised)
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+
+describe('draft auction', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  let page: anchor.web3.PublicKey
+
+  const bid = async (amount: number) => {
+    const [offer, _bump] = await pda(bidSeed(state, bidder.publicKey))
+
+    await program.methods
+      .bid(new anchor.BN(amount))
+      .accounts({ state, treasury, buyer: bidder.publicKey, offer, entryFeeReceiver: initializer.publicKey, stats: (await pda(statsSeed()))[0] })
+      .signers([bidder])
+      .rpc()
+  }
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder.publicKey)
+  })
+
+  it('Draft is created', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing()
+    page = accounts.page
+
+    await program.methods
+      .createDraft(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Dont allow bidding on a draft', async () => {
+    try {
+      await bid(INITIAL_PRICE + 10)
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('Draft')
+    }
+  })
+
+  it('Draft is edited', async () => {
+    await program.methods
+      .editDraft(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE * 2))
+      .accounts({ state, initializer: initializer.publicKey })
+      .signers([initializer])
+      .rpc()
+
+    const account = await program.account.state.fetch(state)
+    expect(account.maxPrice.toNumber()).to.equal(INITIAL_PRICE * 2)
+  })
+
+  it('Draft is published', async () => {
+    await program.methods
+      .publish()
+      .accounts({ state, initializer: initializer.publicKey })
+      .signers([initializer])
+      .rpc()
+
+    await bid(INITIAL_PRICE * 2 + 10)
+  })
+
+  it('Dont allow editing a published auction', async () => {
+    try {
+      await program.methods
+        .editDraft(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+        .accounts({ state, initializer: initializer.publicKey })
+        .signers([initializer])
+        .rpc()
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('NotDraft')
+    }
+  })
+})
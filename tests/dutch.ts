@@ -0,0 +1,139 @@
+import { setTimeout as sleep } from 'timers/promises'
+import chai, { assert, expect } from 'chai'
+import chaiAsPromised from 'chai-as-promised';
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, resultSeed, statsSeed, nextAuctionFn, treasurySeed, treasurySeed } from './utils'
+
+chai.use(chaiAsPromised)
+
+const AUCTION_LENGTH = 60
+const STEP_LENGTH = 10
+
+describe('dutch auction', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const buyer1 = anchor.web3.Keypair.generate()
+  const buyer2 = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  let page: anchor.web3.PublicKey, result: anchor.web3.PublicKey
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(buyer1.publicKey)
+    await airdrop(buyer2.publicKey)
+  })
+
+  it('Dutch auction is initialized with a stepped schedule', async () => {
+    const now = Math.floor(+new Date() / 1000)
+
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing()
+    page = accounts.page
+    result = (await pda(resultSeed(state)))[0]
+
+    await program.methods
+      .initializeDutch(new anchor.BN(AUCTION_LENGTH), [
+        { time: new anchor.BN(now), price: new anchor.BN(1000) },
+        { time: new anchor.BN(now + STEP_LENGTH), price: new anchor.BN(500) },
+      ])
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Dont allow bidding on a dutch auction', async () => {
+    const [offer, _bump] = await pda(bidSeed(state, buyer1.publicKey))
+
+    try {
+      await program.methods
+        .bid(new anchor.BN(2000))
+        .accounts({ state, treasury, buyer: buyer1.publicKey, offer, entryFeeReceiver: initializer.publicKey, stats: (await pda(statsSeed()))[0] })
+        .signers([buyer1])
+        .rpc()
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('InvalidOperation')
+    }
+  })
+
+  it('Accept the price after the first step', async () => {
+    await sleep((STEP_LENGTH + 2) * 1000)
+
+    const balanceBefore = await provider.connection.getBalance(initializer.publicKey)
+
+    await program.methods
+      .acceptPrice()
+      .accounts({ state, beneficiary: initializer.publicKey, buyer: buyer1.publicKey, page, result, protocol: await protocol() })
+      .signers([buyer1])
+      .rpc()
+
+    const balanceAfter = await provider.connection.getBalance(initializer.publicKey)
+    expect(balanceAfter - balanceBefore).to.be.equal(500)
+  })
+
+  it('Dont allow buying twice', async () => {
+    try {
+      await program.methods
+        .acceptPrice()
+        .accounts({ state, beneficiary: initializer.publicKey, buyer: buyer2.publicKey, page, result, protocol: await protocol() })
+        .signers([buyer2])
+        .rpc()
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('Closed')
+    }
+  })
+  it('Linear dutch auction sells between its start and floor price', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    const linearState = await nextAuction(initializer.publicKey)
+    const [linearTreasury, _treasuryBump] = await pda(treasurySeed(linearState))
+    const accounts = await listing()
+
+    await program.methods
+      .initializeLinearDutch(new anchor.BN(AUCTION_LENGTH), new anchor.BN(6000), new anchor.BN(0))
+      .accounts({ initializer: initializer.publicKey, state: linearState, treasury: linearTreasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    await sleep(STEP_LENGTH * 1000)
+
+    const balanceBefore = await provider.connection.getBalance(initializer.publicKey)
+    await program.methods
+      .acceptPrice()
+      .accounts({
+        state: linearState,
+        beneficiary: initializer.publicKey,
+        buyer: buyer2.publicKey,
+        page: accounts.page,
+        result: (await pda(resultSeed(linearState)))[0],
+        protocol: await protocol(),
+      })
+      .signers([buyer2])
+      .rpc()
+    const paid = await provider.connection.getBalance(initializer.publicKey) - balanceBefore
+
+    // The price drops by 100 lamports every second
+    expect(paid).to.be.lessThan(6000 - STEP_LENGTH * 100 + 100)
+    expect(paid).to.be.greaterThan(6000 - (STEP_LENGTH + 5) * 100)
+  })
+})
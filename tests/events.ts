@@ -0,0 +1,78 @@
+import { expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, bidSeed, counterSeed, statsSeed, nextAuctionFn, treasurySeed, eventAuthoritySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+
+// Instruction discriminator and length of the data vector in front of the event carried by `log_event`
+const LOG_EVENT_HEADER = 8 + 4
+
+describe('events', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder.publicKey)
+  })
+
+  it('Auction is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...await listing() })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Bid is emitted through a self-invocation with the event accounts', async () => {
+    const [eventAuthority, _bump] = await pda(eventAuthoritySeed())
+
+    const tx = await program.methods
+      .bid(new anchor.BN(INITIAL_PRICE + 10))
+      .accounts({
+        state,
+        treasury,
+        buyer: bidder.publicKey,
+        offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+        entryFeeReceiver: initializer.publicKey,
+        stats: (await pda(statsSeed()))[0],
+      })
+      .remainingAccounts([
+        { pubkey: eventAuthority, isSigner: false, isWritable: false },
+        { pubkey: program.programId, isSigner: false, isWritable: false },
+      ])
+      .signers([bidder])
+      .rpc()
+
+    const transaction = await provider.connection.getTransaction(tx, { commitment: 'confirmed' })
+    const inner = transaction.meta.innerInstructions.flatMap(inner => inner.instructions)
+    const keys = transaction.transaction.message.accountKeys
+    const logged = inner.filter(ix => keys[ix.programIdIndex].equals(program.programId))
+    expect(logged).to.have.lengthOf(1)
+
+    const data = anchor.utils.bytes.bs58.decode(logged[0].data).slice(LOG_EVENT_HEADER)
+    const event = program.coder.events.decode(Buffer.from(data).toString('base64'))
+    expect(event.name).to.equal('BidPlaced')
+    expect(event.data.bidder.toBase58()).to.equal(bidder.publicKey.toBase58())
+    expect((event.data.amount as anchor.BN).toNumber()).to.equal(INITIAL_PRICE + 10)
+  })
+})
@@ -0,0 +1,89 @@
+import { assert, expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, counterSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+const MAX_DURATION = 2 * AUCTION_LENGTH
+
+describe('extend', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const stranger = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  const extend = (extension: number, signer: anchor.web3.Keypair = initializer) => program.methods
+    .extendAuction(new anchor.BN(extension))
+    .accounts({ state, initializer: signer.publicKey })
+    .signers([signer])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(stranger.publicKey)
+  })
+
+  it('Auction is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    const [treasury, _treasuryBump] = await pda(treasurySeed(state))
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...await listing() })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Dont allow extending without a latest end', async () => {
+    await expectError(extend(10), 'InvalidOperation')
+  })
+
+  it('End is extended within the maximum duration', async () => {
+    const { startTime } = await program.account.state.fetch(state)
+
+    // Only the latest end, no soft close
+    await program.methods
+      .setSoftClose(new anchor.BN(0), new anchor.BN(0), new anchor.BN(0), 0, startTime.add(new anchor.BN(MAX_DURATION)))
+      .accounts({ state, initializer: initializer.publicKey })
+      .signers([initializer])
+      .rpc()
+
+    await extend(AUCTION_LENGTH / 2)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.endTime.toNumber()).to.equal(account.startTime.toNumber() + AUCTION_LENGTH + AUCTION_LENGTH / 2)
+    expect(account.status).to.have.property('extended')
+  })
+
+  it('Dont allow extending past the maximum duration', async () => {
+    await expectError(extend(AUCTION_LENGTH), 'InvalidOperation')
+  })
+
+  it('Dont allow anyone but the seller to extend', async () => {
+    await expectError(extend(10, stranger), 'WrongAccount')
+  })
+})
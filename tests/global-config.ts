@@ -0,0 +1,250 @@
+import { setTimeout as sleep } from 'timers/promises'
+import chai, { assert, expect } from 'chai'
+import chaiAsPromised from 'chai-as-promised'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import {
+  airdropFn, pdaFn, listingFn, bidSeed, counterSeed, feeVaultSeed, globalConfigSeed, nextAuctionFn, reportSeed, resultSeed,
+  statsSeed, treasurySeed
+} from './utils'
+
+const INITIAL_PRICE = 100
+const MAX_DURATION = 120
+const MIN_INCREMENT = 10
+const FEE_BPS = 250
+const AUCTION_LENGTH = 3
+const WINNING_BID = 10_000
+
+chai.use(chaiAsPromised)
+
+describe('global config', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const admin = anchor.web3.Keypair.generate()
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder = anchor.web3.Keypair.generate()
+
+  let config: anchor.web3.PublicKey, feeVault: anchor.web3.PublicKey
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  const create = async (auctionDuration: number) => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    const state = await nextAuction(initializer.publicKey)
+    const [treasury, _treasuryBump] = await pda(treasurySeed(state))
+    const accounts = await listing()
+
+    await program.methods
+      .initialize(new anchor.BN(auctionDuration), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    return { state, treasury, page: accounts.page, account: await program.account.state.fetch(state) }
+  }
+
+  const update = (signer: anchor.web3.Keypair, newAdmin: anchor.web3.PublicKey, maxDuration: number, feeBps: number) => program.methods
+    .updateConfig(newAdmin, new anchor.BN(maxDuration), new anchor.BN(MIN_INCREMENT), feeBps)
+    .accounts({ config, admin: signer.publicKey })
+    .signers([signer])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(admin.publicKey)
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder.publicKey)
+  })
+
+  it('Config is initialized once by the upgrade authority', async () => {
+    config = (await pda(globalConfigSeed()))[0]
+    feeVault = (await pda(feeVaultSeed()))[0]
+    const [programData, _bump] = await anchor.web3.PublicKey.findProgramAddress(
+      [program.programId.toBuffer()],
+      new anchor.web3.PublicKey('BPFLoaderUpgradeab1e11111111111111111111111')
+    )
+
+    // The wallet deploying the program in tests created it before the first test, see `utils`
+    await expect(program.methods
+      .initializeConfig(admin.publicKey, new anchor.BN(MAX_DURATION), new anchor.BN(MIN_INCREMENT), FEE_BPS)
+      .accounts({ config, feeVault, program: program.programId, programData, authority: provider.wallet.publicKey })
+      .rpc()
+    ).to.be.rejected
+
+    await program.methods
+      .updateConfig(admin.publicKey, new anchor.BN(MAX_DURATION), new anchor.BN(MIN_INCREMENT), FEE_BPS)
+      .accounts({ config, admin: provider.wallet.publicKey })
+      .rpc()
+
+    const account = await program.account.globalConfig.fetch(config)
+    expect(account.admin.toBase58()).to.equal(admin.publicKey.toBase58())
+    expect(account.feeBps).to.equal(FEE_BPS)
+  })
+
+  it('New auctions are held to the limits of the config', async () => {
+    const { account } = await create(MAX_DURATION)
+    expect(account.minIncrement.toNumber()).to.equal(MIN_INCREMENT)
+
+    await expectError(create(MAX_DURATION + 1), 'InvalidOperation')
+  })
+
+  it('Protocol fee is taken from the winning bid at settlement', async () => {
+    const { state, treasury, page } = await create(AUCTION_LENGTH)
+
+    await program.methods
+      .bid(new anchor.BN(WINNING_BID))
+      .accounts({
+        state,
+        treasury,
+        buyer: bidder.publicKey,
+        offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+        entryFeeReceiver: initializer.publicKey,
+        stats: (await pda(statsSeed()))[0],
+      })
+      .signers([bidder])
+      .rpc()
+
+    await sleep((AUCTION_LENGTH + 2) * 1000)
+
+    const vaultBefore = await provider.connection.getBalance(feeVault)
+    await program.methods
+      .endAuction()
+      .accounts({
+        state,
+        treasury,
+        initializer: initializer.publicKey,
+        beneficiary: initializer.publicKey,
+        maxBidder: bidder.publicKey,
+        page,
+        report: (await pda(reportSeed(state)))[0],
+        result: (await pda(resultSeed(state)))[0],
+        protocol: { config, feeVault },
+      })
+      .signers([initializer])
+      .rpc()
+
+    const fee = WINNING_BID * FEE_BPS / 10_000
+    expect(await provider.connection.getBalance(feeVault)).to.equal(vaultBefore + fee)
+  })
+
+  it('Admin withdraws the collected fees', async () => {
+    const withdraw = (signer: anchor.web3.Keypair, amount: number) => program.methods
+      .withdrawFees(new anchor.BN(amount))
+      .accounts({ config, feeVault, admin: signer.publicKey, to: initializer.publicKey })
+      .signers([signer])
+      .rpc()
+
+    const fee = WINNING_BID * FEE_BPS / 10_000
+    await expectError(withdraw(bidder, fee), 'WrongAccount')
+    await expectError(withdraw(admin, fee + 1), 'InvalidOperation')
+
+    const balanceBefore = await provider.connection.getBalance(initializer.publicKey)
+    await withdraw(admin, fee)
+    expect(await provider.connection.getBalance(initializer.publicKey)).to.equal(balanceBefore + fee)
+  })
+
+  it('Fee is taken from a sale at the Dutch price too', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    const state = await nextAuction(initializer.publicKey)
+    const [treasury, _treasuryBump] = await pda(treasurySeed(state))
+    const accounts = await listing()
+
+    await program.methods
+      .initializeLinearDutch(new anchor.BN(MAX_DURATION), new anchor.BN(WINNING_BID), new anchor.BN(WINNING_BID))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    const vaultBefore = await provider.connection.getBalance(feeVault)
+    const sellerBefore = await provider.connection.getBalance(initializer.publicKey)
+    await program.methods
+      .acceptPrice()
+      .accounts({
+        state,
+        beneficiary: initializer.publicKey,
+        buyer: bidder.publicKey,
+        page: accounts.page,
+        result: (await pda(resultSeed(state)))[0],
+        protocol: { config, feeVault },
+      })
+      .signers([bidder])
+      .rpc()
+
+    const fee = WINNING_BID * FEE_BPS / 10_000
+    expect(await provider.connection.getBalance(feeVault)).to.equal(vaultBefore + fee)
+    expect(await provider.connection.getBalance(initializer.publicKey)).to.equal(sellerBefore + WINNING_BID - fee)
+  })
+
+  it('Pause stops new auctions and bids until resumed', async () => {
+    const [stats, _bump] = await pda(statsSeed())
+    const setPaused = (signer: anchor.web3.Keypair, paused: boolean) => program.methods
+      .setPaused(paused)
+      .accounts({ config, stats, admin: signer.publicKey })
+      .signers([signer])
+      .rpc()
+    const { state, treasury } = await create(MAX_DURATION)
+    const bid = async () => program.methods
+      .bid(new anchor.BN(INITIAL_PRICE + MIN_INCREMENT))
+      .accounts({
+        state,
+        treasury,
+        buyer: bidder.publicKey,
+        offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+        entryFeeReceiver: initializer.publicKey,
+        stats,
+      })
+      .signers([bidder])
+      .rpc()
+
+    await expectError(setPaused(bidder, true), 'WrongAccount')
+    await setPaused(admin, true)
+    await expectError(create(MAX_DURATION), 'Paused')
+    await expectError(bid(), 'Paused')
+
+    await setPaused(admin, false)
+    await bid()
+  })
+
+  it('Dont allow anyone but the admin to update the config', async () => {
+    await expectError(update(initializer, initializer.publicKey, 0, 0), 'WrongAccount')
+  })
+
+  it('Dont allow a fee above 100%', async () => {
+    await expectError(update(admin, admin.publicKey, MAX_DURATION, 10_001), 'InvalidOperation')
+  })
+
+  it('Admin lifts the limit and hands the config over', async () => {
+    await update(admin, initializer.publicKey, 0, FEE_BPS)
+    await create(2 * MAX_DURATION)
+
+    const account = await program.account.globalConfig.fetch(config)
+    expect(account.admin.toBase58()).to.equal(initializer.publicKey.toBase58())
+    await expectError(update(admin, admin.publicKey, 0, 0), 'WrongAccount')
+  })
+
+  // The config is shared with every other test, which expects neither limits nor a fee
+  after(async () => {
+    await program.methods
+      .updateConfig(provider.wallet.publicKey, new anchor.BN(0), new anchor.BN(0), 0)
+      .accounts({ config, admin: initializer.publicKey })
+      .signers([initializer])
+      .rpc()
+  })
+})
@@ -0,0 +1,195 @@
+import { setTimeout as sleep } from 'timers/promises'
+import chai, { assert, expect } from 'chai'
+import chaiAsPromised from 'chai-as-promised';
+import { keccak_256 } from 'js-sha3'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, reportSeed, resultSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+chai.use(chaiAsPromised)
+
+const INITIAL_PRICE = 100
+const OPEN_LENGTH = 20
+const COMMIT_LENGTH = 10
+const REVEAL_LENGTH = 10
+
+const commitment = (amount: number, salt: Buffer) =>
+  Array.from(Buffer.from(keccak_256.arrayBuffer(Buffer.concat([new anchor.BN(amount).toArrayLike(Buffer, 'le', 8), salt]))))
+
+describe('hybrid auction', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder1 = anchor.web3.Keypair.generate()
+  const bidder2 = anchor.web3.Keypair.generate()
+  const bidder3 = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  let page: anchor.web3.PublicKey, report: anchor.web3.PublicKey, result: anchor.web3.PublicKey
+
+  const salt1 = Buffer.alloc(32, 1)
+  const salt2 = Buffer.alloc(32, 2)
+
+  let timeStart: Date
+
+  const waitFor = async (seconds: number) => {
+    const endTime = +timeStart + (seconds * 1000)
+    const now = +new Date()
+
+    if (endTime >= now)
+      await sleep((endTime - now) + 1000)
+  }
+
+  const bid = async (bidder: anchor.web3.Keypair, amount: number) => {
+    const [offer, _bump] = await pda(bidSeed(state, bidder.publicKey))
+
+    await program.methods
+      .bid(new anchor.BN(amount))
+      .accounts({ state, treasury, buyer: bidder.publicKey, offer, entryFeeReceiver: initializer.publicKey, stats: (await pda(statsSeed()))[0] })
+      .signers([bidder])
+      .rpc()
+  }
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder1.publicKey)
+    await airdrop(bidder2.publicKey)
+    await airdrop(bidder3.publicKey)
+  })
+
+  it('Hybrid auction is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing()
+    page = accounts.page
+    report = (await pda(reportSeed(state)))[0]
+    result = (await pda(resultSeed(state)))[0]
+
+    await program.methods
+      .initializeHybrid(
+        new anchor.BN(OPEN_LENGTH),
+        new anchor.BN(INITIAL_PRICE),
+        2,
+        new anchor.BN(COMMIT_LENGTH),
+        new anchor.BN(REVEAL_LENGTH)
+      )
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    timeStart = new Date()
+  })
+
+  it('Top two bidders qualify in the open phase', async () => {
+    await bid(bidder3, 110)
+    await bid(bidder1, 120)
+    await bid(bidder2, 130)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.finalists.slice(0, 2).map(k => k.toBase58()))
+      .to.deep.equal([bidder2.publicKey.toBase58(), bidder1.publicKey.toBase58()])
+    expect(account.finalistBids.slice(0, 2).map(bid => bid.toNumber())).to.deep.equal([130, 120])
+  })
+
+  it('Finalists commit sealed bids', async () => {
+    await waitFor(OPEN_LENGTH)
+
+    for (const [bidder, amount, salt] of [[bidder1, 200, salt1], [bidder2, 150, salt2]] as const) {
+      const [offer, _bump] = await pda(bidSeed(state, bidder.publicKey))
+      await program.methods
+        .commitFinalBid(commitment(amount, salt))
+        .accounts({ state, offer, buyer: bidder.publicKey })
+        .signers([bidder])
+        .rpc()
+    }
+  })
+
+  it('Dont allow a dropped bidder to commit', async () => {
+    const [offer, _bump] = await pda(bidSeed(state, bidder3.publicKey))
+
+    try {
+      await program.methods
+        .commitFinalBid(commitment(500, salt1))
+        .accounts({ state, offer, buyer: bidder3.publicKey })
+        .signers([bidder3])
+        .rpc()
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('NotFinalist')
+    }
+  })
+
+  it('Highest reveal takes the lead', async () => {
+    await waitFor(OPEN_LENGTH + COMMIT_LENGTH)
+
+    const [offer, _bump] = await pda(bidSeed(state, bidder1.publicKey))
+    await program.methods
+      .revealFinalBid(new anchor.BN(200), Array.from(salt1))
+      .accounts({ state, treasury, offer, buyer: bidder1.publicKey })
+      .signers([bidder1])
+      .rpc()
+
+    const account = await program.account.state.fetch(state)
+    expect(account.maxBidder.toBase58()).to.equal(bidder1.publicKey.toBase58())
+    expect(account.maxPrice.toNumber()).to.equal(200)
+  })
+
+  it('Lower reveal does not take the lead', async () => {
+    const [offer, _bump] = await pda(bidSeed(state, bidder2.publicKey))
+
+    try {
+      await program.methods
+        .revealFinalBid(new anchor.BN(150), Array.from(salt2))
+        .accounts({ state, treasury, offer, buyer: bidder2.publicKey })
+        .signers([bidder2])
+        .rpc()
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('BidTooLow')
+    }
+  })
+
+  it('Close the hybrid auction', async () => {
+    await waitFor(OPEN_LENGTH + COMMIT_LENGTH + REVEAL_LENGTH)
+
+    const balanceBefore = await provider.connection.getBalance(initializer.publicKey)
+
+    await program.methods
+      .endAuction()
+      .accounts({
+        state,
+        treasury,
+        initializer: initializer.publicKey,
+        beneficiary: initializer.publicKey,
+        maxBidder: bidder1.publicKey,
+        page,
+        report,
+        result
+        protocol: await protocol(),
+      })
+      .signers([initializer])
+      .rpc()
+
+    // The seller pays for the report and the result out of the proceeds
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(8 + 136)
+      + await provider.connection.getMinimumBalanceForRentExemption(8 + 146)
+    const balanceAfter = await provider.connection.getBalance(initializer.publicKey)
+    expect(balanceAfter - balanceBefore).to.be.equal(200 - rent)
+  })
+})
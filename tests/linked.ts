@@ -0,0 +1,141 @@
+import { setTimeout as sleep } from 'timers/promises'
+import { assert, expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, linkSeed, reportSeed, resultSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 5
+const FIRST_SHARE_BPS = 6_000
+
+describe('linked auctions', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder = anchor.web3.Keypair.generate()
+
+  // States are derived once the auctions are created
+  const artwork = { state: anchor.web3.PublicKey.default, treasury: anchor.web3.PublicKey.default }
+  const frame = { state: anchor.web3.PublicKey.default, treasury: anchor.web3.PublicKey.default }
+  const pages = new Map<anchor.web3.PublicKey, anchor.web3.PublicKey>()
+
+  let link: anchor.web3.PublicKey
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  const bidAccounts = async (auction: typeof artwork) => ({
+    state: auction.state,
+    treasury: auction.treasury,
+    buyer: bidder.publicKey,
+    offer: (await pda(bidSeed(auction.state, bidder.publicKey)))[0],
+    entryFeeReceiver: initializer.publicKey,
+    stats: (await pda(statsSeed()))[0],
+    systemProgram: anchor.web3.SystemProgram.programId,
+  })
+
+  const finishAccounts = async (auction: typeof artwork) => ({
+    state: auction.state,
+    treasury: auction.treasury,
+    initializer: initializer.publicKey,
+    beneficiary: initializer.publicKey,
+    maxBidder: bidder.publicKey,
+    page: pages.get(auction.state),
+    report: (await pda(reportSeed(auction.state)))[0],
+    result: (await pda(resultSeed(auction.state)))[0],
+    protocol: await protocol(),
+    systemProgram: anchor.web3.SystemProgram.programId,
+  })
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder.publicKey)
+  })
+
+  it('Auctions of a paired lot are linked', async () => {
+    for (const auction of [artwork, frame]) {
+      const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+      auction.state = await nextAuction(initializer.publicKey)
+      auction.treasury = (await pda(treasurySeed(auction.state)))[0]
+      const accounts = await listing()
+      pages.set(auction.state, accounts.page)
+
+      await program.methods
+        .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+        .accounts({ initializer: initializer.publicKey, state: auction.state, treasury: auction.treasury, counter, ...accounts })
+        .signers([initializer])
+        .rpc()
+    }
+
+    link = (await pda(linkSeed(artwork.state, frame.state)))[0]
+    await program.methods
+      .linkAuctions(FIRST_SHARE_BPS)
+      .accounts({ link, first: artwork.state, second: frame.state, initializer: initializer.publicKey })
+      .signers([initializer])
+      .rpc()
+
+    const account = await program.account.state.fetch(artwork.state)
+    expect(account.linkedAuction.toBase58()).to.equal(frame.state.toBase58())
+  })
+
+  it('Linked bid is split between both auctions', async () => {
+    await program.methods
+      .bidLinked(new anchor.BN(1_000))
+      .accounts({ link, first: await bidAccounts(artwork), second: await bidAccounts(frame) })
+      .signers([bidder])
+      .rpc()
+
+    expect((await program.account.state.fetch(artwork.state)).maxPrice.toNumber()).to.equal(600)
+    expect((await program.account.state.fetch(frame.state)).maxPrice.toNumber()).to.equal(400)
+  })
+
+  it('Dont allow settling a pair with the same winner one by one', async () => {
+    await sleep((AUCTION_LENGTH + 1) * 1000)
+
+    await expectError(
+      program.methods
+        .endAuction()
+        .accounts(await finishAccounts(artwork))
+        .remainingAccounts([{ pubkey: frame.state, isSigner: false, isWritable: false }])
+        .signers([initializer])
+        .rpc(),
+      'LinkedSettlement'
+    )
+  })
+
+  it('Pair with the same winner is settled together', async () => {
+    const before = await provider.connection.getBalance(initializer.publicKey)
+
+    await program.methods
+      .settleLinked()
+      .accounts({ link, first: await finishAccounts(artwork), second: await finishAccounts(frame) })
+      .signers([initializer])
+      .rpc()
+
+    expect((await program.account.state.fetch(artwork.state)).status).to.have.property('settled')
+    expect((await program.account.state.fetch(frame.state)).status).to.have.property('settled')
+
+    // The seller got both prices and paid for the two reports and results
+    const rent = 2 * (await provider.connection.getMinimumBalanceForRentExemption(144)
+      + await provider.connection.getMinimumBalanceForRentExemption(154))
+    expect(await provider.connection.getBalance(initializer.publicKey)).to.equal(before + 1_000 - rent)
+  })
+})
@@ -0,0 +1,90 @@
+import { expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, bidSeed, counterSeed, statsSeed, nextAuctionFn, treasurySeed, notifySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+
+describe('notify', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder1 = anchor.web3.Keypair.generate()
+  const bidder2 = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey, notify: anchor.web3.PublicKey
+
+  const bid = async (bidder: anchor.web3.Keypair, amount: number) => program.methods
+    .bid(new anchor.BN(amount))
+    .accounts({
+      state,
+      treasury,
+      buyer: bidder.publicKey,
+      offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+      entryFeeReceiver: initializer.publicKey,
+      stats: (await pda(statsSeed()))[0],
+    })
+    .remainingAccounts([{ pubkey: notify, isSigner: false, isWritable: true }])
+    .signers([bidder])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder1.publicKey)
+    await airdrop(bidder2.publicKey)
+  })
+
+  it('Auction is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...await listing() })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Bidder subscribes to outbid notifications', async () => {
+    notify = (await pda(notifySeed(bidder1.publicKey)))[0]
+
+    await program.methods
+      .createNotify()
+      .accounts({ notify, bidder: bidder1.publicKey })
+      .signers([bidder1])
+      .rpc()
+  })
+
+  it('Notify account changes once the bidder is outbid', async () => {
+    await bid(bidder1, INITIAL_PRICE + 10)
+    expect((await program.account.notify.fetch(notify)).outbid.toNumber()).to.equal(0)
+
+    await bid(bidder2, INITIAL_PRICE + 20)
+
+    const account = await program.account.notify.fetch(notify)
+    expect(account.outbid.toNumber()).to.equal(1)
+    expect(account.auction.toBase58()).to.equal(state.toBase58())
+    expect(account.price.toNumber()).to.equal(INITIAL_PRICE + 20)
+  })
+
+  it('Notifications stop once the account is closed', async () => {
+    await program.methods
+      .closeNotify()
+      .accounts({ notify, bidder: bidder1.publicKey })
+      .signers([bidder1])
+      .rpc()
+
+    expect(await provider.connection.getAccountInfo(notify)).to.equal(null)
+  })
+})
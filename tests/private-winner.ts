@@ -0,0 +1,148 @@
+import { setTimeout as sleep } from 'timers/promises'
+import { assert, expect } from 'chai'
+import { keccak_256 } from 'js-sha3'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, reportSeed, resultSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 5
+
+const identity = (bidder: anchor.web3.PublicKey, salt: Buffer) =>
+  Array.from(Buffer.from(keccak_256.arrayBuffer(Buffer.concat([bidder.toBuffer(), salt]))))
+
+describe('private winner', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder1 = anchor.web3.Keypair.generate()
+  const bidder2 = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  const salt = Buffer.alloc(32, 7)
+
+  let page: anchor.web3.PublicKey, result: anchor.web3.PublicKey
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  const bid = async (bidder: anchor.web3.Keypair, amount: number) => program.methods
+    .bid(new anchor.BN(amount))
+    .accounts({
+      state,
+      treasury,
+      buyer: bidder.publicKey,
+      offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+      entryFeeReceiver: initializer.publicKey,
+      stats: (await pda(statsSeed()))[0],
+    })
+    .signers([bidder])
+    .rpc()
+
+  const reveal = (bidder: anchor.web3.Keypair, salt: Buffer) => program.methods
+    .revealWinner(Array.from(salt))
+    .accounts({ state, result, maxBidder: bidder.publicKey })
+    .signers([bidder])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder1.publicKey)
+    await airdrop(bidder2.publicKey)
+  })
+
+  it('Auction with a private winner is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing()
+    page = accounts.page
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    await program.methods
+      .setPrivateWinner(true)
+      .accounts({ state, initializer: initializer.publicKey })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Dont allow bidding without a concealed identity', async () => {
+    await expectError(bid(bidder1, INITIAL_PRICE + 10), 'IdentityRequired')
+  })
+
+  it('Bidders conceal their identities and bid', async () => {
+    for (const bidder of [bidder1, bidder2]) {
+      await program.methods
+        .concealIdentity(identity(bidder.publicKey, salt))
+        .accounts({ offer: (await pda(bidSeed(state, bidder.publicKey)))[0], state, buyer: bidder.publicKey })
+        .signers([bidder])
+        .rpc()
+    }
+
+    await bid(bidder1, INITIAL_PRICE + 10)
+    await bid(bidder2, INITIAL_PRICE + 20)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.winnerHash).to.deep.equal(identity(bidder2.publicKey, salt))
+  })
+
+  it('Settlement publishes only the concealed winner', async () => {
+    await sleep((AUCTION_LENGTH + 1) * 1000)
+
+    const [report, _reportBump] = await pda(reportSeed(state))
+    result = (await pda(resultSeed(state)))[0]
+    await program.methods
+      .endAuction()
+      .accounts({
+        state,
+        treasury,
+        initializer: initializer.publicKey,
+        beneficiary: initializer.publicKey,
+        maxBidder: bidder2.publicKey,
+        page,
+        report,
+        result,
+        protocol: await protocol(),
+      })
+      .signers([initializer])
+      .rpc()
+
+    const account = await program.account.auctionResult.fetch(result)
+    expect(account.winner.toBase58()).to.equal(anchor.web3.PublicKey.default.toBase58())
+  })
+
+  it('Dont allow revealing with a wrong salt', async () => {
+    await expectError(reveal(bidder2, Buffer.alloc(32, 8)), 'InvalidReveal')
+  })
+
+  it('Winner reveals themselves', async () => {
+    await reveal(bidder2, salt)
+
+    const account = await program.account.auctionResult.fetch(result)
+    expect(account.winner.toBase58()).to.equal(bidder2.publicKey.toBase58())
+  })
+})
@@ -0,0 +1,151 @@
+import { setTimeout as sleep } from 'timers/promises'
+import { assert, expect } from 'chai'
+import { keccak_256 } from 'js-sha3'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, reportSeed, resultSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const RESERVE_PRICE = 100
+const AUCTION_LENGTH = 5
+const REVEAL_LENGTH = 5
+const DEPOSIT = 1_000
+
+const commitment = (amount: number, salt: Buffer) =>
+  Array.from(Buffer.from(keccak_256.arrayBuffer(Buffer.concat([new anchor.BN(amount).toArrayLike(Buffer, 'le', 8), salt]))))
+
+describe('sealed-bid auction', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder1 = anchor.web3.Keypair.generate()
+  const bidder2 = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  const salt1 = Buffer.alloc(32, 1)
+  const salt2 = Buffer.alloc(32, 2)
+
+  let page: anchor.web3.PublicKey
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  const reveal = async (bidder: anchor.web3.Keypair, amount: number, salt: Buffer) => program.methods
+    .revealBid(new anchor.BN(amount), Array.from(salt))
+    .accounts({ state, offer: (await pda(bidSeed(state, bidder.publicKey)))[0], buyer: bidder.publicKey })
+    .signers([bidder])
+    .rpc()
+
+  const refund = async (bidder: anchor.web3.Keypair) => program.methods
+    .refund()
+    .accounts({
+      state,
+      treasury,
+      buyer: bidder.publicKey,
+      offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+    })
+    .signers([bidder])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder1.publicKey)
+    await airdrop(bidder2.publicKey)
+  })
+
+  it('Sealed auction is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing()
+    page = accounts.page
+
+    await program.methods
+      .initializeSealed(new anchor.BN(AUCTION_LENGTH), new anchor.BN(RESERVE_PRICE), new anchor.BN(REVEAL_LENGTH))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Bidders commit with deposits', async () => {
+    for (const [bidder, amount, salt] of [[bidder1, 300, salt1], [bidder2, 200, salt2]] as const) {
+      const [offer, _bump] = await pda(bidSeed(state, bidder.publicKey))
+      await program.methods
+        .commitBid(commitment(amount, salt), new anchor.BN(DEPOSIT))
+        .accounts({ state, treasury, buyer: bidder.publicKey, offer, entryFeeReceiver: initializer.publicKey, stats: (await pda(statsSeed()))[0] })
+        .signers([bidder])
+        .rpc()
+    }
+
+    // Nothing about the bids is known before the reveal
+    const account = await program.account.state.fetch(state)
+    expect(account.maxPrice.toNumber()).to.equal(RESERVE_PRICE)
+  })
+
+  it('Dont allow revealing before the end of the commit phase', async () => {
+    await expectError(reveal(bidder1, 300, salt1), 'Open')
+  })
+
+  it('Dont allow revealing a different bid', async () => {
+    await sleep((AUCTION_LENGTH + 1) * 1000)
+
+    await expectError(reveal(bidder1, 301, salt1), 'InvalidReveal')
+  })
+
+  it('Highest revealed bid wins', async () => {
+    await reveal(bidder2, 200, salt2)
+    await reveal(bidder1, 300, salt1)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.maxPrice.toNumber()).to.equal(300)
+    expect(account.maxBidder.toBase58()).to.equal(bidder1.publicKey.toBase58())
+  })
+
+  it('Winner pays the revealed bid and gets the rest of the deposit back', async () => {
+    await sleep((REVEAL_LENGTH + 1) * 1000)
+
+    const [report, _reportBump] = await pda(reportSeed(state))
+    const [result, _resultBump] = await pda(resultSeed(state))
+    await program.methods
+      .endAuction()
+      .accounts({
+        state,
+        treasury,
+        initializer: initializer.publicKey,
+        beneficiary: initializer.publicKey,
+        maxBidder: bidder1.publicKey,
+        page,
+        report,
+        result,
+        protocol: await protocol(),
+      })
+      .signers([initializer])
+      .rpc()
+
+    await refund(bidder1)
+    await refund(bidder2)
+
+    // Only the winning bid stays with the seller
+    expect(await provider.connection.getBalance(treasury)).to.equal(
+      await provider.connection.getMinimumBalanceForRentExemption(0)
+    )
+  })
+})
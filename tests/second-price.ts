@@ -0,0 +1,120 @@
+import { setTimeout as sleep } from 'timers/promises'
+import { expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, reportSeed, resultSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 5
+
+describe('second-price auction', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder1 = anchor.web3.Keypair.generate()
+  const bidder2 = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  let page: anchor.web3.PublicKey
+
+  const bid = async (bidder: anchor.web3.Keypair, amount: number) => program.methods
+    .bid(new anchor.BN(amount))
+    .accounts({
+      state,
+      treasury,
+      buyer: bidder.publicKey,
+      offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+      entryFeeReceiver: initializer.publicKey,
+      stats: (await pda(statsSeed()))[0],
+    })
+    .signers([bidder])
+    .rpc()
+
+  const refund = async (bidder: anchor.web3.Keypair) => program.methods
+    .refund()
+    .accounts({
+      state,
+      treasury,
+      buyer: bidder.publicKey,
+      offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+    })
+    .signers([bidder])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder1.publicKey)
+    await airdrop(bidder2.publicKey)
+  })
+
+  it('Second-price auction is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing()
+    page = accounts.page
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    await program.methods
+      .setSecondPrice(true)
+      .accounts({ state, initializer: initializer.publicKey })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Runner-up bid is tracked', async () => {
+    await bid(bidder1, 200)
+    await bid(bidder2, 500)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.maxPrice.toNumber()).to.equal(500)
+    expect(account.runnerUpPrice.toNumber()).to.equal(200)
+  })
+
+  it('Winner pays the second highest bid and gets the difference back', async () => {
+    await sleep((AUCTION_LENGTH + 1) * 1000)
+
+    const [report, _reportBump] = await pda(reportSeed(state))
+    const [result, _resultBump] = await pda(resultSeed(state))
+    await program.methods
+      .endAuction()
+      .accounts({
+        state,
+        treasury,
+        initializer: initializer.publicKey,
+        beneficiary: initializer.publicKey,
+        maxBidder: bidder2.publicKey,
+        page,
+        report,
+        result,
+        protocol: await protocol(),
+      })
+      .signers([initializer])
+      .rpc()
+
+    // The seller took the runner-up price out of the two escrowed bids
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(0)
+    expect(await provider.connection.getBalance(treasury)).to.equal(rent + 200 + 500 - 200)
+
+    await refund(bidder1)
+    await refund(bidder2)
+
+    expect(await provider.connection.getBalance(treasury)).to.equal(rent)
+  })
+})
@@ -0,0 +1,81 @@
+import { setTimeout as sleep } from 'timers/promises'
+import { expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, counterSeed, seriesSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const FLOOR_PRICE = 85
+const DECAY_BPS = 1_000
+const AUCTION_LENGTH = 2
+
+describe('auction series', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  let first: anchor.web3.PublicKey, series: anchor.web3.PublicKey
+
+  const relist = async (lastAuction: anchor.web3.PublicKey) => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    const state = await nextAuction(initializer.publicKey)
+    const [treasury, _treasuryBump] = await pda(treasurySeed(state))
+
+    await program.methods
+      .relist(new anchor.BN(AUCTION_LENGTH))
+      .accounts({
+        series,
+        lastAuction,
+        auction: { initializer: initializer.publicKey, state, treasury, counter, ...await listing() },
+      })
+      .signers([initializer])
+      .rpc()
+
+    return state
+  }
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+  })
+
+  it('Series starts with an auction', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    first = await nextAuction(initializer.publicKey)
+    const [treasury, _treasuryBump] = await pda(treasurySeed(first))
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state: first, treasury, counter, ...await listing() })
+      .signers([initializer])
+      .rpc()
+
+    series = (await pda(seriesSeed(first)))[0]
+    await program.methods
+      .createSeries(new anchor.BN(FLOOR_PRICE), DECAY_BPS)
+      .accounts({ series, state: first, initializer: initializer.publicKey })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Failed rounds lower the price down to the floor', async () => {
+    await sleep((AUCTION_LENGTH + 1) * 1000)
+    const second = await relist(first)
+    expect((await program.account.state.fetch(second)).initialPrice.toNumber()).to.equal(90)
+
+    await sleep((AUCTION_LENGTH + 1) * 1000)
+    const third = await relist(second)
+    expect((await program.account.state.fetch(third)).initialPrice.toNumber()).to.equal(FLOOR_PRICE)
+
+    const account = await program.account.series.fetch(series)
+    expect(account.rounds).to.equal(3)
+    expect(account.lastAuction.toBase58()).to.equal(third.toBase58())
+  })
+})
@@ -0,0 +1,106 @@
+import { setTimeout as sleep } from 'timers/promises'
+import { expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, reportSeed, resultSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 5
+
+describe('settle-race', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey, page: anchor.web3.PublicKey
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder.publicKey)
+  })
+
+  it('Auction ends with a bid', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing()
+    page = accounts.page
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    await program.methods
+      .bid(new anchor.BN(INITIAL_PRICE + 10))
+      .accounts({
+        state,
+        treasury,
+        buyer: bidder.publicKey,
+        offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+        entryFeeReceiver: initializer.publicKey,
+        stats: (await pda(statsSeed()))[0],
+      })
+      .signers([bidder])
+      .rpc()
+
+    await sleep((AUCTION_LENGTH + 2) * 1000)
+  })
+
+  it('Only one of two closes sent together settles', async () => {
+    const treasuryBefore = await provider.connection.getBalance(treasury)
+
+    // Two distinct transactions, both sent before either is confirmed
+    const { blockhash } = await provider.connection.getRecentBlockhash()
+    const close = async (lamports: number) => {
+      const tx = await program.methods
+        .endAuction()
+        .accounts({
+          state,
+          treasury,
+          initializer: initializer.publicKey,
+          beneficiary: initializer.publicKey,
+          maxBidder: bidder.publicKey,
+          page,
+          report: (await pda(reportSeed(state)))[0],
+          result: (await pda(resultSeed(state)))[0],
+          protocol: await protocol(),
+        })
+        .preInstructions([anchor.web3.SystemProgram.transfer({
+          fromPubkey: initializer.publicKey,
+          toPubkey: initializer.publicKey,
+          lamports
+        })])
+        .transaction()
+      tx.feePayer = initializer.publicKey
+      tx.recentBlockhash = blockhash
+      tx.sign(initializer)
+
+      return tx.serialize()
+    }
+    const transactions = [await close(0), await close(1)]
+    const signatures = await Promise.all(transactions.map(tx => provider.connection.sendRawTransaction(tx, { skipPreflight: true })))
+
+    const confirmations = await Promise.all(signatures.map(signature => provider.connection.confirmTransaction(signature, 'confirmed')))
+    expect(confirmations.filter(confirmation => confirmation.value.err === null)).to.have.length(1)
+
+    // The winning bid left the treasury once
+    const treasuryAfter = await provider.connection.getBalance(treasury)
+    expect(treasuryBefore - treasuryAfter).to.be.equal(INITIAL_PRICE + 10)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.status).to.have.property('settled')
+  })
+})
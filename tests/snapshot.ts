@@ -0,0 +1,105 @@
+import { assert, expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, bidSeed, counterSeed, snapshotSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+
+describe('participation snapshot', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder1 = anchor.web3.Keypair.generate()
+  const bidder2 = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  const bid = async (bidder: anchor.web3.Keypair, amount: number) => program.methods
+    .bid(new anchor.BN(amount))
+    .accounts({
+      state,
+      treasury,
+      buyer: bidder.publicKey,
+      offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+      entryFeeReceiver: initializer.publicKey,
+      stats: (await pda(statsSeed()))[0],
+    })
+    .signers([bidder])
+    .rpc()
+
+  const snapshot = async (bidder: anchor.web3.Keypair) => {
+    const [snapshot, _bump] = await pda(snapshotSeed(state, bidder.publicKey))
+
+    await program.methods
+      .snapshotParticipation()
+      .accounts({
+        snapshot,
+        state,
+        offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+        bidder: bidder.publicKey,
+      })
+      .signers([bidder])
+      .rpc()
+
+    return snapshot
+  }
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder1.publicKey)
+    await airdrop(bidder2.publicKey)
+  })
+
+  it('Auction is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...await listing() })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Snapshot keeps the peak escrow of the bidder', async () => {
+    await bid(bidder1, INITIAL_PRICE + 10)
+    await snapshot(bidder1)
+
+    await bid(bidder2, INITIAL_PRICE + 20)
+    await bid(bidder1, INITIAL_PRICE + 30)
+    const account = await program.account.participationSnapshot.fetch(await snapshot(bidder1))
+
+    const auction = await program.account.state.fetch(state)
+    expect(account.bidder.toBase58()).to.equal(bidder1.publicKey.toBase58())
+    expect(account.peakEscrow.toNumber()).to.equal(INITIAL_PRICE + 30)
+    expect(account.closesAt.toNumber()).to.equal(auction.endTime.toNumber())
+  })
+
+  it('Dont allow closing the snapshot within the retention period', async () => {
+    const [snapshot, _bump] = await pda(snapshotSeed(state, bidder1.publicKey))
+
+    try {
+      await program.methods
+        .closeSnapshot()
+        .accounts({ snapshot, bidder: bidder1.publicKey })
+        .signers([bidder1])
+        .rpc()
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('SnapshotRetained')
+    }
+  })
+})
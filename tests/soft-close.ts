@@ -0,0 +1,120 @@
+import { expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, bidSeed, counterSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+const EXTENSION = 30
+const MAX_EXTENSION = 45
+const MAX_EXTENSIONS = 1
+
+describe('soft close', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder1 = anchor.web3.Keypair.generate()
+  const bidder2 = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+  let endTime: number
+
+  const create = async (maxExtensions: number, latestEndTime: number) => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...await listing() })
+      .signers([initializer])
+      .rpc()
+
+    // The whole auction stays within the soft close window even once extended, every bid extends it
+    await program.methods
+      .setSoftClose(
+        new anchor.BN(AUCTION_LENGTH + MAX_EXTENSION),
+        new anchor.BN(EXTENSION),
+        new anchor.BN(MAX_EXTENSION),
+        maxExtensions,
+        new anchor.BN(latestEndTime)
+      )
+      .accounts({ state, initializer: initializer.publicKey })
+      .signers([initializer])
+      .rpc()
+
+    endTime = (await program.account.state.fetch(state)).endTime.toNumber()
+  }
+
+  const bid = async (bidder: anchor.web3.Keypair, amount: number) => program.methods
+    .bid(new anchor.BN(amount))
+    .accounts({
+      state,
+      treasury,
+      buyer: bidder.publicKey,
+      offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+      entryFeeReceiver: initializer.publicKey,
+      stats: (await pda(statsSeed()))[0],
+    })
+    .signers([bidder])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder1.publicKey)
+    await airdrop(bidder2.publicKey)
+  })
+
+  it('Auction with a soft close is initialized', async () => {
+    await create(0, 0)
+  })
+
+  it('Bid in the final seconds pushes the end', async () => {
+    await bid(bidder1, INITIAL_PRICE + 10)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.endTime.toNumber()).to.equal(endTime + EXTENSION)
+    expect(account.extended.toNumber()).to.equal(EXTENSION)
+    expect(account.status).to.have.property('extended')
+  })
+
+  it('End is pushed by at most the maximum extension', async () => {
+    await bid(bidder2, INITIAL_PRICE + 20)
+    await bid(bidder1, INITIAL_PRICE + 30)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.endTime.toNumber()).to.equal(endTime + MAX_EXTENSION)
+    expect(account.extended.toNumber()).to.equal(MAX_EXTENSION)
+  })
+
+  it('End is pushed at most the maximum number of times', async () => {
+    await create(MAX_EXTENSIONS, 0)
+
+    await bid(bidder1, INITIAL_PRICE + 10)
+    await bid(bidder2, INITIAL_PRICE + 20)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.endTime.toNumber()).to.equal(endTime + EXTENSION)
+    expect(account.extensions).to.equal(MAX_EXTENSIONS)
+  })
+
+  it('End is never pushed past the latest end time', async () => {
+    await create(0, Math.floor(Date.now() / 1000) + AUCTION_LENGTH + 20)
+    const latestEndTime = (await program.account.state.fetch(state)).latestEndTime.toNumber()
+
+    await bid(bidder1, INITIAL_PRICE + 10)
+    await bid(bidder2, INITIAL_PRICE + 20)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.endTime.toNumber()).to.equal(latestEndTime)
+  })
+})
@@ -0,0 +1,154 @@
+import chai, { assert, expect } from 'chai'
+import chaiAsPromised from 'chai-as-promised';
+import { setTimeout as sleep } from 'timers/promises'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, protocolFn, bidSeed, counterSeed, reportSeed, resultSeed, sponsorSeed, watchSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+chai.use(chaiAsPromised)
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 5
+
+describe('sponsored refunds', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const protocol = protocolFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const marketplace = anchor.web3.Keypair.generate()
+  const sponsor = anchor.web3.Keypair.generate()
+  const stranger = anchor.web3.Keypair.generate()
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidderLow = anchor.web3.Keypair.generate()
+  const bidderHigh = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  let page: anchor.web3.PublicKey
+
+  const bid = async (bidder: anchor.web3.Keypair, amount: number) => {
+    const [offer, _bump] = await pda(bidSeed(state, bidder.publicKey))
+
+    await program.methods
+      .bid(new anchor.BN(amount))
+      .accounts({ state, treasury, buyer: bidder.publicKey, offer, entryFeeReceiver: initializer.publicKey, stats: (await pda(statsSeed()))[0] })
+      .signers([bidder])
+      .rpc()
+  }
+
+  const refundFor = async (signer: anchor.web3.Keypair) => {
+    const [offer, _offerBump] = await pda(bidSeed(state, bidderLow.publicKey))
+    const [sponsorship, _bump] = await pda(sponsorSeed(signer.publicKey, marketplace.publicKey))
+
+    await program.methods
+      .refundFor()
+      .accounts({ state, treasury, bidder: bidderLow.publicKey, offer, sponsorship, sponsor: signer.publicKey })
+      .signers([signer])
+      .rpc()
+  }
+
+  it('Airdropped to humans', async () => {
+    await airdrop(marketplace.publicKey)
+    await airdrop(sponsor.publicKey)
+    await airdrop(stranger.publicKey)
+    await airdrop(initializer.publicKey)
+    await airdrop(bidderLow.publicKey)
+    await airdrop(bidderHigh.publicKey)
+  })
+
+  it('Sponsor is registered', async () => {
+    const [sponsorship, _bump] = await pda(sponsorSeed(sponsor.publicKey, marketplace.publicKey))
+
+    await program.methods
+      .registerSponsor()
+      .accounts({ sponsorship, namespace: marketplace.publicKey, sponsor: sponsor.publicKey })
+      .signers([marketplace])
+      .rpc()
+  })
+
+  it('Auction is settled', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey, marketplace.publicKey))
+    state = await nextAuction(initializer.publicKey, marketplace.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+    const accounts = await listing(marketplace.publicKey)
+    page = accounts.page
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...accounts })
+      .signers([initializer])
+      .rpc()
+
+    await bid(bidderLow, INITIAL_PRICE + 10)
+    await bid(bidderHigh, INITIAL_PRICE + 20)
+
+    // The losing bidder follows the auction to get notified about the settlement
+    const [watch, _watchBump] = await pda(watchSeed(state, bidderLow.publicKey))
+    await program.methods
+      .createWatch()
+      .accounts({ watch, state, user: bidderLow.publicKey })
+      .signers([bidderLow])
+      .rpc()
+
+    await sleep((AUCTION_LENGTH + 1) * 1000)
+
+    const [report, _reportBump] = await pda(reportSeed(state))
+    const [result, _resultBump] = await pda(resultSeed(state))
+    await program.methods
+      .endAuction()
+      .accounts({ state, treasury, initializer: initializer.publicKey, beneficiary: initializer.publicKey, maxBidder: bidderHigh.publicKey, page, report, result, protocol: await protocol() })
+      .remainingAccounts([{ pubkey: watch, isSigner: false, isWritable: true }])
+      .signers([initializer])
+      .rpc()
+
+    expect((await program.account.watch.fetch(watch)).updates.toNumber()).to.equal(1)
+
+    await program.methods
+      .closeWatch()
+      .accounts({ watch, user: bidderLow.publicKey })
+      .signers([bidderLow])
+      .rpc()
+  })
+
+  it('Dont allow an unregistered sponsor to refund', async () => {
+    try {
+      await refundFor(stranger)
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('AccountNotInitialized')
+    }
+  })
+
+  it('Sponsor refunds the bidder', async () => {
+    const before = await provider.connection.getBalance(bidderLow.publicKey)
+
+    let listener: number
+    const event = new Promise<any>(resolve => {
+      listener = program.addEventListener('RefundSponsored', resolve)
+    })
+
+    await refundFor(sponsor)
+
+    const summary = await event
+    await program.removeEventListener(listener)
+
+    expect(summary.bidder.toBase58()).to.equal(bidderLow.publicKey.toBase58())
+    expect(summary.sponsor.toBase58()).to.equal(sponsor.publicKey.toBase58())
+    expect(summary.amount.toNumber()).to.equal(INITIAL_PRICE + 10)
+
+    // The bidder did not pay any fee and also got the rent of the offer back
+    const after = await provider.connection.getBalance(bidderLow.publicKey)
+    assert(after > before + INITIAL_PRICE + 10)
+  })
+})
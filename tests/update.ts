@@ -0,0 +1,90 @@
+import { assert, expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, bidSeed, counterSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+
+describe('update', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  const update = (auctionDuration: number, initialPrice: number, buyNowPrice: number) => program.methods
+    .updateAuction(new anchor.BN(auctionDuration), new anchor.BN(initialPrice), new anchor.BN(buyNowPrice))
+    .accounts({ state, initializer: initializer.publicKey })
+    .signers([initializer])
+    .rpc()
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder.publicKey)
+  })
+
+  it('Auction is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...await listing() })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Parameters change before the first bid', async () => {
+    await update(2 * AUCTION_LENGTH, 2 * INITIAL_PRICE, 10 * INITIAL_PRICE)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.endTime.toNumber()).to.equal(account.startTime.toNumber() + 2 * AUCTION_LENGTH)
+    expect(account.initialPrice.toNumber()).to.equal(2 * INITIAL_PRICE)
+    expect(account.maxPrice.toNumber()).to.equal(2 * INITIAL_PRICE)
+    expect(account.buyNowPrice.toNumber()).to.equal(10 * INITIAL_PRICE)
+  })
+
+  it('Dont allow an end in the past', async () => {
+    await expectError(update(0, INITIAL_PRICE, 0), 'InvalidOperation')
+  })
+
+  it('Dont allow changes once anybody bid', async () => {
+    await program.methods
+      .bid(new anchor.BN(2 * INITIAL_PRICE + 10))
+      .accounts({
+        state,
+        treasury,
+        buyer: bidder.publicKey,
+        offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+        entryFeeReceiver: initializer.publicKey,
+        stats: (await pda(statsSeed()))[0],
+      })
+      .signers([bidder])
+      .rpc()
+
+    await expectError(update(AUCTION_LENGTH, INITIAL_PRICE, 0), 'AlreadyBid')
+  })
+})
@@ -22,6 +22,138 @@ export const bidSeed = (
   bidderPubKey: anchor.web3.PublicKey
 ) => [strToUInt8Array('bid'), statePubKey.toBytes(), bidderPubKey.toBytes()]
 
+// Marketplace the test auctions are created in
+export const NAMESPACE = anchor.web3.PublicKey.default
+
+export const counterSeed = (
+  initializerPubKey: anchor.web3.PublicKey,
+  namespace: anchor.web3.PublicKey = NAMESPACE
+) => [strToUInt8Array('counter'), namespace.toBytes(), initializerPubKey.toBytes()]
+
+export const auctionSeed = (
+  initializerPubKey: anchor.web3.PublicKey,
+  auctionId: anchor.BN,
+  namespace: anchor.web3.PublicKey = NAMESPACE
+) => [strToUInt8Array('auction'), namespace.toBytes(), initializerPubKey.toBytes(), auctionId.toArrayLike(Buffer, 'le', 8)]
+
+// State of the next auction the seller creates
+export const nextAuctionFn = (
+  program: anchor.Program<any>
+) => async (
+  initializerPubKey: anchor.web3.PublicKey,
+  namespace: anchor.web3.PublicKey = NAMESPACE
+) => {
+  const pda = pdaFn(program.programId)
+  const [counter, _counterBump] = await pda(counterSeed(initializerPubKey, namespace))
+  const account = await program.account.counter.fetchNullable(counter)
+  const [state, _stateBump] = await pda(auctionSeed(initializerPubKey, account ? (account as any).nextId : new anchor.BN(0), namespace))
+
+  return state
+}
+
+export const treasurySeed = (
+  statePubKey: anchor.web3.PublicKey
+) => [strToUInt8Array('treasury'), statePubKey.toBytes()]
+
+export const registrySeed = (
+  namespace: anchor.web3.PublicKey = NAMESPACE
+) => [strToUInt8Array('registry'), namespace.toBytes()]
+
+export const pageSeed = (
+  index: number,
+  namespace: anchor.web3.PublicKey = NAMESPACE
+) => [strToUInt8Array('page'), namespace.toBytes(), new anchor.BN(index).toArrayLike(Buffer, 'le', 4)]
+
+// Registry accounts a new auction gets listed in
+export const listingFn = (
+  program: anchor.Program<any>
+) => async (
+  namespace: anchor.web3.PublicKey = NAMESPACE
+) => {
+  const pda = pdaFn(program.programId)
+  const [registry, _registryBump] = await pda(registrySeed(namespace))
+  const account = await program.account.registry.fetchNullable(registry)
+  const [page, _pageBump] = await pda(pageSeed(account ? (account as any).tail : 0, namespace))
+  const [stats, _statsBump] = await pda(statsSeed())
+  const [global, _globalBump] = await pda(globalConfigSeed())
+  const [marketConfig, _marketConfigBump] = await pda(configSeed(namespace))
+
+  return { registry, page, namespace, stats, global, marketConfig }
+}
+
+export const reportSeed = (
+  statePubKey: anchor.web3.PublicKey
+) => [strToUInt8Array('report'), statePubKey.toBytes()]
+
+export const resultSeed = (
+  statePubKey: anchor.web3.PublicKey
+) => [strToUInt8Array('result'), statePubKey.toBytes()]
+
+export const protocolSeed = (
+  namespace: anchor.web3.PublicKey = NAMESPACE
+) => [strToUInt8Array('protocol'), namespace.toBytes()]
+
+export const configSeed = (
+  namespace: anchor.web3.PublicKey = NAMESPACE
+) => [strToUInt8Array('config'), namespace.toBytes()]
+
+export const watchSeed = (
+  statePubKey: anchor.web3.PublicKey,
+  user: anchor.web3.PublicKey
+) => [strToUInt8Array('watch'), statePubKey.toBytes(), user.toBytes()]
+
+export const seriesSeed = (
+  firstAuction: anchor.web3.PublicKey
+) => [strToUInt8Array('series'), firstAuction.toBytes()]
+
+export const linkSeed = (
+  first: anchor.web3.PublicKey,
+  second: anchor.web3.PublicKey
+) => [strToUInt8Array('link'), first.toBytes(), second.toBytes()]
+
+export const statsSeed = () => [strToUInt8Array('stats')]
+
+export const globalConfigSeed = () => [strToUInt8Array('global')]
+
+export const feeVaultSeed = () => [strToUInt8Array('fees')]
+
+// Program-wide config and fee vault every sale pays the protocol fee into
+export const protocolFn = (
+  program: anchor.Program<any>
+) => async () => {
+  const pda = pdaFn(program.programId)
+  const [config, _configBump] = await pda(globalConfigSeed())
+  const [feeVault, _feeVaultBump] = await pda(feeVaultSeed())
+
+  return { config, feeVault }
+}
+
+export const snapshotSeed = (
+  statePubKey: anchor.web3.PublicKey,
+  bidder: anchor.web3.PublicKey
+) => [strToUInt8Array('snapshot'), statePubKey.toBytes(), bidder.toBytes()]
+
+export const notifySeed = (
+  bidder: anchor.web3.PublicKey
+) => [strToUInt8Array('notify'), bidder.toBytes()]
+
+export const sponsorSeed = (
+  sponsor: anchor.web3.PublicKey,
+  namespace: anchor.web3.PublicKey = NAMESPACE
+) => [strToUInt8Array('sponsor'), namespace.toBytes(), sponsor.toBytes()]
+
+export const creditSeed = (
+  maker: anchor.web3.PublicKey,
+  namespace: anchor.web3.PublicKey = NAMESPACE
+) => [strToUInt8Array('credit'), namespace.toBytes(), maker.toBytes()]
+
+export const creditBidSeed = (
+  statePubKey: anchor.web3.PublicKey,
+  maker: anchor.web3.PublicKey
+) => [strToUInt8Array('credit_bid'), statePubKey.toBytes(), maker.toBytes()]
+
+export const eventAuthoritySeed = () => [strToUInt8Array('__event_authority')]
+
 export const speedCheck = (startTime: Date, duration: number, warningDelta: number = 3000) => {
   const endTime = (+startTime) + (duration * 1000)
   const now = +new Date()
@@ -30,3 +162,24 @@ export const speedCheck = (startTime: Date, duration: number, warningDelta: numb
   if (endTime - warningDelta < now)
     console.warn(`Time is running out! You have less than ${warningDelta} seconds before the end!`)
 }
+
+// Every auction is created against the program-wide config, which the wallet deploying the program creates once,
+// without any limit or fee, before the first test
+before(async () => {
+  const program = anchor.workspace.Auction as anchor.Program<any>
+  const provider = anchor.getProvider()
+  const pda = pdaFn(program.programId)
+  const [config, _configBump] = await pda(globalConfigSeed())
+  if (await program.account.globalConfig.fetchNullable(config))
+    return
+
+  const [feeVault, _feeVaultBump] = await pda(feeVaultSeed())
+  const [programData, _programDataBump] = await anchor.web3.PublicKey.findProgramAddress(
+    [program.programId.toBuffer()],
+    new anchor.web3.PublicKey('BPFLoaderUpgradeab1e11111111111111111111111')
+  )
+  await program.methods
+    .initializeConfig(provider.wallet.publicKey, new anchor.BN(0), new anchor.BN(0), 0)
+    .accounts({ config, feeVault, program: program.programId, programData, authority: provider.wallet.publicKey })
+    .rpc()
+})
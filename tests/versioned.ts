@@ -0,0 +1,199 @@
+import { setTimeout as sleep } from 'timers/promises'
+import { assert, expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, bidSeed, counterSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+const ENTRY_FEE = 1_000
+const LOT_ID = 4_242
+
+describe('versioned arguments', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder = anchor.web3.Keypair.generate()
+
+  const create = async (args: any) => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    const state = await nextAuction(initializer.publicKey)
+    const [treasury, _treasuryBump] = await pda(treasurySeed(state))
+
+    await program.methods
+      .createAuction(args)
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...await listing() })
+      .signers([initializer])
+      .rpc()
+
+    return { state, treasury }
+  }
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder.publicKey)
+  })
+
+  it('Version 1 gets the defaults of the later parameters', async () => {
+    const { state, treasury } = await create({
+      v1: { auctionDuration: new anchor.BN(AUCTION_LENGTH), initialPrice: new anchor.BN(INITIAL_PRICE) },
+    })
+
+    const account = await program.account.state.fetch(state)
+    expect(account.initialPrice.toNumber()).to.equal(INITIAL_PRICE)
+    expect(account.maxBidders).to.equal(0)
+    expect(account.entryFee.toNumber()).to.equal(0)
+    expect(account.beneficiary.toBase58()).to.equal(initializer.publicKey.toBase58())
+
+    const [offer, _bump] = await pda(bidSeed(state, bidder.publicKey))
+    await program.methods
+      .placeBid({ v1: { amount: new anchor.BN(INITIAL_PRICE + 10) } })
+      .accounts({ state, treasury, buyer: bidder.publicKey, offer, entryFeeReceiver: initializer.publicKey, stats: (await pda(statsSeed()))[0] })
+      .signers([bidder])
+      .rpc()
+
+    expect((await program.account.state.fetch(state)).maxPrice.toNumber()).to.equal(INITIAL_PRICE + 10)
+  })
+
+  it('Dont allow a bid landing after its maximum valid time', async () => {
+    const { state, treasury } = await create({
+      v1: { auctionDuration: new anchor.BN(AUCTION_LENGTH), initialPrice: new anchor.BN(INITIAL_PRICE) },
+    })
+    const [offer, _bump] = await pda(bidSeed(state, bidder.publicKey))
+    const placeBid = (maxValidTime: number) => program.methods
+      .placeBid({ v2: { amount: new anchor.BN(INITIAL_PRICE + 10), maxValidTime: new anchor.BN(maxValidTime) } })
+      .accounts({ state, treasury, buyer: bidder.publicKey, offer, entryFeeReceiver: initializer.publicKey, stats: (await pda(statsSeed()))[0] })
+      .signers([bidder])
+      .rpc()
+
+    try {
+      await placeBid(Math.floor(Date.now() / 1000) - 30)
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('BidExpired')
+    }
+
+    await placeBid(Math.floor(Date.now() / 1000) + 30)
+  })
+
+  it('Version 2 sets the bidder limit and the entry fee', async () => {
+    const { state } = await create({
+      v2: {
+        auctionDuration: new anchor.BN(AUCTION_LENGTH),
+        initialPrice: new anchor.BN(INITIAL_PRICE),
+        maxBidders: 3,
+        entryFee: new anchor.BN(ENTRY_FEE),
+      },
+    })
+
+    const account = await program.account.state.fetch(state)
+    expect(account.maxBidders).to.equal(3)
+    expect(account.entryFee.toNumber()).to.equal(ENTRY_FEE)
+    expect(account.entryFeeReceiver.toBase58()).to.equal(initializer.publicKey.toBase58())
+  })
+  it('Version 3 sends the proceeds to a beneficiary', async () => {
+    const beneficiary = anchor.web3.Keypair.generate()
+    const { state } = await create({
+      v3: {
+        auctionDuration: new anchor.BN(AUCTION_LENGTH),
+        initialPrice: new anchor.BN(INITIAL_PRICE),
+        maxBidders: 0,
+        entryFee: new anchor.BN(0),
+        beneficiary: beneficiary.publicKey,
+      },
+    })
+
+    const account = await program.account.state.fetch(state)
+    expect(account.beneficiary.toBase58()).to.equal(beneficiary.publicKey.toBase58())
+    expect(account.initializer.toBase58()).to.equal(initializer.publicKey.toBase58())
+  })
+  it('Version 4 carries the lot id of the seller', async () => {
+    const { state } = await create({
+      v4: {
+        auctionDuration: new anchor.BN(AUCTION_LENGTH),
+        initialPrice: new anchor.BN(INITIAL_PRICE),
+        maxBidders: 0,
+        entryFee: new anchor.BN(0),
+        beneficiary: initializer.publicKey,
+        lotId: new anchor.BN(LOT_ID),
+      },
+    })
+
+    const account = await program.account.state.fetch(state)
+    expect(account.lotId.toNumber()).to.equal(LOT_ID)
+  })
+  it('Version 6 schedules the start of bidding', async () => {
+    const startTime = Math.floor(Date.now() / 1000) + 3600
+    const { state, treasury } = await create({
+      v6: {
+        auctionDuration: new anchor.BN(AUCTION_LENGTH),
+        initialPrice: new anchor.BN(INITIAL_PRICE),
+        maxBidders: 0,
+        entryFee: new anchor.BN(0),
+        beneficiary: initializer.publicKey,
+        lotId: new anchor.BN(0),
+        buyNowPrice: new anchor.BN(0),
+        startTime: new anchor.BN(startTime),
+      },
+    })
+
+    const account = await program.account.state.fetch(state)
+    expect(account.startTime.toNumber()).to.equal(startTime)
+    expect(account.endTime.toNumber()).to.equal(startTime + AUCTION_LENGTH)
+
+    const [offer, _bump] = await pda(bidSeed(state, bidder.publicKey))
+    try {
+      await program.methods
+        .bid(new anchor.BN(INITIAL_PRICE + 10))
+        .accounts({ state, treasury, buyer: bidder.publicKey, offer, entryFeeReceiver: initializer.publicKey, stats: (await pda(statsSeed()))[0] })
+        .signers([bidder])
+        .rpc()
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal('NotStarted')
+    }
+  })
+  it('Version 7 announces the category and the tags', async () => {
+    let created: any
+    const listener = program.addEventListener('AuctionCreated', (event) => created = event)
+
+    const { state } = await create({
+      v7: {
+        auctionDuration: new anchor.BN(AUCTION_LENGTH),
+        initialPrice: new anchor.BN(INITIAL_PRICE),
+        maxBidders: 0,
+        entryFee: new anchor.BN(0),
+        beneficiary: initializer.publicKey,
+        lotId: new anchor.BN(0),
+        buyNowPrice: new anchor.BN(0),
+        startTime: null,
+        category: { gaming: {} },
+        tags: new anchor.BN(0b101),
+      },
+    })
+
+    await sleep(1000)
+    await program.removeEventListener(listener)
+
+    expect(created.auction.toBase58()).to.equal(state.toBase58())
+    expect(created.category).to.deep.equal({ gaming: {} })
+    expect(created.tags.toNumber()).to.equal(0b101)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.category).to.deep.equal({ gaming: {} })
+    expect(account.tags.toNumber()).to.equal(0b101)
+  })
+})
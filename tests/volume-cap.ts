@@ -0,0 +1,123 @@
+import { assert, expect } from 'chai'
+import * as anchor from '@project-serum/anchor'
+import { AnchorError, Program } from '@project-serum/anchor'
+import { Auction } from '../target/types/auction'
+import { airdropFn, pdaFn, listingFn, bidSeed, counterSeed, statsSeed, nextAuctionFn, treasurySeed } from './utils'
+
+const INITIAL_PRICE = 100
+const AUCTION_LENGTH = 60
+const VOLUME_CAP = 150
+const WINDOW = 24 * 60 * 60
+
+describe('volume cap', () => {
+  const provider = anchor.Provider.local(undefined, { commitment: 'confirmed' })
+  anchor.setProvider(provider)
+
+  const program = anchor.workspace.Auction as Program<Auction>
+
+  const airdrop = airdropFn(provider)
+  const pda = pdaFn(program.programId)
+  const listing = listingFn(program)
+  const nextAuction = nextAuctionFn(program)
+
+  const initializer = anchor.web3.Keypair.generate()
+  const bidder1 = anchor.web3.Keypair.generate()
+  const bidder2 = anchor.web3.Keypair.generate()
+
+  let state: anchor.web3.PublicKey, treasury: anchor.web3.PublicKey
+
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise
+
+      assert(false)
+    } catch (e) {
+      const err = e as AnchorError
+      expect(err.error.errorCode.code).to.equal(code)
+    }
+  }
+
+  // A capped program counts every bid into the stats, which are only writable when passed once more
+  const bid = async (bidder: anchor.web3.Keypair, amount: number, writableStats = true) => {
+    const [stats, _bump] = await pda(statsSeed())
+
+    return program.methods
+      .bid(new anchor.BN(amount))
+      .accounts({
+        state,
+        treasury,
+        buyer: bidder.publicKey,
+        offer: (await pda(bidSeed(state, bidder.publicKey)))[0],
+        entryFeeReceiver: initializer.publicKey,
+        stats,
+      })
+      .remainingAccounts(writableStats ? [{ pubkey: stats, isWritable: true, isSigner: false }] : [])
+      .signers([bidder])
+      .rpc()
+  }
+
+  const setVolumeCap = async (volumeCap: number, window: number, authority?: anchor.web3.Keypair) => {
+    const [programData, _bump] = await anchor.web3.PublicKey.findProgramAddress(
+      [program.programId.toBuffer()],
+      new anchor.web3.PublicKey('BPFLoaderUpgradeab1e11111111111111111111111')
+    )
+
+    // The wallet deploying the program in tests is its upgrade authority
+    await program.methods
+      .setVolumeCap(new anchor.BN(volumeCap), new anchor.BN(window))
+      .accounts({
+        stats: (await pda(statsSeed()))[0],
+        program: program.programId,
+        programData,
+        authority: authority?.publicKey ?? provider.wallet.publicKey,
+      })
+      .signers(authority ? [authority] : [])
+      .rpc()
+  }
+
+  it('Airdropped to humans', async () => {
+    await airdrop(initializer.publicKey)
+    await airdrop(bidder1.publicKey)
+    await airdrop(bidder2.publicKey)
+  })
+
+  it('Auction is initialized', async () => {
+    const [counter, _bump] = await pda(counterSeed(initializer.publicKey))
+    state = await nextAuction(initializer.publicKey)
+    treasury = (await pda(treasurySeed(state)))[0]
+
+    await program.methods
+      .initialize(new anchor.BN(AUCTION_LENGTH), new anchor.BN(INITIAL_PRICE))
+      .accounts({ initializer: initializer.publicKey, state, treasury, counter, ...await listing() })
+      .signers([initializer])
+      .rpc()
+  })
+
+  it('Dont allow anyone but the upgrade authority to set the cap', async () => {
+    await expectError(setVolumeCap(0, 0, bidder1), 'WrongAccount')
+  })
+
+  it('Capped bids have to pass the stats writable', async () => {
+    await setVolumeCap(VOLUME_CAP, WINDOW)
+
+    await expectError(bid(bidder1, INITIAL_PRICE + 10, false), 'WrongAccount')
+  })
+
+  it('Bids stop once the cap of the window is reached', async () => {
+
+    await bid(bidder1, INITIAL_PRICE + 10)
+    await expectError(bid(bidder2, INITIAL_PRICE + 20), 'VolumeCapReached')
+
+    const stats = await program.account.globalStats.fetch((await pda(statsSeed()))[0])
+    expect(stats.windowVolume.toNumber()).to.equal(INITIAL_PRICE + 10)
+  })
+
+  it('Bids are accepted again without the cap, the stats only read', async () => {
+    await setVolumeCap(0, 0)
+
+    await bid(bidder2, INITIAL_PRICE + 20, false)
+
+    const account = await program.account.state.fetch(state)
+    expect(account.maxBidder.toBase58()).to.equal(bidder2.publicKey.toBase58())
+  })
+})